@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use serde::{Deserialize, Serialize};
+use crate::types::PageId;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Statement {
@@ -13,12 +15,23 @@ pub enum Statement {
     CreateTable {
         name: String,
         columns: Vec<ColumnDef>,
+        foreign_keys: Vec<ForeignKeyDef>,
+        options: Vec<TableOption>, // WITH clause
     },
     DropTable { name: String },
 
+    CreateIndex {
+        name: String,
+        table: String,
+        column: String,
+    },
+    DropIndex { name: String, table: String },
+
     Insert {
         table: String,
+        columns: Vec<String>, // target columns, empty for every column
         rows: Vec<Vec<Expression>>,
+        on_conflict: ConflictPolicy,
     },
 
     Update {
@@ -33,10 +46,73 @@ pub enum Statement {
     },
 
     Select {
-        columns: Vec<Expression>,
+        distinct: bool,
+        columns: Vec<Expression>, // empty for SELECT *
+        aliases: Vec<Option<String>>, // AS alias for each projected column
         table: String,
         selection: Option<Expression>, // WHERE clause
+        group_by: Vec<String>,  // GROUP BY column names
+        order_by: Vec<OrderByItem>,   // ORDER BY clause
     },
+
+    // SELECT without FROM, evaluating constant expressions into a single row
+    SelectConstant {
+        columns: Vec<Expression>,
+        aliases: Vec<Option<String>>,
+    },
+
+    Explain {
+        analyze: bool,  // execute the statement and report actual rows and timings
+        stmt: Box<Statement>,
+    },
+
+    Purge { table: String },
+
+    // session setting, applied by the interpreter rather than analyzed and executed
+    Set { name: String, value: Literal },
+
+    // debugging aid describing a data page of the current database
+    DumpPage { page_id: PageId },
+
+    // storage size of every table in the current database
+    ShowTableStatus,
+
+    // debugging aid locating the rows of a table matching selection by their record id
+    ShowRecords { table: String, selection: Option<Expression> },
+
+    // columns of a table with their types, constraints and comments
+    Describe { table: String },
+
+    // CREATE TABLE statement recreating a table
+    ShowCreateTable { table: String },
+}
+
+impl Statement {
+    /// Return every top-level expression in the statement
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Statement::Insert { rows, on_conflict, .. } => {
+                let assignments = match on_conflict {
+                    ConflictPolicy::Update { assignments, .. } => assignments.as_mut_slice(),
+                    _ => &mut [],
+                };
+                rows.iter_mut().flatten().chain(assignments.iter_mut().map(|a| &mut a.value)).collect()
+            }
+            Statement::Update { assignments, selection, .. } => {
+                assignments.iter_mut().map(|a| &mut a.value).chain(selection.iter_mut()).collect()
+            }
+            Statement::Delete { selection, .. } | Statement::ShowRecords { selection, .. } => selection.iter_mut().collect(),
+            Statement::Select { columns, selection, order_by, .. } => {
+                columns.iter_mut()
+                    .chain(selection.iter_mut())
+                    .chain(order_by.iter_mut().map(|item| &mut item.expr))
+                    .collect()
+            }
+            Statement::SelectConstant { columns, .. } => columns.iter_mut().collect(),
+            Statement::Explain { stmt, .. } => stmt.expressions_mut(),
+            _ => vec![],
+        }
+    }
 }
 
 /// Column Definition
@@ -44,6 +120,71 @@ pub enum Statement {
 pub struct ColumnDef {
     pub name: String,
     pub data_type: DataType,
+    // NOT NULL constraint, the column rejects NULL
+    #[serde(default)]
+    pub not_null: bool,
+    // PRIMARY KEY constraint, the column holds a distinct value in every row, implies NOT NULL
+    #[serde(default)]
+    pub primary_key: bool,
+    // COMMENT documenting the column, shown by DESCRIBE and SHOW CREATE TABLE
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// FOREIGN KEY (column) REFERENCES ref_table (ref_column) in CREATE TABLE
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKeyDef {
+    pub column: String,
+    pub ref_table: String,
+    pub ref_column: String,
+    pub on_delete: ReferentialAction,
+}
+
+/// Action taken on the rows referencing a deleted row, set by ON DELETE of a foreign key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReferentialAction {
+    #[default]
+    Restrict,   // the DELETE fails
+    Cascade,    // the referencing rows are deleted too
+    SetNull,    // the foreign key column of the referencing rows is set to NULL
+}
+
+impl fmt::Display for ReferentialAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReferentialAction::Restrict => write!(f, "RESTRICT"),
+            ReferentialAction::Cascade => write!(f, "CASCADE"),
+            ReferentialAction::SetNull => write!(f, "SET NULL"),
+        }
+    }
+}
+
+/// Handling of an inserted row holding the primary key or index key of an existing row,
+/// set by INSERT OR REPLACE, INSERT OR IGNORE and INSERT ... ON CONFLICT DO UPDATE
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    #[default]
+    Abort,      // the INSERT fails
+    Replace,    // the existing rows are deleted before the row is inserted
+    Ignore,     // the row is skipped
+    Update {    // the existing row holding the same value of column is updated instead
+        column: String,
+        assignments: Vec<Assignment>,
+    },
+}
+
+/// Table storage option in CREATE TABLE ... WITH (name = value)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableOption {
+    pub name: String,
+    pub value: Literal,
+}
+
+/// ORDER BY item: projection alias, 1-based projection position or expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderByItem {
+    pub expr: Expression,
+    pub descending: bool,
 }
 
 /// Column Data Type
@@ -54,12 +195,61 @@ pub enum DataType {
 }
 
 impl DataType {
+    /// Whether a value of type expr can be stored in a column of this type, NULL fits every column
     pub fn check_type(&self, expr: &ExprType) -> bool {
+        if *expr == ExprType::Null {
+            return true;
+        }
         match self {
             DataType::Int => *expr == ExprType::Int,
             DataType::Char(_) => *expr == ExprType::Char,
         }
     }
+
+    /// Bytes a value of this type occupies in a serialized record:
+    /// INT is a little-endian i32 of 4 bytes, CHAR(n) is n bytes padded with '\0'
+    pub fn size(&self) -> usize {
+        match self {
+            DataType::Int => 4,
+            DataType::Char(length) => *length as usize,
+        }
+    }
+
+    /// Check a value fits the width of this type. CHAR(n) holds n bytes of UTF-8 rather than
+    /// n characters, so a character outside ASCII takes 2 to 4 of its bytes
+    pub fn check_length(&self, value: &Literal) -> Result<(), String> {
+        match (self, value) {
+            (DataType::Char(length), Literal::String(v)) if v.len() > *length as usize => Err(format!(
+                "Value of {} bytes ({} characters) is too long for type CHAR({})",
+                v.len(), v.chars().count(), length
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Value stored in a column omitted from INSERT, as there is no DEFAULT yet
+    pub fn default_value(&self) -> Literal {
+        match self {
+            DataType::Int => Literal::Int(0),
+            DataType::Char(_) => Literal::String(String::new()),
+        }
+    }
+
+    pub fn expr_type(&self) -> ExprType {
+        match self {
+            DataType::Int => ExprType::Int,
+            DataType::Char(_) => ExprType::Char,
+        }
+    }
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::Int => write!(f, "INT"),
+            DataType::Char(length) => write!(f, "CHAR({length})"),
+        }
+    }
 }
 
 /// Expression
@@ -74,8 +264,17 @@ pub enum Expression {
         op: BinaryOp,
         rhs: Box<Expression>,
     },
+    In {
+        target: Box<Expression>,
+        list: Vec<Expression>, // never empty
+    },
     Identifier(String),
     Literal(Literal),
+    Parameter(usize), // ? placeholder in prepared statement
+    Aggregate {
+        func: AggregateFunc,
+        arg: Option<Box<Expression>>, // None for COUNT(*)
+    },
 }
 
 /// Expression Data Type
@@ -84,12 +283,33 @@ pub enum ExprType {
     Int,
     Char,
     Bool,
+    Null,   // type of the NULL literal, compatible with every other type
+}
+
+impl ExprType {
+    /// Common type of two operands, the other type if one of them is NULL
+    /// None if the types are incompatible
+    pub fn unify(&self, other: &ExprType) -> Option<ExprType> {
+        match (self, other) {
+            (ExprType::Null, t) | (t, ExprType::Null) => Some(t.clone()),
+            (a, b) if a == b => Some(a.clone()),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UnaryOp {
     Not,
     Neg,
+    IsTrue,
+    IsFalse,
+    IsNull,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunc {
+    Count, Sum, Avg, Min, Max,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -100,11 +320,14 @@ pub enum BinaryOp {
 }
 
 /// Literal
-#[derive(Debug, Clone)]
+/// NULL is equal to itself here, so rows and group keys holding NULL compare equal.
+/// SQL comparisons of NULL evaluate to NULL in the executor instead
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Literal {
     Int(i32),
     String(String),
     Bool(bool),
+    Null,
 }
 
 impl PartialEq for Literal {
@@ -113,6 +336,7 @@ impl PartialEq for Literal {
             (Literal::Int(a), Literal::Int(b)) => a == b,
             (Literal::String(a), Literal::String(b)) => a == b,
             (Literal::Bool(a), Literal::Bool(b)) => a == b,
+            (Literal::Null, Literal::Null) => true,
             _ => false,
         }
     }
@@ -120,13 +344,25 @@ impl PartialEq for Literal {
 
 impl Eq for Literal {}
 
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Literal::Int(v) => (0u8, v).hash(state),
+            Literal::String(v) => (1u8, v).hash(state),
+            Literal::Bool(v) => (2u8, v).hash(state),
+            Literal::Null => 3u8.hash(state),
+        }
+    }
+}
+
 impl PartialOrd for Literal {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
             (Literal::Int(a), Literal::Int(b)) => a.partial_cmp(b),
             (Literal::String(a), Literal::String(b)) => a.partial_cmp(b),
             (Literal::Bool(a), Literal::Bool(b)) => a.partial_cmp(b),
-            _ => None, // different types are not comparable
+            (Literal::Null, Literal::Null) => Some(Ordering::Equal),
+            _ => None, // different types and NULL with a value are not comparable
         }
     }
 }
@@ -138,11 +374,12 @@ impl fmt::Display for Literal {
             Literal::String(v) => write!(f, "'{v}'"),
             Literal::Bool(true) => write!(f, "TRUE"),
             Literal::Bool(false) => write!(f, "FALSE"),
+            Literal::Null => write!(f, "NULL"),
         }
     }
 }
 
-/// Assignment statement in update
+/// Assignment statement in update, and in ON CONFLICT DO UPDATE of insert
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Assignment {
     pub column: String,
@@ -158,51 +395,78 @@ pub struct Record {
 // AST node methods
 impl Record {
 
-    /// Convert record to raw bytes
-    pub fn serialize(&self) -> Result<Vec<u8>, String> {
-        let mut buf = Vec::new();
+    /// Convert record to raw bytes, each value taking exactly the size of its column type,
+    /// so deserialize with the same schema reads it back.
+    /// NULL is written as zero bytes, the caller records which columns are NULL
+    pub fn serialize(&self, schema: &[ColumnDef]) -> Result<Vec<u8>, String> {
+        if self.record.len() != schema.len() {
+            return Err(format!("Record has {} values, schema has {} columns", self.record.len(), schema.len()));
+        }
+        let mut buf = Vec::with_capacity(schema.iter().map(|col| col.data_type.size()).sum());
 
-        for value in &self.record {
-            match value {
-                Literal::Int(v) => {
-                    buf.extend_from_slice(&v.to_le_bytes());
+        for (value, col) in self.record.iter().zip(schema) {
+            match (value, &col.data_type) {
+                (Literal::Null, _) => {
+                    buf.extend(std::iter::repeat_n(0u8, col.data_type.size()));
                 }
-                Literal::String(v) => {
-                    buf.extend_from_slice(&v.as_bytes());
+                (Literal::Int(v), DataType::Int) => {
+                    buf.extend_from_slice(&v.to_le_bytes());
                 }
-                Literal::Bool(v) => {
-                    buf.extend_from_slice(&[*v as u8]);
+                (Literal::String(v), DataType::Char(_)) => {
+                    let size = col.data_type.size();
+                    col.data_type.check_length(value).map_err(|e| format!("{} in column '{}'", e, col.name))?;
+                    buf.extend_from_slice(v.as_bytes());
+                    buf.extend(std::iter::repeat_n(0u8, size - v.len()));
                 }
+                _ => return Err(format!("Value {} does not match column '{}' of type {}", value, col.name, col.data_type)),
             }
         }
 
         Ok(buf)
     }
 
+    /// Compare records by column semantics rather than raw values, as used by set-based operators
+    /// CHAR values are equal if they only differ in trailing padding
+    pub fn logical_eq(&self, other: &Record, schema: &[ColumnDef]) -> bool {
+        self.record.len() == other.record.len()
+            && self.record.iter().zip(&other.record).zip(schema)
+                .all(|((a, b), col)| logical_value(a, col) == logical_value(b, col))
+    }
+
+    /// Hash record consistently with logical_eq
+    pub fn logical_hash<H: Hasher>(&self, schema: &[ColumnDef], state: &mut H) {
+        for (value, col) in self.record.iter().zip(schema) {
+            logical_value(value, col).hash(state);
+        }
+    }
+
     /// Deserialize record with given schema
     pub fn deserialize(buf: &[u8], schema: &Vec<ColumnDef>) -> Result<Self, String> {
         let mut offset = 0;
         let mut record = Vec::with_capacity(schema.len());
 
         for col in schema {
+            let length = col.data_type.size();
             match col.data_type {
                 DataType::Int => {
-                    if offset + 4 > buf.len() {
+                    if offset + length > buf.len() {
                         return Err("Unexpected end while reading INT".to_string());
                     }
 
-                    let v = i32::from_le_bytes(buf[offset..offset + 4].try_into().unwrap());
-                    offset += 4;
+                    let v = i32::from_le_bytes(buf[offset..offset + length].try_into().unwrap());
+                    offset += length;
                     record.push(Literal::Int(v));
                 }
-                DataType::Char(v) => {
-                    let length = v as usize;
+                DataType::Char(_) => {
                     if offset + length > buf.len() {
                         return Err("Unexpected end while reading CHAR".to_string());
                     }
 
                     let bytes = &buf[offset..offset + length];
-                    let s = String::from_utf8_lossy(bytes).trim_end_matches('\0').to_string();
+                    let s = std::str::from_utf8(bytes)
+                        .map_err(|e| format!("CHAR value is not valid UTF-8: {}", e))?
+                        .trim_end_matches('\0')
+                        .to_string();
                     offset += length;
                     record.push(Literal::String(s));
                 }
@@ -211,4 +475,13 @@ impl Record {
 
         Ok(Record { record })
     }
-}
\ No newline at end of file
+}
+
+/// Value of a column with CHAR padding removed
+pub(crate) fn logical_value(value: &Literal, col: &ColumnDef) -> Literal {
+    match (value, &col.data_type) {
+        (Literal::String(v), DataType::Char(_)) =>
+            Literal::String(v.trim_end_matches(['\0', ' ']).to_string()),
+        _ => value.clone(),
+    }
+}
@@ -1,5 +1,6 @@
-use crate::compiler::ast::{ColumnDef, ExprType, Literal};
-use crate::types::ColumnId;
+use crate::compiler::ast::{AggregateFunc, ColumnDef, ExprType, Literal};
+use crate::interpreter::catalog_table::{ForeignKey, TableOptions};
+use crate::types::{ColumnId, PageId};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct BoundExprNode {
@@ -25,6 +26,13 @@ pub enum BoundExpr {
     // unary
     Minus(Box<BoundExpr>),
     Not(Box<BoundExpr>),
+    IsTrue(Box<BoundExpr>),
+    IsFalse(Box<BoundExpr>),
+    IsNull(Box<BoundExpr>),
+    // membership of a value in a list
+    In(Box<BoundExpr>, Vec<BoundExpr>),
+    // aggregate, only in the select list
+    Aggregate(AggregateFunc, Option<Box<BoundExpr>>),
     // primary
     Column(ColumnId),
     Literal(Literal),
@@ -40,30 +48,94 @@ pub enum BoundStmt {
     CreateTable {
         name: String,
         columns: Vec<ColumnDef>,
+        foreign_keys: Vec<ForeignKey>,
+        options: TableOptions,
     },
 
     DropTable { name: String },
 
+    // statements binding column ids carry the schema generation of their table at analysis,
+    // the executor rejects them if the table has changed since
+    CreateIndex {
+        name: String,
+        table: String,
+        generation: u64,
+        column: ColumnId,
+    },
+
+    DropIndex { name: String, table: String },
+
     Insert {
         table: String,
+        generation: u64,
         rows: Vec<Vec<BoundExprNode>>,
+        on_conflict: BoundConflictPolicy,
     },
 
     Update {
         table: String,
+        generation: u64,
         assignments: Vec<BoundAssignment>,
         selection: Option<BoundExprNode>,
     },
 
     Select {
         table: String,
+        generation: u64,
+        distinct: bool,
         columns: Vec<BoundExpr>,
+        names: Vec<String>,     // output name of each select item
         selection: Option<BoundExprNode>,
+        group_by: Vec<ColumnId>,
+        order_by: Vec<BoundOrderBy>,
     },
     
+    SelectConstant {
+        columns: Vec<BoundExpr>,
+        names: Vec<String>,
+    },
+
     Delete {
         table: String,
+        generation: u64,
+        selection: Option<BoundExprNode>,
+    },
+
+    Explain {
+        analyze: bool,
+        stmt: Box<BoundStmt>,
+    },
+
+    Purge { table: String },
+
+    DumpPage { page_id: PageId },
+
+    ShowTableStatus,
+
+    ShowRecords {
+        table: String,
+        generation: u64,
         selection: Option<BoundExprNode>,
+    },
+
+    Describe { table: String },
+
+    ShowCreateTable { table: String },
+}
+
+impl BoundStmt {
+    /// Table of the statement and its schema generation at analysis, for statements binding column ids
+    pub fn schema_generation(&self) -> Option<(&str, u64)> {
+        match self {
+            BoundStmt::CreateIndex { table, generation, .. }
+            | BoundStmt::Insert { table, generation, .. }
+            | BoundStmt::Update { table, generation, .. }
+            | BoundStmt::Select { table, generation, .. }
+            | BoundStmt::Delete { table, generation, .. }
+            | BoundStmt::ShowRecords { table, generation, .. } => Some((table, *generation)),
+            BoundStmt::Explain { stmt, .. } => stmt.schema_generation(),
+            _ => None,
+        }
     }
 }
 
@@ -71,4 +143,23 @@ pub enum BoundStmt {
 pub struct BoundAssignment {
     pub column_id: ColumnId,
     pub value: BoundExpr,
-}
\ No newline at end of file
+}
+
+/// Conflict policy of an insert, with the ON CONFLICT DO UPDATE column and assignments bound
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundConflictPolicy {
+    Abort,
+    Replace,
+    Ignore,
+    Update {
+        column: ColumnId,   // a primary key or indexed column
+        assignments: Vec<BoundAssignment>,
+    },
+}
+
+/// Sort key evaluated against each selected row
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundOrderBy {
+    pub expr: BoundExpr,
+    pub descending: bool,
+}
@@ -1,11 +1,12 @@
 use crate::compiler::ast::*;
 use crate::compiler::scanner::Scanner;
 use crate::compiler::token::{Token, TokenType};
-use crate::types::{DbError, DbResult};
+use crate::types::{DbError, DbResult, PageId, MAX_CHAR_LENGTH};
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    param_count: usize, // number of ? parameters in current statement
 }
 
 // parsing statements
@@ -22,7 +23,7 @@ impl Parser {
             }
         }
 
-        Ok(Parser { tokens, current: 0 })
+        Ok(Parser { tokens, current: 0, param_count: 0 })
     }
 
     /// Main parser method
@@ -34,6 +35,21 @@ impl Parser {
         Ok(statements)
     }
 
+    /// Parse statements, pairing each with its source text rebuilt from tokens
+    pub fn parse_with_source(&mut self) -> DbResult<Vec<(Statement, String)>> {
+        let mut statements = vec![];
+        while !self.is_at_end() {
+            let start = self.current;
+            let stmt = self.parse_statement()?;
+            let source = self.tokens[start..self.current].iter()
+                .map(|token| token.lexeme.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            statements.push((stmt, source));
+        }
+        Ok(statements)
+    }
+
     /**
     statement := create_database_stmt
     | drop_stmt
@@ -45,8 +61,17 @@ impl Parser {
     | update_stmt
     | select_stmt
     | delete_stmt
+    | explain_stmt
+    | purge_stmt
+    | set_stmt
+    | dump_page_stmt
+    | show_table_status_stmt
+    | show_records_stmt
+    | show_create_table_stmt
+    | describe_stmt
      */
     fn parse_statement(&mut self) -> DbResult<Statement> {
+        self.param_count = 0;
         match self.peek().token_type {
             TokenType::Create => self.parse_create(),
             TokenType::Drop   => self.parse_drop(),
@@ -56,13 +81,21 @@ impl Parser {
             TokenType::Update => self.parse_update(),
             TokenType::Select => self.parse_select(),
             TokenType::Delete => self.parse_delete(),
+            TokenType::Explain => self.parse_explain(),
+            TokenType::Purge => self.parse_purge(),
+            TokenType::Set => self.parse_set(),
+            // DUMP is not reserved, so it stays usable as a name
+            TokenType::Identifier(ref name) if name == "DUMP" => self.parse_dump_page(),
+            TokenType::Identifier(ref name) if name == "SHOW" => self.parse_show(),
+            TokenType::Identifier(ref name) if name == "DESCRIBE" => self.parse_describe(),
             _ => Err(DbError::ParseError(format!("Unexpected token {:?} at line {}", self.peek(), self.peek().line))),
         }
     }
 
     /**
     create_database_stmt := CREATE DATABASE identifier;
-    create_table_stmt := CREATE TABLE identifier ( column_def_stmt (, column_def_stmt)* );
+    create_table_stmt := CREATE TABLE identifier ( column_def_stmt (, (column_def_stmt | foreign_key))* ) (WITH table_options)?;
+    create_index_stmt := CREATE INDEX identifier ON identifier ( identifier );
      */
     fn parse_create(&mut self) -> DbResult<Statement> {
         self.consume(TokenType::Create)?;
@@ -80,22 +113,45 @@ impl Parser {
                 self.consume(TokenType::LParen)?;
 
                 let mut columns = vec![self.parse_column_def()?];
+                let mut foreign_keys = vec![];
                 while self.match_token(TokenType::Comma) {
-                    columns.push(self.parse_column_def()?);
+                    if self.peek().token_type == TokenType::Foreign {
+                        foreign_keys.push(self.parse_foreign_key()?);
+                    } else {
+                        columns.push(self.parse_column_def()?);
+                    }
                 }
 
+                self.consume(TokenType::RParen)?;
+
+                let options = if self.match_token(TokenType::With) {
+                    self.parse_table_options()?
+                } else { vec![] };
+
+                self.consume(TokenType::Semicolon)?;
+
+                Ok(Statement::CreateTable { name, columns, foreign_keys, options })
+            },
+            TokenType::Index => {
+                self.consume(TokenType::Index)?;
+                let name = self.consume_identifier()?;
+                self.consume(TokenType::On)?;
+                let table = self.consume_identifier()?;
+                self.consume(TokenType::LParen)?;
+                let column = self.consume_identifier()?;
                 self.consume(TokenType::RParen)?;
                 self.consume(TokenType::Semicolon)?;
 
-                Ok(Statement::CreateTable { name, columns })
+                Ok(Statement::CreateIndex { name, table, column })
             },
-            _ => Err(DbError::ParseError(format!("Expected DATABASE or TABLE at line {}", token.line).into())),
+            _ => Err(DbError::ParseError(format!("Expected DATABASE, TABLE or INDEX at line {}", token.line).into())),
         }
     }
 
     /**
     drop_database_stmt := DROP DATABASE identifier;
     drop_table_stmt := DROP TABLE identifier;
+    drop_index_stmt := DROP INDEX identifier ON identifier;
     */
     fn parse_drop(&mut self) -> DbResult<Statement> {
         self.consume(TokenType::Drop)?;
@@ -113,7 +169,15 @@ impl Parser {
                 self.consume(TokenType::Semicolon)?;
                 Ok(Statement::DropTable { name })
             },
-            _ => Err(DbError::ParseError(format!("Expected DATABASE or TABLE at line {}", token.line).into())),
+            TokenType::Index => {
+                self.consume(TokenType::Index)?;
+                let name = self.consume_identifier()?;
+                self.consume(TokenType::On)?;
+                let table = self.consume_identifier()?;
+                self.consume(TokenType::Semicolon)?;
+                Ok(Statement::DropIndex { name, table })
+            },
+            _ => Err(DbError::ParseError(format!("Expected DATABASE, TABLE or INDEX at line {}", token.line).into())),
         }
     }
 
@@ -138,12 +202,75 @@ impl Parser {
     }
 
     /**
-    column_def_stmt := identifier: data_type_stmt
+    column_def_stmt := identifier: data_type_stmt ( NOT NULL | PRIMARY KEY | COMMENT string_literal )*
     */
     fn parse_column_def(&mut self) -> DbResult<ColumnDef> {
         let name = self.consume_identifier()?;
         let data_type = self.parse_data_type()?;
-        Ok(ColumnDef { name, data_type })
+        let mut column = ColumnDef { name, data_type, not_null: false, primary_key: false, comment: None };
+        loop {
+            if self.match_token(TokenType::Not) {
+                self.consume(TokenType::Null)?;
+                column.not_null = true;
+            } else if self.match_token(TokenType::Primary) {
+                // KEY is not reserved, so it stays usable as a name
+                match self.consume_identifier()?.as_str() {
+                    "KEY" => {}
+                    other => return Err(DbError::ParseError(format!("Expected KEY after PRIMARY, got {}", other))),
+                }
+                column.primary_key = true;
+                column.not_null = true;
+            } else if matches!(self.peek().token_type, TokenType::Identifier(ref name) if name == "COMMENT") {
+                // COMMENT is not reserved, so it stays usable as a name
+                self.advance();
+                match self.advance().token_type {
+                    TokenType::StringLiteral(comment) => column.comment = Some(comment),
+                    t => return Err(DbError::ParseError(format!("Expected a string after COMMENT, got {:?}", t))),
+                }
+            } else {
+                return Ok(column);
+            }
+        }
+    }
+
+    /**
+    foreign_key := FOREIGN KEY ( identifier ) REFERENCES identifier ( identifier ) [ ON DELETE (CASCADE | RESTRICT | SET NULL) ]
+    */
+    fn parse_foreign_key(&mut self) -> DbResult<ForeignKeyDef> {
+        self.consume(TokenType::Foreign)?;
+        // KEY is not reserved, so it stays usable as a name
+        match self.consume_identifier()?.as_str() {
+            "KEY" => {}
+            other => return Err(DbError::ParseError(format!("Expected KEY after FOREIGN, got {}", other))),
+        }
+        self.consume(TokenType::LParen)?;
+        let column = self.consume_identifier()?;
+        self.consume(TokenType::RParen)?;
+
+        self.consume(TokenType::References)?;
+        let ref_table = self.consume_identifier()?;
+        self.consume(TokenType::LParen)?;
+        let ref_column = self.consume_identifier()?;
+        self.consume(TokenType::RParen)?;
+
+        let mut on_delete = ReferentialAction::default();
+        if self.match_token(TokenType::On) {
+            self.consume(TokenType::Delete)?;
+            on_delete = if self.match_token(TokenType::Set) {
+                if !self.match_token(TokenType::Null) {
+                    return Err(DbError::ParseError("ON DELETE SET DEFAULT is not supported".to_string()));
+                }
+                ReferentialAction::SetNull
+            } else {
+                // CASCADE and RESTRICT are not reserved, so they stay usable as names
+                match self.consume_identifier()?.as_str() {
+                    "CASCADE" => ReferentialAction::Cascade,
+                    "RESTRICT" => ReferentialAction::Restrict,
+                    other => return Err(DbError::ParseError(format!("Expected CASCADE, RESTRICT or SET NULL after ON DELETE, got {}", other))),
+                }
+            };
+        }
+        Ok(ForeignKeyDef { column, ref_table, ref_column, on_delete })
     }
 
     /**
@@ -155,22 +282,70 @@ impl Parser {
             TokenType::Int => Ok(DataType::Int),
             TokenType::Char => {
                 self.consume(TokenType::LParen)?;
-                let len = self.consume_int_literal()? as u32;
+                let len = self.consume_int_literal()?;
+                if len < 1 || len as u32 > MAX_CHAR_LENGTH {
+                    return Err(DbError::ParseError(format!(
+                        "CHAR length must be between 1 and {}, got {} on line {:?}", MAX_CHAR_LENGTH, len, token.line)));
+                }
                 self.consume(TokenType::RParen)?;
-                Ok(DataType::Char(len))
+                Ok(DataType::Char(len as u32))
             }
             _ => Err(DbError::ParseError(format!("Expected valid data type on line {:?}", token.line)))
         }
     }
 
     /**
-    insert_stmt := INSERT INTO identifier VALUES row ( , row )* ;
+    table_options := ( table_option (, table_option)* )
+    table_option := identifier = literal
+    */
+    fn parse_table_options(&mut self) -> DbResult<Vec<TableOption>> {
+        self.consume(TokenType::LParen)?;
+
+        let mut options = vec![self.parse_table_option()?];
+        while self.match_token(TokenType::Comma) {
+            options.push(self.parse_table_option()?);
+        }
+
+        self.consume(TokenType::RParen)?;
+        Ok(options)
+    }
+
+    fn parse_table_option(&mut self) -> DbResult<TableOption> {
+        let name = self.consume_identifier()?;
+        self.consume(TokenType::Equal)?;
+        let value = self.parse_literal()?;
+        Ok(TableOption { name, value })
+    }
+
+    /**
+    insert_stmt := INSERT ( OR ( REPLACE | IGNORE ) )? INTO identifier ( "(" identifier ( , identifier )* ")" )?
+                   VALUES row ( , row )* ( ON CONFLICT "(" identifier ")" DO UPDATE SET assignment ( , assignment )* )? ;
     */
     fn parse_insert(&mut self) -> DbResult<Statement> {
         self.consume(TokenType::Insert)?;
+
+        // REPLACE and IGNORE are not reserved, so they stay usable as names
+        let mut on_conflict = ConflictPolicy::default();
+        if self.match_token(TokenType::Or) {
+            on_conflict = match self.consume_identifier()?.as_str() {
+                "REPLACE" => ConflictPolicy::Replace,
+                "IGNORE" => ConflictPolicy::Ignore,
+                other => return Err(DbError::ParseError(format!("Expected REPLACE or IGNORE after INSERT OR, got {}", other))),
+            };
+        }
         self.consume(TokenType::Into)?;
 
         let table = self.consume_identifier()?;
+
+        // optional column list, empty means every column in declaration order
+        let mut columns = Vec::new();
+        if self.match_token(TokenType::LParen) {
+            columns.push(self.consume_identifier()?);
+            while self.match_token(TokenType::Comma) {
+                columns.push(self.consume_identifier()?);
+            }
+            self.consume(TokenType::RParen)?;
+        }
         self.consume(TokenType::Values)?;
 
         let mut rows = Vec::new();
@@ -179,9 +354,34 @@ impl Parser {
             rows.push(self.parse_value_row()?);
         }
 
+        // CONFLICT and DO are not reserved either
+        if self.match_token(TokenType::On) {
+            if on_conflict != ConflictPolicy::Abort {
+                return Err(DbError::ParseError("INSERT OR cannot be combined with ON CONFLICT".to_string()));
+            }
+            match self.consume_identifier()?.as_str() {
+                "CONFLICT" => {}
+                other => return Err(DbError::ParseError(format!("Expected CONFLICT after ON, got {}", other))),
+            }
+            self.consume(TokenType::LParen)?;
+            let column = self.consume_identifier()?;
+            self.consume(TokenType::RParen)?;
+            match self.consume_identifier()?.as_str() {
+                "DO" => {}
+                other => return Err(DbError::ParseError(format!("Expected DO after ON CONFLICT, got {}", other))),
+            }
+            self.consume(TokenType::Update)?;
+            self.consume(TokenType::Set)?;
+            let mut assignments = vec![self.parse_assignment()?];
+            while self.match_token(TokenType::Comma) {
+                assignments.push(self.parse_assignment()?);
+            }
+            on_conflict = ConflictPolicy::Update { column, assignments };
+        }
+
         self.consume(TokenType::Semicolon)?;
 
-        Ok(Statement::Insert {table, rows})
+        Ok(Statement::Insert {table, columns, rows, on_conflict})
     }
 
     /**
@@ -207,16 +407,159 @@ impl Parser {
     }
 
     /**
-    select_stmt := SELECT expression (,expression)* FROM identifier (WHERE expression)?;
+    select_stmt := SELECT DISTINCT? ("*" | select_item (, select_item)*) FROM identifier (WHERE expression)?
+        (GROUP BY identifier (, identifier)*)? (ORDER BY order_by_item (, order_by_item)*)?;
+        | SELECT select_item (, select_item)*;
+    select_item := expression (AS identifier)?
     */
     fn parse_select(&mut self) -> DbResult<Statement> {
         self.consume(TokenType::Select)?;
+        let distinct = self.match_token(TokenType::Distinct);
+
+        // SELECT * leaves the column list empty, the analyzer expands it to every column
+        let mut columns = vec![];
+        let mut aliases = vec![];
+        if !self.match_token(TokenType::Star) {
+            loop {
+                columns.push(self.parse_expression()?);
+                aliases.push(if self.match_token(TokenType::As) {
+                    Some(self.consume_identifier()?)
+                } else { None });
+
+                if !self.match_token(TokenType::Comma) {
+                    break;
+                }
+            }
+        }
 
-        let mut columns = vec![self.parse_expression()?];
-        while self.match_token(TokenType::Comma) {
-            columns.push(self.parse_expression()?);
+        // without FROM the select list is evaluated once, it has no columns to expand *
+        if !distinct && !columns.is_empty() && self.match_token(TokenType::Semicolon) {
+            return Ok(Statement::SelectConstant { columns, aliases });
+        }
+
+        self.consume(TokenType::From)?;
+        let table = self.consume_identifier()?;
+
+        let selection = if self.match_token(TokenType::Where) {
+            Some(self.parse_expression()?)
+        } else { None };
+
+        let mut group_by = vec![];
+        if self.match_token(TokenType::Group) {
+            self.consume(TokenType::By)?;
+            group_by.push(self.consume_identifier()?);
+            while self.match_token(TokenType::Comma) {
+                group_by.push(self.consume_identifier()?);
+            }
+        }
+
+        let mut order_by = vec![];
+        if self.match_token(TokenType::Order) {
+            self.consume(TokenType::By)?;
+            order_by.push(self.parse_order_by_item()?);
+            while self.match_token(TokenType::Comma) {
+                order_by.push(self.parse_order_by_item()?);
+            }
+        }
+
+        self.consume(TokenType::Semicolon)?;
+
+        Ok(Statement::Select {table, distinct, columns, aliases, selection, group_by, order_by})
+    }
+
+    /**
+    order_by_item := expression (ASC | DESC)?
+    */
+    fn parse_order_by_item(&mut self) -> DbResult<OrderByItem> {
+        let expr = self.parse_expression()?;
+        let descending = if self.match_token(TokenType::Desc) {
+            true
+        } else {
+            self.match_token(TokenType::Asc);
+            false
+        };
+        Ok(OrderByItem { expr, descending })
+    }
+
+    /**
+    explain_stmt := EXPLAIN (ANALYZE)? select_stmt
+    */
+    fn parse_explain(&mut self) -> DbResult<Statement> {
+        self.consume(TokenType::Explain)?;
+        let analyze = self.match_token(TokenType::Analyze);
+
+        if self.peek().token_type != TokenType::Select {
+            return Err(DbError::ParseError(format!(
+                "Expected SELECT after EXPLAIN at line {}", self.peek().line
+            )));
         }
+        let stmt = self.parse_select()?;
+
+        Ok(Statement::Explain { analyze, stmt: Box::new(stmt) })
+    }
+
+    /**
+    purge_stmt := PURGE identifier;
+    */
+    fn parse_purge(&mut self) -> DbResult<Statement> {
+        self.consume(TokenType::Purge)?;
+        let table = self.consume_identifier()?;
+        self.consume(TokenType::Semicolon)?;
+
+        Ok(Statement::Purge { table })
+    }
 
+    /**
+    set_stmt := SET identifier = literal;
+    */
+    fn parse_set(&mut self) -> DbResult<Statement> {
+        self.consume(TokenType::Set)?;
+        let name = self.consume_identifier()?;
+        self.consume(TokenType::Equal)?;
+        let value = self.parse_literal()?;
+        self.consume(TokenType::Semicolon)?;
+
+        Ok(Statement::Set { name, value })
+    }
+
+    /**
+    dump_page_stmt := DUMP PAGE integer;
+    */
+    fn parse_dump_page(&mut self) -> DbResult<Statement> {
+        self.advance();
+        let keyword = self.consume_identifier()?;
+        if keyword != "PAGE" {
+            return Err(DbError::ParseError(format!("Expected PAGE after DUMP, got {}", keyword)));
+        }
+        let page_id = match self.advance().token_type {
+            TokenType::IntLiteral(v) if v > 0 => v as PageId,
+            t => return Err(DbError::ParseError(format!("Expected a positive page id, got {:?}", t))),
+        };
+        self.consume(TokenType::Semicolon)?;
+
+        Ok(Statement::DumpPage { page_id })
+    }
+
+    /**
+    show_table_status_stmt := SHOW TABLE STATUS;
+    show_records_stmt := SHOW RECORDS FROM identifier (WHERE expression)?;
+    show_create_table_stmt := SHOW CREATE TABLE identifier;
+    */
+    fn parse_show(&mut self) -> DbResult<Statement> {
+        self.advance();
+        if self.match_token(TokenType::Table) {
+            return self.parse_show_table_status();
+        }
+        if self.match_token(TokenType::Create) {
+            self.consume(TokenType::Table)?;
+            let table = self.consume_identifier()?;
+            self.consume(TokenType::Semicolon)?;
+            return Ok(Statement::ShowCreateTable { table });
+        }
+        let keyword = self.consume_identifier()?;
+        if keyword != "RECORDS" {
+            return Err(DbError::ParseError(format!("Expected TABLE, CREATE or RECORDS after SHOW, got {}", keyword)));
+        }
         self.consume(TokenType::From)?;
         let table = self.consume_identifier()?;
 
@@ -226,7 +569,28 @@ impl Parser {
 
         self.consume(TokenType::Semicolon)?;
 
-        Ok(Statement::Select {table, columns, selection})
+        Ok(Statement::ShowRecords { table, selection })
+    }
+
+    fn parse_show_table_status(&mut self) -> DbResult<Statement> {
+        let keyword = self.consume_identifier()?;
+        if keyword != "STATUS" {
+            return Err(DbError::ParseError(format!("Expected STATUS after SHOW TABLE, got {}", keyword)));
+        }
+        self.consume(TokenType::Semicolon)?;
+
+        Ok(Statement::ShowTableStatus)
+    }
+
+    /**
+    describe_stmt := DESCRIBE identifier;
+    */
+    fn parse_describe(&mut self) -> DbResult<Statement> {
+        self.advance();
+        let table = self.consume_identifier()?;
+        self.consume(TokenType::Semicolon)?;
+
+        Ok(Statement::Describe { table })
     }
 
     /**
@@ -332,7 +696,8 @@ Expression -> LogicalOr
 LogicalOr -> LogicalAnd ("OR" LogicalAnd)*
 LogicalAnd -> Equality ("AND" Equality)*
 Equality -> Comparison ( ("==" | "!=") Comparison)*
-Comparison -> Additive ( (">" | ">=" | "<" | "<=") Additive )*
+Comparison -> Additive ( (">" | ">=" | "<" | "<=") Additive | "BETWEEN" Additive "AND" Additive
+    | "IN" "(" Expression ("," Expression)* ")" )*
 Additive -> Multiplicative ( ("+" | "-") Multiplicative )*
 Multiplicative -> Unary ( ("*" | "/") Unary )*
 Unary -> ("NOT" | "-") Unary | Primary
@@ -361,12 +726,12 @@ impl Parser {
         Ok(expr)
     }
 
-    /// LogicalAnd -> Equality ("AND" Equality)*
+    /// LogicalAnd -> IsTest ("AND" IsTest)*
     fn parse_logical_and(&mut self) -> DbResult<Expression> {
-        let mut expr = self.parse_equality()?;
+        let mut expr = self.parse_is_test()?;
 
         while self.match_token(TokenType::And) {
-            let right = self.parse_equality()?;
+            let right = self.parse_is_test()?;
             expr = Expression::Binary {
                 lhs: Box::new(expr),
                 op: BinaryOp::And,
@@ -377,6 +742,31 @@ impl Parser {
         Ok(expr)
     }
 
+    /// IsTest -> Equality ( "IS" ["NOT"] ("TRUE" | "FALSE" | "NULL") )*
+    /// "x IS NOT TRUE" is parsed as "NOT (x IS TRUE)"
+    fn parse_is_test(&mut self) -> DbResult<Expression> {
+        let mut expr = self.parse_equality()?;
+
+        while self.match_token(TokenType::Is) {
+            let negated = self.match_token(TokenType::Not);
+            let token = self.advance();
+            let op = match token.token_type {
+                TokenType::BoolLiteral(true) => UnaryOp::IsTrue,
+                TokenType::BoolLiteral(false) => UnaryOp::IsFalse,
+                TokenType::Null => UnaryOp::IsNull,
+                t => return Err(DbError::ParseError(format!(
+                    "Expected TRUE, FALSE or NULL after IS, got {:?} at line {:?}", t, token.line))),
+            };
+
+            expr = Expression::Unary { op, expr: Box::new(expr) };
+            if negated {
+                expr = Expression::Unary { op: UnaryOp::Not, expr: Box::new(expr) };
+            }
+        }
+
+        Ok(expr)
+    }
+
     /// Equality -> Comparison ( ("==" | "!=") Comparison)*
     fn parse_equality(&mut self) -> DbResult<Expression> {
         let mut expr = self.parse_comparison()?;
@@ -401,11 +791,47 @@ impl Parser {
         Ok(expr)
     }
 
-    /// Comparison -> Additive ( (">" | ">=" | "<" | "<=") Additive )*
+    /// Comparison -> Additive ( (">" | ">=" | "<" | "<=") Additive | "BETWEEN" Additive "AND" Additive
+    ///     | "IN" "(" Expression ("," Expression)* ")" )*
+    /// "x BETWEEN low AND high" is parsed as "x >= low AND x <= high"
+    /// "IN ()" is a parse error
     fn parse_comparison(&mut self) -> DbResult<Expression> {
         let mut expr = self.parse_additive()?;
 
         loop {
+            if self.match_token(TokenType::In) {
+                self.consume(TokenType::LParen)?;
+                let mut list = vec![self.parse_expression()?];
+                while self.match_token(TokenType::Comma) {
+                    list.push(self.parse_expression()?);
+                }
+                self.consume(TokenType::RParen)?;
+
+                expr = Expression::In { target: Box::new(expr), list };
+                continue;
+            }
+
+            if self.match_token(TokenType::Between) {
+                let low = self.parse_additive()?;
+                self.consume(TokenType::And)?;
+                let high = self.parse_additive()?;
+
+                expr = Expression::Binary {
+                    lhs: Box::new(Expression::Binary {
+                        lhs: Box::new(expr.clone()),
+                        op: BinaryOp::Gte,
+                        rhs: Box::new(low),
+                    }),
+                    op: BinaryOp::And,
+                    rhs: Box::new(Expression::Binary {
+                        lhs: Box::new(expr),
+                        op: BinaryOp::Lte,
+                        rhs: Box::new(high),
+                    }),
+                };
+                continue;
+            }
+
             let op = match self.peek().token_type {
                 TokenType::Greater => BinaryOp::Gt,
                 TokenType::GEqual => BinaryOp::Gte,
@@ -494,12 +920,13 @@ impl Parser {
         }
     }
 
-    /// Primary -> Literal | Identifier | "(" Expression ")"
+    /// Primary -> Literal | Identifier | "?" | "(" Expression ")"
     fn parse_primary(&mut self) -> DbResult<Expression> {
         match &self.peek().token_type {
             TokenType::IntLiteral(_)
             | TokenType::StringLiteral(_)
-            | TokenType::BoolLiteral(_) => {
+            | TokenType::BoolLiteral(_)
+            | TokenType::Null => {
                 let lit = self.parse_literal()?;
                 Ok(Expression::Literal(lit))
             }
@@ -507,9 +934,19 @@ impl Parser {
             TokenType::Identifier(name) => {
                 let name = name.clone();
                 self.advance();
+                if self.peek().token_type == TokenType::LParen {
+                    return self.parse_function_call(&name);
+                }
                 Ok(Expression::Identifier(name))
             }
 
+            TokenType::Question => {
+                // parameters are numbered by their position in the statement
+                self.advance();
+                self.param_count += 1;
+                Ok(Expression::Parameter(self.param_count - 1))
+            }
+
             TokenType::LParen => {
                 self.advance();
                 let expr = self.parse_expression()?;
@@ -524,13 +961,35 @@ impl Parser {
         }
     }
 
-    /// literal = int | string | bool
+    /// function_call := COUNT "(" ("*" | expression) ")" | (SUM | AVG | MIN | MAX) "(" expression ")"
+    fn parse_function_call(&mut self, name: &str) -> DbResult<Expression> {
+        let func = match name {
+            "COUNT" => AggregateFunc::Count,
+            "SUM" => AggregateFunc::Sum,
+            "AVG" => AggregateFunc::Avg,
+            "MIN" => AggregateFunc::Min,
+            "MAX" => AggregateFunc::Max,
+            _ => return Err(DbError::ParseError(format!("Unknown function '{}' at line {}", name, self.peek().line))),
+        };
+
+        self.consume(TokenType::LParen)?;
+        let arg = if func == AggregateFunc::Count && self.match_token(TokenType::Star) {
+            None
+        } else {
+            Some(Box::new(self.parse_expression()?))
+        };
+        self.consume(TokenType::RParen)?;
+        Ok(Expression::Aggregate { func, arg })
+    }
+
+    /// literal = int | string | bool | NULL
     fn parse_literal(&mut self) -> DbResult<Literal> {
         let token = self.advance();
         match token.token_type {
             TokenType::IntLiteral(v) => Ok(Literal::Int(v)),
             TokenType::StringLiteral(s) => Ok(Literal::String(s)),
             TokenType::BoolLiteral(b) => Ok(Literal::Bool(b)),
+            TokenType::Null => Ok(Literal::Null),
             t => Err(DbError::ParseError(format!("Expected literal, got {:?} at line {:?}", t, token.line))),
         }
     }
@@ -1,4 +1,4 @@
-use crate::compiler::token::{Token, TokenType};
+use crate::compiler::token::{Span, Token, TokenType};
 use crate::types::{DbError, DbResult};
 
 pub struct Scanner {
@@ -6,6 +6,10 @@ pub struct Scanner {
     start: usize,
     current: usize,
     line: usize,
+    // index of the first character of the current line
+    line_start: usize,
+    // line and column where the token being scanned starts
+    start_position: (usize, usize),
 }
 
 impl Scanner {
@@ -16,6 +20,28 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_position: (1, 1),
+        }
+    }
+
+    /// Scan the whole source for editor tooling such as syntax highlighting.
+    /// Unlike next_token, invalid input does not stop scanning: it becomes a TokenType::Error
+    /// token covering the offending text, and scanning resumes after it.
+    /// The last token is always Eof
+    pub fn tokenize_all(&mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = match self.next_token() {
+                Ok(token) => token,
+                Err(DbError::ScannerError(msg)) => self.make_token(TokenType::Error(msg)),
+                Err(e) => self.make_token(TokenType::Error(format!("{:?}", e))),
+            };
+            let is_eof = token.token_type == TokenType::Eof;
+            tokens.push(token);
+            if is_eof {
+                return tokens;
+            }
         }
     }
 
@@ -41,15 +67,33 @@ impl Scanner {
         if self.current + 1 >= self.source.len() { '\0' } else { self.source[self.current + 1] }
     }
 
+    /// Column of the current character in its line
+    fn column(&self) -> usize {
+        self.current - self.line_start + 1
+    }
+
+    /// Record that the character just consumed was a line break
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     /// Create token of given type
     fn add_token(&self, token_type: TokenType) -> DbResult<Token> {
+        Ok(self.make_token(token_type))
+    }
+
+    /// Create token of given type from the text scanned since the token start
+    fn make_token(&self, token_type: TokenType) -> Token {
         let lexeme: String = self.source[self.start..self.current].iter().collect();
+        let (line, column) = self.start_position;
 
-        Ok(Token {
+        Token {
             token_type,
             lexeme,
             line: self.line,
-        })
+            span: Span { line, column, end_line: self.line, end_column: self.column() },
+        }
     }
 
     /// Skip whitespace and comment
@@ -59,8 +103,8 @@ impl Scanner {
                 ' ' | '\r' | '\t' => { self.advance(); },
                 '\n' => {
                     // enter new line
-                    self.line += 1;
                     self.advance();
+                    self.new_line();
                 }
                 '-' if self.peek_next() == '-' => {
                     // SQL comment: -- until end of line
@@ -103,9 +147,29 @@ impl Scanner {
             "DISCONNECT" => TokenType::Disconnect,
             "TRUE" => TokenType::BoolLiteral(true),
             "FALSE" => TokenType::BoolLiteral(false),
+            "NULL" => TokenType::Null,
             "AND" => TokenType::And,
             "NOT" => TokenType::Not,
             "OR" => TokenType::Or,
+            "WITH" => TokenType::With,
+            "AS" => TokenType::As,
+            "ORDER" => TokenType::Order,
+            "BY" => TokenType::By,
+            "ASC" => TokenType::Asc,
+            "DESC" => TokenType::Desc,
+            "EXPLAIN" => TokenType::Explain,
+            "ANALYZE" => TokenType::Analyze,
+            "IS" => TokenType::Is,
+            "PURGE" => TokenType::Purge,
+            "GROUP" => TokenType::Group,
+            "DISTINCT" => TokenType::Distinct,
+            "BETWEEN" => TokenType::Between,
+            "IN" => TokenType::In,
+            "ON" => TokenType::On,
+            "FOREIGN" => TokenType::Foreign,
+            "REFERENCES" => TokenType::References,
+            "PRIMARY" => TokenType::Primary,
+            "INDEX" => TokenType::Index,
             _ => TokenType::Identifier(upper),
         };
 
@@ -119,17 +183,18 @@ impl Scanner {
         }
 
         let value: String = self.source[self.start..self.current].iter().collect();
-        let int_value = value.parse::<i32>().unwrap();
+        let int_value = value.parse::<i32>().map_err(|_| DbError::ScannerError(format!(
+            "integer literal {} out of range at line {}", value, self.line
+        )))?;
         self.add_token(TokenType::IntLiteral(int_value))
     }
 
     /// Scan string
     fn string(&mut self, quote: char) -> DbResult<Token> {
         while self.peek() != quote && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
@@ -147,6 +212,7 @@ impl Scanner {
     pub fn next_token(&mut self) -> DbResult<Token> {
         self.skip_whitespace();
         self.start = self.current;
+        self.start_position = (self.line, self.column());
 
         if self.is_at_end() {
             return self.add_token(TokenType::Eof);
@@ -179,6 +245,7 @@ impl Scanner {
             '-' => self.add_token(TokenType::Minus),
             '*' => self.add_token(TokenType::Star),
             '/' => self.add_token(TokenType::Slash),
+            '?' => self.add_token(TokenType::Question),
             '"' | '\'' => self.string(c),
             '0'..='9' => self.number(),
             'A'..='Z' | 'a'..='z' | '_' =>self.identifier(),
@@ -15,6 +15,7 @@ pub enum TokenType {
     Slash,      // /
     Plus,       // +
     Minus,      // -
+    Question,   // ?
 
     // data types
     Int,
@@ -29,15 +30,30 @@ pub enum TokenType {
     // keywords
     Create, Database, Drop, Table, Insert, Into,
     Values, Update, Set, Where, Select, Delete, From,
-    Connect, To, Disconnect, And, Or, Not,
+    Connect, To, Disconnect, And, Or, Not, With,
+    As, Order, By, Asc, Desc, Explain, Analyze, Is,
+    Purge, Group, Distinct, Foreign, References, On, Between, In, Null,
+    Primary, Index,
 
     // special
+    Error(String), // invalid input, only produced by Scanner::tokenize_all
     Eof,
 }
 
+/// Source range of a token. Lines and columns start at 1, columns count characters,
+/// and the end position is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub line: usize, // line number for error handling
+    pub span: Span,
 }
\ No newline at end of file
@@ -0,0 +1,112 @@
+use crate::compiler::bounded_ast::{BoundConflictPolicy, BoundExpr, BoundExprNode, BoundStmt};
+use crate::interpreter::analyzer::Analyzer;
+use crate::types::{DbError, DbResult};
+
+impl Analyzer {
+
+    /// Check every column reference in a bound statement is within its table's schema,
+    /// so the executor can index rows by column id without panicking
+    pub fn validate_bound_stmt(&self, stmt: &BoundStmt) -> DbResult<()> {
+        let (table, exprs): (&str, Vec<&BoundExpr>) = match stmt {
+            BoundStmt::Insert { table, rows, on_conflict, .. } => {
+                let column_count = self.column_count(table)?;
+                for row in rows {
+                    if row.len() != column_count {
+                        return Err(DbError::ColumnMismatch(format!(
+                            "Expected {} values for table '{}', got {}", column_count, table, row.len()
+                        )));
+                    }
+                }
+                let mut exprs: Vec<&BoundExpr> = rows.iter().flatten().map(|node| &node.expr).collect();
+                if let BoundConflictPolicy::Update { column, assignments } = on_conflict {
+                    check_column(*column, column_count, table)?;
+                    for assignment in assignments {
+                        check_column(assignment.column_id, column_count, table)?;
+                    }
+                    exprs.extend(assignments.iter().map(|a| &a.value));
+                }
+                (table, exprs)
+            }
+            BoundStmt::Update { table, assignments, selection, .. } => {
+                let column_count = self.column_count(table)?;
+                for assignment in assignments {
+                    check_column(assignment.column_id, column_count, table)?;
+                }
+                let mut exprs: Vec<&BoundExpr> = assignments.iter().map(|a| &a.value).collect();
+                exprs.extend(selection_expr(selection));
+                (table, exprs)
+            }
+            BoundStmt::Select { table, columns, selection, group_by, order_by, .. } => {
+                let column_count = self.column_count(table)?;
+                for column_id in group_by {
+                    check_column(*column_id, column_count, table)?;
+                }
+                let mut exprs: Vec<&BoundExpr> = columns.iter().collect();
+                exprs.extend(selection_expr(selection));
+                exprs.extend(order_by.iter().map(|item| &item.expr));
+                (table, exprs)
+            }
+            BoundStmt::Delete { table, selection, .. }
+            | BoundStmt::ShowRecords { table, selection, .. } => (table, selection_expr(selection).collect()),
+            BoundStmt::Explain { stmt, .. } => return self.validate_bound_stmt(stmt),
+            _ => return Ok(()),
+        };
+
+        let column_count = self.column_count(table)?;
+        for expr in exprs {
+            validate_expr(expr, column_count, table)?;
+        }
+        Ok(())
+    }
+
+    fn column_count(&self, table: &str) -> DbResult<usize> {
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.as_ref()
+            .ok_or_else(|| DbError::DatabaseNotFound("A database connection does not exist".to_string()))?;
+        let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table)
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
+        Ok(schema.columns.len())
+    }
+}
+
+fn selection_expr(selection: &Option<BoundExprNode>) -> impl Iterator<Item = &BoundExpr> {
+    selection.iter().map(|node| &node.expr)
+}
+
+fn check_column(column_id: usize, column_count: usize, table: &str) -> DbResult<()> {
+    if column_id >= column_count {
+        return Err(DbError::ColumnNotFound(format!(
+            "Column id {} is out of range for table '{}' with {} columns", column_id, table, column_count
+        )));
+    }
+    Ok(())
+}
+
+fn validate_expr(expr: &BoundExpr, column_count: usize, table: &str) -> DbResult<()> {
+    match expr {
+        BoundExpr::Column(column_id) => check_column(*column_id, column_count, table),
+        BoundExpr::Literal(_) | BoundExpr::Aggregate(_, None) => Ok(()),
+        BoundExpr::Aggregate(_, Some(e)) => validate_expr(e, column_count, table),
+        BoundExpr::Minus(e) | BoundExpr::Not(e)
+        | BoundExpr::IsTrue(e) | BoundExpr::IsFalse(e) | BoundExpr::IsNull(e) => validate_expr(e, column_count, table),
+        BoundExpr::In(target, list) => {
+            validate_expr(target, column_count, table)?;
+            list.iter().try_for_each(|e| validate_expr(e, column_count, table))
+        }
+        BoundExpr::Equals(l, r)
+        | BoundExpr::NotEquals(l, r)
+        | BoundExpr::Gt(l, r)
+        | BoundExpr::Gte(l, r)
+        | BoundExpr::Lt(l, r)
+        | BoundExpr::Lte(l, r)
+        | BoundExpr::And(l, r)
+        | BoundExpr::Or(l, r)
+        | BoundExpr::Add(l, r)
+        | BoundExpr::Sub(l, r)
+        | BoundExpr::Mul(l, r)
+        | BoundExpr::Div(l, r) => {
+            validate_expr(l, column_count, table)?;
+            validate_expr(r, column_count, table)
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::fs;
 use crate::compiler::bounded_ast::BoundStmt;
 use crate::interpreter::analyzer::Analyzer;
 use crate::types::{DbError, DbResult};
@@ -7,15 +8,27 @@ impl Analyzer {
     pub fn analyze_create_database(&self, name: &str) -> DbResult<BoundStmt> {
         let ctx = self.context.read().unwrap();
 
-        // check no duplicate database name in catalog
-        if ctx.global_catalog.has_database(name) {
-            return Err(DbError::DuplicateDatabase(format!("Database {} already exists", name)));
+        // check no duplicate database name in catalog, ignoring case
+        if let Some(existing) = ctx.global_catalog.find_database_ignore_case(name) {
+            return Err(DbError::DuplicateDatabase(format!("Database {} already exists", existing)));
         }
 
         // check no duplicate database name in file system
+        // directories differing only in case collide on case-insensitive file systems
         let database_dir = ctx.dbms_root_dir.join(name);
         if database_dir.exists() {
-            return Err(DbError::DuplicateDatabase(format!("The database directory '{}' already exists.", name)));
+            return Err(DbError::DuplicateDatabase(format!(
+                "The database directory '{}' already exists but is not in the catalog. Remove it to recreate the database.", name)));
+        }
+        if let Ok(entries) = fs::read_dir(&ctx.dbms_root_dir) {
+            let upper = name.to_uppercase();
+            for entry in entries.flatten() {
+                let dir_name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() && dir_name.to_uppercase() == upper {
+                    return Err(DbError::DuplicateDatabase(
+                        format!("The database directory '{}' already exists.", dir_name)));
+                }
+            }
         }
 
         Ok(BoundStmt::CreateDatabase { name: String::from(name) })
@@ -7,9 +7,10 @@ use crate::types::{DbError, DbResult};
 impl Analyzer {
 
     /// analyze WHERE condition in SQL statement
+    /// rows are selected only where the condition is TRUE, not FALSE or NULL
     pub fn analyze_where_clause(&self, selection: &Expression, schema: &TableSchema) -> DbResult<BoundExprNode> {
         let bound = self.analyze_expression(selection, schema)?;
-        if bound.expr_type != ExprType::Bool {
+        if !matches!(bound.expr_type, ExprType::Bool | ExprType::Null) {
             return Err(DbError::TypeMismatch("WHERE clause must evaluate to a boolean expression".to_string()));
         }
         Ok(bound)
@@ -24,6 +25,12 @@ impl Analyzer {
                 self.analyze_unary(op, expr, &schema),
             Expression::Binary { op, lhs, rhs } => 
                 self.analyze_binary(op, lhs, rhs, schema),
+            Expression::In { target, list } =>
+                self.analyze_in(target, list, schema),
+            Expression::Parameter(index) =>
+                Err(DbError::ParameterError(format!("Parameter {} is not bound", index + 1))),
+            Expression::Aggregate { func, .. } =>
+                Err(DbError::ExpressionNotFound(format!("{:?} is only allowed as a select list item", func))),
         }
     }
 
@@ -32,17 +39,38 @@ impl Analyzer {
             Literal::Int(_) => Ok(BoundExprNode{ expr_type: ExprType::Int, expr: BoundExpr::Literal(lit.clone())}),
             Literal::String(_) => Ok(BoundExprNode{ expr_type: ExprType::Char, expr: BoundExpr::Literal(lit.clone())}),
             Literal::Bool(_) => Ok(BoundExprNode{ expr_type: ExprType::Bool, expr: BoundExpr::Literal(lit.clone())}),
+            Literal::Null => Ok(BoundExprNode{ expr_type: ExprType::Null, expr: BoundExpr::Literal(lit.clone())}),
         }
     }
 
     fn analyze_identifier(&self, name: &String, schema: &TableSchema) -> DbResult<BoundExprNode> {
         let column_id = *schema.column_index.get(name)
-            .ok_or_else(|| DbError::ColumnNotFound(format!("Unknown column '{}'", name)))?;
+            .ok_or_else(|| schema.column_not_found(name))?;
         let column_def = &schema.columns[column_id];
         let expr_type = self.parse_data_type(&column_def.data_type);
         Ok(BoundExprNode{ expr_type, expr: BoundExpr::Column(column_id) })
     }
 
+    /// every list item must have the type of target, or be NULL
+    fn analyze_in(&self, target: &Expression, list: &[Expression], schema: &TableSchema) -> DbResult<BoundExprNode> {
+        let target = self.analyze_expression(target, schema)?;
+        let mut items = Vec::with_capacity(list.len());
+        for item in list {
+            let item = self.analyze_expression(item, schema)?;
+            if item.expr_type.unify(&target.expr_type).is_none() {
+                return Err(DbError::TypeMismatch(format!(
+                    "Mismatched type in IN list, target '{:?}' item '{:?}'",
+                    target.expr_type, item.expr_type
+                )));
+            }
+            items.push(item.expr);
+        }
+        Ok(BoundExprNode {
+            expr_type: ExprType::Bool,
+            expr: BoundExpr::In(Box::new(target.expr), items),
+        })
+    }
+
     fn analyze_binary(
         &self,
         op: &BinaryOp,
@@ -60,11 +88,18 @@ impl Analyzer {
             | BinaryOp::Gte
             | BinaryOp::Lt
             | BinaryOp::Lte => {
-                if left.expr_type != right.expr_type {
+                let Some(operand_type) = left.expr_type.unify(&right.expr_type) else {
                     return Err(DbError::TypeMismatch(format!(
                         "Mismatched type, LHS '{:?}' RHS '{:?}'",
                         left.expr_type, right.expr_type
                     )));
+                };
+                // only equality is defined on CHAR and BOOL, ordering requires INT
+                if !matches!(op, BinaryOp::Eq | BinaryOp::NotEq) && !matches!(operand_type, ExprType::Int | ExprType::Null) {
+                    return Err(DbError::TypeMismatch(format!(
+                        "Ordering comparison requires INT operands, found '{:?}'",
+                        operand_type
+                    )));
                 }
                 let expr = match op {
                     BinaryOp::Eq =>
@@ -88,7 +123,8 @@ impl Analyzer {
             }
             // logical operators
             BinaryOp::And | BinaryOp::Or => {
-                if left.expr_type != ExprType::Bool || right.expr_type != ExprType::Bool {
+                let is_bool = |t: &ExprType| matches!(t, ExprType::Bool | ExprType::Null);
+                if !is_bool(&left.expr_type) || !is_bool(&right.expr_type) {
                     return Err(DbError::TypeMismatch(
                         "Logical operators require boolean operands".to_string()
                     ));
@@ -110,12 +146,12 @@ impl Analyzer {
             | BinaryOp::Sub
             | BinaryOp::Mul
             | BinaryOp::Div => {
-                if left.expr_type != right.expr_type {
+                let Some(result_type) = left.expr_type.unify(&right.expr_type) else {
                     return Err(DbError::TypeMismatch(format!(
                         "Arithmetic type mismatch LHS '{:?}' RHS '{:?}'",
                         left.expr_type, right.expr_type
                     )));
-                }
+                };
                 let expr = match op {
                     BinaryOp::Add =>
                         BoundExpr::Add(Box::new(left.expr), Box::new(right.expr)),
@@ -128,7 +164,7 @@ impl Analyzer {
                     _ => unreachable!(),
                 };
                 Ok(BoundExprNode {
-                    expr_type: left.expr_type,
+                    expr_type: result_type,
                     expr,
                 })
             }
@@ -140,7 +176,7 @@ impl Analyzer {
         match op {
             UnaryOp::Neg => {
                 // negative sign requires numerical type
-                if node.expr_type == ExprType::Int {
+                if matches!(node.expr_type, ExprType::Int | ExprType::Null) {
                     Ok(BoundExprNode{ expr_type: node.expr_type, expr: BoundExpr::Minus(Box::new(node.expr))})
                 } else {
                     Err(DbError::TypeMismatch(
@@ -149,13 +185,27 @@ impl Analyzer {
             }
             UnaryOp::Not => {
                 // NOT requires boolean type
-                if node.expr_type == ExprType::Bool {
+                if matches!(node.expr_type, ExprType::Bool | ExprType::Null) {
                     Ok(BoundExprNode{ expr_type: node.expr_type, expr: BoundExpr::Not(Box::new(node.expr))})
                 } else {
                     Err(DbError::TypeMismatch(
                         format!("Expect bool type after 'NOT', got '{:?}'", expr)))
                 }
             }
+            UnaryOp::IsTrue | UnaryOp::IsFalse => {
+                // IS TRUE / IS FALSE requires boolean type
+                if !matches!(node.expr_type, ExprType::Bool | ExprType::Null) {
+                    return Err(DbError::TypeMismatch(
+                        format!("Expect bool type before 'IS', got '{:?}'", expr)));
+                }
+                let inner = Box::new(node.expr);
+                let expr = if *op == UnaryOp::IsTrue { BoundExpr::IsTrue(inner) } else { BoundExpr::IsFalse(inner) };
+                Ok(BoundExprNode{ expr_type: ExprType::Bool, expr })
+            }
+            UnaryOp::IsNull => {
+                // any value can be tested for NULL
+                Ok(BoundExprNode{ expr_type: ExprType::Bool, expr: BoundExpr::IsNull(Box::new(node.expr)) })
+            }
         }
     }
 
@@ -2,12 +2,13 @@ mod database_analyzer;
 mod table_ddl_analyzer;
 mod expression_analyzer;
 mod select_analyzer;
+mod bound_validator;
 
 use std::sync::{Arc, RwLock};
 use crate::compiler::ast::Statement;
 use crate::compiler::bounded_ast::BoundStmt;
 use crate::interpreter::execution_context::ExecutionContext;
-use crate::types::DbResult;
+use crate::types::{DbError, DbResult};
 
 pub struct Analyzer {
     context: Arc<RwLock<ExecutionContext>>,
@@ -19,7 +20,7 @@ impl Analyzer {
     }
 
     pub fn analyze(&mut self, stmt: Statement) -> DbResult<BoundStmt> {
-        match &stmt {
+        let bound = match &stmt {
             Statement::CreateDatabase { name } => {
                 self.analyze_create_database(name)
             }
@@ -32,24 +33,56 @@ impl Analyzer {
             Statement::DisconnectDatabase {} => {
                 self.analyze_disconnect_database()
             }
-            Statement::CreateTable { name, columns } => {
-                self.analyze_create_table(name, columns)
+            Statement::CreateTable { name, columns, foreign_keys, options } => {
+                self.analyze_create_table(name, columns, foreign_keys, options)
             }
             Statement::DropTable { name } => {
                 self.analyze_drop_table(name)
             }
-            Statement::Insert { table, rows } => {
-                self.analyze_insert(table, rows)
+            Statement::CreateIndex { name, table, column } => {
+                self.analyze_create_index(name, table, column)
+            }
+            Statement::DropIndex { name, table } => {
+                self.analyze_drop_index(name, table)
+            }
+            Statement::Insert { table, columns, rows, on_conflict } => {
+                self.analyze_insert(table, columns, rows, on_conflict)
             }
             Statement::Update { table, assignments, selection } => {
                 self.analyze_update(table, assignments, selection)
             }
-            Statement::Select { columns, table, selection } => {
-                self.analyze_select(table, columns, selection)
+            Statement::Select { distinct, columns, aliases, table, selection, group_by, order_by } => {
+                self.analyze_select(table, *distinct, columns, aliases, selection, group_by, order_by)
+            }
+            Statement::SelectConstant { columns, aliases } => {
+                self.analyze_select_constant(columns, aliases)
             }
             Statement::Delete { table, selection } => {
                 self.analyze_delete(table, selection)
             }
-        }
+            Statement::Explain { analyze, stmt } => {
+                Ok(BoundStmt::Explain { analyze: *analyze, stmt: Box::new(self.analyze(*stmt.clone())?) })
+            }
+            Statement::Purge { table } => {
+                self.analyze_purge(table)
+            }
+            Statement::DumpPage { page_id } => Ok(BoundStmt::DumpPage { page_id: *page_id }),
+            Statement::ShowTableStatus => Ok(BoundStmt::ShowTableStatus),
+            Statement::ShowRecords { table, selection } => {
+                self.analyze_show_records(table, selection)
+            }
+            Statement::Describe { table } => {
+                self.analyze_table_metadata(table).map(|table| BoundStmt::Describe { table })
+            }
+            Statement::ShowCreateTable { table } => {
+                self.analyze_table_metadata(table).map(|table| BoundStmt::ShowCreateTable { table })
+            }
+            Statement::Set { .. } => {
+                Err(DbError::InternalError("SET is applied by the interpreter".to_string()))
+            }
+        }?;
+
+        self.validate_bound_stmt(&bound)?;
+        Ok(bound)
     }
 }
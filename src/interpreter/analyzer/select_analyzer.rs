@@ -1,15 +1,39 @@
-use crate::compiler::ast::Expression;
-use crate::compiler::bounded_ast::BoundStmt;
+use crate::compiler::ast::{AggregateFunc, ExprType, Expression, Literal, OrderByItem};
+use crate::compiler::bounded_ast::{BoundExpr, BoundOrderBy, BoundStmt};
 use crate::interpreter::analyzer::Analyzer;
+use crate::interpreter::catalog_table::TableSchema;
 use crate::types::{DbError, DbResult};
 
 impl Analyzer {
 
+    /// Bind the select list of a SELECT without FROM against a schema without columns
+    pub fn analyze_select_constant(&mut self, columns: &[Expression], aliases: &[Option<String>]) -> DbResult<BoundStmt> {
+        let schema = TableSchema::default();
+        let mut bound_columns = Vec::with_capacity(columns.len());
+        for c in columns {
+            if matches!(c, Expression::Aggregate { .. }) {
+                return Err(DbError::ExpressionNotFound("Aggregates need a FROM clause".to_string()));
+            }
+            let bound = self.analyze_expression(c, &schema).map_err(|e| match e {
+                DbError::ColumnNotFound(_) => DbError::ColumnNotFound("Columns cannot be referenced without a FROM clause".to_string()),
+                e => e,
+            })?;
+            bound_columns.push(bound.expr);
+        }
+
+        Ok(BoundStmt::SelectConstant { columns: bound_columns, names: output_names(columns, aliases) })
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn analyze_select(
         &mut self,
         table: &str,
+        distinct: bool,
         column: &Vec<Expression>,
-        selection: &Option<Expression>
+        aliases: &[Option<String>],
+        selection: &Option<Expression>,
+        group_by: &[String],
+        order_by: &[OrderByItem],
     ) -> DbResult<BoundStmt> {
         let ctx = self.context.read().unwrap();
 
@@ -18,10 +42,44 @@ impl Analyzer {
         let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table)
             .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
 
-        // resolve column identifiers to column id
+        // resolve column identifiers to column id, SELECT * projects every column in declaration order
         let mut columns = Vec::new();
+        let names = if column.is_empty() {
+            columns.extend((0..schema.columns.len()).map(BoundExpr::Column));
+            schema.columns.iter().map(|c| c.name.clone()).collect()
+        } else {
+            output_names(column, aliases)
+        };
         for c in column {
-            columns.push(self.analyze_expression(c, schema)?.expr);
+            columns.push(match c {
+                Expression::Aggregate { func, arg } =>
+                    BoundExpr::Aggregate(*func, self.analyze_aggregate_argument(*func, arg, schema)?),
+                _ => self.analyze_expression(c, schema)?.expr,
+            });
+        }
+
+        let mut bound_group_by = Vec::new();
+        for name in group_by {
+            let column_id = *schema.column_index.get(name)
+                .ok_or_else(|| schema.column_not_found(name))?;
+            bound_group_by.push(column_id);
+        }
+
+        // without GROUP BY, an aggregate query returns a single row, leaving no place for row values.
+        // with GROUP BY, every row value must be constant within a group
+        let aggregates = columns.iter().filter(|c| matches!(c, BoundExpr::Aggregate(..))).count();
+        if bound_group_by.is_empty() && aggregates > 0 && aggregates < columns.len() {
+            return Err(DbError::ColumnMismatch(
+                "Aggregates cannot be mixed with non-aggregate select items".to_string()
+            ));
+        }
+        for c in &columns {
+            if !matches!(c, BoundExpr::Aggregate(..)) && !bound_group_by.is_empty()
+                && !references_only(c, &bound_group_by) {
+                return Err(DbError::ColumnMismatch(format!(
+                    "Select item {:?} must be an aggregate or a GROUP BY column", c
+                )));
+            }
         }
 
         // analyze condition expression
@@ -32,10 +90,128 @@ impl Analyzer {
             None => None,
         };
 
+        let mut bound_order_by = Vec::new();
+        for item in order_by {
+            bound_order_by.push(BoundOrderBy {
+                expr: self.analyze_order_by_item(&item.expr, &columns, aliases, schema)?,
+                descending: item.descending,
+            });
+        }
+        // duplicates are removed from the projected rows, which must determine the sort order
+        if let Some(item) = bound_order_by.iter().find(|item| distinct && !columns.contains(&item.expr)) {
+            return Err(DbError::ColumnMismatch(format!(
+                "ORDER BY key {:?} must appear in the select list of SELECT DISTINCT", item.expr
+            )));
+        }
+        // groups are sorted by values shared by all their rows
+        if let Some(item) = bound_order_by.iter()
+            .find(|item| !bound_group_by.is_empty() && !references_only(&item.expr, &bound_group_by)) {
+            return Err(DbError::ColumnMismatch(format!(
+                "ORDER BY key {:?} must only reference GROUP BY columns", item.expr
+            )));
+        }
+
         Ok(BoundStmt::Select {
             table: String::from(table),
+            generation: schema.generation,
+            distinct,
             columns,
-            selection: bound_selection
+            names,
+            selection: bound_selection,
+            group_by: bound_group_by,
+            order_by: bound_order_by,
         })
     }
-}
\ No newline at end of file
+
+    /// Aggregates take a column of the table, COUNT also takes *.
+    /// SUM and AVG require an INT column, MIN and MAX accept any type
+    fn analyze_aggregate_argument(
+        &self,
+        func: AggregateFunc,
+        arg: &Option<Box<Expression>>,
+        schema: &TableSchema,
+    ) -> DbResult<Option<Box<BoundExpr>>> {
+        let column = match arg.as_deref() {
+            None => return Ok(None),
+            Some(expr @ Expression::Identifier(_)) => self.analyze_expression(expr, schema)?,
+            Some(expr) => return Err(DbError::TypeMismatch(format!("{:?} expects a column name, got {:?}", func, expr))),
+        };
+
+        if matches!(func, AggregateFunc::Sum | AggregateFunc::Avg) && column.expr_type != ExprType::Int {
+            return Err(DbError::TypeMismatch(format!("{:?} requires an INT column, got {:?}", func, column.expr_type)));
+        }
+        Ok(Some(Box::new(column.expr)))
+    }
+
+    /// Resolve ORDER BY reference, in order:
+    /// 1. integer literal: 1-based position in projection list
+    /// 2. identifier matching a projection alias (alias wins over a column of the same name)
+    /// 3. any expression over table columns
+    fn analyze_order_by_item(
+        &self,
+        expr: &Expression,
+        columns: &[BoundExpr],
+        aliases: &[Option<String>],
+        schema: &TableSchema,
+    ) -> DbResult<BoundExpr> {
+        match expr {
+            Expression::Literal(Literal::Int(position)) => {
+                if *position < 1 || *position as usize > columns.len() {
+                    return Err(DbError::ColumnNotFound(format!(
+                        "ORDER BY position {} is not in select list", position
+                    )));
+                }
+                Ok(columns[*position as usize - 1].clone())
+            }
+            Expression::Identifier(name) => {
+                let mut matches = aliases.iter().enumerate()
+                    .filter(|(_, alias)| alias.as_deref() == Some(name.as_str()));
+
+                match (matches.next(), matches.next()) {
+                    (Some(_), Some(_)) => Err(DbError::ColumnMismatch(format!(
+                        "ORDER BY alias '{}' is ambiguous", name
+                    ))),
+                    (Some((i, _)), None) => Ok(columns[i].clone()),
+                    (None, _) => Ok(self.analyze_expression(expr, schema)?.expr),
+                }
+            }
+            _ => Ok(self.analyze_expression(expr, schema)?.expr),
+        }
+    }
+}
+
+/// Whether an expression only reads the given columns, so it has one value per group
+fn references_only(expr: &BoundExpr, column_ids: &[usize]) -> bool {
+    match expr {
+        BoundExpr::Column(id) => column_ids.contains(id),
+        BoundExpr::Literal(_) => true,
+        BoundExpr::Aggregate(..) => false,
+        BoundExpr::Minus(e) | BoundExpr::Not(e) | BoundExpr::IsTrue(e) | BoundExpr::IsFalse(e) | BoundExpr::IsNull(e) =>
+            references_only(e, column_ids),
+        BoundExpr::In(target, list) =>
+            references_only(target, column_ids) && list.iter().all(|e| references_only(e, column_ids)),
+        BoundExpr::Equals(l, r) | BoundExpr::Gt(l, r) | BoundExpr::Gte(l, r) | BoundExpr::Lt(l, r)
+        | BoundExpr::Lte(l, r) | BoundExpr::NotEquals(l, r) | BoundExpr::And(l, r) | BoundExpr::Or(l, r)
+        | BoundExpr::Add(l, r) | BoundExpr::Sub(l, r) | BoundExpr::Mul(l, r) | BoundExpr::Div(l, r) =>
+            references_only(l, column_ids) && references_only(r, column_ids),
+    }
+}
+
+/// Name of each select item in query results: its alias, the name of a column,
+/// the call of an aggregate such as COUNT(*), or ?column? for any other expression
+fn output_names(columns: &[Expression], aliases: &[Option<String>]) -> Vec<String> {
+    columns.iter().enumerate()
+        .map(|(i, column)| match (aliases.get(i).cloned().flatten(), column) {
+            (Some(alias), _) => alias,
+            (None, Expression::Identifier(name)) => name.clone(),
+            (None, Expression::Aggregate { func, arg }) => {
+                let arg = match arg.as_deref() {
+                    Some(Expression::Identifier(name)) => name.as_str(),
+                    _ => "*",
+                };
+                format!("{}({})", format!("{:?}", func).to_uppercase(), arg)
+            }
+            _ => "?column?".to_string(),
+        })
+        .collect()
+}
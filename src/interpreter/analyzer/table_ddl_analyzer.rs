@@ -1,18 +1,26 @@
 use std::collections::HashSet;
-use crate::compiler::ast::{Assignment, ColumnDef, Expression};
-use crate::compiler::bounded_ast::{BoundAssignment, BoundStmt};
+use crate::compiler::ast::{Assignment, ColumnDef, ConflictPolicy, DataType, ExprType, Expression, ForeignKeyDef, Literal, ReferentialAction, TableOption};
+use crate::compiler::bounded_ast::{BoundAssignment, BoundConflictPolicy, BoundExpr, BoundExprNode, BoundStmt};
 use crate::interpreter::analyzer::Analyzer;
-use crate::types::{ColumnId, DbError, DbResult};
+use crate::interpreter::catalog_table::{ForeignKey, TableOptions, TableSchema};
+use crate::types::{ColumnId, DbError, DbResult, MIN_FILLFACTOR};
 
 impl Analyzer {
 
-    pub fn analyze_create_table(&mut self, name: &str, columns: &Vec<ColumnDef>) -> DbResult<BoundStmt> {
+    pub fn analyze_create_table(
+        &mut self,
+        name: &str,
+        columns: &Vec<ColumnDef>,
+        foreign_keys: &[ForeignKeyDef],
+        options: &Vec<TableOption>
+    ) -> DbResult<BoundStmt> {
         let ctx = self.context.read().unwrap();
 
         // check there is no duplicate table name in current database
+        // names differing only in case collide, as they can't be told apart in queries
         let database = ctx.current_db.as_ref().unwrap();
-        if ctx.catalogs.get(database).unwrap().get_table_schema(name).is_some() {
-            return Err(DbError::DuplicateTable(format!("The table '{}' already exists", name)));
+        if let Some(existing) = ctx.catalogs.get(database).unwrap().find_table_ignore_case(name) {
+            return Err(DbError::DuplicateTable(format!("The table '{}' already exists", existing)));
         }
 
         // check for duplicate column names
@@ -25,7 +33,137 @@ impl Analyzer {
             }
         }
 
-        Ok(BoundStmt::CreateTable { name: String::from(name), columns: columns.clone() })
+        if columns.iter().filter(|col| col.primary_key).count() > 1 {
+            return Err(DbError::ConstraintViolation(format!("Table '{}' has more than one PRIMARY KEY column", name)));
+        }
+
+        // each foreign key column references a column of the same type in an existing table
+        let mut bound_foreign_keys: Vec<ForeignKey> = Vec::new();
+        for fk in foreign_keys {
+            let column = columns.iter().position(|col| col.name == fk.column)
+                .ok_or_else(|| DbError::ColumnNotFound(format!("Foreign key column '{}' is not a column of '{}'", fk.column, name)))?;
+            if bound_foreign_keys.iter().any(|bound| bound.column == column) {
+                return Err(DbError::DuplicateColumn(format!("Column '{}' has more than one foreign key", fk.column)));
+            }
+            if fk.ref_table == name {
+                return Err(DbError::ConstraintViolation(format!("Table '{}' cannot reference itself", name)));
+            }
+
+            let ref_schema = ctx.catalogs.get(database).unwrap().get_table_schema(&fk.ref_table)
+                .ok_or_else(|| DbError::TableNotFound(format!("Referenced table '{}' does not exist", fk.ref_table)))?;
+            let ref_column = *ref_schema.column_index.get(&fk.ref_column)
+                .ok_or_else(|| ref_schema.column_not_found(&fk.ref_column))?;
+            let (data_type, ref_data_type) = (&columns[column].data_type, &ref_schema.columns[ref_column].data_type);
+            if data_type.expr_type() != ref_data_type.expr_type() {
+                return Err(DbError::TypeMismatch(format!(
+                    "Foreign key column '{}' of type {} cannot reference '{}.{}' of type {}",
+                    fk.column, data_type, ref_schema.name, fk.ref_column, ref_data_type
+                )));
+            }
+            if fk.on_delete == ReferentialAction::SetNull && columns[column].not_null {
+                return Err(DbError::ConstraintViolation(format!(
+                    "Foreign key column '{}' is NOT NULL and cannot be set to NULL on delete", fk.column
+                )));
+            }
+            bound_foreign_keys.push(ForeignKey {
+                column,
+                ref_table: ref_schema.name.clone(),
+                ref_column: fk.ref_column.clone(),
+                on_delete: fk.on_delete,
+            });
+        }
+
+        let options = self.analyze_table_options(options)?;
+
+        Ok(BoundStmt::CreateTable {
+            name: String::from(name),
+            columns: columns.clone(),
+            foreign_keys: bound_foreign_keys,
+            options,
+        })
+    }
+
+    /// Resolve the WITH clause of CREATE TABLE into table options
+    fn analyze_table_options(&self, options: &Vec<TableOption>) -> DbResult<TableOptions> {
+        let mut table_options = TableOptions::default();
+        let mut existing = HashSet::new();
+
+        for option in options {
+            // check for duplicate options
+            if !existing.insert(&option.name) {
+                return Err(DbError::InvalidTableOption(format!("Duplicate table option '{}'", option.name)));
+            }
+
+            match (option.name.as_str(), &option.value) {
+                ("FILLFACTOR", Literal::Int(v)) => {
+                    if *v < MIN_FILLFACTOR as i32 || *v > 100 {
+                        return Err(DbError::InvalidTableOption(format!(
+                            "FILLFACTOR must be between {} and 100, got {}", MIN_FILLFACTOR, v
+                        )));
+                    }
+                    table_options.fillfactor = *v as u8;
+                }
+                ("FILLFACTOR", v) => {
+                    return Err(DbError::InvalidTableOption(format!("FILLFACTOR expects an integer, got {}", v)));
+                }
+                ("SOFT_DELETE", Literal::Bool(v)) => {
+                    table_options.soft_delete = *v;
+                }
+                ("SOFT_DELETE", v) => {
+                    return Err(DbError::InvalidTableOption(format!("SOFT_DELETE expects a boolean, got {}", v)));
+                }
+                ("RETENTION", Literal::Int(v)) => {
+                    if *v < 0 {
+                        return Err(DbError::InvalidTableOption(format!("RETENTION must not be negative, got {}", v)));
+                    }
+                    table_options.retention = *v as u32;
+                }
+                ("RETENTION", v) => {
+                    return Err(DbError::InvalidTableOption(format!("RETENTION expects an integer, got {}", v)));
+                }
+                ("MAX_PAGES", Literal::Int(v)) => {
+                    if *v < 1 {
+                        return Err(DbError::InvalidTableOption(format!("MAX_PAGES must be at least 1, got {}", v)));
+                    }
+                    table_options.max_pages = Some(*v as u32);
+                }
+                ("MAX_PAGES", v) => {
+                    return Err(DbError::InvalidTableOption(format!("MAX_PAGES expects an integer, got {}", v)));
+                }
+                ("PREALLOCATE", Literal::Int(v)) => {
+                    if *v < 0 {
+                        return Err(DbError::InvalidTableOption(format!("PREALLOCATE must not be negative, got {}", v)));
+                    }
+                    table_options.preallocate = *v as u32;
+                }
+                ("PREALLOCATE", v) => {
+                    return Err(DbError::InvalidTableOption(format!("PREALLOCATE expects an integer, got {}", v)));
+                }
+                ("BULK_INDEX", Literal::Int(v)) => {
+                    if *v < 0 {
+                        return Err(DbError::InvalidTableOption(format!("BULK_INDEX must not be negative, got {}", v)));
+                    }
+                    table_options.bulk_index = *v as u32;
+                }
+                ("BULK_INDEX", v) => {
+                    return Err(DbError::InvalidTableOption(format!("BULK_INDEX expects an integer, got {}", v)));
+                }
+                _ => {
+                    return Err(DbError::InvalidTableOption(format!("Unknown table option '{}'", option.name)));
+                }
+            }
+        }
+
+        // the preallocated pages count towards the page quota
+        if let Some(max_pages) = table_options.max_pages {
+            if table_options.preallocate > max_pages {
+                return Err(DbError::InvalidTableOption(format!(
+                    "PREALLOCATE ({}) exceeds MAX_PAGES ({})", table_options.preallocate, max_pages
+                )));
+            }
+        }
+
+        Ok(table_options)
     }
 
     pub fn analyze_drop_table(&mut self, name: &str) -> DbResult<BoundStmt> {
@@ -33,14 +171,74 @@ impl Analyzer {
 
         // check the table exists in database
         let database = ctx.current_db.as_ref().unwrap();
-        if ctx.catalogs.get(database).unwrap().get_table_schema(name).is_none() {
+        let catalog = ctx.catalogs.get(database).unwrap();
+        if catalog.get_table_schema(name).is_none() {
             return Err(DbError::TableNotFound(format!("The table '{}' does not exists", name)));
         }
 
+        // dropping a referenced table would leave the referencing rows dangling
+        if let Some(child) = catalog.referencing_tables(name).first() {
+            return Err(DbError::ConstraintViolation(format!(
+                "Table '{}' is referenced by a foreign key of '{}'", name, child.name
+            )));
+        }
+
         Ok(BoundStmt::DropTable { name: String::from(name) })
     }
 
-    pub fn analyze_insert(&mut self, table: &str, rows: &Vec<Vec<Expression>>) -> DbResult<BoundStmt> {
+    pub fn analyze_create_index(&mut self, name: &str, table: &str, column: &str) -> DbResult<BoundStmt> {
+        let ctx = self.context.read().unwrap();
+
+        // check the index name is not taken by an index of any table in current database
+        let database = ctx.current_db.as_ref().unwrap();
+        let catalog = ctx.catalogs.get(database).unwrap();
+        if let Some((schema, index)) = catalog.find_index(name) {
+            return Err(DbError::DuplicateIndex(format!(
+                "Index '{}' already exists on table '{}'", index.name, schema.name
+            )));
+        }
+
+        // check the indexed column exists and holds INT keys
+        let schema = catalog.get_table_schema(table)
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
+        let column_id = *schema.column_index.get(column)
+            .ok_or_else(|| schema.column_not_found(column))?;
+        if schema.columns[column_id].data_type != DataType::Int {
+            return Err(DbError::TypeMismatch(format!(
+                "Cannot index column '{}' of type {}, only INT columns can be indexed",
+                schema.columns[column_id].name, schema.columns[column_id].data_type
+            )));
+        }
+
+        Ok(BoundStmt::CreateIndex {
+            name: name.to_string(),
+            table: table.to_string(),
+            generation: schema.generation,
+            column: column_id,
+        })
+    }
+
+    pub fn analyze_drop_index(&mut self, name: &str, table: &str) -> DbResult<BoundStmt> {
+        let ctx = self.context.read().unwrap();
+
+        // check the index exists on the table
+        let database = ctx.current_db.as_ref().unwrap();
+        let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table)
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
+        if schema.get_index(name).is_none() {
+            return Err(DbError::IndexNotFound(format!("Index '{}' does not exist on table '{}'", name, table)));
+        }
+
+        Ok(BoundStmt::DropIndex { name: name.to_string(), table: table.to_string() })
+    }
+
+    pub fn analyze_insert(
+        &mut self,
+        table: &str,
+        columns: &[String],
+        rows: &Vec<Vec<Expression>>,
+        on_conflict: &ConflictPolicy,
+    ) -> DbResult<BoundStmt> {
         let ctx = self.context.read().unwrap();
 
         // check the table exists in database
@@ -48,24 +246,59 @@ impl Analyzer {
         let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table)
             .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
 
+        // position of each column's value in a row, None for columns omitted from the column list
+        let positions: Vec<Option<usize>> = if columns.is_empty() {
+            (0..schema.columns.len()).map(Some).collect()
+        } else {
+            let mut positions = vec![None; schema.columns.len()];
+            for (position, name) in columns.iter().enumerate() {
+                let column_id = *schema.column_index.get(name)
+                    .ok_or_else(|| schema.column_not_found(name))?;
+                if positions[column_id].replace(position).is_some() {
+                    return Err(DbError::DuplicateColumn(format!("Column '{}' is listed more than once", name)));
+                }
+            }
+            positions
+        };
+        let expected = if columns.is_empty() { schema.columns.len() } else { columns.len() };
+
         // check the rows match table schema
         let mut bounded_rows = vec!();
         for (row_index, row) in rows.iter().enumerate() {
             // check number of columns
-            if row.len() != schema.columns.len() {
+            if row.len() != expected {
                 return Err(DbError::ColumnMismatch(
                     format!("Row {} has {} values, but table '{}' expects {} columns",
-                            row_index + 1, row.len(), table, schema.columns.len()
+                            row_index + 1, row.len(), table, expected
                 )));
             }
 
-            // check record data type
+            // check record data type, reordering values into schema column order
             let mut bounded_row = vec!();
-            for (col_index, (expr, column)) in row.iter()
+            for (col_index, (position, column)) in positions.iter()
                 .zip(schema.columns.iter())
                 .enumerate() {
-                let bound_expr = self.analyze_expression(expr, schema)?;
+                let Some(position) = position else {
+                    bounded_row.push(BoundExprNode {
+                        expr_type: column.data_type.expr_type(),
+                        expr: BoundExpr::Literal(column.data_type.default_value()),
+                    });
+                    continue;
+                };
+                let bound_expr = self.analyze_expression(&row[*position], schema)?;
+                if column.not_null && bound_expr.expr_type == ExprType::Null {
+                    return Err(DbError::ConstraintViolation(format!(
+                        "NULL at Row {}, Column {} violates NOT NULL column '{}'", row_index + 1, col_index + 1, column.name
+                    )));
+                }
                 if column.data_type.check_type(&bound_expr.expr_type) {
+                    // reject a literal that is too long before any row is written, with the same
+                    // rule serialization enforces on computed values
+                    if let BoundExpr::Literal(value) = &bound_expr.expr {
+                        column.data_type.check_length(value).map_err(|e| DbError::TypeMismatch(format!(
+                            "{} at Row {}, Column {} ('{}')", e, row_index + 1, col_index + 1, column.name
+                        )))?;
+                    }
                     bounded_row.push(bound_expr);
                 } else {
                     return Err(DbError::TypeMismatch(
@@ -78,13 +311,30 @@ impl Analyzer {
             bounded_rows.push(bounded_row);
         }
 
-        Ok(BoundStmt::Insert { table: String::from(table), rows: bounded_rows })
+        let on_conflict = match on_conflict {
+            ConflictPolicy::Abort => BoundConflictPolicy::Abort,
+            ConflictPolicy::Replace => BoundConflictPolicy::Replace,
+            ConflictPolicy::Ignore => BoundConflictPolicy::Ignore,
+            ConflictPolicy::Update { column, assignments } => {
+                let column_id = *schema.column_index.get(column)
+                    .ok_or_else(|| schema.column_not_found(column))?;
+                // only a value no two rows share identifies the row to update
+                if schema.primary_key() != Some(column_id) && !schema.indexes.iter().any(|index| index.column == column_id) {
+                    return Err(DbError::ConstraintViolation(format!(
+                        "ON CONFLICT column '{}' of table '{}' is neither a primary key nor indexed", column, table
+                    )));
+                }
+                BoundConflictPolicy::Update { column: column_id, assignments: self.analyze_assignments(assignments, schema)? }
+            }
+        };
+
+        Ok(BoundStmt::Insert { table: String::from(table), generation: schema.generation, rows: bounded_rows, on_conflict })
     }
 
     pub fn analyze_update(
         &mut self,
         table: &str,
-        assignments: &Vec<Assignment>,
+        assignments: &[Assignment],
         selection: &Option<Expression>
     ) -> DbResult<BoundStmt> {
         let ctx = self.context.read().unwrap();
@@ -98,14 +348,34 @@ impl Analyzer {
         if assignments.is_empty() {
             return Err(DbError::ExpressionNotFound("UPDATE must specify at least one column assignment".to_string()));
         }
+        let bound_assignments = self.analyze_assignments(assignments, schema)?;
 
+        // bind WHERE clause
+        let bound_selection = match selection {
+            Some(expr) => {
+                Some(self.analyze_where_clause(expr, schema)?)
+            }
+            None => None,
+        };
+
+        Ok(BoundStmt::Update {
+            table: table.to_string(),
+            generation: schema.generation,
+            assignments: bound_assignments,
+            selection: bound_selection,
+        })
+    }
+
+    /// Bind the assignments of UPDATE or ON CONFLICT DO UPDATE, each to a distinct column
+    /// and with a value of the column type
+    fn analyze_assignments(&self, assignments: &[Assignment], schema: &TableSchema) -> DbResult<Vec<BoundAssignment>> {
         let mut existing = HashSet::<ColumnId>::new();
         let mut bound_assignments = Vec::with_capacity(assignments.len());
         for assignment in assignments {
             // resolve column name to column id
             let column_id = *schema.column_index
                 .get(&assignment.column)
-                .ok_or_else(|| DbError::ColumnNotFound(format!("Column '{}' does not exist in {}", assignment.column, table)))?;
+                .ok_or_else(|| schema.column_not_found(&assignment.column))?;
 
             // check no duplicate columns
             if !existing.insert(column_id) {
@@ -115,10 +385,19 @@ impl Analyzer {
             // check update expression and data type compatibility
             let upd_expr = self.analyze_expression(&assignment.value, schema)?;
             let column_def = &schema.columns[column_id];
+            if column_def.not_null && upd_expr.expr_type == ExprType::Null {
+                return Err(DbError::ConstraintViolation(format!(
+                    "Cannot set NOT NULL column '{}' to NULL", column_def.name
+                )));
+            }
             if !column_def.data_type.check_type(&upd_expr.expr_type) {
                 return Err(DbError::TypeMismatch(
                     format!("The expression evaluates to a different data type than column {:?}", column_def)));
             }
+            if let BoundExpr::Literal(value) = &upd_expr.expr {
+                column_def.data_type.check_length(value)
+                    .map_err(|e| DbError::TypeMismatch(format!("{} in column '{}'", e, column_def.name)))?;
+            }
 
             // bind assignment
             bound_assignments.push(BoundAssignment {
@@ -126,6 +405,20 @@ impl Analyzer {
                 value: upd_expr.expr,
             });
         }
+        Ok(bound_assignments)
+    }
+
+    pub fn analyze_delete(
+        &mut self,
+        table: &str,
+        selection: &Option<Expression>
+    ) -> DbResult<BoundStmt> {
+        let ctx = self.context.read().unwrap();
+
+        // check the table exists in database
+        let database = ctx.current_db.as_ref().unwrap();
+        let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table)
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
 
         // bind WHERE clause
         let bound_selection = match selection {
@@ -135,36 +428,59 @@ impl Analyzer {
             None => None,
         };
 
-        Ok(BoundStmt::Update {
+        Ok(BoundStmt::Delete {
             table: table.to_string(),
-            assignments: bound_assignments,
+            generation: schema.generation,
             selection: bound_selection,
         })
     }
 
-    pub fn analyze_delete(
+    pub fn analyze_show_records(
         &mut self,
         table: &str,
         selection: &Option<Expression>
     ) -> DbResult<BoundStmt> {
         let ctx = self.context.read().unwrap();
 
-        // check the table exists in database
         let database = ctx.current_db.as_ref().unwrap();
         let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table)
             .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
 
-        // bind WHERE clause
         let bound_selection = match selection {
-            Some(expr) => {
-                Some(self.analyze_where_clause(expr, schema)?)
-            }
+            Some(expr) => Some(self.analyze_where_clause(expr, schema)?),
             None => None,
         };
 
-        Ok(BoundStmt::Delete {
+        Ok(BoundStmt::ShowRecords {
             table: table.to_string(),
+            generation: schema.generation,
             selection: bound_selection,
         })
     }
+
+    /// Check the table read by DESCRIBE and SHOW CREATE TABLE exists, returning its name
+    pub fn analyze_table_metadata(&mut self, table: &str) -> DbResult<String> {
+        let ctx = self.context.read().unwrap();
+
+        let database = ctx.current_db.as_ref().unwrap();
+        if ctx.catalogs.get(database).unwrap().get_table_schema(table).is_none() {
+            return Err(DbError::TableNotFound(format!("Table '{}' does not exist", table)));
+        }
+        Ok(table.to_string())
+    }
+
+    pub fn analyze_purge(&mut self, table: &str) -> DbResult<BoundStmt> {
+        let ctx = self.context.read().unwrap();
+
+        // check the table exists in database and keeps tombstones
+        let database = ctx.current_db.as_ref()
+            .ok_or_else(|| DbError::DatabaseNotFound("A database connection does not exist".to_string()))?;
+        let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table)
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
+        if !schema.options.soft_delete {
+            return Err(DbError::InvalidTableOption(format!("Table '{}' does not use SOFT_DELETE", table)));
+        }
+
+        Ok(BoundStmt::Purge { table: table.to_string() })
+    }
 }
\ No newline at end of file
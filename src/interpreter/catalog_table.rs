@@ -2,15 +2,97 @@ use std::collections::HashMap;
 use std::{fs, io};
 use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
-use crate::compiler::ast::ColumnDef;
-use crate::types::{DbError, PageId};
+use crate::compiler::ast::{ColumnDef, DataType, Literal, Record, ReferentialAction};
+use crate::storage::page::data_page::PAYLOAD_SIZE;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::types::{ColumnId, DbError, DbResult, PageId, DEFAULT_FILLFACTOR, DEFAULT_RETENTION, MAX_SLOTS};
+use crate::{bitmap_get, bitmap_set};
+
+/// Table storage options, set by CREATE TABLE ... WITH (...)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableOptions {
+    // percentage of a data page filled by INSERT, the remaining space is kept for updates.
+    // also used as the fill factor of the B+ tree pages of the table's indexes
+    pub fillfactor: u8,
+    // DELETE marks rows with a tombstone instead of removing them, PURGE reclaims them later
+    pub soft_delete: bool,
+    // seconds a tombstoned row is kept before PURGE may reclaim it
+    pub retention: u32,
+    // maximum number of data pages of the table, INSERT fails instead of growing beyond it
+    pub max_pages: Option<u32>,
+    // data pages written to the data file when the table is created, filled by INSERT in order
+    pub preallocate: u32,
+    // rows added by one statement from which indexes are rebuilt in bulk instead of updated per row, 0 never
+    pub bulk_index: u32,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        Self { fillfactor: DEFAULT_FILLFACTOR, soft_delete: false, retention: DEFAULT_RETENTION, max_pages: None, preallocate: 0, bulk_index: 0 }
+    }
+}
+
+impl TableOptions {
+    /// Bytes of page payload INSERT leaves free under the fill factor
+    pub fn reserved_space(&self) -> usize {
+        PAYLOAD_SIZE * (100 - self.fillfactor as usize) / 100
+    }
+}
+
+/// Foreign key of a table, set by CREATE TABLE ... FOREIGN KEY (column) REFERENCES ref_table (ref_column).
+/// Every value of column must appear in ref_column of some row of ref_table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignKey {
+    pub column: ColumnId,
+    pub ref_table: String,
+    pub ref_column: String,
+    #[serde(default)]
+    pub on_delete: ReferentialAction,
+}
+
+/// Index of a table, set by CREATE INDEX name ON table (column).
+/// A B+ tree maps each value of an INT column to the row holding it, so indexed values are unique.
+/// NULL values are not indexed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableIndex {
+    pub name: String,
+    pub column: ColumnId,
+    // root page of the B+ tree, which moves when the root splits or collapses
+    pub root: PageId,
+}
+
+// record header of soft-delete tables: deletion time in seconds since UNIX epoch, 0 for live rows
+const TOMBSTONE_SIZE: usize = size_of::<u64>();
+
+/// Current time in seconds since UNIX epoch, as stored in tombstones
+pub fn current_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
 
 /// Table metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TableSchema {
     pub name: String,
     pub columns: Vec<ColumnDef>,
     pub first_page_id: PageId,
+    #[serde(default)]
+    pub options: TableOptions,
+    #[serde(default)]
+    pub foreign_keys: Vec<ForeignKey>,
+    // logical column id stored at each physical position of a record
+    // empty for catalogs written before physical layouts, whose records follow declaration order
+    #[serde(default)]
+    pub physical_order: Vec<usize>,
+    // records carry a bitmap of their NULL columns after the tombstone
+    // false for catalogs written before NULL support, whose records cannot hold NULL
+    #[serde(default)]
+    pub null_bitmap: bool,
+    #[serde(default)]
+    pub indexes: Vec<TableIndex>,
+    // changes whenever the table is created, rebuilt or has an index added or removed
+    #[serde(default)]
+    pub generation: u64,
     // derived fields are reconstructed during runtime
     #[serde(skip)]
     pub column_index: HashMap<String, usize>,
@@ -23,12 +105,230 @@ impl TableSchema {
             .enumerate()
             .map(|(i, col)| (col.name.to_uppercase(), i))
             .collect();
+        if self.physical_order.is_empty() {
+            self.physical_order = (0..self.columns.len()).collect();
+        }
     }
+
+    /// CREATE TABLE statement recreating the table, with options that differ from the defaults
+    pub fn create_table_sql(&self) -> String {
+        let columns: Vec<String> = self.columns.iter()
+            .map(|col| {
+                // PRIMARY KEY implies NOT NULL
+                let constraint = if col.primary_key { " PRIMARY KEY" } else if col.not_null { " NOT NULL" } else { "" };
+                let comment = match &col.comment {
+                    // string literals have no escapes, so quote with the quote the comment does not contain
+                    Some(comment) if comment.contains('\'') => format!(" COMMENT \"{}\"", comment),
+                    Some(comment) => format!(" COMMENT '{}'", comment),
+                    None => String::new(),
+                };
+                format!("{} {}{}{}", col.name, col.data_type, constraint, comment)
+            })
+            .collect();
+
+        let defaults = TableOptions::default();
+        let mut options = Vec::new();
+        if self.options.fillfactor != defaults.fillfactor {
+            options.push(format!("FILLFACTOR = {}", self.options.fillfactor));
+        }
+        if self.options.soft_delete != defaults.soft_delete {
+            options.push(format!("SOFT_DELETE = {}", Literal::Bool(self.options.soft_delete)));
+        }
+        if self.options.retention != defaults.retention {
+            options.push(format!("RETENTION = {}", self.options.retention));
+        }
+        if let Some(max_pages) = self.options.max_pages {
+            options.push(format!("MAX_PAGES = {}", max_pages));
+        }
+        if self.options.preallocate != defaults.preallocate {
+            options.push(format!("PREALLOCATE = {}", self.options.preallocate));
+        }
+        if self.options.bulk_index != defaults.bulk_index {
+            options.push(format!("BULK_INDEX = {}", self.options.bulk_index));
+        }
+
+        let mut columns = columns;
+        for fk in &self.foreign_keys {
+            let mut foreign_key = format!("FOREIGN KEY ({}) REFERENCES {} ({})", self.columns[fk.column].name, fk.ref_table, fk.ref_column);
+            if fk.on_delete != ReferentialAction::Restrict {
+                foreign_key.push_str(&format!(" ON DELETE {}", fk.on_delete));
+            }
+            columns.push(foreign_key);
+        }
+
+        let mut sql = format!("CREATE TABLE {} ({})", self.name, columns.join(", "));
+        if !options.is_empty() {
+            sql.push_str(&format!(" WITH ({})", options.join(", ")));
+        }
+        sql.push(';');
+        sql
+    }
+
+    /// Index of the table with the given name, ignoring case
+    pub fn get_index(&self, name: &str) -> Option<&TableIndex> {
+        self.indexes.iter().find(|index| index.name.eq_ignore_ascii_case(name))
+    }
+
+    /// CREATE INDEX statements recreating the indexes of the table
+    pub fn create_index_sql(&self) -> Vec<String> {
+        self.indexes.iter()
+            .map(|index| format!("CREATE INDEX {} ON {} ({});", index.name, self.name, self.columns[index.column].name))
+            .collect()
+    }
+
+    /// Column declared PRIMARY KEY, if any
+    pub fn primary_key(&self) -> Option<ColumnId> {
+        self.columns.iter().position(|col| col.primary_key)
+    }
+
+    /// Error for a column name not in the table, listing the valid columns
+    /// and suggesting the closest one when the name looks like a typo
+    pub fn column_not_found(&self, name: &str) -> DbError {
+        let available: Vec<&str> = self.columns.iter().map(|col| col.name.as_str()).collect();
+        let suggestion = available.iter()
+            .map(|col| (edit_distance(&col.to_uppercase(), &name.to_uppercase()), col))
+            .filter(|(distance, _)| *distance <= 2)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, col)| format!(", did you mean '{}'?", col))
+            .unwrap_or_default();
+
+        DbError::ColumnNotFound(format!(
+            "Unknown column '{}' in table '{}'{} Available columns: {}",
+            name,
+            self.name,
+            if suggestion.is_empty() { ".".to_string() } else { suggestion },
+            available.join(", ")
+        ))
+    }
+
+    /// Physical layout of new tables: fixed-width INT columns before CHAR columns,
+    /// otherwise keeping declaration order
+    pub fn default_physical_order(columns: &[ColumnDef]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..columns.len()).collect();
+        order.sort_by_key(|&i| !matches!(columns[i].data_type, DataType::Int));
+        order
+    }
+
+    /// Number of records INSERT puts in one data page under the fill factor.
+    /// Records of a table have a fixed size, so it is the same for every page
+    pub fn records_per_page(&self) -> usize {
+        let defaults = Record { record: self.columns.iter().map(|col| col.data_type.default_value()).collect() };
+        let record_size = self.serialize_record(&defaults).map_or(1, |bytes| bytes.len().max(1));
+        ((PAYLOAD_SIZE - self.options.reserved_space()) / record_size).clamp(1, MAX_SLOTS)
+    }
+
+    /// Size of the header stored before the column values of each record:
+    /// the tombstone of soft-delete tables, then the NULL bitmap with one bit per column
+    pub fn record_header_size(&self) -> usize {
+        self.tombstone_size() + self.null_bitmap_size()
+    }
+
+    fn tombstone_size(&self) -> usize {
+        if self.options.soft_delete { TOMBSTONE_SIZE } else { 0 }
+    }
+
+    fn null_bitmap_size(&self) -> usize {
+        if self.null_bitmap { self.columns.len().div_ceil(8) } else { 0 }
+    }
+
+    /// Deletion time of a tombstoned record, None for live records
+    pub fn deleted_at(&self, buf: &[u8]) -> Option<u64> {
+        if !self.options.soft_delete {
+            return None;
+        }
+        let timestamp = u64::from_le_bytes(buf[..TOMBSTONE_SIZE].try_into().ok()?);
+        (timestamp != 0).then_some(timestamp)
+    }
+
+    /// Copy of a serialized record marked as deleted at the given time
+    pub fn tombstone(&self, buf: &[u8], timestamp: u64) -> Vec<u8> {
+        debug_assert!(self.options.soft_delete);
+        let mut result = buf.to_vec();
+        result[..TOMBSTONE_SIZE].copy_from_slice(&timestamp.max(1).to_le_bytes());
+        result
+    }
+
+    /// Serialize record in logical column order to its physical layout
+    /// CHAR values are padded to the declared length, NULL values are marked in the NULL
+    /// bitmap and take the full width of their column, so records keep a fixed size
+    /// Return Err(DbError::ColumnMismatch) if the record does not have a value for each column
+    pub fn serialize_record(&self, record: &Record) -> DbResult<Vec<u8>> {
+        if record.record.len() != self.columns.len() {
+            return Err(DbError::ColumnMismatch(format!(
+                "Expected {} values for table '{}', got {}", self.columns.len(), self.name, record.record.len()
+            )));
+        }
+
+        let mut header = vec![0u8; self.record_header_size()];
+        for (column_id, value) in record.record.iter().enumerate() {
+            if *value == Literal::Null {
+                if self.columns[column_id].not_null {
+                    return Err(DbError::ConstraintViolation(format!(
+                        "Column '{}' of table '{}' is NOT NULL", self.columns[column_id].name, self.name
+                    )));
+                }
+                if !self.null_bitmap {
+                    return Err(DbError::TypeMismatch(format!(
+                        "Table '{}' was created before NULL support and cannot store NULL in column '{}'",
+                        self.name, self.columns[column_id].name
+                    )));
+                }
+                bitmap_set!(header[self.tombstone_size()..], column_id, true);
+            }
+        }
+
+        let mut physical = Vec::with_capacity(self.physical_order.len());
+        let mut physical_columns = Vec::with_capacity(self.physical_order.len());
+        for &column_id in &self.physical_order {
+            let value = &record.record[column_id];
+            let column = &self.columns[column_id];
+            column.data_type.check_length(value)
+                .map_err(|e| DbError::TypeMismatch(format!("{} in column '{}'", e, column.name)))?;
+            physical.push(value.clone());
+            physical_columns.push(column.clone());
+        }
+
+        header.extend(Record { record: physical }.serialize(&physical_columns).map_err(DbError::InternalError)?);
+        Ok(header)
+    }
+
+    /// Deserialize record from its physical layout to logical column order
+    pub fn deserialize_record(&self, buf: &[u8]) -> Result<Record, String> {
+        let physical_columns: Vec<ColumnDef> = self.physical_order.iter()
+            .map(|&i| self.columns[i].clone())
+            .collect();
+        let physical = Record::deserialize(&buf[self.record_header_size()..], &physical_columns)?;
+
+        let null_bitmap = &buf[self.tombstone_size()..self.record_header_size()];
+        let mut record = vec![Literal::Int(0); self.columns.len()];
+        for (value, &column_id) in physical.record.into_iter().zip(&self.physical_order) {
+            record[column_id] = if bitmap_get!(null_bitmap, column_id) { Literal::Null } else { value };
+        }
+        Ok(Record { record })
+    }
+}
+
+/// Levenshtein distance between two names
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
 }
 
 #[derive(Serialize, Deserialize, Default)]
 struct CatalogData {
     tables: HashMap<String, TableSchema>,
+    // last schema generation handed out, so a table recreated under the same name never reuses one
+    #[serde(default)]
+    generation: u64,
 }
 
 pub struct Catalog {
@@ -40,7 +340,7 @@ impl Catalog {
 
     pub fn new(path: PathBuf) -> Self {
         let data = Self::load_catalog(&path).unwrap_or_else(|_| {
-            CatalogData { tables: HashMap::new() }
+            CatalogData::default()
         });
         
         Self {
@@ -53,7 +353,22 @@ impl Catalog {
         self.data.tables.contains_key(name.to_uppercase().as_str())
     }
 
-    pub fn add_table(&mut self, table_schema: TableSchema) -> Result<(), DbError> {
+    /// Name of an existing table equal to name ignoring case
+    pub fn find_table_ignore_case(&self, name: &str) -> Option<&str> {
+        let name = name.to_uppercase();
+        self.data.tables.iter()
+            .find(|(key, table)| key.to_uppercase() == name || table.name.to_uppercase() == name)
+            .map(|(_, table)| table.name.as_str())
+    }
+
+    /// Generation for a table whose schema changes, greater than any handed out before
+    fn next_generation(&mut self) -> u64 {
+        self.data.generation += 1;
+        self.data.generation
+    }
+
+    pub fn add_table(&mut self, mut table_schema: TableSchema) -> Result<(), DbError> {
+        table_schema.generation = self.next_generation();
         self.data.tables.insert(table_schema.name.to_uppercase(), table_schema);
         self.save_catalog().map_err(|e| DbError::InternalError(e.to_string()))
     }
@@ -63,6 +378,78 @@ impl Catalog {
         self.save_catalog().map_err(|e| DbError::InternalError(e.to_string()))
     }
 
+    /// Replace table with replacement, which takes over its name, and remove the replacement's
+    /// own entry. Both changes are saved together. Return the schema previously stored under table
+    pub fn swap_table(&mut self, table: &str, replacement: &str) -> Result<TableSchema, DbError> {
+        let (table_key, replacement_key) = (table.to_uppercase(), replacement.to_uppercase());
+        if !self.data.tables.contains_key(&table_key) {
+            return Err(DbError::TableNotFound(format!("Table '{}' does not exist", table)));
+        }
+        let mut schema = self.data.tables.remove(&replacement_key)
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", replacement)))?;
+
+        schema.name = self.data.tables[&table_key].name.clone();
+        schema.generation = self.next_generation();
+        let old = self.data.tables.insert(table_key, schema).unwrap();
+        self.save_catalog().map_err(|e| DbError::InternalError(e.to_string()))?;
+        Ok(old)
+    }
+
+    /// Index with the given name on any table, with the table it belongs to
+    pub fn find_index(&self, name: &str) -> Option<(&TableSchema, &TableIndex)> {
+        let name = name.to_uppercase();
+        self.tables().into_iter()
+            .find_map(|schema| schema.indexes.iter()
+                .find(|index| index.name.to_uppercase() == name)
+                .map(|index| (schema, index)))
+    }
+
+    pub fn add_index(&mut self, table: &str, index: TableIndex) -> Result<(), DbError> {
+        let generation = self.next_generation();
+        let schema = self.data.tables.get_mut(&table.to_uppercase())
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
+        schema.indexes.push(index);
+        schema.generation = generation;
+        self.save_catalog().map_err(|e| DbError::InternalError(e.to_string()))
+    }
+
+    pub fn remove_index(&mut self, table: &str, name: &str) -> Result<(), DbError> {
+        let generation = self.next_generation();
+        let schema = self.data.tables.get_mut(&table.to_uppercase())
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
+        schema.indexes.retain(|index| !index.name.eq_ignore_ascii_case(name));
+        schema.generation = generation;
+        self.save_catalog().map_err(|e| DbError::InternalError(e.to_string()))
+    }
+
+    /// Record the root pages of the indexes of table in index order, saving the catalog
+    /// only if a root has moved
+    pub fn set_index_roots(&mut self, table: &str, roots: &[PageId]) -> Result<(), DbError> {
+        let schema = self.data.tables.get_mut(&table.to_uppercase())
+            .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?;
+        if schema.indexes.iter().map(|index| index.root).eq(roots.iter().copied()) {
+            return Ok(());
+        }
+        for (index, &root) in schema.indexes.iter_mut().zip(roots) {
+            index.root = root;
+        }
+        self.save_catalog().map_err(|e| DbError::InternalError(e.to_string()))
+    }
+
+    /// Tables with a foreign key referencing table
+    pub fn referencing_tables(&self, table: &str) -> Vec<&TableSchema> {
+        self.tables().into_iter()
+            .filter(|schema| schema.foreign_keys.iter().any(|fk| fk.ref_table == table))
+            .collect()
+    }
+
+    /// Every table in the catalog, ordered by name
+    pub fn tables(&self) -> Vec<&TableSchema> {
+        let mut tables: Vec<&TableSchema> = self.data.tables.values().collect();
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+        tables
+    }
+
     pub fn get_table_schema(&self, table: &str) -> Option<&TableSchema> {
         self.data.tables.get(&table.to_uppercase())
     }
@@ -82,6 +469,7 @@ impl Catalog {
     pub fn save_catalog(&self) -> Result<(), Box<dyn std::error::Error>> {
         let data = CatalogData {
             tables: self.data.tables.clone(),
+            generation: self.data.generation,
         };
 
         let json = serde_json::to_string_pretty(&data)?;
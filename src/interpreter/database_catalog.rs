@@ -4,6 +4,9 @@ use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
 use crate::types::CATALOG_FILE;
 
+// suffix of a database directory renamed by DROP DATABASE before it is deleted
+const DROPPING_SUFFIX: &str = ".dropping";
+
 
 /// Database metadata
 #[derive(Serialize, Deserialize)]
@@ -11,6 +14,19 @@ pub struct GlobalCatalogData {
     pub databases: HashSet<String>,
 }
 
+/// Databases whose catalog entry and directory disagree
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OrphanReport {
+    pub directories: Vec<String>,       // directories without catalog entry, kept on disk
+    pub catalog_entries: Vec<String>,   // catalog entries without directory, removed from catalog
+}
+
+impl OrphanReport {
+    pub fn is_empty(&self) -> bool {
+        self.directories.is_empty() && self.catalog_entries.is_empty()
+    }
+}
+
 pub struct GlobalCatalog {
     dbms_root_dir: PathBuf,
     data: GlobalCatalogData,
@@ -51,16 +67,84 @@ impl GlobalCatalog {
         self.data.databases.contains(&name.to_uppercase())
     }
 
+    /// Name of an existing database equal to name ignoring case
+    pub fn find_database_ignore_case(&self, name: &str) -> Option<&str> {
+        let name = name.to_uppercase();
+        self.data.databases.iter()
+            .find(|db| db.to_uppercase() == name)
+            .map(|db| db.as_str())
+    }
+
     pub fn add_database(&mut self, name: String) {
         let name = name.to_uppercase();
         self.data.databases.insert(name);
         self.save_catalog().expect("Failure to update catalog during CREATE DATABASE");
     }
 
-    pub fn remove_database(&mut self, name: &str) {
-        let name = &name.to_uppercase();
-        self.data.databases.remove(name);
-        self.save_catalog().expect("Failed to update catalog during DROP DATABASE");
+    /// Remove database from catalog. If the catalog cannot be saved the entry is kept,
+    /// so the catalog in memory still matches the one on disk
+    pub fn remove_database(&mut self, name: &str) -> io::Result<()> {
+        let name = name.to_uppercase();
+        if !self.data.databases.remove(&name) {
+            return Ok(());
+        }
+        self.save_catalog().inspect_err(|_| {
+            self.data.databases.insert(name);
+        })
+    }
+
+    /// Hidden directory a dropped database is moved to before deletion,
+    /// so the database directory disappears in a single rename
+    pub fn dropping_path(&self, name: &str) -> PathBuf {
+        self.dbms_root_dir.join(format!(".{}{}", name.to_uppercase(), DROPPING_SUFFIX))
+    }
+
+    /// Compare catalog against the database directories in dbms root directory.
+    /// Catalog entries without a directory are removed, as there is no data to recover.
+    /// Directories without a catalog entry are only reported, since they may hold user data.
+    /// Directories left by an interrupted DROP DATABASE are deleted
+    pub fn reconcile(&mut self) -> io::Result<OrphanReport> {
+        let mut directories = HashSet::new();
+        match fs::read_dir(&self.dbms_root_dir) {
+            Ok(entries) => {
+                for entry in entries {
+                    let entry = entry?;
+                    if !entry.file_type()?.is_dir() {
+                        continue;
+                    }
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if name.starts_with('.') && name.ends_with(DROPPING_SUFFIX) {
+                        fs::remove_dir_all(entry.path())?;
+                    } else {
+                        directories.insert(name);
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut report = OrphanReport::default();
+        for dir in &directories {
+            if !self.data.databases.contains(dir) {
+                report.directories.push(dir.clone());
+            }
+        }
+        for db in &self.data.databases {
+            if !directories.contains(db) {
+                report.catalog_entries.push(db.clone());
+            }
+        }
+        report.directories.sort();
+        report.catalog_entries.sort();
+
+        if !report.catalog_entries.is_empty() {
+            for db in &report.catalog_entries {
+                self.data.databases.remove(db);
+            }
+            self.save_catalog()?;
+        }
+        Ok(report)
     }
 
     pub fn list_databases(&self) -> Vec<String> {
@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+use paste::paste;
+use crate::compiler::ast::Literal;
+use crate::interpreter::catalog_table::TableSchema;
+use crate::interpreter::Interpreter;
+use crate::types::{DbError, DbResult};
+use crate::with_read_pages;
+
+// rows written by each INSERT statement of a dump
+const DUMP_ROWS_PER_INSERT: usize = 100;
+
+impl Interpreter {
+    /// Export a database as SQL statements that recreate it when run with execute_sql.
+    /// Rows hidden by soft delete are not exported
+    pub fn dump(&self, db: &str) -> DbResult<String> {
+        let ctx = self.context.read().unwrap();
+        let database = db.to_uppercase();
+        let (catalog, storage_engine) = match (ctx.catalogs.get(&database), ctx.storage_engines.get(&database)) {
+            (Some(catalog), Some(storage_engine)) => (catalog, storage_engine),
+            _ => return Err(DbError::DatabaseNotFound(format!("Database '{}' does not exist", db))),
+        };
+
+        let mut sql = format!("CREATE DATABASE {};\nCONNECT TO {};\n", database, database);
+        for schema in parents_first(catalog.tables()) {
+            sql.push_str(&schema.create_table_sql());
+            sql.push('\n');
+
+            // scan rows in heap order
+            let mut rows = Vec::new();
+            let mut page_id = schema.first_page_id;
+            while page_id != 0 {
+                with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
+                    for (_, record_bytes) in storage_engine.iter_record(&page) {
+                        if schema.deleted_at(&record_bytes).is_some() {
+                            continue;
+                        }
+                        let row = schema.deserialize_record(&record_bytes)
+                            .map_err(DbError::InternalError)?;
+                        rows.push(format_row(&row.record, &schema.name)?);
+                    }
+                    page_id = page.get_next_id();
+                });
+            }
+
+            for chunk in rows.chunks(DUMP_ROWS_PER_INSERT) {
+                sql.push_str(&format!("INSERT INTO {} VALUES {};\n", schema.name, chunk.join(", ")));
+            }
+
+            // indexes are built once the rows are loaded
+            for index_sql in schema.create_index_sql() {
+                sql.push_str(&index_sql);
+                sql.push('\n');
+            }
+        }
+
+        Ok(sql)
+    }
+}
+
+/// Tables ordered so that each table comes after the tables its foreign keys reference,
+/// keeping name order otherwise. Foreign keys never form a cycle, as a table can only
+/// reference tables created before it
+fn parents_first(mut tables: Vec<&TableSchema>) -> Vec<&TableSchema> {
+    let mut ordered = Vec::with_capacity(tables.len());
+    let mut created = HashSet::new();
+    while !tables.is_empty() {
+        let ready = tables.iter()
+            .position(|schema| schema.foreign_keys.iter().all(|fk| created.contains(&fk.ref_table)))
+            .unwrap_or(0);
+        let schema = tables.remove(ready);
+        created.insert(schema.name.clone());
+        ordered.push(schema);
+    }
+    ordered
+}
+
+/// Row as a VALUES tuple
+fn format_row(values: &[Literal], table: &str) -> DbResult<String> {
+    let mut formatted = Vec::with_capacity(values.len());
+    for value in values {
+        // string literals have no escape sequence for a quote
+        if let Literal::String(s) = value {
+            if s.contains('\'') {
+                return Err(DbError::InternalError(format!(
+                    "Cannot dump value {:?} of table '{}': string literals cannot contain quotes", s, table
+                )));
+            }
+        }
+        formatted.push(value.to_string());
+    }
+    Ok(format!("({})", formatted.join(", ")))
+}
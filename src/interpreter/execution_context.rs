@@ -4,7 +4,7 @@ use std::sync::Arc;
 use crate::interpreter::catalog_table::Catalog;
 use crate::interpreter::database_catalog::GlobalCatalog;
 use crate::storage::{StorageConfig, StorageEngine};
-use crate::types::{CATALOG_FILE, DEFAULT_BUFFERPOOL_REPLACEMENT, DEFAULT_BUFFERPOOL_SIZE};
+use crate::types::{CATALOG_FILE, CONFIG_FILE};
 
 pub struct ExecutionContext {
     pub dbms_root_dir: PathBuf,
@@ -36,11 +36,17 @@ impl ExecutionContext {
             return Err(String::from("A storage engine for the database already exists"));
         }
 
-        let storage_config = StorageConfig {
-            database_dir: self.database_dir(&db_name),
-            bufferpool_capacity: DEFAULT_BUFFERPOOL_SIZE,
-            bufferpool_replacement_strategy: DEFAULT_BUFFERPOOL_REPLACEMENT
+        // use the database's config file if present, otherwise the defaults
+        let database_dir = self.database_dir(&db_name);
+        let config_path = database_dir.join(CONFIG_FILE);
+        let mut storage_config = if config_path.exists() {
+            StorageConfig::load(&config_path)
+                .map_err(|e| format!("Invalid storage config {}: {}", config_path.display(), e))?
+        } else {
+            StorageConfig::default()
         };
+        storage_config.database_dir = database_dir;
+
         let storage_engine = StorageEngine::new(storage_config)
             .expect("Failed to create storage engine");
         self.storage_engines.insert(db_name.clone(), Arc::new(storage_engine));
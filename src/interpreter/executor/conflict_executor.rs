@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use crate::compiler::ast::{logical_value, Literal, Record};
+use crate::compiler::bounded_ast::BoundConflictPolicy;
+use crate::interpreter::catalog_table::TableSchema;
+use crate::interpreter::executor::index_executor::index_key;
+use crate::interpreter::executor::{Executor, ExprContext};
+use crate::storage::StorageEngine;
+use crate::types::{ColumnId, DbError, DbResult};
+
+/// Key of a row that no other row of its table may hold
+#[derive(PartialEq, Eq, Hash)]
+enum UniqueKey {
+    PrimaryKey(Literal),
+    Index(usize, i64),  // position of the index in the table, key
+}
+
+/// Primary key of row, then its key in each index of table
+fn unique_keys(schema: &TableSchema, row: &Record) -> Vec<UniqueKey> {
+    let primary_key = schema.primary_key()
+        .map(|column| UniqueKey::PrimaryKey(logical_value(&row.record[column], &schema.columns[column])));
+    let index_keys = schema.indexes.iter().enumerate()
+        .filter_map(|(i, index)| index_key(row, index.column).map(|key| UniqueKey::Index(i, key)));
+    primary_key.into_iter().chain(index_keys).collect()
+}
+
+/// Key of row on column, a primary key or indexed column. None if NULL, which conflicts with nothing
+fn column_key(schema: &TableSchema, row: &Record, column: ColumnId) -> Option<UniqueKey> {
+    if schema.primary_key() == Some(column) {
+        return Some(UniqueKey::PrimaryKey(logical_value(&row.record[column], &schema.columns[column])));
+    }
+    let i = schema.indexes.iter().position(|index| index.column == column)?;
+    index_key(row, column).map(|key| UniqueKey::Index(i, key))
+}
+
+/// Rows written by an INSERT once its conflict policy is applied
+pub(super) struct ResolvedInsert {
+    pub inserted: Vec<Record>,              // rows to insert
+    pub replaced: Vec<Record>,              // live rows deleted, replaced by inserted rows
+    pub updated: Vec<(Record, Record)>,     // live rows updated by ON CONFLICT DO UPDATE, and their new values
+}
+
+impl Executor {
+
+    /// Apply the conflict policy of an INSERT of new_rows into table. A row conflicts with an
+    /// existing row or an earlier row of the statement holding one of its unique keys.
+    /// IGNORE skips the row, REPLACE keeps it and replaces the rows it conflicts with, and
+    /// DO UPDATE updates the existing row holding its value of the conflict column instead.
+    /// Nothing is written, the caller applies the result once it passes the constraint checks
+    pub(super) fn resolve_conflicts(
+        &self,
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        new_rows: Vec<Record>,
+        policy: &BoundConflictPolicy,
+    ) -> DbResult<ResolvedInsert> {
+        let mut resolved = ResolvedInsert { inserted: Vec::with_capacity(new_rows.len()), replaced: vec![], updated: vec![] };
+        if *policy == BoundConflictPolicy::Abort {
+            resolved.inserted = new_rows;
+            return Ok(resolved);
+        }
+
+        // the primary key has no index, so its live rows are looked up by value
+        let by_primary_key: HashMap<Literal, Record> = match schema.primary_key() {
+            Some(column) => self.matching_rows(storage_engine, schema, &None)?.into_iter()
+                .map(|row| (logical_value(&row.record[column], &schema.columns[column]), row))
+                .collect(),
+            None => HashMap::new(),
+        };
+        let existing = |key: &UniqueKey| match key {
+            UniqueKey::PrimaryKey(value) => by_primary_key.get(value).cloned(),
+            UniqueKey::Index(i, key) => Self::index_scan(storage_engine, schema, &schema.indexes[*i], *key),
+        };
+
+        for row in new_rows {
+            let keys = unique_keys(schema, &row);
+            let conflicts = |other: &Record| unique_keys(schema, other).iter().any(|key| keys.contains(key));
+
+            match policy {
+                BoundConflictPolicy::Abort => unreachable!(),
+                BoundConflictPolicy::Ignore => {
+                    if resolved.inserted.iter().any(conflicts) || keys.iter().any(|key| existing(key).is_some()) {
+                        continue;
+                    }
+                }
+                BoundConflictPolicy::Replace => {
+                    // a later row of the statement replaces an earlier one, like it would in separate statements
+                    resolved.inserted.retain(|other| !conflicts(other));
+                    for old in keys.iter().filter_map(existing) {
+                        if !resolved.replaced.contains(&old) {
+                            resolved.replaced.push(old);
+                        }
+                    }
+                }
+                BoundConflictPolicy::Update { column, assignments } => {
+                    let Some(old) = column_key(schema, &row, *column).and_then(|key| existing(&key)) else {
+                        resolved.inserted.push(row);
+                        continue;
+                    };
+                    // the assignments see the existing row, whose new values could not be told apart from a second update
+                    if resolved.updated.iter().any(|(updated, _)| *updated == old) {
+                        return Err(DbError::ConstraintViolation(format!(
+                            "ON CONFLICT DO UPDATE cannot update the row with {} = {} of table '{}' twice",
+                            schema.columns[*column].name, old.record[*column], schema.name
+                        )));
+                    }
+                    let expr_ctx = ExprContext { row: Some(&old) };
+                    let mut new = old.clone();
+                    for assign in assignments {
+                        new.record[assign.column_id] = self.execute_expression(&assign.value, &expr_ctx)?;
+                    }
+                    resolved.updated.push((old, new));
+                    continue;
+                }
+            }
+            resolved.inserted.push(row);
+        }
+        Ok(resolved)
+    }
+}
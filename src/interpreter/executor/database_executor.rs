@@ -23,21 +23,37 @@ impl Executor {
         Ok(ExecResult::Success(format!("Database '{}' created successfully", name)))
     }
 
+    /// Drop database in steps that each leave catalog and file system consistent:
+    /// 1. rename the database directory aside, a single atomic operation
+    /// 2. remove the catalog entry, renaming the directory back if the catalog cannot be saved
+    /// 3. delete the renamed directory, which GlobalCatalog::reconcile finishes on the next startup if interrupted
     pub fn drop_database(&mut self, name: &str) -> DbResult<ExecResult> {
         let mut ctx = self.context.write().unwrap();
 
-        // remove database directory
         let root = Path::new(&ctx.dbms_root_dir);
         let db_path = root.join(name);
-        if db_path.exists() {
-            std::fs::remove_dir_all(&db_path)
+        let dropping_path = ctx.global_catalog.dropping_path(name);
+        let renamed = db_path.exists();
+        if renamed {
+            std::fs::rename(&db_path, &dropping_path)
                 .map_err(|e| DbError::InternalError(format!("Failed to delete database '{}': {}", name, e)))?;
         }
 
-        // remove database from catalog
-        ctx.global_catalog.remove_database(name);
+        if let Err(e) = ctx.global_catalog.remove_database(name) {
+            if renamed {
+                std::fs::rename(&dropping_path, &db_path).map_err(|e| DbError::InternalError(format!(
+                    "Failed to restore database '{}' after catalog update failed: {}", name, e
+                )))?;
+            }
+            return Err(DbError::InternalError(format!("Failed to update catalog while dropping '{}': {}", name, e)));
+        }
         ctx.storage_engines.remove(name);
 
+        // the database is already gone from the catalog, a leftover directory is removed on restart
+        if renamed {
+            let _ = std::fs::remove_dir_all(&dropping_path);
+        }
+
         Ok(ExecResult::Success(format!("Database '{}' dropped successfully.", name)))
     }
 
@@ -49,8 +65,9 @@ impl Executor {
 
     pub fn disconnect_database(&mut self) -> DbResult<ExecResult> {
         let mut ctx = self.context.write().unwrap();
-        // deactivate storage engine and flush all dirty pages
-        ctx.storage_engines.get(ctx.current_db.as_deref().unwrap()).unwrap().deactivate();
+        // deactivate storage engine and flush all dirty pages, staying connected if they cannot be written
+        ctx.storage_engines.get(ctx.current_db.as_deref().unwrap()).unwrap().deactivate()
+            .map_err(|e| DbError::InternalError(format!("Failed to flush database: {:?}", e)))?;
         ctx.current_db = None;
         Ok(ExecResult::Success("Disconnected from database".to_string()))
     }
@@ -0,0 +1,176 @@
+use paste::paste;
+use crate::compiler::ast::Literal;
+use crate::compiler::bounded_ast::BoundExprNode;
+use crate::interpreter::catalog_table::TableSchema;
+use crate::interpreter::executor::{Executor, ExprContext};
+use crate::interpreter::ExecResult;
+use crate::storage::page::data_page::PAYLOAD_SIZE;
+use crate::storage::StorageEngine;
+use crate::types::{DbError, DbResult, PageId, PAGE_SIZE};
+use crate::with_read_pages;
+
+/// Whether page_id is in the page chain of table
+fn table_has_page(storage_engine: &StorageEngine, schema: &TableSchema, page_id: PageId) -> bool {
+    let mut curr = schema.first_page_id;
+    while curr != 0 {
+        if curr == page_id {
+            return true;
+        }
+        with_read_pages!(storage_engine.buffer_pool, [(curr, page)], {
+            curr = page.get_next_id();
+        });
+    }
+    false
+}
+
+impl Executor {
+
+    /// Describe a page of a table in the current database, one line per row: the table, the page header,
+    /// then each slot with its record decoded against the table schema. The page is only read
+    pub fn dump_page(&self, page_id: PageId) -> DbResult<ExecResult> {
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.as_ref().unwrap();
+        let storage_engine = ctx.storage_engines.get(database).unwrap();
+
+        // only pages of a table chain are known to hold data records
+        let schema = ctx.catalogs.get(database).unwrap().tables().into_iter()
+            .find(|schema| table_has_page(storage_engine, schema, page_id))
+            .ok_or_else(|| DbError::PageNotFound(format!("Page {} does not belong to any table", page_id)))?;
+
+        let describe_record = |bytes: &[u8]| {
+            let values = match schema.deserialize_record(bytes) {
+                Ok(row) => row.record.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(", "),
+                Err(e) => return format!("undecodable: {}", e),
+            };
+            match schema.deleted_at(bytes) {
+                Some(timestamp) => format!("({}) deleted at {}", values, timestamp),
+                None => format!("({})", values),
+            }
+        };
+        let lines = storage_engine.dump_page_with(page_id, describe_record)
+            .map_err(|e| DbError::PageNotFound(format!("Page {} cannot be read: {:?}", page_id, e)))?;
+
+        let rows = std::iter::once(format!("Table {}", schema.name))
+            .chain(lines)
+            .map(|line| vec![line])
+            .collect();
+        Ok(ExecResult::QueryResult(rows))
+    }
+
+    /// Locate the live rows of table matching selection, one row per record with its page id and
+    /// slot id, so the heap location of a row can be compared with the record id stored in an index
+    pub fn show_records(&self, table: &str, selection: &Option<BoundExprNode>) -> DbResult<ExecResult> {
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.as_ref().unwrap();
+        let storage_engine = ctx.storage_engines.get(database).unwrap();
+        let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table).unwrap();
+
+        // the first error evaluating selection stops matching and is returned
+        let mut error = None;
+        let record_ids = storage_engine.find_record(schema.first_page_id, |record_bytes| {
+            if error.is_some() || schema.deleted_at(record_bytes).is_some() {
+                return false;
+            }
+            let Some(condition) = selection else { return true };
+            let row = schema.deserialize_record(record_bytes).expect("Error deserializing record");
+            match self.execute_expression(&condition.expr, &ExprContext { row: Some(&row) }) {
+                Ok(value) => value == Literal::Bool(true),
+                Err(e) => {
+                    error = Some(e);
+                    false
+                }
+            }
+        });
+        if let Some(e) = error {
+            return Err(e);
+        }
+
+        let rows = record_ids.into_iter()
+            .map(|rid| vec![rid.page_id.to_string(), rid.slot_id.to_string()])
+            .collect();
+        Ok(ExecResult::QueryResult(rows))
+    }
+
+    /// Storage size of every table in the current database, one row per table in name order:
+    /// name, heap pages, live rows, logical size as the bytes of live records, physical size as
+    /// heap pages times PAGE_SIZE, and storage amplification as physical over logical size.
+    /// Amplification grows with deleted records, free space left in pages and record headers
+    pub fn show_table_status(&self) -> DbResult<ExecResult> {
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.as_ref().unwrap();
+        let storage_engine = ctx.storage_engines.get(database).unwrap();
+
+        let mut rows = Vec::new();
+        for schema in ctx.catalogs.get(database).unwrap().tables() {
+            let (mut pages, mut live_rows, mut logical) = (0, 0, 0);
+            let mut page_id = schema.first_page_id;
+            while page_id != 0 {
+                with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
+                    for (slot_id, record_bytes) in storage_engine.iter_record(&page) {
+                        // overflow pages hold a spilled record in chunks of a page payload
+                        if page.get_overflow(slot_id).is_some() {
+                            pages += record_bytes.len().div_ceil(PAYLOAD_SIZE);
+                        }
+                        if schema.deleted_at(&record_bytes).is_none() {
+                            live_rows += 1;
+                            logical += record_bytes.len();
+                        }
+                    }
+                    page_id = page.get_next_id();
+                });
+                pages += 1;
+            }
+
+            let physical = pages * PAGE_SIZE;
+            let amplification = if logical == 0 {
+                "NULL".to_string()
+            } else {
+                format!("{:.2}", physical as f64 / logical as f64)
+            };
+            rows.push(vec![
+                schema.name.clone(),
+                pages.to_string(),
+                live_rows.to_string(),
+                logical.to_string(),
+                physical.to_string(),
+                amplification,
+            ]);
+        }
+        Ok(ExecResult::QueryResult(rows))
+    }
+
+    /// Columns of table in declaration order, one row per column: name, data type,
+    /// constraint (PRIMARY KEY, NOT NULL or empty) and comment, NULL for a column without one
+    pub fn describe(&self, table: &str) -> DbResult<ExecResult> {
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.as_ref().unwrap();
+        let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table).unwrap();
+
+        let rows = schema.columns.iter()
+            .map(|col| {
+                // PRIMARY KEY implies NOT NULL
+                let constraint = if col.primary_key { "PRIMARY KEY" } else if col.not_null { "NOT NULL" } else { "" };
+                vec![
+                    col.name.clone(),
+                    col.data_type.to_string(),
+                    constraint.to_string(),
+                    col.comment.clone().unwrap_or_else(|| "NULL".to_string()),
+                ]
+            })
+            .collect();
+        Ok(ExecResult::QueryResult(rows))
+    }
+
+    /// CREATE TABLE statement recreating table, followed by a row for each of its CREATE INDEX statements
+    pub fn show_create_table(&self, table: &str) -> DbResult<ExecResult> {
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.as_ref().unwrap();
+        let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table).unwrap();
+
+        let rows = std::iter::once(schema.create_table_sql())
+            .chain(schema.create_index_sql())
+            .map(|sql| vec![sql])
+            .collect();
+        Ok(ExecResult::QueryResult(rows))
+    }
+}
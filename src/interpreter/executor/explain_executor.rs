@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+use crate::compiler::bounded_ast::{BoundExpr, BoundStmt};
+use crate::interpreter::ExecResult;
+use crate::interpreter::executor::Executor;
+use crate::interpreter::executor::index_executor::index_lookup;
+use crate::interpreter::executor::select_executor::SelectStats;
+use crate::types::{DbError, DbResult};
+
+impl Executor {
+
+    /// Describe the plan of a statement, one operator per row from the top of the plan down.
+    /// With analyze, the statement is executed and each operator reports its actual output
+    /// rows and elapsed time; the query's own rows are discarded
+    pub fn explain(&self, analyze: bool, stmt: &BoundStmt) -> DbResult<ExecResult> {
+        let BoundStmt::Select { table, distinct, columns, selection, group_by, order_by, .. } = stmt else {
+            return Err(DbError::InternalError("EXPLAIN only supports SELECT".to_string()));
+        };
+
+        // index the scan looks the selection up in, if any
+        let index = {
+            let ctx = self.context.read().unwrap();
+            let database = ctx.current_db.as_ref().unwrap();
+            let schema = ctx.catalogs.get(database).unwrap().get_table_schema(table).unwrap();
+            index_lookup(schema, selection).map(|(index, _)| index.name.clone())
+        };
+        let scan = match &index {
+            Some(index) => format!("Index Scan using {} on {}", index, table),
+            None => format!("Seq Scan on {}", table),
+        };
+
+        let mut stats = SelectStats::default();
+        let mut total_time = Duration::ZERO;
+        let mut output_rows = 0;
+        if analyze {
+            let start = Instant::now();
+            output_rows = self.select_rows(table, *distinct, columns, selection, group_by, order_by, &mut stats)?.len();
+            total_time = start.elapsed();
+        }
+
+        let actual = |rows: usize, time: Duration| if analyze {
+            format!(" (actual rows={} time={:.3}ms)", rows, time.as_secs_f64() * 1000.0)
+        } else {
+            String::new()
+        };
+
+        let mut plan = Vec::new();
+        if *distinct {
+            plan.push(format!("Unique{}", actual(output_rows, stats.sort_time)));
+        }
+        if !group_by.is_empty() {
+            plan.push(format!("Aggregate (group keys={}){}", group_by.len(), actual(output_rows, stats.scan_time)));
+        } else if columns.iter().any(|c| matches!(c, BoundExpr::Aggregate(..))) {
+            plan.push(format!("Aggregate{}", actual(1, stats.scan_time)));
+        }
+        if !order_by.is_empty() {
+            plan.push(format!("Sort (keys={}){}", order_by.len(), actual(stats.rows_matched, stats.sort_time)));
+        }
+        if selection.is_some() {
+            plan.push(format!("Filter{}", actual(stats.rows_matched, stats.filter_time)));
+        }
+        plan.push(if analyze {
+            format!("{} (actual rows={} pages={} time={:.3}ms)",
+                    scan, stats.rows_scanned, stats.pages_read, stats.scan_time.as_secs_f64() * 1000.0)
+        } else {
+            scan
+        });
+
+        // indent child operators under their parent
+        let mut rows: Vec<Vec<String>> = plan.into_iter().enumerate()
+            .map(|(depth, line)| vec![format!("{}{}", "  ".repeat(depth), line)])
+            .collect();
+        if analyze {
+            rows.push(vec![format!("Execution Time: {:.3}ms", total_time.as_secs_f64() * 1000.0)]);
+        }
+
+        Ok(ExecResult::QueryResult(rows))
+    }
+}
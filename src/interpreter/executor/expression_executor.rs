@@ -25,6 +25,8 @@ impl Executor {
                 self.eval_cmp(l, r, ctx, |a, b| a < b),
             BoundExpr::Lte(l, r) =>
                 self.eval_cmp(l, r, ctx, |a, b| a <= b),
+            BoundExpr::In(target, list) =>
+                self.eval_in(target, list, ctx),
 
             // logical
             BoundExpr::And(l, r) =>
@@ -47,6 +49,16 @@ impl Executor {
                 self.eval_minus(e, ctx),
             BoundExpr::Not(e) =>
                 self.eval_not(e, ctx),
+            BoundExpr::IsTrue(e) =>
+                self.eval_is(e, true, ctx),
+            BoundExpr::IsFalse(e) =>
+                self.eval_is(e, false, ctx),
+            BoundExpr::IsNull(e) =>
+                Ok(Literal::Bool(self.execute_expression(e, ctx)? == Literal::Null)),
+
+            // aggregates are accumulated by the select executor over all rows
+            BoundExpr::Aggregate(func, _) =>
+                Err(DbError::InternalError(format!("{:?} cannot be evaluated on a single row", func))),
         }
     }
 
@@ -61,7 +73,7 @@ impl Executor {
         }
     }
 
-    // comparison helper
+    // comparison helper, NULL compared to anything is NULL
     fn eval_cmp<F>(
         &self, lhs: &BoundExpr, rhs: &BoundExpr, ctx: &ExprContext, cmp_func: F) -> DbResult<Literal>
     where
@@ -69,9 +81,30 @@ impl Executor {
     {
         let lhs = self.execute_expression(lhs, ctx)?;
         let rhs = self.execute_expression(rhs, ctx)?;
+        if lhs == Literal::Null || rhs == Literal::Null {
+            return Ok(Literal::Null);
+        }
         Ok(Literal::Bool(cmp_func(&lhs, &rhs)))
     }
 
+    // IN helper, stops at the first list item equal to target
+    // without a match the result is NULL if target or any item is NULL, like a chain of OR
+    fn eval_in(&self, target: &BoundExpr, list: &[BoundExpr], ctx: &ExprContext) -> DbResult<Literal> {
+        let target = self.execute_expression(target, ctx)?;
+        if target == Literal::Null {
+            return Ok(Literal::Null);
+        }
+        let mut unknown = false;
+        for item in list {
+            match self.execute_expression(item, ctx)? {
+                Literal::Null => unknown = true,
+                item if item == target => return Ok(Literal::Bool(true)),
+                _ => {}
+            }
+        }
+        Ok(if unknown { Literal::Null } else { Literal::Bool(false) })
+    }
+
     // arithmetic helper
     fn eval_arith<F>(&self, lhs: &BoundExpr, rhs: &BoundExpr, ctx: &ExprContext, func: F) -> DbResult<Literal>
     where
@@ -81,6 +114,8 @@ impl Executor {
         let rhs = self.execute_expression(rhs, ctx)?;
 
         match (lhs, rhs) {
+            (Literal::Null, _) | (_, Literal::Null) =>
+                Ok(Literal::Null),
             (Literal::Int(a), Literal::Int(b)) =>
                 func(a, b)
                     .map(Literal::Int)
@@ -90,7 +125,7 @@ impl Executor {
         }
     }
 
-    // logical AND helper
+    // logical AND helper, FALSE wins over NULL
     fn eval_and(&self, lhs: &BoundExpr, rhs: &BoundExpr, ctx: &ExprContext) -> DbResult<Literal> {
         let lhs = self.execute_expression(lhs, ctx)?;
         if let Literal::Bool(false) = lhs {
@@ -98,13 +133,15 @@ impl Executor {
         }
 
         let rhs = self.execute_expression(rhs, ctx)?;
-        match rhs {
-            Literal::Bool(b) => Ok(Literal::Bool(b)),
+        match (lhs, rhs) {
+            (_, Literal::Bool(false)) => Ok(Literal::Bool(false)),
+            (Literal::Null, Literal::Bool(_)) | (_, Literal::Null) => Ok(Literal::Null),
+            (Literal::Bool(_), Literal::Bool(b)) => Ok(Literal::Bool(b)),
             _ => Err(DbError::TypeMismatch("AND requires BOOL operands".to_string()))
         }
     }
 
-    // logical OR helper
+    // logical OR helper, TRUE wins over NULL
     fn eval_or(&self, lhs: &BoundExpr, rhs: &BoundExpr, ctx: &ExprContext, ) -> DbResult<Literal> {
         let lhs = self.execute_expression(lhs, ctx)?;
         if let Literal::Bool(true) = lhs {
@@ -112,24 +149,38 @@ impl Executor {
         }
 
         let rhs = self.execute_expression(rhs, ctx)?;
-        match rhs {
-            Literal::Bool(b) => Ok(Literal::Bool(b)),
+        match (lhs, rhs) {
+            (_, Literal::Bool(true)) => Ok(Literal::Bool(true)),
+            (Literal::Null, Literal::Bool(_)) | (_, Literal::Null) => Ok(Literal::Null),
+            (Literal::Bool(_), Literal::Bool(b)) => Ok(Literal::Bool(b)),
             _ => Err(DbError::TypeMismatch("OR requires BOOL operands".to_string()))
         }
     }
 
-    // NOT helper
+    // NOT helper, NOT NULL is NULL
     fn eval_not(&self, expr: &BoundExpr, ctx: &ExprContext) -> DbResult<Literal> {
         match self.execute_expression(expr, ctx)? {
             Literal::Bool(v) => Ok(Literal::Bool(!v)),
+            Literal::Null => Ok(Literal::Null),
             _ => Err(DbError::TypeMismatch("NOT requires BOOL".to_string()))
         }
     }
 
+    // IS TRUE / IS FALSE helper, never evaluates to anything but a BOOL
+    // NULL is neither TRUE nor FALSE
+    fn eval_is(&self, expr: &BoundExpr, expected: bool, ctx: &ExprContext) -> DbResult<Literal> {
+        match self.execute_expression(expr, ctx)? {
+            Literal::Bool(v) => Ok(Literal::Bool(v == expected)),
+            Literal::Null => Ok(Literal::Bool(false)),
+            _ => Err(DbError::TypeMismatch("IS TRUE / IS FALSE requires BOOL".to_string()))
+        }
+    }
+
     // minus sign helper
     fn eval_minus(&self, expr: &BoundExpr, ctx: &ExprContext) -> DbResult<Literal> {
 
         match self.execute_expression(expr, ctx)? {
+            Literal::Null => Ok(Literal::Null),
             Literal::Int(v) => Ok(Literal::Int(-v)),
             _ => Err(DbError::TypeMismatch("Unary minus requires numerical type".to_string()))
         }
@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+use paste::paste;
+use crate::compiler::ast::{logical_value, Literal, Record, ReferentialAction};
+use crate::compiler::bounded_ast::BoundExprNode;
+use crate::interpreter::catalog_table::TableSchema;
+use crate::interpreter::execution_context::ExecutionContext;
+use crate::interpreter::executor::{Executor, ExprContext};
+use crate::storage::StorageEngine;
+use crate::types::{ColumnId, DbError, DbResult};
+use crate::with_read_pages;
+
+/// Referencing rows changed by a DELETE, as (table, column, values, action), see plan_delete
+type ReferencingRows = (String, ColumnId, HashSet<Literal>, ReferentialAction);
+
+impl Executor {
+
+    /// Whether changes to table need foreign key checks, as a referencing or a referenced table
+    pub(super) fn has_foreign_keys(ctx: &ExecutionContext, database: &str, table: &str) -> bool {
+        let catalog = ctx.catalogs.get(database).unwrap();
+        !catalog.get_table_schema(table).unwrap().foreign_keys.is_empty()
+            || !catalog.referencing_tables(table).is_empty()
+    }
+
+    /// Live rows of table matching selection, every row without selection
+    pub(super) fn matching_rows(
+        &self,
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        selection: &Option<BoundExprNode>,
+    ) -> DbResult<Vec<Record>> {
+        let mut rows = Vec::new();
+        let mut page_id = schema.first_page_id;
+        while page_id != 0 {
+            with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
+                for (_, record_bytes) in storage_engine.iter_record(&page) {
+                    if schema.deleted_at(&record_bytes).is_some() {
+                        continue;
+                    }
+                    let row = schema.deserialize_record(&record_bytes)
+                        .expect("Error deserializing record");
+                    if let Some(condition) = selection {
+                        if self.execute_expression(
+                            &condition.expr,
+                            &ExprContext { row: Some(&row) },
+                        )? != Literal::Bool(true) {
+                            continue;
+                        }
+                    }
+                    rows.push(row);
+                }
+                page_id = page.get_next_id();
+            });
+        }
+        Ok(rows)
+    }
+
+    /// Enforce foreign keys for a change of table replacing old_rows by new_rows, paired by
+    /// position: INSERT has no old rows and DELETE has no new rows.
+    /// Called before the change is applied, so a violation leaves the table untouched:
+    /// 1. values written to a foreign key column must exist in the referenced column,
+    ///    except NULL, which references nothing
+    /// 2. values removed from a referenced column must not be referenced anymore,
+    ///    unless another row of table still holds them. Foreign keys with ON DELETE CASCADE
+    ///    or SET NULL are skipped on DELETE, whose referencing rows are found by plan_delete
+    pub(super) fn check_foreign_keys(
+        &self,
+        ctx: &ExecutionContext,
+        database: &str,
+        table: &str,
+        old_rows: &[Record],
+        new_rows: &[Record],
+    ) -> DbResult<()> {
+        let catalog = ctx.catalogs.get(database).unwrap();
+        let storage_engine = ctx.storage_engines.get(database).unwrap();
+        let schema = catalog.get_table_schema(table).unwrap();
+
+        for fk in &schema.foreign_keys {
+            let column = &schema.columns[fk.column];
+            // values left unchanged by UPDATE were checked when they were written
+            let written: Vec<Literal> = new_rows.iter().enumerate()
+                .filter(|(i, row)| old_rows.get(*i).is_none_or(|old| old.record[fk.column] != row.record[fk.column]))
+                .map(|(_, row)| logical_value(&row.record[fk.column], column))
+                .filter(|value| *value != Literal::Null)
+                .collect();
+            if written.is_empty() {
+                continue;
+            }
+
+            let ref_schema = catalog.get_table_schema(&fk.ref_table).unwrap();
+            let ref_column = ref_schema.column_index[&fk.ref_column];
+            let existing: HashSet<Literal> = self.matching_rows(storage_engine, ref_schema, &None)?.iter()
+                .map(|row| logical_value(&row.record[ref_column], &ref_schema.columns[ref_column]))
+                .collect();
+            if let Some(value) = written.iter().find(|value| !existing.contains(value)) {
+                return Err(DbError::ConstraintViolation(format!(
+                    "Value {} of '{}.{}' has no matching row in '{}.{}'",
+                    value, table, column.name, fk.ref_table, fk.ref_column
+                )));
+            }
+        }
+
+        let deleting = new_rows.is_empty();
+        for child in catalog.referencing_tables(table) {
+            for fk in child.foreign_keys.iter().filter(|fk| fk.ref_table == table) {
+                if deleting && fk.on_delete != ReferentialAction::Restrict {
+                    continue;
+                }
+                let ref_column = schema.column_index[&fk.ref_column];
+                let removed = self.removed_values(storage_engine, schema, ref_column, old_rows, new_rows)?;
+                if removed.is_empty() {
+                    continue;
+                }
+
+                let child_column = &child.columns[fk.column];
+                if let Some(row) = self.matching_rows(storage_engine, child, &None)?.iter()
+                    .find(|row| removed.contains(&logical_value(&row.record[fk.column], child_column))) {
+                    return Err(DbError::ConstraintViolation(format!(
+                        "Value {} of '{}.{}' is still referenced by '{}.{}'",
+                        row.record[fk.column], table, fk.ref_column, child.name, child_column.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rows changed by a DELETE of old_rows from table, as (table, column, values, action): the rows
+    /// of table whose column holds one of values are deleted by CASCADE, or have column set to NULL
+    /// by SET NULL. The rows of table itself are not included.
+    /// Follows ON DELETE CASCADE through every level of referencing tables, and checks the
+    /// foreign keys of each table reached before anything is changed
+    pub(super) fn plan_delete(
+        &self,
+        ctx: &ExecutionContext,
+        database: &str,
+        table: &str,
+        old_rows: Vec<Record>,
+    ) -> DbResult<Vec<ReferencingRows>> {
+        let catalog = ctx.catalogs.get(database).unwrap();
+        let storage_engine = ctx.storage_engines.get(database).unwrap();
+
+        let mut plan = Vec::new();
+        let mut pending = vec![(table.to_string(), old_rows)];
+        while let Some((table, old_rows)) = pending.pop() {
+            self.check_foreign_keys(ctx, database, &table, &old_rows, &[])?;
+
+            let schema = catalog.get_table_schema(&table).unwrap();
+            for child in catalog.referencing_tables(&table) {
+                for fk in child.foreign_keys.iter().filter(|fk| fk.ref_table == table && fk.on_delete != ReferentialAction::Restrict) {
+                    let ref_column = schema.column_index[&fk.ref_column];
+                    let removed = self.removed_values(storage_engine, schema, ref_column, &old_rows, &[])?;
+                    if removed.is_empty() {
+                        continue;
+                    }
+
+                    let child_column = &child.columns[fk.column];
+                    let child_rows: Vec<Record> = self.matching_rows(storage_engine, child, &None)?.into_iter()
+                        .filter(|row| removed.contains(&logical_value(&row.record[fk.column], child_column)))
+                        .collect();
+                    if child_rows.is_empty() {
+                        continue;
+                    }
+                    plan.push((child.name.clone(), fk.column, removed, fk.on_delete));
+                    if fk.on_delete == ReferentialAction::Cascade {
+                        pending.push((child.name.clone(), child_rows));
+                    } else {
+                        // setting the column to NULL is an UPDATE of the referencing rows, checked like one
+                        let nulled: Vec<Record> = child_rows.iter()
+                            .map(|row| {
+                                let mut row = row.clone();
+                                row.record[fk.column] = Literal::Null;
+                                row
+                            })
+                            .collect();
+                        self.check_foreign_keys(ctx, database, &child.name, &child_rows, &nulled)?;
+                    }
+                }
+            }
+        }
+        Ok(plan)
+    }
+
+    /// Values of column no row of table holds anymore once old_rows are replaced by new_rows
+    /// NULL is never included, as no row references it
+    fn removed_values(
+        &self,
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        column: ColumnId,
+        old_rows: &[Record],
+        new_rows: &[Record],
+    ) -> DbResult<HashSet<Literal>> {
+        let value_of = |row: &Record| logical_value(&row.record[column], &schema.columns[column]);
+
+        // rows deleted or updated to a different value
+        let changed: Vec<usize> = (0..old_rows.len())
+            .filter(|&i| new_rows.get(i).is_none_or(|new| value_of(new) != value_of(&old_rows[i])))
+            .collect();
+        if changed.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        // number of rows of table holding each value after the change
+        let mut counts: HashMap<Literal, usize> = HashMap::new();
+        for row in self.matching_rows(storage_engine, schema, &None)? {
+            *counts.entry(value_of(&row)).or_default() += 1;
+        }
+        for &i in &changed {
+            if let Some(count) = counts.get_mut(&value_of(&old_rows[i])) {
+                *count = count.saturating_sub(1);
+            }
+            if let Some(new) = new_rows.get(i) {
+                *counts.entry(value_of(new)).or_default() += 1;
+            }
+        }
+        Ok(changed.iter()
+            .map(|&i| value_of(&old_rows[i]))
+            .filter(|value| *value != Literal::Null && counts.get(value).is_none_or(|&count| count == 0))
+            .collect())
+    }
+}
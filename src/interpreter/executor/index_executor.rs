@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use paste::paste;
+use crate::compiler::ast::{Literal, Record};
+use crate::compiler::bounded_ast::{BoundExpr, BoundExprNode};
+use crate::interpreter::catalog_table::{TableIndex, TableOptions, TableSchema};
+use crate::interpreter::executor::Executor;
+use crate::interpreter::ExecResult;
+use crate::storage::bplus_tree::BPlusTree;
+use crate::storage::page::index_page::{get_internal_capacity, get_leaf_capacity, RecordId};
+use crate::storage::StorageEngine;
+use crate::types::{ColumnId, DbError, DbResult, PageId};
+use crate::with_read_pages;
+
+/// Key of a row in an index over column, None for NULL, which is not indexed
+pub(super) fn index_key(row: &Record, column: ColumnId) -> Option<i64> {
+    match row.record[column] {
+        Literal::Int(v) => Some(v as i64),
+        _ => None,
+    }
+}
+
+/// B+ tree of an index, whose pages split once they fill past the fill factor of the table
+fn open_index(storage_engine: &StorageEngine, options: &TableOptions, root: PageId) -> BPlusTree {
+    let max_keys = |capacity: usize| ((capacity - 1) * options.fillfactor as usize / 100).max(2);
+    BPlusTree::new(
+        root,
+        Arc::clone(&storage_engine.index_buffer_pool),
+        max_keys(get_internal_capacity()),
+        max_keys(get_leaf_capacity()),
+    )
+}
+
+/// Index and key to look up for a selection comparing an indexed column to a constant,
+/// alone or as one operand of AND. The whole selection is still evaluated on the row found
+pub(super) fn index_lookup<'a>(schema: &'a TableSchema, selection: &Option<BoundExprNode>) -> Option<(&'a TableIndex, i64)> {
+    fn find<'a>(schema: &'a TableSchema, expr: &BoundExpr) -> Option<(&'a TableIndex, i64)> {
+        match expr {
+            BoundExpr::And(left, right) => find(schema, left).or_else(|| find(schema, right)),
+            BoundExpr::Equals(left, right) => {
+                let (column, key) = match (left.as_ref(), right.as_ref()) {
+                    (BoundExpr::Column(column), BoundExpr::Literal(Literal::Int(key)))
+                    | (BoundExpr::Literal(Literal::Int(key)), BoundExpr::Column(column)) => (*column, *key),
+                    _ => return None,
+                };
+                schema.indexes.iter()
+                    .find(|index| index.column == column)
+                    .map(|index| (index, key as i64))
+            }
+            _ => None,
+        }
+    }
+    find(schema, &selection.as_ref()?.expr)
+}
+
+impl Executor {
+
+    /// Build a B+ tree over column from the live rows of table and record it in the catalog.
+    /// Fails if two rows hold the same value, as the tree maps each key to a single row
+    pub fn create_index(&mut self, name: &str, table: &str, column: ColumnId) -> DbResult<ExecResult> {
+        let mut ctx = self.context.write().unwrap();
+        let database = ctx.current_db.clone().unwrap();
+
+        let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
+        let storage_engine = ctx.storage_engines.get(&database).unwrap();
+
+        // collect the key and record id of every live row
+        let mut entries = Vec::new();
+        let mut page_id = schema.first_page_id;
+        while page_id != 0 {
+            with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
+                for (slot_id, record_bytes) in storage_engine.iter_record(&page) {
+                    if schema.deleted_at(&record_bytes).is_some() {
+                        continue;
+                    }
+                    let row = schema.deserialize_record(&record_bytes)
+                        .expect("Error deserializing record");
+                    if let Some(key) = index_key(&row, column) {
+                        entries.push((key, RecordId { page_id, slot_id }));
+                    }
+                }
+                page_id = page.get_next_id();
+            });
+        }
+
+        entries.sort_by_key(|&(key, _)| key);
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(DbError::ConstraintViolation(format!(
+                "Cannot create index '{}': value {} appears more than once in column '{}.{}'",
+                name, pair[0].0, schema.name, schema.columns[column].name
+            )));
+        }
+
+        // bulk_load allocates the root of the new tree
+        let mut tree = open_index(storage_engine, &schema.options, 0);
+        tree.bulk_load(&entries);
+        let _ = storage_engine.free_list.flush_all();
+
+        let index = TableIndex { name: name.to_string(), column, root: tree.get_root() };
+        ctx.catalogs.get_mut(&database).unwrap().add_index(table, index)?;
+        Ok(ExecResult::Success(format!("Index '{}' created on table '{}' with {} entries", name, table, entries.len())))
+    }
+
+    /// Free every page of the B+ tree of an index, then remove the index from the catalog
+    pub fn drop_index(&mut self, name: &str, table: &str) -> DbResult<ExecResult> {
+        let mut ctx = self.context.write().unwrap();
+        let database = ctx.current_db.clone().unwrap();
+
+        let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
+        let index = schema.get_index(name)
+            .ok_or_else(|| DbError::IndexNotFound(format!("Index '{}' does not exist on table '{}'", name, table)))?;
+        let storage_engine = ctx.storage_engines.get(&database).unwrap();
+        open_index(storage_engine, &schema.options, index.root).free_pages();
+
+        ctx.catalogs.get_mut(&database).unwrap().remove_index(table, name)?;
+        Ok(ExecResult::Success(format!("Index '{}' dropped from table '{}'", name, table)))
+    }
+
+    /// Enforce the unique keys of the indexes of table for a change replacing old_rows by new_rows.
+    /// Called before the change is applied, so a violation leaves the table untouched
+    pub(super) fn check_indexes(
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        old_rows: &[Record],
+        new_rows: &[Record],
+    ) -> DbResult<()> {
+        for index in &schema.indexes {
+            let mut tree = open_index(storage_engine, &schema.options, index.root);
+            let replaced: HashSet<i64> = old_rows.iter()
+                .filter_map(|row| index_key(row, index.column))
+                .collect();
+
+            // a new key may only be in the index already if a replaced row holds it
+            let mut added = HashSet::new();
+            for key in new_rows.iter().filter_map(|row| index_key(row, index.column)) {
+                if !added.insert(key) || (!replaced.contains(&key) && !tree.search(&key).is_empty()) {
+                    return Err(DbError::ConstraintViolation(format!(
+                        "Duplicate value {} for index '{}' on '{}.{}'",
+                        key, index.name, schema.name, schema.columns[index.column].name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the keys of removed_rows from the indexes of table, then add the keys of added_rows
+    /// pointing to their records. Removing first lets rows of one statement swap keys.
+    /// At least BULK_INDEX added rows are merged into each index by a rebuild.
+    /// Return the root of each index afterwards, in index order
+    pub(super) fn update_indexes(
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        removed_rows: &[Record],
+        added_rows: &[(Record, RecordId)],
+    ) -> Vec<PageId> {
+        schema.indexes.iter()
+            .map(|index| {
+                let mut tree = open_index(storage_engine, &schema.options, index.root);
+                for key in removed_rows.iter().filter_map(|row| index_key(row, index.column)) {
+                    for rid in tree.search(&key) {
+                        tree.delete(key, rid);
+                    }
+                }
+                let entries = added_rows.iter()
+                    .filter_map(|(row, rid)| index_key(row, index.column).map(|key| (key, *rid)));
+                if schema.options.bulk_index > 0 && added_rows.len() >= schema.options.bulk_index as usize {
+                    tree.bulk_insert(&entries.collect::<Vec<_>>());
+                } else {
+                    for (key, rid) in entries {
+                        tree.insert(key, rid);
+                    }
+                }
+                tree.get_root()
+            })
+            .collect()
+    }
+
+    /// Live row of table holding key in index, if any
+    pub(super) fn index_scan(
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        index: &TableIndex,
+        key: i64,
+    ) -> Option<Record> {
+        let mut tree = open_index(storage_engine, &schema.options, index.root);
+        let rid = *tree.search(&key).first()?;
+        let row;
+        with_read_pages!(storage_engine.buffer_pool, [(rid.page_id, page)], {
+            row = storage_engine.get_record(&page, rid.slot_id)
+                .filter(|record_bytes| schema.deleted_at(record_bytes).is_none())
+                .map(|record_bytes| schema.deserialize_record(&record_bytes).expect("Error deserializing record"));
+        });
+        row
+    }
+
+    /// Free the pages of every index of table
+    pub(super) fn free_index_pages(storage_engine: &StorageEngine, schema: &TableSchema) {
+        for index in &schema.indexes {
+            open_index(storage_engine, &schema.options, index.root).free_pages();
+        }
+    }
+
+    /// Ids of the pages of every index of schema
+    pub(super) fn index_page_ids(storage_engine: &StorageEngine, schema: &TableSchema) -> Vec<PageId> {
+        schema.indexes.iter()
+            .flat_map(|index| open_index(storage_engine, &schema.options, index.root).page_ids())
+            .collect()
+    }
+}
@@ -1,17 +1,25 @@
 mod database_executor;
 mod table_ddl_executor;
 mod expression_executor;
-mod select_executor;
+pub mod select_executor;
+mod explain_executor;
+mod foreign_key_executor;
+mod primary_key_executor;
+mod conflict_executor;
+mod index_executor;
+mod debug_executor;
 
 use std::sync::{Arc, RwLock};
-use crate::compiler::ast::Record;
+use crate::compiler::ast::{Literal, Record};
 use crate::compiler::bounded_ast::BoundStmt;
 use crate::interpreter::ExecResult;
 use crate::interpreter::execution_context::ExecutionContext;
-use crate::types::DbResult;
+use crate::types::{DbError, DbResult};
 
 pub struct Executor {
     pub context: Arc<RwLock<ExecutionContext>>,
+    // trim trailing spaces of CHAR values in query output
+    trim_char_output: bool,
 }
 
 /// Context for executing an expression
@@ -22,26 +30,76 @@ pub struct ExprContext<'a> {
 
 impl Executor {
     pub fn new(context: Arc<RwLock<ExecutionContext>>) -> Self {
-        Self { context }
+        Self { context, trim_char_output: false }
+    }
+
+    /// Trim trailing spaces of CHAR values in query output, showing values the way DISTINCT and
+    /// GROUP BY compare them rather than as stored
+    pub fn with_trim_char_output(mut self, enabled: bool) -> Self {
+        self.trim_char_output = enabled;
+        self
+    }
+
+    /// Value as shown in query output
+    pub fn output_value(&self, value: Literal) -> Literal {
+        match value {
+            Literal::String(s) if self.trim_char_output => Literal::String(s.trim_end_matches(' ').to_string()),
+            value => value,
+        }
+    }
+
+    /// Format a value for query output
+    pub fn format_value(&self, value: &Literal) -> String {
+        self.output_value(value.clone()).to_string()
     }
 
     pub fn execute(&mut self, stmt: BoundStmt) -> DbResult<ExecResult> {
+        if let Some((table, generation)) = stmt.schema_generation() {
+            self.check_schema_generation(table, generation)?;
+        }
+
         match stmt {
             BoundStmt::CreateDatabase { name } => self.create_database(&name),
             BoundStmt::DropDatabase { name } => self.drop_database(&name),
             BoundStmt::ConnectDatabase { name } => self.connect_database(&name),
             BoundStmt::DisconnectDatabase {} => self.disconnect_database(),
-            BoundStmt::CreateTable {name, columns } => self.create_table(&name , columns),
+            BoundStmt::CreateTable { name, columns, foreign_keys, options } => self.create_table(&name , columns, foreign_keys, options),
             BoundStmt::DropTable { name } => self.drop_table(&name),
-            BoundStmt::Insert { table, rows } => self.insert(&table, &rows),
-            BoundStmt::Update { table, assignments, selection } => 
+            BoundStmt::CreateIndex { name, table, column, .. } => self.create_index(&name, &table, column),
+            BoundStmt::DropIndex { name, table } => self.drop_index(&name, &table),
+            BoundStmt::Insert { table, rows, on_conflict, .. } => self.insert(&table, &rows, &on_conflict),
+            BoundStmt::Update { table, assignments, selection, .. } => 
                 self.update(&table, &assignments, &selection),
-            BoundStmt::Select { table, columns, selection } => {
-                self.select(&table, &columns, &selection)
+            BoundStmt::Select { table, distinct, columns, names, selection, group_by, order_by, .. } => {
+                self.select(&table, distinct, &columns, names, &selection, &group_by, &order_by)
             }
-            BoundStmt::Delete { table, selection } => {
+            BoundStmt::SelectConstant { columns, names } => self.select_constant(&columns, names),
+            BoundStmt::Delete { table, selection, .. } => {
                 self.delete(&table, &selection)
             }
+            BoundStmt::Explain { analyze, stmt } => self.explain(analyze, &stmt),
+            BoundStmt::Purge { table } => self.purge(&table),
+            BoundStmt::DumpPage { page_id } => self.dump_page(page_id),
+            BoundStmt::ShowTableStatus => self.show_table_status(),
+            BoundStmt::ShowRecords { table, selection, .. } => self.show_records(&table, &selection),
+            BoundStmt::Describe { table } => self.describe(&table),
+            BoundStmt::ShowCreateTable { table } => self.show_create_table(&table),
+        }
+    }
+
+    /// Reject a statement bound against an earlier schema of table, whose column ids and
+    /// pages may no longer be valid
+    fn check_schema_generation(&self, table: &str, generation: u64) -> DbResult<()> {
+        let ctx = self.context.read().unwrap();
+        let current = ctx.current_db.as_ref()
+            .and_then(|database| ctx.catalogs.get(database))
+            .and_then(|catalog| catalog.get_table_schema(table))
+            .map(|schema| schema.generation);
+        if current != Some(generation) {
+            return Err(DbError::SchemaChanged(format!(
+                "Table '{}' has changed since the statement was analyzed", table
+            )));
         }
+        Ok(())
     }
 }
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use crate::compiler::ast::{logical_value, Literal, Record};
+use crate::interpreter::catalog_table::TableSchema;
+use crate::interpreter::executor::Executor;
+use crate::storage::StorageEngine;
+use crate::types::{DbError, DbResult};
+
+impl Executor {
+
+    /// Enforce the primary key of table for a change replacing old_rows by new_rows:
+    /// no two rows may hold the same key once the change is applied.
+    /// Called before the change is applied, so a violation leaves the table untouched
+    pub(super) fn check_primary_key(
+        &self,
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        old_rows: &[Record],
+        new_rows: &[Record],
+    ) -> DbResult<()> {
+        let Some(column) = schema.primary_key() else { return Ok(()) };
+        let value_of = |row: &Record| logical_value(&row.record[column], &schema.columns[column]);
+
+        // number of rows holding each key, without the rows being replaced
+        let mut counts: HashMap<Literal, usize> = HashMap::new();
+        for row in self.matching_rows(storage_engine, schema, &None)? {
+            *counts.entry(value_of(&row)).or_default() += 1;
+        }
+        for row in old_rows {
+            if let Some(count) = counts.get_mut(&value_of(row)) {
+                *count = count.saturating_sub(1);
+            }
+        }
+
+        for row in new_rows {
+            let value = value_of(row);
+            let count = counts.entry(value.clone()).or_default();
+            *count += 1;
+            if *count > 1 {
+                return Err(DbError::ConstraintViolation(format!(
+                    "Duplicate value {} for primary key '{}.{}'", value, schema.name, schema.columns[column].name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
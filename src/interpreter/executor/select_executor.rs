@@ -1,19 +1,95 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 use paste::paste;
-use crate::compiler::ast::{Literal, Record};
-use crate::compiler::bounded_ast::{BoundExpr, BoundExprNode};
+use crate::compiler::ast::{logical_value, AggregateFunc, Literal, Record};
+use crate::compiler::bounded_ast::{BoundExpr, BoundExprNode, BoundOrderBy};
 use crate::interpreter::ExecResult;
+use crate::interpreter::query_result::QueryResult;
 use crate::interpreter::executor::{Executor, ExprContext};
-use crate::types::DbResult;
-use crate::with_read_pages;
+use crate::interpreter::executor::index_executor::index_lookup;
+use crate::types::{ColumnId, DbError, DbResult, PageId, SlotId};
+use crate::{trace_record, with_read_pages};
+
+/// Measurements collected while executing a SELECT, reported by EXPLAIN ANALYZE
+#[derive(Debug, Default)]
+pub struct SelectStats {
+    pub pages_read: usize,
+    pub rows_scanned: usize,   // rows read from the table heap
+    pub rows_matched: usize,   // rows passing WHERE clause
+    pub scan_time: Duration,   // heap scan, including filter and projection
+    pub filter_time: Duration, // evaluating WHERE clause, part of scan_time
+    pub sort_time: Duration,   // duplicate removal and sort
+}
+
+/// Position of a paged scan, the slot to resume from in a table heap.
+/// Clients only see it as an opaque token produced by encode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanCursor {
+    table: String,
+    page_id: PageId,
+    slot_id: SlotId,
+}
+
+impl ScanCursor {
+    /// Token layout: 8 hex digits page id, 2 hex digits slot id, then the table name
+    pub fn encode(&self) -> String {
+        format!("{:08x}{:02x}{}", self.page_id, self.slot_id, self.table)
+    }
+
+    pub fn decode(token: &str) -> DbResult<Self> {
+        let invalid = || DbError::InvalidCursor(format!("Malformed scan cursor '{}'", token));
+        let page_id = token.get(..8).and_then(|s| PageId::from_str_radix(s, 16).ok()).ok_or_else(invalid)?;
+        let slot_id = token.get(8..10).and_then(|s| SlotId::from_str_radix(s, 16).ok()).ok_or_else(invalid)?;
+        let table = token.get(10..).filter(|s| !s.is_empty()).ok_or_else(invalid)?;
+        Ok(Self { table: table.to_string(), page_id, slot_id })
+    }
+}
 
 impl Executor {
 
+    /// Return the rows of a SELECT as typed values, under the output names of the select items
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, columns, names, selection, group_by, order_by), fields(pages_read, rows)))]
     pub fn select(
         &self,
         table: &str,
+        distinct: bool,
         columns: &Vec<BoundExpr>,
-        selection: &Option<BoundExprNode>
+        names: Vec<String>,
+        selection: &Option<BoundExprNode>,
+        group_by: &[ColumnId],
+        order_by: &[BoundOrderBy],
     ) -> DbResult<ExecResult> {
+        let mut stats = SelectStats::default();
+        let rows = self.select_rows(table, distinct, columns, selection, group_by, order_by, &mut stats)?;
+
+        trace_record!("pages_read", stats.pages_read);
+        trace_record!("rows", rows.len());
+        Ok(ExecResult::Rows(QueryResult { columns: names, rows }))
+    }
+
+    /// Evaluate the select list of a SELECT without FROM into its single row
+    pub fn select_constant(&self, columns: &[BoundExpr], names: Vec<String>) -> DbResult<ExecResult> {
+        let expr_ctx = ExprContext { row: None };
+        let row = columns.iter()
+            .map(|col| Ok(self.output_value(self.execute_expression(col, &expr_ctx)?)))
+            .collect::<DbResult<Vec<Literal>>>()?;
+        Ok(ExecResult::Rows(QueryResult { columns: names, rows: vec![row] }))
+    }
+
+    /// Scan table heap, filter, group, project, deduplicate and sort rows, recording measurements in stats
+    #[allow(clippy::too_many_arguments)]
+    pub fn select_rows(
+        &self,
+        table: &str,
+        distinct: bool,
+        columns: &Vec<BoundExpr>,
+        selection: &Option<BoundExprNode>,
+        group_by: &[ColumnId],
+        order_by: &[BoundOrderBy],
+        stats: &mut SelectStats,
+    ) -> DbResult<Vec<Vec<Literal>>> {
 
         let ctx = self.context.read().unwrap();
         let database = ctx.current_db.clone().unwrap();
@@ -25,36 +101,335 @@ impl Executor {
         let mut page_id = schema.first_page_id;
         let mut next_id;
 
+        // aggregate queries fold rows into groups, in the order each group is first seen.
+        // without GROUP BY there is a single group, returned even if no row matches
+        let grouped = !group_by.is_empty() || columns.iter().any(|c| matches!(c, BoundExpr::Aggregate(..)));
+        let mut groups: Vec<Group> = Vec::new();
+        let mut group_ids: HashMap<Vec<Literal>, usize> = HashMap::new();
+        if grouped && group_by.is_empty() {
+            groups.push(Group::new(columns, None));
+            group_ids.insert(vec![], 0);
+        }
+        let mut matched = 0;
+        let mut filter_time = Duration::ZERO;
+
+        let scan_start = Instant::now();
+        let mut process_row = |row: Record| -> DbResult<()> {
+            let expr_ctx = ExprContext { row: Some(&row) };
+
+            // skip the row unless the condition evaluates to TRUE, FALSE and NULL both reject it
+            // no condition means updating every row
+            if let Some(condition) = selection {
+                let filter_start = Instant::now();
+                let value = self.execute_expression(&condition.expr, &expr_ctx)?;
+                filter_time += filter_start.elapsed();
+                if value != Literal::Bool(true) {
+                    return Ok(());
+                }
+            }
+
+            matched += 1;
+            if grouped {
+                // CHAR keys ignore trailing padding, like set-based operators
+                let key = group_by.iter()
+                    .map(|&id| logical_value(&row.record[id], &schema.columns[id]))
+                    .collect();
+                let group_id = *group_ids.entry(key).or_insert_with(|| {
+                    groups.push(Group::new(columns, Some(row.clone())));
+                    groups.len() - 1
+                });
+                for aggregator in groups[group_id].aggregators.iter_mut().flatten() {
+                    aggregator.accumulate(self, &expr_ctx)?;
+                }
+                return Ok(());
+            }
+
+            let mut projected = Vec::new();
+            for col in columns {
+                projected.push(self.output_value(self.execute_expression(col, &expr_ctx)?));
+            }
+
+            let mut sort_key = Vec::with_capacity(order_by.len());
+            for item in order_by {
+                sort_key.push(self.execute_expression(&item.expr, &expr_ctx)?);
+            }
+
+            result.push((sort_key, projected));
+            Ok(())
+        };
+
+        if let Some((index, key)) = index_lookup(schema, selection) {
+            // an equality condition on an indexed column matches at most one row,
+            // read from the table heap at the record id found in the index
+            if let Some(row) = Self::index_scan(storage_engine, schema, index, key) {
+                stats.pages_read += 1;
+                stats.rows_scanned += 1;
+                process_row(row)?;
+            }
+        } else {
+            while page_id != 0 {
+                stats.pages_read += 1;
+                with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
+                    next_id = page.get_next_id();
+                    for (_, record_bytes) in storage_engine.iter_record(&page) {
+                        stats.rows_scanned += 1;
+                        if schema.deleted_at(&record_bytes).is_some() {
+                            continue;
+                        }
+                        let row = schema.deserialize_record(&record_bytes)
+                            .expect("Error deserializing record");
+                        process_row(row)?;
+                    }
+                    page_id = next_id;
+                });
+            }
+        }
+        stats.rows_matched = matched;
+        stats.filter_time = filter_time;
+        stats.scan_time = scan_start.elapsed();
+
+        // row values and sort keys of a group are read from its first row,
+        // the analyzer only allows expressions over GROUP BY columns there
+        for group in &groups {
+            let expr_ctx = ExprContext { row: group.first_row.as_ref() };
+            let mut projected = Vec::with_capacity(columns.len());
+            for (col, aggregator) in columns.iter().zip(&group.aggregators) {
+                projected.push(match aggregator {
+                    Some(aggregator) => aggregator.result(self)?,
+                    None => self.output_value(self.execute_expression(col, &expr_ctx)?),
+                });
+            }
+
+            let mut sort_key = Vec::new();
+            if !group_by.is_empty() {
+                for item in order_by {
+                    sort_key.push(self.execute_expression(&item.expr, &expr_ctx)?);
+                }
+            }
+            result.push((sort_key, projected));
+        }
+
+        // keep the first occurrence of each projected row
+        let sort_start = Instant::now();
+        if distinct {
+            let mut seen = HashSet::new();
+            result.retain(|(_, row)| seen.insert(row.clone()));
+        }
+
+        // stable sort keeps heap order among equal keys
+        if !order_by.is_empty() {
+            result.sort_by(|(a, _), (b, _)| compare_sort_keys(a, b, order_by));
+        }
+        stats.sort_time = sort_start.elapsed();
+
+        Ok(result.into_iter().map(|(_, row)| row).collect())
+    }
+
+    /// Fetch at most limit matching rows in heap order, starting at cursor
+    /// or at the beginning of the table. Returns the cursor to resume from,
+    /// None once the scan has reached the end of the table.
+    /// Rows inserted behind the cursor during the scan are not returned,
+    /// rows inserted ahead of it are
+    pub fn select_page(
+        &self,
+        table: &str,
+        columns: &[BoundExpr],
+        selection: &Option<BoundExprNode>,
+        cursor: Option<&ScanCursor>,
+        limit: usize,
+    ) -> DbResult<(Vec<Vec<String>>, Option<ScanCursor>)> {
+
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.clone().unwrap();
+
+        let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
+        let storage_engine = ctx.storage_engines.get(&database).unwrap();
+
+        let (mut page_id, mut start_slot) = match cursor {
+            Some(cursor) if cursor.table != table => {
+                return Err(DbError::InvalidCursor(format!(
+                    "Scan cursor belongs to table '{}', not '{}'", cursor.table, table
+                )));
+            }
+            Some(cursor) => (cursor.page_id, cursor.slot_id as usize),
+            None => (schema.first_page_id, 0),
+        };
+        let mut next_id;
+        let mut result = Vec::new();
+
         while page_id != 0 {
             with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
                 next_id = page.get_next_id();
-                for (_, record_bytes) in page.iter_record() {
-                    let row = Record::deserialize(record_bytes, &schema.columns)
+                for (slot_id, record_bytes) in storage_engine.iter_record(&page) {
+                    if (slot_id as usize) < start_slot || schema.deleted_at(&record_bytes).is_some() {
+                        continue;
+                    }
+                    if result.len() == limit {
+                        let next = ScanCursor { table: table.to_string(), page_id, slot_id };
+                        return Ok((result, Some(next)));
+                    }
+
+                    let row = schema.deserialize_record(&record_bytes)
                         .expect("Error deserializing record");
-                    let expr_ctx = ExprContext { row: Some(&row) };
-
-                    // skip the row only if the condition evaluates to false
-                    // no condition means updating every row
-                    if let Some(condition) = selection {
-                        if let Literal::Bool(false) = self.execute_expression(
-                            &condition.expr,
-                            &expr_ctx
-                        )? {
-                            continue;
-                        }
+                    if let Some(projected) = self.filter_project(&row, columns, selection)? {
+                        result.push(projected);
                     }
+                }
+                page_id = next_id;
+                start_slot = 0;
+            });
+        }
+        Ok((result, None))
+    }
 
-                    let mut projected = Vec::new();
-                    for col in columns {
-                        projected.push(self.execute_expression(col, &expr_ctx)?.to_string());
+    /// Fetch at most limit matching rows in reverse heap order, from the last slot of the
+    /// last page of the table, so the rows appended last come first
+    pub fn select_reverse(
+        &self,
+        table: &str,
+        columns: &[BoundExpr],
+        selection: &Option<BoundExprNode>,
+        limit: usize,
+    ) -> DbResult<Vec<Vec<String>>> {
+
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.clone().unwrap();
+
+        let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
+        let storage_engine = ctx.storage_engines.get(&database).unwrap();
+        let mut result = Vec::new();
+
+        for page_id in storage_engine.page_chain(schema.first_page_id).into_iter().rev() {
+            with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
+                let records: Vec<_> = storage_engine.iter_record(&page).collect();
+                for (_, record_bytes) in records.into_iter().rev() {
+                    if result.len() == limit {
+                        return Ok(result);
+                    }
+                    if schema.deleted_at(&record_bytes).is_some() {
+                        continue;
                     }
 
-                    result.push(projected);
+                    let row = schema.deserialize_record(&record_bytes)
+                        .expect("Error deserializing record");
+                    if let Some(projected) = self.filter_project(&row, columns, selection)? {
+                        result.push(projected);
+                    }
                 }
-                page_id = next_id;
             });
         }
+        Ok(result)
+    }
+
+    /// Projected values of row, or None if row does not satisfy selection
+    fn filter_project(&self, row: &Record, columns: &[BoundExpr], selection: &Option<BoundExprNode>) -> DbResult<Option<Vec<String>>> {
+        let expr_ctx = ExprContext { row: Some(row) };
+        if let Some(condition) = selection {
+            if self.execute_expression(&condition.expr, &expr_ctx)? != Literal::Bool(true) {
+                return Ok(None);
+            }
+        }
+
+        let mut projected = Vec::with_capacity(columns.len());
+        for col in columns {
+            projected.push(self.format_value(&self.execute_expression(col, &expr_ctx)?));
+        }
+        Ok(Some(projected))
+    }
+}
+
+/// Rows sharing the same GROUP BY values
+struct Group<'a> {
+    first_row: Option<Record>,
+    aggregators: Vec<Option<Aggregator<'a>>>, // one per aggregate select item
+}
+
+impl<'a> Group<'a> {
+    fn new(columns: &'a [BoundExpr], first_row: Option<Record>) -> Self {
+        let aggregators = columns.iter()
+            .map(|c| match c {
+                BoundExpr::Aggregate(func, arg) => Some(Aggregator::new(*func, arg.as_deref())),
+                _ => None,
+            })
+            .collect();
+        Self { first_row, aggregators }
+    }
+}
+
+/// Running state of one aggregate select item
+struct Aggregator<'a> {
+    func: AggregateFunc,
+    arg: Option<&'a BoundExpr>,
+    count: usize,
+    sum: i64,
+    extreme: Option<Literal>, // current MIN or MAX
+}
+
+impl<'a> Aggregator<'a> {
+    fn new(func: AggregateFunc, arg: Option<&'a BoundExpr>) -> Self {
+        Self { func, arg, count: 0, sum: 0, extreme: None }
+    }
+
+    /// Fold one matching row into the aggregate.
+    /// Rows whose argument is NULL are skipped, so COUNT(column) counts only non-NULL values
+    fn accumulate(&mut self, executor: &Executor, ctx: &ExprContext) -> DbResult<()> {
+        let Some(arg) = self.arg else {
+            self.count += 1;
+            return Ok(());
+        };
+        let value = executor.execute_expression(arg, ctx)?;
+        if value == Literal::Null {
+            return Ok(());
+        }
+        self.count += 1;
+
+        match self.func {
+            AggregateFunc::Count => {}
+            AggregateFunc::Sum | AggregateFunc::Avg => {
+                if let Literal::Int(value) = value {
+                    self.sum += value as i64;
+                }
+            }
+            AggregateFunc::Min | AggregateFunc::Max => {
+                let wanted = if self.func == AggregateFunc::Min { Ordering::Less } else { Ordering::Greater };
+                if self.extreme.as_ref().is_none_or(|current| value.partial_cmp(current) == Some(wanted)) {
+                    self.extreme = Some(value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Result value. AVG truncates toward zero, and every aggregate
+    /// except COUNT is NULL over an input without non-NULL values
+    /// Return Err(DbError::ArithmeticError) if COUNT or SUM does not fit an INT
+    fn result(&self, executor: &Executor) -> DbResult<Literal> {
+        let int = |value: i64| i32::try_from(value).map(Literal::Int).map_err(|_| DbError::ArithmeticError(format!(
+            "{} result {} is out of range for INT", format!("{:?}", self.func).to_uppercase(), value
+        )));
+        match self.func {
+            AggregateFunc::Count => int(self.count as i64),
+            _ if self.count == 0 => Ok(Literal::Null),
+            AggregateFunc::Sum => int(self.sum),
+            AggregateFunc::Avg => int(self.sum / self.count as i64),
+            AggregateFunc::Min | AggregateFunc::Max => Ok(executor.output_value(self.extreme.clone().unwrap())),
+        }
+    }
+}
 
-        Ok(ExecResult::QueryResult(result))
+/// NULL sorts after every value in ascending order, and before every value in descending order
+fn compare_sort_keys(a: &[Literal], b: &[Literal], order_by: &[BoundOrderBy]) -> Ordering {
+    for ((x, y), item) in a.iter().zip(b).zip(order_by) {
+        let ordering = match (x, y) {
+            (Literal::Null, Literal::Null) => Ordering::Equal,
+            (Literal::Null, _) => Ordering::Greater,
+            (_, Literal::Null) => Ordering::Less,
+            _ => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+        };
+        let ordering = if item.descending { ordering.reverse() } else { ordering };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
     }
-}
\ No newline at end of file
+    Ordering::Equal
+}
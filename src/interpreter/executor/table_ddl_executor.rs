@@ -1,30 +1,51 @@
 use std::collections::HashMap;
+use crate::storage::page::data_page::{stored_len, DataPage};
+use crate::storage::page::index_page::RecordId;
 use crate::storage::page::page::{Page, PageError};
 use paste::paste;
-use crate::compiler::ast::{ColumnDef, Literal, Record};
+use crate::compiler::ast::{logical_value, ColumnDef, Literal, Record, ReferentialAction};
+use crate::interpreter::executor::conflict_executor::ResolvedInsert;
 use crate::interpreter::executor::{Executor, ExprContext};
-use crate::types::{DbError, DbResult, NO_FLUSH};
+use crate::storage::StorageEngine;
+use crate::types::{DbError, DbResult, PageId, NO_FLUSH};
 use crate::{with_create_pages, with_read_pages, with_write_pages};
-use crate::compiler::bounded_ast::{BoundAssignment, BoundExprNode};
-use crate::interpreter::catalog_table::TableSchema;
+use crate::compiler::bounded_ast::{BoundAssignment, BoundConflictPolicy, BoundExprNode};
+use crate::interpreter::catalog_table::{current_timestamp, ForeignKey, TableOptions, TableSchema};
 use crate::interpreter::ExecResult;
 
+/// Rows rewritten by an update: their number, and the rows whose index entries change,
+/// as they were and as they are now stored
+type RowUpdates = (usize, Vec<Record>, Vec<(Record, RecordId)>);
+
 impl Executor {
 
-    pub fn create_table(&mut self, name: &str, columns: Vec<ColumnDef>) -> DbResult<ExecResult> {
+    pub fn create_table(
+        &mut self,
+        name: &str,
+        columns: Vec<ColumnDef>,
+        foreign_keys: Vec<ForeignKey>,
+        options: TableOptions,
+    ) -> DbResult<ExecResult> {
         let mut ctx = self.context.write().unwrap();
         let database = ctx.current_db.clone().unwrap();
 
-        // create first table page
+        // create first table page, or write every preallocated page to the data file
+        // with the first one of the chain as first table page
         let page_id;
         let storage_engine = ctx.storage_engines.get(&database).unwrap();
-        with_create_pages!(storage_engine.buffer_pool, [(page_id, page)], NO_FLUSH, {});
+        if options.preallocate > 0 {
+            page_id = storage_engine.preallocate(options.preallocate as usize)
+                .map_err(|e| DbError::InternalError(format!("Failed to preallocate pages of table '{}': {:?}", name, e)))?[0];
+        } else {
+            with_create_pages!(storage_engine.buffer_pool, [(page_id, page)], NO_FLUSH, {});
+        }
         
         // create column index
         let mut column_index = HashMap::new();
         for i in 0..columns.len() {
             column_index.insert(columns[i].name.clone(), i);
         }
+        let physical_order = TableSchema::default_physical_order(&columns);
 
         // insert table information to catalog
         let schema = TableSchema {
@@ -32,6 +53,12 @@ impl Executor {
             columns,
             column_index,
             first_page_id: page_id,
+            options,
+            foreign_keys,
+            physical_order,
+            null_bitmap: true,
+            indexes: vec![],
+            generation: 0,
         };
 
         match ctx.catalogs.get_mut(&database).unwrap().add_table(schema) {
@@ -44,67 +71,234 @@ impl Executor {
         let mut ctx = self.context.write().unwrap();
         let database = ctx.current_db.clone().unwrap();
 
-        // mark all pages of table as freed
-        let mut page_id = ctx.catalogs.get(&database).unwrap().get_table_schema(name).unwrap().first_page_id;
-        let mut next_id;
+        // mark all pages of table and its indexes as freed
+        let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(name).unwrap();
         let storage_engine = ctx.storage_engines.get(&database).unwrap();
+        Self::free_table_pages(storage_engine, schema.first_page_id);
+        Self::free_index_pages(storage_engine, schema);
+
+        // shrink the data file if the table was at its end. The freed pages are reused anyway,
+        // so the drop still succeeds if their space cannot be released now
+        let _ = storage_engine.reclaim_space();
+
+        // remove table information from catalog
+        match ctx.catalogs.get_mut(&database).unwrap().remove_table(name) {
+            Ok(_) => Ok(ExecResult::Success(format!("Table '{}' dropped successfully", name))),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the contents of table with a freshly built replacement table, for rebuilding a table in place.
+    /// The replacement takes over the name of table and the old pages of table are freed.
+    /// The context write lock is held throughout, so other statements see either the old or the new table
+    pub fn swap_table(&mut self, table: &str, replacement: &str) -> DbResult<ExecResult> {
+        let mut ctx = self.context.write().unwrap();
+        let database = ctx.current_db.clone()
+            .ok_or_else(|| DbError::ConnectionNotFound("A database connection does not exist".to_string()))?;
+
+        // switch the catalog before freeing pages, so a crash in between only leaks the old pages
+        let old_schema = ctx.catalogs.get_mut(&database).unwrap().swap_table(table, replacement)?;
+        let storage_engine = ctx.storage_engines.get(&database).unwrap();
+        Self::free_table_pages(storage_engine, old_schema.first_page_id);
+        Self::free_index_pages(storage_engine, &old_schema);
+
+        Ok(ExecResult::Success(format!("Table '{}' replaced by '{}'", table, replacement)))
+    }
+
+    /// Free every page in the page chain starting at first_page_id, and the overflow pages of its records
+    fn free_table_pages(storage_engine: &StorageEngine, first_page_id: PageId) {
+        storage_engine.forget_page_chain(first_page_id);
+        let mut page_id = first_page_id;
+        let mut next_id;
         while page_id != 0 {
             with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
                 next_id = page.get_next_id();
+                for (slot_id, _) in page.iter_record() {
+                    if let Some(overflow_id) = page.get_overflow(slot_id) {
+                        storage_engine.free_overflow(overflow_id);
+                    }
+                }
                 storage_engine.buffer_pool.free_page(page_id, NO_FLUSH);
                 page_id = next_id
             });
         }
+    }
 
-        // remove table information from catalog
-        match ctx.catalogs.get_mut(&database).unwrap().remove_table(name) {
-            Ok(_) => Ok(ExecResult::Success(format!("Table '{}' dropped successfully", name))),
-            Err(e) => Err(e),
+    /// Rebuild the free list of the current database when its header pages are lost or corrupt.
+    /// The pages of every table and index in the catalog stay used and every other page becomes free
+    pub fn rebuild_free_list(&mut self) -> DbResult<ExecResult> {
+        let ctx = self.context.write().unwrap();
+        let database = ctx.current_db.clone()
+            .ok_or_else(|| DbError::ConnectionNotFound("A database connection does not exist".to_string()))?;
+        let storage_engine = ctx.storage_engines.get(&database).unwrap();
+
+        let tables = ctx.catalogs.get(&database).unwrap().tables();
+        let first_page_ids: Vec<PageId> = tables.iter().map(|schema| schema.first_page_id).collect();
+        let index_page_ids: Vec<PageId> = tables.iter()
+            .flat_map(|schema| Self::index_page_ids(storage_engine, schema))
+            .collect();
+        storage_engine.rebuild_free_list(&first_page_ids, &index_page_ids);
+
+        Ok(ExecResult::Success(format!("Free list of database '{}' rebuilt", database)))
+    }
+
+    /// Place records in copies of the pages of table the way insert does, without writing anything.
+    /// Return the number of pages in the page chain and the number of pages insert would append
+    fn pages_appended(storage_engine: &StorageEngine, schema: &TableSchema, records: &[Vec<u8>]) -> (usize, usize) {
+        let reserved_space = schema.options.reserved_space();
+        let read_page = |page_id: PageId| {
+            let copy: DataPage;
+            with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], { copy = *page; });
+            copy
+        };
+
+        // insert stays on the last page of the chain after appending a page,
+        // and moves on to the appended pages once that page is full
+        let mut chain_pages = 1;
+        let mut current = read_page(schema.first_page_id);
+        let mut appended: Vec<DataPage> = Vec::new();
+        let mut position = None;    // appended page insert is at, None while in the chain
+        for record in records {
+            let stored = vec![0u8; stored_len(record.len())];
+            loop {
+                let page = match position {
+                    None => &mut current,
+                    Some(i) => &mut appended[i],
+                };
+                if page.can_fit(stored.len() + reserved_space) {
+                    page.insert_record(&stored);
+                    break;
+                }
+                if position.map_or(!appended.is_empty(), |i| i + 1 < appended.len()) {
+                    position = Some(position.map_or(0, |i| i + 1));
+                } else if position.is_none() && current.get_next_id() != 0 {
+                    current = read_page(current.get_next_id());
+                    chain_pages += 1;
+                } else {
+                    // the copy is never written, so its page id is not allocated
+                    let mut new_page = DataPage::new(PageId::MAX);
+                    new_page.insert_record(&stored);
+                    appended.push(new_page);
+                    break;
+                }
+            }
         }
+
+        // pages are only appended once the whole chain is walked
+        (chain_pages, appended.len())
     }
 
-    pub fn insert(&mut self, table: &str, rows: &Vec<Vec<BoundExprNode>>) -> DbResult<ExecResult> {
-        let ctx = self.context.read().unwrap();
+    pub fn insert(&mut self, table: &str, rows: &[Vec<BoundExprNode>], on_conflict: &BoundConflictPolicy) -> DbResult<ExecResult> {
+        // changes to the indexes of the table are recorded in the catalog
+        let mut ctx = self.context.write().unwrap();
         let database = ctx.current_db.clone().unwrap();
-        let num_rows = rows.len();
         let expr_ctx = ExprContext { row: None };
 
         // write records to pages
-        let mut page_id = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap().first_page_id;
+        let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
+        let mut page_id = schema.first_page_id;
+        let reserved_space = schema.options.reserved_space();
         let storage_engine = ctx.storage_engines.get(&database).unwrap();
-        for record in rows {
-            let record_values = Record {
+        let records = rows.iter()
+            .map(|record| Ok(Record {
                 record: record.iter()
                     .map(|e| {
                         self.execute_expression(&e.expr, &expr_ctx)
                     })
                     .collect::<Result<Vec<Literal>, DbError>>()?,
-            };
-            let record_bytes = record_values.serialize().expect("Error serializing record");
+            }))
+            .collect::<DbResult<Vec<Record>>>()?;
+        let ResolvedInsert { inserted: records, replaced, updated } = self.resolve_conflicts(storage_engine, schema, records, on_conflict)?;
+        let num_rows = records.len();
+
+        // rows updated on conflict lead, paired with their old values as in UPDATE
+        // only one of replaced and updated has rows, depending on the conflict policy
+        let (mut old_rows, mut new_rows): (Vec<Record>, Vec<Record>) = updated.iter().cloned().unzip();
+        old_rows.extend(replaced.iter().cloned());
+        new_rows.extend(records.iter().cloned());
+        self.check_primary_key(storage_engine, schema, &old_rows, &new_rows)?;
+        Self::check_indexes(storage_engine, schema, &old_rows, &new_rows)?;
+        self.check_foreign_keys(&ctx, &database, table, &old_rows, &new_rows)?;
+
+        // the page quota is checked before any row is written, so a rejected statement changes nothing
+        let records_bytes = records.iter()
+            .map(|record| schema.serialize_record(record))
+            .collect::<DbResult<Vec<_>>>()?;
+        if let Some(max_pages) = schema.options.max_pages {
+            let (chain_pages, appended_pages) = Self::pages_appended(storage_engine, schema, &records_bytes);
+            if appended_pages > 0 && chain_pages + appended_pages > max_pages as usize {
+                return Err(DbError::QuotaExceeded(format!(
+                    "Table '{}' reached its limit of {} pages", table, max_pages
+                )));
+            }
+        }
+
+        // rows replaced by INSERT OR REPLACE are deleted first, freeing their keys
+        let deleted = if replaced.is_empty() {
+            vec![]
+        } else {
+            self.delete_rows(storage_engine, schema, |row| Ok(replaced.contains(row)))?
+        };
+
+        // rows updated by ON CONFLICT DO UPDATE are rewritten like UPDATE rewrites them
+        let (updated_count, removed_rows, added_rows) = if updated.is_empty() {
+            (0, vec![], vec![])
+        } else {
+            self.update_rows(storage_engine, schema, !schema.indexes.is_empty(), |row| {
+                Ok(updated.iter().find(|(old, _)| old == row).map(|(_, new)| new.clone()))
+            })?
+        };
+
+        // record id of each inserted record, for the indexes of the table
+        let mut inserted = Vec::with_capacity(records.len());
+        for (record_values, record_bytes) in records.iter().zip(&records_bytes) {
             loop {
                 // attempt to insert to current page
                 with_write_pages!(storage_engine.buffer_pool, [(page_id, page)], NO_FLUSH, {
-                    if page.insert_record(&record_bytes).is_none() {
+                    // keep the space reserved by table fill factor free
+                    if !page.can_fit(stored_len(record_bytes.len()) + reserved_space) {
                         // there is no sufficient space in current page
                         if page.get_next_id() == 0 {
                             // reach the end of heap file, append new page
                             let new_page_id;
                             with_create_pages!(storage_engine.buffer_pool, [(new_page_id, new_page)], NO_FLUSH, {
                                 page.set_next_id(new_page_id);
-                                new_page.insert_record(&record_bytes).expect("Error inserting record to new page");
+                                let slot_id = storage_engine.insert_record(&mut new_page, record_bytes).expect("Error inserting record to new page");
+                                inserted.push((record_values.clone(), RecordId { page_id: new_page_id, slot_id }));
                             });
                             break;
                         }
                         // try insert to next page
                         page_id = page.get_next_id();
                     } else {
+                        let slot_id = storage_engine.insert_record(&mut page, record_bytes).expect("Error inserting record to page");
+                        inserted.push((record_values.clone(), RecordId { page_id, slot_id }));
                         break;
                     }
                 });
             }
         }
 
-        Ok(ExecResult::AffectedRows(num_rows, format!("Insert {} records to table '{}'", num_rows, table)))
+        let index_roots = Self::update_indexes(
+            storage_engine,
+            schema,
+            &[deleted.as_slice(), &removed_rows].concat(),
+            &[inserted, added_rows].concat(),
+        );
+
+        // pages appended above are allocated without flushing, write the free list once
+        // headers that cannot be written stay dirty for a later flush, the rows are inserted either way
+        let _ = storage_engine.free_list.flush_all();
+        ctx.catalogs.get_mut(&database).unwrap().set_index_roots(table, &index_roots)?;
+
+        let mut message = format!("Insert {} records to table '{}'", num_rows, table);
+        if !deleted.is_empty() {
+            message.push_str(&format!(" replacing {} rows", deleted.len()));
+        }
+        if updated_count > 0 {
+            message.push_str(&format!(" and update {} rows", updated_count));
+        }
+        Ok(ExecResult::AffectedRows(num_rows + updated_count, message))
     }
 
     pub fn update(
@@ -114,15 +308,80 @@ impl Executor {
         selection: &Option<BoundExprNode>
     ) -> DbResult<ExecResult> {
 
-        let ctx = self.context.read().unwrap();
+        // changes to the indexes of the table are recorded in the catalog
+        let mut ctx = self.context.write().unwrap();
         let database = ctx.current_db.clone().unwrap();
 
         let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
         let storage_engine = ctx.storage_engines.get(&database).unwrap();
 
+        // check the new values against the primary key, indexes and foreign keys before changing any row
+        let updates_key = schema.primary_key()
+            .is_some_and(|column| assignments.iter().any(|assign| assign.column_id == column));
+        let updates_index = schema.indexes.iter()
+            .any(|index| assignments.iter().any(|assign| assign.column_id == index.column));
+        let has_foreign_keys = Self::has_foreign_keys(&ctx, &database, table);
+        if updates_key || updates_index || has_foreign_keys {
+            let old_rows = self.matching_rows(storage_engine, schema, selection)?;
+            let mut new_rows = Vec::with_capacity(old_rows.len());
+            for old in &old_rows {
+                let expr_ctx = ExprContext { row: Some(old) };
+                let mut new = old.clone();
+                for assign in assignments {
+                    new.record[assign.column_id] = self.execute_expression(&assign.value, &expr_ctx)?;
+                }
+                new_rows.push(new);
+            }
+            if updates_key {
+                self.check_primary_key(storage_engine, schema, &old_rows, &new_rows)?;
+            }
+            if updates_index {
+                Self::check_indexes(storage_engine, schema, &old_rows, &new_rows)?;
+            }
+            if has_foreign_keys {
+                self.check_foreign_keys(&ctx, &database, table, &old_rows, &new_rows)?;
+            }
+        }
+
+        let (updated_count, removed_rows, added_rows) = self.update_rows(storage_engine, schema, updates_index, |row| {
+            // skip the row unless the condition evaluates to TRUE, FALSE and NULL both reject it
+            // no condition means updating every row
+            let expr_ctx = ExprContext { row: Some(row) };
+            if let Some(condition) = selection {
+                if self.execute_expression(&condition.expr, &expr_ctx)? != Literal::Bool(true) {
+                    return Ok(None);
+                }
+            }
+
+            // apply update
+            let mut new_row = row.clone();
+            for assign in assignments {
+                new_row.record[assign.column_id] = self.execute_expression(&assign.value, &expr_ctx)?;
+            }
+            Ok(Some(new_row))
+        })?;
+
+        let index_roots = Self::update_indexes(storage_engine, schema, &removed_rows, &added_rows);
+        ctx.catalogs.get_mut(&database).unwrap().set_index_roots(table, &index_roots)?;
+
+        Ok(ExecResult::AffectedRows(updated_count, format!("Updated {} rows in table '{}'", updated_count, table)))
+    }
+
+    /// Rewrite the live rows of table for which new_row gives a new value. A record that grew beyond
+    /// the free space of its page is spilled to overflow pages, or moved if the page is full
+    fn update_rows(
+        &self,
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        updates_index: bool,
+        new_row: impl Fn(&Record) -> DbResult<Option<Record>>,
+    ) -> DbResult<RowUpdates> {
         let mut page_id = schema.first_page_id;
         let mut next_id;
         let mut updated_count = 0;
+        // rows whose index keys or record ids change, as they were and as they are now stored
+        let mut removed_rows = Vec::new();
+        let mut added_rows = Vec::new();
 
         while page_id != 0 {
             with_write_pages!(storage_engine.buffer_pool, [(page_id, page)], NO_FLUSH, {
@@ -130,61 +389,68 @@ impl Executor {
                 let mut updates = Vec::new();
                 next_id = page.get_next_id();
 
-                for (slot_id, record_bytes) in page.iter_record() {
-                    let mut row = Record::deserialize(record_bytes, &schema.columns)
-                        .expect("Error deserializing record");
-
-                    // skip the row only if the condition evaluates to false
-                    // no condition means updating every row
-                    let expr_ctx = ExprContext { row: Some(&row.clone()) };
-                    if let Some(condition) = selection {
-                        if let Literal::Bool(false) = self.execute_expression(
-                            &condition.expr,
-                            &expr_ctx,
-                        )? {
-                            continue;
-                        }
+                for (slot_id, record_bytes) in storage_engine.iter_record(&page) {
+                    if schema.deleted_at(&record_bytes).is_some() {
+                        continue;
                     }
+                    let old_row = schema.deserialize_record(&record_bytes)
+                        .expect("Error deserializing record");
+                    let Some(row) = new_row(&old_row)? else {
+                        continue;
+                    };
 
-                    // apply update and serialize result
-                    for assign in assignments {
-                        row.record[assign.column_id] = self.execute_expression(&assign.value, &expr_ctx)?;
-                    }
-                    let result_bytes = row.serialize().expect("Error serializing result");
-                    updates.push((slot_id, result_bytes));
+                    // serialize result
+                    let result_bytes = schema.serialize_record(&row)?;
+                    updates.push((slot_id, result_bytes, old_row, row));
                 }
 
                 // apply update to page
-                for (slot_id, result_bytes) in updates {
-                    match page.update_record(slot_id, &result_bytes) {
-                        Ok(_) => { updated_count += 1; },
+                for (slot_id, result_bytes, old_row, new_row) in updates {
+                    match storage_engine.update_record(&mut page, slot_id, &result_bytes) {
+                        Ok(_) => {
+                            if updates_index {
+                                removed_rows.push(old_row);
+                                added_rows.push((new_row, RecordId { page_id, slot_id }));
+                            }
+                            updated_count += 1;
+                        },
                         Err(PageError::RecordSizeChanged) => {
-                            // the record size has changed, delete the record
+                            // the page cannot hold even an overflow pointer, delete the record
                             // and insert a new record in table
-                            page.delete_record(slot_id).expect("Error deleting record");
+                            storage_engine.delete_record(&mut page, slot_id).expect("Error deleting record");
 
                             let mut insert_page_id = schema.first_page_id;
+                            let moved_to;
                             loop {
                                 // iterate through table to find space for insertion
                                 with_write_pages!( storage_engine.buffer_pool, [(insert_page_id, insert_page)], NO_FLUSH, {
                                     // successfully insert new record
-                                    if insert_page.insert_record(&result_bytes).is_some() {
+                                    if let Some(slot_id) = storage_engine.insert_record(&mut insert_page, &result_bytes) {
+                                        moved_to = RecordId { page_id: insert_page_id, slot_id };
                                         break;
                                     }
 
                                     // append new pages
                                     if insert_page.get_next_id() == 0 {
                                         let new_page_id;
+                                        let slot_id;
                                         with_create_pages!(storage_engine.buffer_pool, [(new_page_id, new_page)], NO_FLUSH, {
                                             insert_page.set_next_id(new_page_id);
-                                            new_page.insert_record(&result_bytes).expect("Error inserting record to new page");
+                                            slot_id = storage_engine.insert_record(&mut new_page, &result_bytes).expect("Error inserting record to new page");
                                         });
+                                        moved_to = RecordId { page_id: new_page_id, slot_id };
                                         break;
                                     }
 
                                     insert_page_id = insert_page.get_next_id();
                                 });
                             }
+
+                            // the record moved, so its index entries point to the new record id
+                            if !schema.indexes.is_empty() {
+                                removed_rows.push(old_row);
+                                added_rows.push((new_row, moved_to));
+                            }
                             updated_count += 1;
                         },
                         Err(e) => panic!("Unexpected update error: {:?}", e),
@@ -194,7 +460,7 @@ impl Executor {
             page_id = next_id;
         }
 
-        Ok(ExecResult::AffectedRows(updated_count, format!("Updated {} rows in table '{}'", updated_count, table)))
+        Ok((updated_count, removed_rows, added_rows))
     }
 
     pub fn delete(
@@ -203,52 +469,156 @@ impl Executor {
         selection: &Option<BoundExprNode>
     ) -> DbResult<ExecResult> {
 
-        let ctx = self.context.read().unwrap();
+        // changes to the indexes of the table and cascaded tables are recorded in the catalog
+        let mut ctx = self.context.write().unwrap();
         let database = ctx.current_db.clone().unwrap();
 
         let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
         let storage_engine = ctx.storage_engines.get(&database).unwrap();
 
+        // rows still referenced by a foreign key cannot be deleted, and ON DELETE CASCADE
+        // and SET NULL find the referencing rows deleted or set to NULL along with them
+        let referencing = if Self::has_foreign_keys(&ctx, &database, table) {
+            let old_rows = self.matching_rows(storage_engine, schema, selection)?;
+            self.plan_delete(&ctx, &database, table, old_rows)?
+        } else {
+            vec![]
+        };
+
+        let deleted = self.delete_rows(storage_engine, schema, |row| {
+            // Apply selection predicate
+            match selection {
+                Some(condition) => Ok(
+                    self.execute_expression(&condition.expr, &ExprContext { row: Some(row) })? == Literal::Bool(true)
+                ),
+                None => Ok(true),
+            }
+        })?;
+        let deleted_count = deleted.len();
+        let mut index_roots = vec![(table.to_string(), Self::update_indexes(storage_engine, schema, &deleted, &[]))];
+
+        let (mut cascaded_count, mut nulled_count) = (0, 0);
+        for (child, column, values, action) in referencing {
+            let child_schema = ctx.catalogs.get(&database).unwrap().get_table_schema(&child).unwrap();
+            let column_def = &child_schema.columns[column];
+            let references = |row: &Record| values.contains(&logical_value(&row.record[column], column_def));
+            if action == ReferentialAction::Cascade {
+                let cascaded = self.delete_rows(storage_engine, child_schema, |row| Ok(references(row)))?;
+                cascaded_count += cascaded.len();
+                index_roots.push((child, Self::update_indexes(storage_engine, child_schema, &cascaded, &[])));
+            } else {
+                let updates_index = child_schema.indexes.iter().any(|index| index.column == column);
+                let (updated_count, removed_rows, added_rows) = self.update_rows(storage_engine, child_schema, updates_index, |row| {
+                    Ok(references(row).then(|| {
+                        let mut row = row.clone();
+                        row.record[column] = Literal::Null;
+                        row
+                    }))
+                })?;
+                nulled_count += updated_count;
+                index_roots.push((child, Self::update_indexes(storage_engine, child_schema, &removed_rows, &added_rows)));
+            }
+        }
+
+        let catalog = ctx.catalogs.get_mut(&database).unwrap();
+        for (table, roots) in index_roots {
+            catalog.set_index_roots(&table, &roots)?;
+        }
+
+        let mut message = format!("Deleted {} rows from table '{}'", deleted_count, table);
+        if cascaded_count > 0 {
+            message.push_str(&format!(" and {} referencing rows", cascaded_count));
+        }
+        if nulled_count > 0 {
+            message.push_str(&format!(", setting {} referencing rows to NULL", nulled_count));
+        }
+        Ok(ExecResult::AffectedRows(deleted_count, message))
+    }
+
+    /// Delete the live rows of table for which should_delete holds, returning the deleted rows.
+    /// Tombstoned rows of soft-delete tables count as deleted, their index keys are free for new rows
+    fn delete_rows(
+        &self,
+        storage_engine: &StorageEngine,
+        schema: &TableSchema,
+        should_delete: impl Fn(&Record) -> DbResult<bool>,
+    ) -> DbResult<Vec<Record>> {
         let mut page_id = schema.first_page_id;
         let mut next_id;
-        let mut deleted_count = 0;
+        let mut deleted = Vec::new();
+        let now = current_timestamp();
 
         while page_id != 0 {
             with_write_pages!(storage_engine.buffer_pool, [(page_id, page)], NO_FLUSH, {
                 let mut to_delete = Vec::new();
                 next_id = page.get_next_id();
 
-                for (slot_id, record_bytes) in page.iter_record() {
-                    let row = Record::deserialize(record_bytes, &schema.columns)
+                for (slot_id, record_bytes) in storage_engine.iter_record(&page) {
+                    if schema.deleted_at(&record_bytes).is_some() {
+                        continue;
+                    }
+                    let row = schema.deserialize_record(&record_bytes)
                         .expect("Error deserializing record");
-
-                    let expr_ctx = ExprContext { row: Some(&row) };
-
-                    // Apply selection predicate
-                    if let Some(condition) = selection {
-                        if let Literal::Bool(false) = self.execute_expression(
-                            &condition.expr,
-                            &expr_ctx,
-                        )? {
-                            continue;
-                        }
+                    if should_delete(&row)? {
+                        to_delete.push((slot_id, row));
                     }
-                    to_delete.push(slot_id);
                 }
 
                 // Apply deletions after iteration
-                for slot_id in to_delete {
-                    page.delete_record(slot_id)
-                        .expect("Error deleting record");
-                    deleted_count += 1;
+                // soft-delete tables keep the record in place with a tombstone
+                for (slot_id, row) in to_delete {
+                    if schema.options.soft_delete {
+                        let tombstone = schema.tombstone(&storage_engine.get_record(&page, slot_id).unwrap(), now);
+                        storage_engine.update_record(&mut page, slot_id, &tombstone)
+                            .expect("Error marking record deleted");
+                    } else {
+                        storage_engine.delete_record(&mut page, slot_id)
+                            .expect("Error deleting record");
+                    }
+                    deleted.push(row);
+                }
+            });
+            page_id = next_id;
+        }
+
+        Ok(deleted)
+    }
+
+    /// Reclaim the records of a soft-delete table tombstoned longer than its retention window
+    pub fn purge(&mut self, table: &str) -> DbResult<ExecResult> {
+        let ctx = self.context.read().unwrap();
+        let database = ctx.current_db.clone().unwrap();
+
+        let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
+        let storage_engine = ctx.storage_engines.get(&database).unwrap();
+        let cutoff = current_timestamp().saturating_sub(schema.options.retention as u64);
+
+        let mut page_id = schema.first_page_id;
+        let mut next_id;
+        let mut purged_count = 0;
+
+        while page_id != 0 {
+            with_write_pages!(storage_engine.buffer_pool, [(page_id, page)], NO_FLUSH, {
+                next_id = page.get_next_id();
+
+                let expired: Vec<_> = storage_engine.iter_record(&page)
+                    .filter(|(_, record_bytes)| {
+                        schema.deleted_at(record_bytes).is_some_and(|deleted_at| deleted_at <= cutoff)
+                    })
+                    .map(|(slot_id, _)| slot_id)
+                    .collect();
+
+                for slot_id in expired {
+                    storage_engine.delete_record(&mut page, slot_id).expect("Error purging record");
+                    purged_count += 1;
                 }
             });
             page_id = next_id;
         }
 
         Ok(ExecResult::AffectedRows(
-            deleted_count,
-            format!("Deleted {} rows from table '{}'", deleted_count, table)
+            purged_count,
+            format!("Purged {} rows from table '{}'", purged_count, table)
         ))
     }
-}
\ No newline at end of file
+}
@@ -3,33 +3,56 @@ pub mod database_catalog;
 pub mod executor;
 pub mod analyzer;
 pub mod catalog_table;
+pub mod prepared_statement;
+pub mod query_result;
+pub mod slow_query_log;
+mod dump;
 
 use std::path::Path;
 use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
 use execution_context::ExecutionContext;
-use database_catalog::GlobalCatalog;
-use crate::compiler::ast::Statement;
+use database_catalog::{GlobalCatalog, OrphanReport};
+use crate::compiler::ast::{ConflictPolicy, Expression, Statement};
+use crate::compiler::parser::Parser;
+use crate::compiler::scanner::Scanner;
 use crate::interpreter::analyzer::Analyzer;
 use crate::interpreter::executor::Executor;
+use crate::interpreter::executor::select_executor::ScanCursor;
+use crate::compiler::bounded_ast::{BoundExpr, BoundExprNode, BoundStmt};
+use crate::interpreter::prepared_statement::PreparedStatement;
+use crate::interpreter::query_result::QueryResult;
+use crate::interpreter::slow_query_log::SlowQueryLog;
+use crate::compiler::ast::Literal;
 use crate::types::DbError::DatabaseNotFound;
-use crate::types::DbResult;
+use crate::types::{DbError, DbResult};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum ExecResult {
     Success(String),
     AffectedRows(usize, String),
-    QueryResult(Vec<Vec<String>>),
+    QueryResult(Vec<Vec<String>>),  // rows of text, describing the database or a query plan
+    Rows(QueryResult),              // typed rows of a SELECT
 }
 
 pub struct Interpreter {
     pub context: Arc<RwLock<ExecutionContext>>,
+    slow_query_log: Option<SlowQueryLog>,
+    orphans: OrphanReport,
+    // reject UPDATE and DELETE without a WHERE clause
+    safe_updates: bool,
+    // trim trailing spaces of CHAR values in query output
+    trim_char_output: bool,
 }
 
 impl Interpreter {
     pub fn new(dbms_root_dir: impl AsRef<Path>) -> Self {
         let dbms_root_dir = dbms_root_dir.as_ref().to_path_buf();
 
-        let catalog = GlobalCatalog::new(&dbms_root_dir);
+        // drop catalog entries of missing databases before opening them
+        let mut catalog = GlobalCatalog::new(&dbms_root_dir);
+        let orphans = catalog.reconcile().expect("Failed to reconcile database catalog");
         let databases = catalog.list_databases();
 
         let context = Arc::new(RwLock::new(
@@ -45,29 +68,244 @@ impl Interpreter {
             }
         }
 
-        Self { context }
+        Self { context, slow_query_log: None, orphans, safe_updates: false, trim_char_output: false }
+    }
+
+    /// Databases found inconsistent between catalog and file system on startup
+    pub fn orphans(&self) -> &OrphanReport {
+        &self.orphans
+    }
+
+    /// Enable or disable logging of slow statements
+    pub fn set_slow_query_log(&mut self, log: Option<SlowQueryLog>) {
+        self.slow_query_log = log;
+    }
+
+    /// Enable or disable safe updates mode, where UPDATE and DELETE must have a WHERE clause.
+    /// Use WHERE TRUE to modify every row explicitly
+    pub fn set_safe_updates(&mut self, enabled: bool) {
+        self.safe_updates = enabled;
+    }
+
+    /// Enable or disable trimming trailing spaces of CHAR values in query output, off by default.
+    /// Stored values and comparisons are unchanged
+    pub fn set_trim_char_output(&mut self, enabled: bool) {
+        self.trim_char_output = enabled;
+    }
+
+    /// Apply a session setting of a SET statement
+    fn set_option(&mut self, name: &str, value: Literal) -> DbResult<ExecResult> {
+        let enabled = match value {
+            Literal::Bool(enabled) => enabled,
+            value => return Err(DbError::TypeMismatch(format!("Setting '{}' expects TRUE or FALSE, got {}", name, value))),
+        };
+        match name.to_uppercase().as_str() {
+            "TRIM_CHAR_OUTPUT" => self.set_trim_char_output(enabled),
+            "SAFE_UPDATES" => self.set_safe_updates(enabled),
+            _ => return Err(DbError::InvalidSetting(format!("Unknown setting '{}'", name))),
+        }
+        Ok(ExecResult::Success(format!("Setting '{}' set to {}", name, value)))
+    }
+
+    fn executor(&self) -> Executor {
+        Executor::new(Arc::clone(&self.context)).with_trim_char_output(self.trim_char_output)
     }
 
     /// Entry point for interactive SQL interpreter
     pub fn execute(&mut self, stmt: Statement) -> DbResult<ExecResult> {
-        // only database-level statements are permitted if a database connection does not exist
+        self.execute_statement(stmt, None)
+    }
+
+    /// Execute statement, with its source text (if known) for the slow query log
+    fn execute_statement(&mut self, stmt: Statement, sql: Option<&str>) -> DbResult<ExecResult> {
+        let mut analyzer = Analyzer::new(Arc::clone(&self.context));
+        let mut executor = self.executor();
+        self.execute_with(&mut analyzer, &mut executor, stmt, sql)
+    }
+
+    /// Execute statement with the given analyzer and executor, which may be shared by a batch
+    fn execute_with(
+        &mut self,
+        analyzer: &mut Analyzer,
+        executor: &mut Executor,
+        stmt: Statement,
+        sql: Option<&str>,
+    ) -> DbResult<ExecResult> {
+        // only database-level statements, SELECT without FROM and SET are permitted if a database connection does not exist
         if self.context.read().unwrap().current_db.is_none() {
             if !matches!(stmt,
                 Statement::CreateDatabase { name: _ } |
                 Statement::DropDatabase { name: _ } |
                 Statement::ConnectDatabase { name: _ } |
-                Statement::DisconnectDatabase {}
+                Statement::DisconnectDatabase {} |
+                Statement::SelectConstant { .. } |
+                Statement::Set { .. }
             ) {
                 return Err(DatabaseNotFound("A database connection does not exist".to_string()));
             }
         }
 
+        if let Statement::Set { name, value } = stmt {
+            return self.set_option(&name, value);
+        }
+
+        if self.safe_updates {
+            match &stmt {
+                Statement::Update { table, selection: None, .. } | Statement::Delete { table, selection: None } => {
+                    return Err(DbError::UnsafeUpdate(format!(
+                        "Safe updates mode rejects modifying every row of '{}' without a WHERE clause, use WHERE TRUE to override",
+                        table
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        // statement text is only needed when the slow query log is enabled
+        let sql = self.slow_query_log.as_ref()
+            .map(|_| sql.map_or_else(|| format!("{:?}", stmt), str::to_string));
+
+        let bound = analyzer.analyze(stmt)?;
+        let start = Instant::now();
+        let result = executor.execute(bound);
+
+        if let (Some(log), Some(sql)) = (self.slow_query_log.as_mut(), sql) {
+            log.record(&sql, start.elapsed(), &result);
+        }
+        result
+    }
+
+    /// Parse and execute every statement in a SQL string, returning one result per statement
+    pub fn execute_sql(&mut self, sql: &str) -> DbResult<Vec<DbResult<ExecResult>>> {
+        let mut scanner = Scanner::new(sql);
+        let mut parser = Parser::new(&mut scanner)?;
+        let statements = parser.parse_with_source()?;
+
+        Ok(statements.into_iter()
+            .map(|(stmt, sql)| self.execute_statement(stmt, Some(&sql)))
+            .collect())
+    }
+
+    /// Run a script as one batch: every statement is parsed up front, then analyzed and executed in order
+    /// by a single analyzer and executor. Each statement still takes the context lock for its own
+    /// analysis and execution, so other sessions may interleave between statements.
+    /// Results are reported per statement, exactly as with execute_sql
+    pub fn run(&mut self, sql: &str) -> DbResult<Vec<DbResult<ExecResult>>> {
+        let mut scanner = Scanner::new(sql);
+        let mut parser = Parser::new(&mut scanner)?;
+        let statements = parser.parse_with_source()?;
+
+        let mut analyzer = Analyzer::new(Arc::clone(&self.context));
+        let mut executor = self.executor();
+        Ok(statements.into_iter()
+            .map(|(stmt, sql)| self.execute_with(&mut analyzer, &mut executor, stmt, Some(&sql)))
+            .collect())
+    }
+
+    /// Parse a single statement once so it can be executed with different parameters
+    pub fn prepare(&self, sql: &str) -> DbResult<PreparedStatement> {
+        let mut scanner = Scanner::new(sql);
+        let mut parser = Parser::new(&mut scanner)?;
+        let mut statements = parser.parse()?;
+
+        if statements.len() != 1 {
+            return Err(DbError::ParseError(format!(
+                "Expected exactly one statement to prepare, got {}", statements.len()
+            )));
+        }
+        Ok(PreparedStatement::new(statements.remove(0)))
+    }
+
+    /// Execute a single SELECT in pages of at most limit rows.
+    /// Pass None to start the scan and the returned token to fetch the next page,
+    /// the token is None once every row has been returned.
+    /// Rows come back in heap order, so DISTINCT, ORDER BY and aggregates are not supported
+    pub fn execute_paged(&mut self, sql: &str, cursor: Option<&str>, limit: usize) -> DbResult<(Vec<Vec<String>>, Option<String>)> {
+        let (table, columns, selection) = self.analyze_heap_scan(sql, "Paged")?;
+        let cursor = cursor.map(ScanCursor::decode).transpose()?;
+        let executor = self.executor();
+        let (rows, next) = executor.select_page(&table, &columns, &selection, cursor.as_ref(), limit)?;
+        Ok((rows, next.map(|c| c.encode())))
+    }
+
+    /// Execute a single SELECT scanning the table heap tail first, returning at most limit rows.
+    /// For an append-only table this gives the most recently inserted rows, newest first.
+    /// Like execute_paged, DISTINCT, ORDER BY and aggregates are not supported
+    pub fn execute_reverse(&mut self, sql: &str, limit: usize) -> DbResult<Vec<Vec<String>>> {
+        let (table, columns, selection) = self.analyze_heap_scan(sql, "Reverse")?;
+        self.executor().select_reverse(&table, &columns, &selection, limit)
+    }
+
+    /// Parse and bind a single SELECT whose rows can be returned in heap order,
+    /// as the table, select list and WHERE clause. kind names the scan in errors
+    fn analyze_heap_scan(&self, sql: &str, kind: &str) -> DbResult<(String, Vec<BoundExpr>, Option<BoundExprNode>)> {
+        if self.context.read().unwrap().current_db.is_none() {
+            return Err(DatabaseNotFound("A database connection does not exist".to_string()));
+        }
+
+        let mut scanner = Scanner::new(sql);
+        let mut parser = Parser::new(&mut scanner)?;
+        let mut statements = parser.parse()?;
+        if statements.len() != 1 {
+            return Err(DbError::ParseError(format!(
+                "{} SELECT expects exactly one statement, got {}", kind, statements.len()
+            )));
+        }
+
+        let mut analyzer = Analyzer::new(Arc::clone(&self.context));
+        let BoundStmt::Select { table, distinct, columns, selection, group_by, order_by, .. } = analyzer.analyze(statements.remove(0))? else {
+            return Err(DbError::ParseError(format!("{} SELECT only runs SELECT statements", kind)));
+        };
+        if distinct || !order_by.is_empty() || !group_by.is_empty() || columns.iter().any(|c| matches!(c, BoundExpr::Aggregate(..))) {
+            return Err(DbError::ParseError(format!(
+                "{} SELECT does not support DISTINCT, ORDER BY, GROUP BY or aggregates", kind
+            )));
+        }
+        Ok((table, columns, selection))
+    }
+
+    pub fn execute_prepared(&mut self, prepared: &PreparedStatement, params: &[Literal]) -> DbResult<ExecResult> {
+        let stmt = prepared.bind(params)?;
+        self.execute(stmt)
+    }
+
+    /// Insert rows of every column of table as they are pulled from rows, so a large dataset is
+    /// never held in memory at once. Rows are inserted in batches filling one data page each,
+    /// as an INSERT of the batch, and the dirty pages are flushed after every batch.
+    /// Return the number of rows inserted. A failing batch stops the load, earlier batches stay inserted
+    pub fn insert_iter(&mut self, table: &str, rows: impl IntoIterator<Item = Vec<Literal>>) -> DbResult<usize> {
+        let batch_size = {
+            let ctx = self.context.read().unwrap();
+            let database = ctx.current_db.as_ref()
+                .ok_or_else(|| DatabaseNotFound("A database connection does not exist".to_string()))?;
+            ctx.catalogs.get(database).unwrap().get_table_schema(table)
+                .ok_or_else(|| DbError::TableNotFound(format!("Table '{}' does not exist", table)))?
+                .records_per_page()
+        };
+
         let mut analyzer = Analyzer::new(Arc::clone(&self.context));
-        let mut executor = Executor::new(Arc::clone(&self.context));
+        let mut executor = self.executor();
+        let mut rows = rows.into_iter().peekable();
+        let mut count = 0;
+        while rows.peek().is_some() {
+            let batch: Vec<Vec<Expression>> = rows.by_ref()
+                .take(batch_size)
+                .map(|row| row.into_iter().map(Expression::Literal).collect())
+                .collect();
+            let stmt = Statement::Insert {
+                table: table.to_string(),
+                columns: vec![],
+                rows: batch,
+                on_conflict: ConflictPolicy::Abort,
+            };
+            if let ExecResult::AffectedRows(inserted, _) = self.execute_with(&mut analyzer, &mut executor, stmt, None)? {
+                count += inserted;
+            }
 
-        match analyzer.analyze(stmt) { 
-            Ok(stmt) => executor.execute(stmt),
-            Err(msg) => Err(msg),
+            let ctx = self.context.read().unwrap();
+            ctx.storage_engines.get(ctx.current_db.as_deref().unwrap()).unwrap().deactivate()
+                .map_err(|e| DbError::InternalError(format!("Failed to flush database: {:?}", e)))?;
         }
+        Ok(count)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,66 @@
+use crate::compiler::ast::{Expression, Literal, Statement};
+use crate::types::{DbError, DbResult};
+
+/// Parsed statement with ? parameters, executed repeatedly with different bindings
+#[derive(Debug, Clone)]
+pub struct PreparedStatement {
+    pub stmt: Statement,
+    pub param_count: usize,
+}
+
+impl PreparedStatement {
+    pub fn new(mut stmt: Statement) -> Self {
+        let mut param_count = 0;
+        for expr in stmt.expressions_mut() {
+            count_parameters(expr, &mut param_count);
+        }
+        Self { stmt, param_count }
+    }
+
+    /// Substitute parameters with literal values, in order of appearance
+    pub fn bind(&self, params: &[Literal]) -> DbResult<Statement> {
+        if params.len() != self.param_count {
+            return Err(DbError::ParameterError(format!(
+                "Expected {} parameters, got {}", self.param_count, params.len()
+            )));
+        }
+
+        let mut stmt = self.stmt.clone();
+        for expr in stmt.expressions_mut() {
+            bind_expression(expr, params);
+        }
+        Ok(stmt)
+    }
+}
+
+fn count_parameters(expr: &Expression, count: &mut usize) {
+    match expr {
+        Expression::Parameter(index) => *count = (*count).max(index + 1),
+        Expression::Unary { expr, .. } | Expression::Aggregate { arg: Some(expr), .. } => count_parameters(expr, count),
+        Expression::Binary { lhs, rhs, .. } => {
+            count_parameters(lhs, count);
+            count_parameters(rhs, count);
+        }
+        Expression::In { target, list } => {
+            count_parameters(target, count);
+            list.iter().for_each(|e| count_parameters(e, count));
+        }
+        Expression::Identifier(_) | Expression::Literal(_) | Expression::Aggregate { arg: None, .. } => {}
+    }
+}
+
+fn bind_expression(expr: &mut Expression, params: &[Literal]) {
+    match expr {
+        Expression::Parameter(index) => *expr = Expression::Literal(params[*index].clone()),
+        Expression::Unary { expr, .. } | Expression::Aggregate { arg: Some(expr), .. } => bind_expression(expr, params),
+        Expression::Binary { lhs, rhs, .. } => {
+            bind_expression(lhs, params);
+            bind_expression(rhs, params);
+        }
+        Expression::In { target, list } => {
+            bind_expression(target, params);
+            list.iter_mut().for_each(|e| bind_expression(e, params));
+        }
+        Expression::Identifier(_) | Expression::Literal(_) | Expression::Aggregate { arg: None, .. } => {}
+    }
+}
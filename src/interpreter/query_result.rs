@@ -0,0 +1,63 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::compiler::ast::Literal;
+
+/// Rows of a SELECT as typed values, with the output name of each column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Literal>>,
+}
+
+impl QueryResult {
+    /// Rows with each value formatted as a SQL literal, CHAR values quoted
+    pub fn formatted_rows(&self) -> Vec<Vec<String>> {
+        self.rows.iter()
+            .map(|row| row.iter().map(|value| value.to_string()).collect())
+            .collect()
+    }
+
+    /// Render the result as an ASCII table under a header of the column names
+    pub fn render(&self) -> String {
+        render_table(&self.columns, &self.formatted_rows())
+    }
+}
+
+impl fmt::Display for QueryResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+/// Render rows as an ASCII table, one line per row with each column padded to its widest cell.
+/// Non-empty columns are shown as a header line above a separator. A table without rows is "(no rows)"
+pub fn render_table(columns: &[String], rows: &[Vec<String>]) -> String {
+    if rows.is_empty() {
+        return "(no rows)\n".to_string();
+    }
+
+    // compute column widths
+    let mut widths: Vec<usize> = columns.iter().map(|name| name.chars().count()).collect();
+    widths.resize(rows[0].len(), 0);
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    let mut push_line = |cells: &[String]| {
+        for (cell, width) in cells.iter().zip(&widths) {
+            out.push_str(&format!("{:width$} ", cell, width = width));
+        }
+        out.push('\n');
+    };
+    if !columns.is_empty() {
+        push_line(columns);
+        push_line(&widths.iter().map(|&width| "-".repeat(width)).collect::<Vec<_>>());
+    }
+    for row in rows {
+        push_line(row);
+    }
+    out
+}
@@ -0,0 +1,38 @@
+use std::io::Write;
+use std::time::Duration;
+use crate::interpreter::ExecResult;
+use crate::types::DbResult;
+
+/// Logs statements whose execution takes at least `threshold` to a sink
+pub struct SlowQueryLog {
+    pub threshold: Duration,
+    sink: Box<dyn Write + Send>,
+}
+
+impl SlowQueryLog {
+    pub fn new(threshold: Duration, sink: Box<dyn Write + Send>) -> Self {
+        Self { threshold, sink }
+    }
+
+    /// Write one line for the statement if it exceeds the threshold
+    pub fn record(&mut self, sql: &str, duration: Duration, result: &DbResult<ExecResult>) {
+        if duration < self.threshold {
+            return;
+        }
+
+        let rows = match result {
+            Ok(ExecResult::AffectedRows(count, _)) => *count,
+            Ok(ExecResult::QueryResult(rows)) => rows.len(),
+            Ok(ExecResult::Rows(result)) => result.rows.len(),
+            Ok(ExecResult::Success(_)) | Err(_) => 0,
+        };
+
+        // logging failures must not fail the statement
+        let _ = writeln!(
+            self.sink,
+            "slow query: duration={:.3}ms rows={} sql={}",
+            duration.as_secs_f64() * 1000.0, rows, sql
+        );
+        let _ = self.sink.flush();
+    }
+}
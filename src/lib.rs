@@ -3,4 +3,6 @@ pub mod types;
 pub mod compiler;
 #[macro_use]
 pub mod macros;
-pub mod interpreter;
\ No newline at end of file
+pub mod interpreter;
+#[cfg(feature = "server")]
+pub mod server;
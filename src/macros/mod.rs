@@ -1 +1,2 @@
-pub mod page_macros;
\ No newline at end of file
+pub mod page_macros;
+pub mod trace_macros;
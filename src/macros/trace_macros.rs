@@ -0,0 +1,10 @@
+/// Record a field on the current tracing span. No-op without the tracing feature
+#[macro_export]
+macro_rules! trace_record {
+    ($field:literal, $value:expr) => {{
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record($field, $value);
+        #[cfg(not(feature = "tracing"))]
+        let _ = &$value;
+    }};
+}
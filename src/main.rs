@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use raincloud_db::compiler::parser::Parser;
 use raincloud_db::compiler::scanner::Scanner;
 use raincloud_db::interpreter::{Interpreter, ExecResult};
+use raincloud_db::interpreter::query_result::render_table;
 use raincloud_db::types::DbError;
 
 fn execute_input(input: &str, interpreter: &mut Interpreter) {
@@ -39,7 +40,11 @@ fn print_exec_result(result: ExecResult) {
         }
 
         ExecResult::QueryResult(rows) => {
-            print_table(rows);
+            print!("{}", render_table(&[], &rows));
+        }
+
+        ExecResult::Rows(result) => {
+            print!("{}", result);
         }
     }
 }
@@ -48,31 +53,6 @@ fn print_db_error(error: DbError) {
     println!("ERROR: {:?}", error);
 }
 
-fn print_table(rows: Vec<Vec<String>>) {
-    if rows.is_empty() {
-        println!("(no rows)");
-        return;
-    }
-
-    // Compute column widths
-    let col_count = rows[0].len();
-    let mut widths = vec![0; col_count];
-
-    for row in &rows {
-        for (i, cell) in row.iter().enumerate() {
-            widths[i] = widths[i].max(cell.len());
-        }
-    }
-
-    // Print rows
-    for row in rows {
-        for (i, cell) in row.iter().enumerate() {
-            print!("{:width$} ", cell, width = widths[i]);
-        }
-        println!();
-    }
-}
-
 fn main() {
     let dbms_root = PathBuf::from("C:\\Home\\Project\\RaincloudDB\\db");
     let mut interpreter = Interpreter::new(dbms_root);
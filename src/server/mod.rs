@@ -0,0 +1,364 @@
+//! Minimal TCP server exposing the SQL interpreter to other processes.
+//!
+//! Every message is a frame: u8 message type, u32 big-endian body length, body.
+//! Clients may run SQL directly (QUERY) or prepare a statement once and execute it
+//! with different parameters (PREPARE / BIND / EXECUTE). See docs/server_protocol.md
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use crate::compiler::ast::Literal;
+use crate::interpreter::{ExecResult, Interpreter};
+use crate::interpreter::prepared_statement::PreparedStatement;
+use crate::types::{DbError, DbResult};
+
+pub type Response = DbResult<Vec<DbResult<ExecResult>>>;
+
+// maximum size of a single frame body
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+// client message types
+pub const MSG_QUERY: u8 = b'Q';
+pub const MSG_PREPARE: u8 = b'P';
+pub const MSG_BIND: u8 = b'B';
+pub const MSG_EXECUTE: u8 = b'X';
+pub const MSG_CLOSE: u8 = b'C';
+
+// server message types
+pub const MSG_RESULT: u8 = b'R';
+pub const MSG_PREPARED: u8 = b'S';
+pub const MSG_OK: u8 = b'K';
+pub const MSG_ERROR: u8 = b'E';
+
+// parameter type tags
+const PARAM_INT: u8 = 0;
+const PARAM_CHAR: u8 = 1;
+const PARAM_BOOL: u8 = 2;
+const PARAM_NULL: u8 = 3;
+
+pub struct Server {
+    listener: TcpListener,
+    interpreter: Arc<Mutex<Interpreter>>,
+    stopped: Arc<AtomicBool>,   // set once a statement panics
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs, interpreter: Interpreter) -> io::Result<Server> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            interpreter: Arc::new(Mutex::new(interpreter)),
+            stopped: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept clients, serving each connection on its own thread.
+    /// All connections share one interpreter, so statements are serialized.
+    /// Return an error once a statement has panicked, see run_statement
+    pub fn serve(&self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            if self.stopped.load(Ordering::SeqCst) {
+                return Err(io::Error::other("server stopped after a statement panicked"));
+            }
+            let interpreter = Arc::clone(&self.interpreter);
+            let stopped = Arc::clone(&self.stopped);
+            thread::spawn(move || {
+                // a failed connection only affects its own client
+                let _ = Session::new(stream, interpreter, stopped).run();
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A prepared statement owned by one connection, with its latest parameter bindings
+struct SessionStatement {
+    prepared: PreparedStatement,
+    params: Vec<Literal>,
+}
+
+struct Session {
+    stream: TcpStream,
+    interpreter: Arc<Mutex<Interpreter>>,
+    stopped: Arc<AtomicBool>,
+    statements: HashMap<u32, SessionStatement>,
+    next_statement_id: u32,
+}
+
+impl Session {
+    fn new(stream: TcpStream, interpreter: Arc<Mutex<Interpreter>>, stopped: Arc<AtomicBool>) -> Self {
+        Self { stream, interpreter, stopped, statements: HashMap::new(), next_statement_id: 1 }
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        // loop until the client closes the connection, or the server stops
+        while let Some((msg_type, body)) = read_frame(&mut self.stream)? {
+            if self.stopped.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            let mut body = body.as_slice();
+            match msg_type {
+                MSG_QUERY => {
+                    let sql = String::from_utf8(body.to_vec()).map_err(invalid_data)?;
+                    let response = run_statement(&self.interpreter, &self.stopped, |interpreter| interpreter.execute_sql(&sql));
+                    self.send_json(MSG_RESULT, &response)?;
+                }
+                MSG_PREPARE => {
+                    let sql = String::from_utf8(body.to_vec()).map_err(invalid_data)?;
+                    let prepared = run_statement(&self.interpreter, &self.stopped, |interpreter| interpreter.prepare(&sql));
+                    match prepared {
+                        Ok(prepared) => {
+                            let id = self.next_statement_id;
+                            self.next_statement_id += 1;
+
+                            let mut reply = id.to_be_bytes().to_vec();
+                            reply.extend_from_slice(&(prepared.param_count as u16).to_be_bytes());
+                            self.statements.insert(id, SessionStatement { prepared, params: vec![] });
+                            write_frame(&mut self.stream, MSG_PREPARED, &reply)?;
+                        }
+                        Err(err) => self.send_json(MSG_ERROR, &err)?,
+                    }
+                }
+                MSG_BIND => {
+                    let id = read_u32(&mut body)?;
+                    let params = decode_params(&mut body)?;
+                    match self.statements.get_mut(&id) {
+                        Some(stmt) if stmt.prepared.param_count == params.len() => {
+                            stmt.params = params;
+                            write_frame(&mut self.stream, MSG_OK, &[])?;
+                        }
+                        Some(stmt) => {
+                            let err = DbError::ParameterError(format!(
+                                "Expected {} parameters, got {}", stmt.prepared.param_count, params.len()
+                            ));
+                            self.send_json(MSG_ERROR, &err)?;
+                        }
+                        None => self.send_json(MSG_ERROR, &unknown_statement(id))?,
+                    }
+                }
+                MSG_EXECUTE => {
+                    let id = read_u32(&mut body)?;
+                    let response: Response = match self.statements.get(&id) {
+                        Some(stmt) => Ok(vec![run_statement(&self.interpreter, &self.stopped, |interpreter| {
+                            interpreter.execute_prepared(&stmt.prepared, &stmt.params)
+                        })]),
+                        None => Err(unknown_statement(id)),
+                    };
+                    self.send_json(MSG_RESULT, &response)?;
+                }
+                MSG_CLOSE => {
+                    let id = read_u32(&mut body)?;
+                    match self.statements.remove(&id) {
+                        Some(_) => write_frame(&mut self.stream, MSG_OK, &[])?,
+                        None => self.send_json(MSG_ERROR, &unknown_statement(id))?,
+                    }
+                }
+                _ => return Err(invalid_data(format!("unknown message type {}", msg_type))),
+            }
+        }
+        Ok(())
+    }
+
+    fn send_json(&mut self, msg_type: u8, value: &impl serde::Serialize) -> io::Result<()> {
+        let body = serde_json::to_vec(value).map_err(invalid_data)?;
+        write_frame(&mut self.stream, msg_type, &body)
+    }
+}
+
+/// Run f on the shared interpreter, reporting a panic as an error to the client.
+/// A statement that panicked may leave the database half changed and its locks poisoned,
+/// so the server is stopped: sessions close their connections and serve returns
+fn run_statement<T>(
+    interpreter: &Mutex<Interpreter>,
+    stopped: &AtomicBool,
+    f: impl FnOnce(&mut Interpreter) -> DbResult<T>,
+) -> DbResult<T> {
+    let mut interpreter = interpreter.lock().unwrap_or_else(|e| e.into_inner());
+    panic::catch_unwind(AssertUnwindSafe(|| f(&mut interpreter))).unwrap_or_else(|payload| {
+        stopped.store(true, Ordering::SeqCst);
+        let message = payload.downcast_ref::<&str>().copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown cause");
+        Err(DbError::InternalError(format!("Statement panicked, server is stopping: {}", message)))
+    })
+}
+
+fn unknown_statement(id: u32) -> DbError {
+    DbError::ParameterError(format!("Prepared statement {} does not exist", id))
+}
+
+fn invalid_data(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Read one frame, returning None on a clean end of stream
+pub fn read_frame(stream: &mut impl Read) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let mut header = [0u8; 5];
+    match stream.read_exact(&mut header) {
+        Ok(()) => {},
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let len = u32::from_be_bytes(header[1..5].try_into().unwrap()) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(invalid_data("frame exceeds maximum size"));
+    }
+
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body)?;
+    Ok(Some((header[0], body)))
+}
+
+pub fn write_frame(stream: &mut impl Write, msg_type: u8, body: &[u8]) -> io::Result<()> {
+    if body.len() > MAX_FRAME_SIZE {
+        return Err(invalid_data("frame exceeds maximum size"));
+    }
+    stream.write_all(&[msg_type])?;
+    stream.write_all(&(body.len() as u32).to_be_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+fn read_bytes<'a>(buf: &mut &'a [u8], len: usize) -> io::Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err(invalid_data("unexpected end of message"));
+    }
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(bytes)
+}
+
+fn read_u32(buf: &mut &[u8]) -> io::Result<u32> {
+    Ok(u32::from_be_bytes(read_bytes(buf, 4)?.try_into().unwrap()))
+}
+
+fn read_string(buf: &mut &[u8], len: usize) -> io::Result<String> {
+    String::from_utf8(read_bytes(buf, len)?.to_vec()).map_err(invalid_data)
+}
+
+/// Parameters: u16 count, then per parameter a type tag and value
+/// (INT: i32, CHAR: u32 length + UTF-8 bytes, BOOL: u8, NULL: no value), all big-endian
+pub fn encode_params(params: &[Literal]) -> Vec<u8> {
+    let mut buf = (params.len() as u16).to_be_bytes().to_vec();
+    for param in params {
+        match param {
+            Literal::Int(v) => {
+                buf.push(PARAM_INT);
+                buf.extend_from_slice(&v.to_be_bytes());
+            }
+            Literal::String(s) => {
+                buf.push(PARAM_CHAR);
+                buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+                buf.extend_from_slice(s.as_bytes());
+            }
+            Literal::Bool(b) => {
+                buf.push(PARAM_BOOL);
+                buf.push(*b as u8);
+            }
+            Literal::Null => buf.push(PARAM_NULL),
+        }
+    }
+    buf
+}
+
+pub fn decode_params(buf: &mut &[u8]) -> io::Result<Vec<Literal>> {
+    let count = u16::from_be_bytes(read_bytes(buf, 2)?.try_into().unwrap());
+    let mut params = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let param = match read_bytes(buf, 1)?[0] {
+            PARAM_INT => Literal::Int(i32::from_be_bytes(read_bytes(buf, 4)?.try_into().unwrap())),
+            PARAM_CHAR => {
+                let len = read_u32(buf)? as usize;
+                Literal::String(read_string(buf, len)?)
+            }
+            PARAM_BOOL => Literal::Bool(read_bytes(buf, 1)?[0] != 0),
+            PARAM_NULL => Literal::Null,
+            tag => return Err(invalid_data(format!("unknown parameter type {}", tag))),
+        };
+        params.push(param);
+    }
+    Ok(params)
+}
+
+/// Handle of a statement prepared on the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatementHandle {
+    pub id: u32,
+    pub param_count: u16,
+}
+
+/// Blocking client for the server protocol
+pub struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Client> {
+        Ok(Self { stream: TcpStream::connect(addr)? })
+    }
+
+    pub fn query(&mut self, sql: &str) -> io::Result<Response> {
+        let (_, body) = self.request(MSG_QUERY, sql.as_bytes(), MSG_RESULT)?;
+        serde_json::from_slice(&body).map_err(invalid_data)
+    }
+
+    pub fn prepare(&mut self, sql: &str) -> io::Result<DbResult<StatementHandle>> {
+        self.request_or_error(MSG_PREPARE, sql.as_bytes(), MSG_PREPARED, |mut body| {
+            let id = read_u32(&mut body)?;
+            let param_count = u16::from_be_bytes(read_bytes(&mut body, 2)?.try_into().unwrap());
+            Ok(StatementHandle { id, param_count })
+        })
+    }
+
+    pub fn bind(&mut self, stmt: StatementHandle, params: &[Literal]) -> io::Result<DbResult<()>> {
+        let mut body = stmt.id.to_be_bytes().to_vec();
+        body.extend_from_slice(&encode_params(params));
+        self.request_or_error(MSG_BIND, &body, MSG_OK, |_| Ok(()))
+    }
+
+    /// Execute a prepared statement with its most recent bindings
+    pub fn execute(&mut self, stmt: StatementHandle) -> io::Result<DbResult<ExecResult>> {
+        let (_, body) = self.request(MSG_EXECUTE, &stmt.id.to_be_bytes(), MSG_RESULT)?;
+        let response: Response = serde_json::from_slice(&body).map_err(invalid_data)?;
+        Ok(response.and_then(|mut results| results.remove(0)))
+    }
+
+    pub fn close(&mut self, stmt: StatementHandle) -> io::Result<DbResult<()>> {
+        self.request_or_error(MSG_CLOSE, &stmt.id.to_be_bytes(), MSG_OK, |_| Ok(()))
+    }
+
+    fn request(&mut self, msg_type: u8, body: &[u8], expected: u8) -> io::Result<(u8, Vec<u8>)> {
+        write_frame(&mut self.stream, msg_type, body)?;
+        let (reply_type, reply) = read_frame(&mut self.stream)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "server closed connection"))?;
+        if reply_type != expected && reply_type != MSG_ERROR {
+            return Err(invalid_data(format!("unexpected message type {}", reply_type)));
+        }
+        Ok((reply_type, reply))
+    }
+
+    /// Send a request whose reply is either the expected message or an ERROR message
+    fn request_or_error<T>(
+        &mut self,
+        msg_type: u8,
+        body: &[u8],
+        expected: u8,
+        decode: impl FnOnce(&[u8]) -> io::Result<T>,
+    ) -> io::Result<DbResult<T>> {
+        let (reply_type, reply) = self.request(msg_type, body, expected)?;
+        if reply_type == MSG_ERROR {
+            let err: DbError = serde_json::from_slice(&reply).map_err(invalid_data)?;
+            return Ok(Err(err));
+        }
+        decode(&reply).map(Ok)
+    }
+}
@@ -1,12 +1,14 @@
 use paste::paste;
+use std::collections::HashSet;
 use std::sync::Arc;
 use crate::types::{PageId, FLUSH};
 use crate::storage::bufferpool::BufferPool;
 use crate::storage::page::index_page::{get_internal_capacity, get_leaf_capacity, IndexPage, IndexType, RecordId};
-use crate::{with_create_pages, with_read_pages, with_write_pages};
+use crate::{trace_record, with_create_pages, with_read_pages, with_write_pages};
 use crate::storage::page::page::Page;
 
-/// B+ Tree Invariant: left keys < parent separator <= right keys
+/// B+ Tree Invariant: left keys <= parent separator <= right keys
+/// A key may hold several records, whose entries may span several leaves
 pub struct BPlusTree {
     root: PageId,
     buffer_pool: Arc<BufferPool<IndexPage>>,
@@ -37,28 +39,52 @@ impl BPlusTree {
         }
     }
 
-    /// Search record by key
-    pub fn search(&mut self, key: &i64) -> Option<RecordId> {
-        let mut curr_id = self.root;
+    /// Current root page id, which changes when the root splits or collapses
+    pub fn get_root(&self) -> PageId {
+        self.root
+    }
+
+    /// Search records by key, returning every record under it in insertion order
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    pub fn search(&mut self, key: &i64) -> Vec<RecordId> {
+        self.range_scan(*key, *key)
+    }
+
+    /// Record ids of the keys from start to end inclusive, in key order. Descends to the leftmost leaf
+    /// that may hold start, then follows leaf links until a leaf ends past end
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self), fields(pages_read)))]
+    pub fn range_scan(&mut self, start: i64, end: i64) -> Vec<RecordId> {
+        let mut rids = Vec::new();
+        if start > end {
+            return rids;
+        }
+
+        let mut curr_id = self.first_leaf(start);
+        let mut pages_read = 0;
         loop {
-            with_read_pages!(self.buffer_pool, [(curr_id, curr_page)], {
-                match curr_page.page_type {
-                    IndexType::Internal => {
-                        curr_id = curr_page.search_child(key)?;
-                    }
-                    IndexType::Leaf => {
-                        return curr_page.search_rid(key).cloned();
-                    }
-                }
+            pages_read += 1;
+            trace_record!("pages_read", pages_read);
+            let next;
+            with_read_pages!(self.buffer_pool, [(curr_id, leaf)], {
+                rids.extend(leaf.search_range(&start, &end).into_iter().copied());
+                // an empty leaf says nothing about the keys after it,
+                // and records under end may continue in the next leaf
+                next = match leaf.max_key() {
+                    Some(max) if max > end => None,
+                    _ => leaf.get_next(),
+                };
             });
+            match next {
+                Some(next_id) => curr_id = next_id,
+                None => return rids,
+            }
         }
     }
 
-    /// Insert (key, rid). Split pages if exceed bound
+    /// Insert (key, rid), keeping the records already under key. Split pages if exceed bound
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, rid)))]
     pub fn insert(&mut self, key: i64, rid: RecordId) {
         let root_id = self.root;
-        self.print_tree();
-        println!("insert {key}");
 
         // early insert if tree is empty (leaf root)
         let mut insertion_complete = false;
@@ -74,7 +100,8 @@ impl BPlusTree {
 
         // Step 1: insert into leaf
         let leaf_id = stack.pop().expect("Error: leaf node not found");
-        let mut promote: Option<(i64, PageId)> = None;
+        // (promoted key, split page, new sibling right of it)
+        let mut promote: Option<(i64, PageId, PageId)> = None;
 
         with_write_pages!(self.buffer_pool, [(leaf_id, leaf_page)], FLUSH, {
             leaf_page.insert_record(key, rid);
@@ -85,16 +112,17 @@ impl BPlusTree {
                 with_create_pages!(self.buffer_pool, [(sib_id, sib_page)], FLUSH, {
                     let (promoted_key, new_sibling_page) = leaf_page.split(sib_id);
                     *sib_page = new_sibling_page;
-                    promote = Some((promoted_key, sib_id));
+                    promote = Some((promoted_key, leaf_id, sib_id));
                 });
             }
         });
 
         // Step 2: propagate promotion upward
-        while let Some((promoted_key, promoted_child)) = promote.take() {
+        while let Some((promoted_key, split_child, promoted_child)) = promote.take() {
             if let Some(parent_id) = stack.pop() {
                 with_write_pages!(self.buffer_pool, [(parent_id, parent_page)], FLUSH, {
-                    parent_page.insert_child(promoted_key, promoted_child);
+                    // separators may repeat, so the sibling goes by position rather than by key
+                    parent_page.insert_child_after(split_child, promoted_key, promoted_child);
 
                     // split parent if exceeds capacity
                     if parent_page.keys.len() > self.internal_max_keys {
@@ -103,7 +131,7 @@ impl BPlusTree {
                             let (promoted_key, sibling_page) = parent_page.split(sib_id);
                             *sib_page = sibling_page;
                             sib_page.page_type = IndexType::Internal;
-                            promote = Some((promoted_key, sib_id));
+                            promote = Some((promoted_key, parent_id, sib_id));
                         });
                     }
                 });
@@ -120,11 +148,10 @@ impl BPlusTree {
         }
     }
 
-    /// Delete given key. Use redistribution and merge.
+    /// Delete rid from the records under key, keeping the other records of key. Use redistribution and merge.
     /// Return true if deletion succeed
-    pub fn delete(&mut self, key: i64) -> bool {
-        self.print_tree();
-        println!("delete {key}");
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    pub fn delete(&mut self, key: i64, rid: RecordId) -> bool {
         // if the tree is empty, there is no node to delete
         let root_id = self.root;
         let mut tree_empty = false;
@@ -136,63 +163,60 @@ impl BPlusTree {
         if tree_empty { return false }
 
         // Step 1: delete from leaf
-        let mut stack = self.descend_to_leaf(key);
+        let mut stack = Vec::new();
+        if !self.find_record_path(self.root, key, rid, &mut stack) { return false }
         let leaf_id = stack.pop().unwrap();
         let mut underflow_node = None;
 
-        let mut deletion_failed = false;
         with_write_pages!(self.buffer_pool, [(leaf_id, leaf_page)], FLUSH, {
-            if !leaf_page.remove_key(key) {
-                deletion_failed = true;
-            }
-            // key successfully removed
+            leaf_page.remove_record(key, rid);
             if leaf_page.keys.len() < self.leaf_min_keys {
                 underflow_node = Some(leaf_id);
             }
         });
-        if deletion_failed { return false }
 
         while let Some(child_id) = underflow_node.take() {
-            if let Some(parent_id) = stack.pop() {
-                with_write_pages!(self.buffer_pool, [(parent_id, parent_page), (child_id, child_page)], FLUSH, {
-                    let index = parent_page.get_children()
-                        .iter()
-                        .position(|&id| id == child_id)
-                        .expect("Error: child not found in parent");
-                    let min_keys = if child_page.page_type == IndexType::Leaf { self.leaf_min_keys } else { self.internal_min_keys };
-
-                    // Step 2: Attempt to fix underflow with redistribution
-                    // try borrow from left sibling, update parent separator using returned key
-                    let left_sibling = if index > 0 { Some(parent_page.get_children()[index - 1]) } else { None };
-                    let right_sibling = if index < parent_page.get_children().len() - 1
-                        {Some(parent_page.get_children()[index + 1])} else { None };
-
-                    // try to redistribute from left sibling
-                    let mut redistribute_succeed = false;
-                    if let Some(left_id) = left_sibling {
-                        with_write_pages!(self.buffer_pool, [(left_id, left_page)], FLUSH, {
-                            let old_sep = parent_page.keys[index - 1];
-                            if let Some(new_sep) = child_page.redistribute(&mut left_page, old_sep, true, min_keys) {
-                                parent_page.keys[index - 1] = new_sep;
-                                redistribute_succeed = true;
-                            }
-                        });
-                    }
-                    // try to redistribute from right sibling
-                    if let Some(right_id) = right_sibling {
-                        with_write_pages!(self.buffer_pool, [(right_id, right_page)], FLUSH, {
-                            let old_sep = parent_page.keys[index];
-                            if let Some(new_sep) = child_page.redistribute(&mut right_page, old_sep, false, min_keys) {
-                                parent_page.keys[index] = new_sep;
-                                redistribute_succeed = true;
-                            }
-                        });
-                    }
-                    if redistribute_succeed { return true }
+            // an underflowing root is fixed by the collapse below
+            let Some(parent_id) = stack.pop() else { break };
+            with_write_pages!(self.buffer_pool, [(parent_id, parent_page), (child_id, child_page)], FLUSH, {
+                let index = parent_page.get_children()
+                    .iter()
+                    .position(|&id| id == child_id)
+                    .expect("Error: child not found in parent");
+                let min_keys = if child_page.page_type == IndexType::Leaf { self.leaf_min_keys } else { self.internal_min_keys };
+
+                // Step 2: Attempt to fix underflow with redistribution
+                // try borrow from left sibling, update parent separator using returned key
+                let left_sibling = if index > 0 { Some(parent_page.get_children()[index - 1]) } else { None };
+                let right_sibling = if index < parent_page.get_children().len() - 1
+                    {Some(parent_page.get_children()[index + 1])} else { None };
+
+                // try to redistribute from left sibling
+                let mut redistribute_succeed = false;
+                if let Some(left_id) = left_sibling {
+                    with_write_pages!(self.buffer_pool, [(left_id, left_page)], FLUSH, {
+                        let old_sep = parent_page.keys[index - 1];
+                        if let Some(new_sep) = child_page.redistribute(&mut left_page, old_sep, true, min_keys) {
+                            parent_page.keys[index - 1] = new_sep;
+                            redistribute_succeed = true;
+                        }
+                    });
+                }
+                // try to redistribute from right sibling if the left sibling could not lend a key
+                if let (false, Some(right_id)) = (redistribute_succeed, right_sibling) {
+                    with_write_pages!(self.buffer_pool, [(right_id, right_page)], FLUSH, {
+                        let old_sep = parent_page.keys[index];
+                        if let Some(new_sep) = child_page.redistribute(&mut right_page, old_sep, false, min_keys) {
+                            parent_page.keys[index] = new_sep;
+                            redistribute_succeed = true;
+                        }
+                    });
+                }
 
-                    // Step 3: merge with sibling
-                    // Merge with left sibling if possible
+                // Step 3: merge with sibling
+                if !redistribute_succeed {
                     if let Some(left_id) = left_sibling {
+                        // Merge with left sibling if possible
                         with_write_pages!(self.buffer_pool, [(left_id, left_page)], FLUSH, {
                             let sep_key = parent_page.keys.remove(index - 1); // remove parent separator
                             left_page.merge(&mut child_page);
@@ -202,13 +226,9 @@ impl BPlusTree {
                             }
                             parent_page.get_children_mut().remove(index);
                             self.buffer_pool.free_page(child_id, FLUSH);
-                            underflow_node = Some(parent_id);
                         });
-                        continue;
-                    }
-
-                    // Merge with right sibling
-                    if let Some(right_id) = right_sibling {
+                    } else if let Some(right_id) = right_sibling {
+                        // Merge with right sibling
                         with_write_pages!(self.buffer_pool, [(right_id, right_page)], FLUSH, {
                             let sep_key = parent_page.keys.remove(index); // remove parent separator
                             // insert new parent separator
@@ -218,29 +238,194 @@ impl BPlusTree {
                             child_page.merge(&mut right_page);
                             parent_page.get_children_mut().remove(index + 1);
                             self.buffer_pool.free_page(right_id, FLUSH);
-                            underflow_node = Some(parent_id);
                         });
-                        continue;
                     }
+
+                    // the merge took a key from the parent, only continue upward if it now underflows
+                    if parent_page.keys.len() < self.internal_min_keys {
+                        underflow_node = Some(parent_id);
+                    }
+                }
+            });
+        }
+
+        // Reached root, collapse if it is left with a single child
+        let root_id = self.root;
+        with_read_pages!(self.buffer_pool, [(root_id, root_page)], {
+            if root_page.page_type == IndexType::Internal && root_page.get_children().len() == 1 {
+                let new_root = root_page.get_children()[0];
+                self.root = new_root;
+                self.buffer_pool.free_page(root_id, FLUSH);
+            }
+        });
+
+        true
+    }
+
+    /// All (key, rid) pairs in key order
+    pub fn iter(&self) -> impl Iterator<Item = (i64, RecordId)> {
+        let mut entries = Vec::new();
+        self.collect_node(self.root, &mut entries, &mut Vec::new());
+        entries.into_iter()
+    }
+
+    /// Number of pages used by the tree
+    pub fn page_count(&self) -> usize {
+        self.page_ids().len()
+    }
+
+    /// Ids of the internal and leaf pages of the tree, each parent before its children
+    pub fn page_ids(&self) -> Vec<PageId> {
+        let mut pages = Vec::new();
+        self.collect_node(self.root, &mut Vec::new(), &mut pages);
+        pages
+    }
+
+    /// Free every page of the tree, for dropping the index it stores
+    pub fn free_pages(self) {
+        for page_id in self.page_ids() {
+            self.buffer_pool.free_page(page_id, FLUSH);
+        }
+    }
+
+    /// Rebuild the tree densely from its leaves and free the old pages.
+    /// Reduces index size and height after many deletes
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    pub fn compact(&mut self) {
+        let mut entries = Vec::new();
+        let mut old_pages = Vec::new();
+        self.collect_node(self.root, &mut entries, &mut old_pages);
+
+        self.bulk_load(&entries);
+        for page_id in old_pages {
+            self.buffer_pool.free_page(page_id, FLUSH);
+        }
+    }
+
+    /// Merge entries into the tree by rebuilding it with bulk_load and freeing the old pages.
+    /// Cheaper than inserting one key at a time when entries are many
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self, entries)))]
+    pub fn bulk_insert(&mut self, entries: &[(i64, RecordId)]) {
+        let mut merged = Vec::new();
+        let mut old_pages = Vec::new();
+        self.collect_node(self.root, &mut merged, &mut old_pages);
+
+        // stable sort keeps the existing records of a key before the new ones
+        merged.extend_from_slice(entries);
+        merged.sort_by_key(|&(key, _)| key);
+        self.bulk_load(&merged);
+        for page_id in old_pages {
+            self.buffer_pool.free_page(page_id, FLUSH);
+        }
+    }
+
+    /// Build a new tree bottom-up from entries sorted by key, filling pages as much as the
+    /// size bounds allow. Pages of the previous tree are not freed
+    pub fn bulk_load(&mut self, entries: &[(i64, RecordId)]) {
+        debug_assert!(entries.windows(2).all(|w| w[0].0 <= w[1].0));
+
+        if entries.is_empty() {
+            let root_id;
+            with_create_pages!(self.buffer_pool, [(root_id, root_page)], FLUSH, {
+                root_page.page_type = IndexType::Leaf;
+            });
+            self.root = root_id;
+            return;
+        }
+
+        // leaf level, linked in key order: (min key of subtree, page id)
+        let mut level: Vec<(i64, PageId)> = Vec::new();
+        let mut prev_leaf: Option<PageId> = None;
+        for chunk in even_chunks(entries, self.leaf_max_keys) {
+            let leaf_id;
+            with_create_pages!(self.buffer_pool, [(leaf_id, leaf_page)], FLUSH, {
+                for &(key, rid) in chunk {
+                    leaf_page.insert_record(key, rid);
+                }
+            });
+            if let Some(prev_id) = prev_leaf {
+                with_write_pages!(self.buffer_pool, [(prev_id, prev_page)], FLUSH, {
+                    prev_page.set_next(leaf_id);
                 });
-            }  else {
-                // Reached root, collapse if needed
-                let root_id = self.root;
-                with_read_pages!(self.buffer_pool, [(root_id, root_page)], {
-                    if root_page.page_type == IndexType::Internal && root_page.get_children().len() == 1 {
-                        let new_root = root_page.get_children()[0];
-                        self.root = new_root;
-                        self.buffer_pool.free_page(root_id, FLUSH);
+            }
+            prev_leaf = Some(leaf_id);
+            level.push((chunk[0].0, leaf_id));
+        }
+
+        // internal levels, separator of each child is the min key of its subtree
+        while level.len() > 1 {
+            let mut parents = Vec::new();
+            for chunk in even_chunks(&level, self.internal_max_keys + 1) {
+                let parent_id;
+                with_create_pages!(self.buffer_pool, [(parent_id, parent_page)], FLUSH, {
+                    parent_page.page_type = IndexType::Internal;
+                    parent_page.get_children_mut().push(chunk[0].1);
+                    for &(min_key, child_id) in &chunk[1..] {
+                        parent_page.insert_child(min_key, child_id);
                     }
                 });
-                break;
+                parents.push((chunk[0].0, parent_id));
             }
+            level = parents;
         }
 
-        true
+        self.root = level[0].1;
+    }
+
+    /// In-order walk collecting leaf entries and every visited page
+    fn collect_node(&self, page_id: PageId, entries: &mut Vec<(i64, RecordId)>, pages: &mut Vec<PageId>) {
+        pages.push(page_id);
+        with_read_pages!(self.buffer_pool, [(page_id, page)], {
+            match page.page_type {
+                IndexType::Leaf => {
+                    entries.extend(page.keys.iter().copied().zip(page.get_rids().iter().copied()));
+                }
+                IndexType::Internal => {
+                    for &child_id in page.get_children() {
+                        self.collect_node(child_id, entries, pages);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Leftmost leaf that may hold key, where a scan for key starts
+    fn first_leaf(&self, key: i64) -> PageId {
+        let mut curr_id = self.root;
+        loop {
+            with_read_pages!(self.buffer_pool, [(curr_id, curr_page)], {
+                match curr_page.page_type {
+                    IndexType::Internal => {
+                        curr_id = curr_page.search_first_child(&key).expect("Error: internal search child failed");
+                    }
+                    IndexType::Leaf => return curr_id,
+                }
+            });
+        }
     }
 
-    /// Find given key in leaf page
+    /// Push the path from page_id to the leaf holding rid under key onto path, returning false if no leaf holds it.
+    /// Records under one key may span several leaves, so every child that may hold key is searched
+    fn find_record_path(&self, page_id: PageId, key: i64, rid: RecordId, path: &mut Vec<PageId>) -> bool {
+        path.push(page_id);
+        let (found, candidates) = with_read_pages!(self.buffer_pool, [(page_id, page)], {
+            match page.page_type {
+                IndexType::Leaf => (page.search_range(&key, &key).contains(&&rid), Vec::new()),
+                IndexType::Internal => {
+                    let first = page.keys.partition_point(|k| *k < key);
+                    let last = page.keys.partition_point(|k| *k <= key);
+                    (false, page.get_children()[first..=last].to_vec())
+                }
+            }
+        });
+        if found || candidates.into_iter().any(|child_id| self.find_record_path(child_id, key, rid, path)) {
+            return true;
+        }
+        path.pop();
+        false
+    }
+
+    /// Path from the root to the rightmost leaf that may hold key, where key is inserted
     fn descend_to_leaf(&self, key: i64) -> Vec::<PageId> {
         let mut stack: Vec<PageId> = Vec::new();
         let mut curr_id = self.root;
@@ -263,15 +448,94 @@ impl BPlusTree {
         stack
     }
 
+    /// Debug Helper: Check the structure of the tree, returning the first violation found:
+    /// a page reached twice (a cycle or a shared child), decreasing keys, a page holding more keys than its
+    /// maximum, an internal node whose children do not match its keys, a key outside its parent's separators,
+    /// or leaves at different depths
+    pub fn validate(&self) -> Result<(), String> {
+        let mut visited = HashSet::new();
+        let mut leaf_depth = None;
+        self.validate_node(self.root, None, None, 0, &mut Vec::new(), &mut visited, &mut leaf_depth)
+    }
+
+    /// Recursive helper of validate, keys of the subtree must lie in [lower, upper].
+    /// path holds the ancestors of page_id, to tell a cycle from a page shared by two parents
+    #[allow(clippy::too_many_arguments)]
+    fn validate_node(
+        &self,
+        page_id: PageId,
+        lower: Option<i64>,
+        upper: Option<i64>,
+        depth: usize,
+        path: &mut Vec<PageId>,
+        visited: &mut HashSet<PageId>,
+        leaf_depth: &mut Option<usize>,
+    ) -> Result<(), String> {
+        if path.contains(&page_id) {
+            return Err(format!("Cycle: page {} is its own ancestor through {:?}", page_id, path));
+        }
+        if !visited.insert(page_id) {
+            return Err(format!("Page {} is referenced by more than one parent", page_id));
+        }
+
+        let (page_type, keys, children) = with_read_pages!(self.buffer_pool, [(page_id, page)], {
+            let children = match page.page_type {
+                IndexType::Internal => page.get_children().clone(),
+                IndexType::Leaf => Vec::new(),
+            };
+            (page.page_type, page.keys.clone(), children)
+        });
+
+        if keys.windows(2).any(|w| w[0] > w[1]) {
+            return Err(format!("Keys of page {} are not sorted: {:?}", page_id, keys));
+        }
+        let max_keys = if page_type == IndexType::Leaf { self.leaf_max_keys } else { self.internal_max_keys };
+        if keys.len() > max_keys {
+            return Err(format!("Page {} holds {} keys, more than its maximum of {}", page_id, keys.len(), max_keys));
+        }
+        if let Some(&key) = keys.iter().find(|&&k| lower.is_some_and(|l| k < l) || upper.is_some_and(|u| k > u)) {
+            return Err(format!("Key {} of page {} is outside its separators [{:?}, {:?}]", key, page_id, lower, upper));
+        }
+
+        match page_type {
+            IndexType::Leaf => {
+                if leaf_depth.is_some_and(|d| d != depth) {
+                    return Err(format!("Leaf {} is at depth {}, other leaves at depth {}", page_id, depth, leaf_depth.unwrap()));
+                }
+                *leaf_depth = Some(depth);
+            }
+            IndexType::Internal => {
+                if children.len() != keys.len() + 1 {
+                    return Err(format!(
+                        "Internal page {} has {} keys but {} children", page_id, keys.len(), children.len()
+                    ));
+                }
+                path.push(page_id);
+                for (i, &child_id) in children.iter().enumerate() {
+                    let child_lower = if i == 0 { lower } else { Some(keys[i - 1]) };
+                    let child_upper = if i == keys.len() { upper } else { Some(keys[i]) };
+                    self.validate_node(child_id, child_lower, child_upper, depth + 1, path, visited, leaf_depth)?;
+                }
+                path.pop();
+            }
+        }
+        Ok(())
+    }
+
     /// Debug Helper: Print the B+ tree in a readable form
     pub fn print_tree(&self) {
         println!("B+ Tree (root id: {})", self.root);
-        self.print_node(self.root, 0);
+        self.print_node(self.root, 0, &mut HashSet::new());
     }
 
-    /// Recursive helper to print a node and its children
-    fn print_node(&self, page_id: PageId, level: usize) {
+    /// Recursive helper to print a node and its children.
+    /// A page reached twice is printed once, so a corrupt tree with a cycle still terminates
+    fn print_node(&self, page_id: PageId, level: usize, visited: &mut HashSet<PageId>) {
         let indent = "  ".repeat(level);
+        if !visited.insert(page_id) {
+            println!("{}<page {} already visited>", indent, page_id);
+            return;
+        }
 
         with_read_pages!(self.buffer_pool, [(page_id, page)], {
             match page.page_type {
@@ -281,10 +545,25 @@ impl BPlusTree {
                 IndexType::Internal => {
                     println!("{}Internal[{}] keys: {:?}", indent, page_id, page.keys);
                     for &child_id in page.get_children() {
-                        self.print_node(child_id, level + 1);
+                        self.print_node(child_id, level + 1, visited);
                     }
                 }
             }
         });
     }
-}
\ No newline at end of file
+}
+
+/// Split items into the fewest chunks of at most max_len items, with chunk sizes differing by at most one
+/// so that no chunk underflows
+fn even_chunks<T>(items: &[T], max_len: usize) -> Vec<&[T]> {
+    let count = items.len().div_ceil(max_len);
+    let (base, extra) = (items.len() / count, items.len() % count);
+    let mut chunks = Vec::with_capacity(count);
+    let mut start = 0;
+    for i in 0..count {
+        let len = base + usize::from(i < extra);
+        chunks.push(&items[start..start + len]);
+        start += len;
+    }
+    chunks
+}
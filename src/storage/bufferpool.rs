@@ -3,11 +3,13 @@ use crate::storage::disk_manager::DiskManager;
 use std::collections::HashMap;
 use std::sync::{RwLock, Arc, Condvar, Mutex};
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
 use crate::storage::free_list::FreeList;
 use crate::storage::replacement_strategy::{
     ReplacementStrategy, ReplacementStrategyType, replacement_strategy_factory
 };
-use crate::types::{PageId, NO_FLUSH};
+use crate::types::{PageId, EVICT_MAX_STALLED_ROUNDS, EVICT_WAIT_MS, NO_FLUSH};
+use crate::trace_record;
 
 #[derive(Debug)]
 pub struct BufferFrame<P: Page> {
@@ -53,13 +55,25 @@ impl<P: Page> Drop for PageGuard<P> {
     }
 }
 
+/// Buffer pool counters since the pool was created, for tuning its capacity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferPoolStats {
+    pub hits: usize,            // fetches served from memory
+    pub misses: usize,          // fetches read from disk
+    pub evictions: usize,       // pages evicted to make room or by evict_page
+    pub current_size: usize,    // pages currently held in the pool
+}
+
 pub struct BufferPool<P: Page> {
     page_table: RwLock<HashMap<PageId, Arc<BufferFrame<P>>>>,
     capacity: usize,
     disk: Arc<dyn DiskManager<P>>,
     strategy: Mutex<Box<dyn ReplacementStrategy>>,
-    free_list: Arc<Mutex<FreeList>>,
-    evict_cv: (Mutex<usize>, Condvar)  // condvar to notify an eviction is available
+    free_list: Arc<FreeList>,
+    evict_cv: (Mutex<usize>, Condvar),  // condvar to notify an eviction is available
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
 }
 
 impl<P: Page + 'static> BufferPool<P> {
@@ -67,7 +81,7 @@ impl<P: Page + 'static> BufferPool<P> {
         capacity: usize,
         strategy_type: ReplacementStrategyType,
         disk: Arc<dyn DiskManager<P>>,
-        free_list: Arc<Mutex<FreeList>>,
+        free_list: Arc<FreeList>,
     ) -> Self {
         let strategy = replacement_strategy_factory(
             strategy_type
@@ -79,21 +93,57 @@ impl<P: Page + 'static> BufferPool<P> {
             disk,
             strategy: Mutex::new(strategy),
             free_list,
-            evict_cv: (Mutex::new(0), Condvar::new())
+            evict_cv: (Mutex::new(0), Condvar::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            evictions: AtomicUsize::new(0),
         }
     }
 
+    /// Maximum number of frames held in the pool
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
     /// Get the current bufferpool size
     pub fn current_size(&self) -> usize {
         self.page_table.read().unwrap().len()
     }
 
+    /// Snapshot of the hit, miss and eviction counters and the current size
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            current_size: self.current_size(),
+        }
+    }
+
+    /// Snapshot of resident pages as (page id, dirty, pin count), ordered by page id.
+    /// Only the page table lock is taken, so it is safe to call while holding page latches
+    pub fn resident_pages(&self) -> Vec<(PageId, bool, usize)> {
+        let frames = self.page_table.read().unwrap();
+        let mut pages: Vec<(PageId, bool, usize)> = frames.iter()
+            .map(|(&page_id, frame)| (
+                page_id,
+                frame.is_dirty.load(Ordering::Acquire),
+                frame.pin_count.load(Ordering::SeqCst),
+            ))
+            .collect();
+        pages.sort_by_key(|(page_id, _, _)| *page_id);
+        pages
+    }
+
     /// Fetch a page into memory, pinning it. Evicts a page if needed (blocking).
+    /// Return Err(PageError::EvictionStalled) if the pool is full and its pages stay pinned
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self), fields(disk_read = false)))]
     pub fn fetch_page(self: &Arc<Self>, page_id: PageId) -> Result<PageGuard<P>, PageError> {
         // page is already in memory
         {
             let frames = self.page_table.read().unwrap();
             if let Some(frame) = frames.get(&page_id) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
                 self.strategy.lock().unwrap().update(page_id);
                 return Ok(PageGuard::new(Arc::clone(frame), Arc::clone(self)));
             }
@@ -101,6 +151,8 @@ impl<P: Page + 'static> BufferPool<P> {
 
         // fetch page from disk
         let page = self.disk.read_page(page_id).ok_or(PageError::InvalidPage)?;
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        trace_record!("disk_read", true);
 
         // evict if full
         loop {
@@ -109,7 +161,7 @@ impl<P: Page + 'static> BufferPool<P> {
                 frames.len() >= self.capacity
             };
             if !need_evict { break; }
-            self.evict_one(); // will block until space is available
+            self.evict_one()?; // will block until space is available
         }
 
         let mut frames = self.page_table.write().unwrap();
@@ -133,11 +185,9 @@ impl<P: Page + 'static> BufferPool<P> {
     }
 
     /// Create a new in-memory page
+    /// Return Err(PageError::EvictionStalled) if the pool is full and its pages stay pinned
     pub fn create_page(self: &Arc<Self>) -> Result<PageGuard<P>, PageError> {
-        let page_id = self.free_list.lock().unwrap().allocate(NO_FLUSH);
-        let page = P::new(page_id);
-
-        // evict if full
+        // evict if full, before allocating so a failed eviction does not leak a page id
         loop {
             let need_evict = {
                 let frames = self.page_table.read().unwrap();
@@ -146,9 +196,12 @@ impl<P: Page + 'static> BufferPool<P> {
             if !need_evict {
                 break;
             }
-            self.evict_one();
+            self.evict_one()?;
         }
 
+        let page_id = self.free_list.allocate(NO_FLUSH);
+        let page = P::new(page_id);
+
         let frame = Arc::new(BufferFrame {
             page: RwLock::new(page),
             is_dirty: AtomicBool::new(true), // new page needs to be written to disk
@@ -157,11 +210,8 @@ impl<P: Page + 'static> BufferPool<P> {
 
         {
             let mut frames = self.page_table.write().unwrap();
-            // page id should be unique, but if some logic reuses ids, guard anyway:
-            if let Some(existing) = frames.get(&page_id) {
-                self.strategy.lock().unwrap().update(page_id);
-                return Ok(PageGuard::new(Arc::clone(existing), Arc::clone(self)));
-            }
+            // a freed page id may be reallocated while its old frame is still cached,
+            // replace the stale frame so the new page starts empty
             frames.insert(page_id, Arc::clone(&frame));
         }
         self.strategy.lock().unwrap().update(page_id);
@@ -170,7 +220,7 @@ impl<P: Page + 'static> BufferPool<P> {
 
     // Mark a page as freed
     pub fn free_page(&self, page_id: PageId, flush: bool) {
-        self.free_list.lock().unwrap().deallocate(page_id, flush).unwrap();
+        self.free_list.deallocate(page_id, flush).unwrap();
     }
 
     /// Flush a single dirty page in buffer pool
@@ -179,28 +229,60 @@ impl<P: Page + 'static> BufferPool<P> {
             .get(&page_id)
             .ok_or(PageError::InvalidPage)?
             .clone();
+        self.write_frame(&frame)
+    }
 
+    /// Write a frame to disk if dirty. The frame stays dirty if the write fails
+    fn write_frame(&self, frame: &BufferFrame<P>) -> Result<(), PageError> {
         if frame.is_dirty.swap(false, Ordering::SeqCst) {
             let page = frame.page.read().unwrap();
-            self.disk.write_page(&page);
+            if let Err(e) = self.disk.write_page(&page) {
+                frame.is_dirty.store(true, Ordering::SeqCst);
+                return Err(PageError::DiskWrite(e.kind()));
+            }
         }
+        Ok(())
+    }
 
+    /// Flush a page if dirty and remove it from buffer pool, so the next fetch reads it from disk
+    /// Return Err(PageError::InvalidPage) if the page is not in buffer pool
+    /// Return Err(PageError::PageLatched) if the page is pinned
+    /// Return Err(PageError::DiskWrite) if the dirty page cannot be written, it stays in buffer pool
+    pub fn evict_page(&self, page_id: PageId) -> Result<(), PageError> {
+        // hold page table lock so the page can't be pinned during eviction
+        {
+            let mut frames = self.page_table.write().unwrap();
+            let frame = frames.get(&page_id).ok_or(PageError::InvalidPage)?;
+
+            if frame.pin_count.load(Ordering::SeqCst) != 0 {
+                return Err(PageError::PageLatched);
+            }
+
+            self.write_frame(frame)?;
+            frames.remove(&page_id);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // the page no longer counts as an eviction candidate.
+        // the page table lock is released first, evict_one takes it while holding this lock
+        let mut unpinned_count = self.evict_cv.0.lock().unwrap();
+        *unpinned_count = unpinned_count.saturating_sub(1);
         Ok(())
     }
 
     /// Flush all dirty pages in buffer pool
-    pub fn flush_all(&self) {
+    /// Pages that cannot be written stay dirty, and the first error is returned after trying every page
+    pub fn flush_all(&self) -> Result<(), PageError> {
         // clone refs to avoid holding the table lock during I/O
         let frames: Vec<Arc<BufferFrame<P>>> = {
             let map = self.page_table.read().unwrap();
             map.values().cloned().collect()
         };
+        let mut result = Ok(());
         for frame in frames {
-            if frame.is_dirty.swap(false, Ordering::SeqCst) {
-                let page = frame.page.read().unwrap();
-                self.disk.write_page(&page);
-            }
+            result = result.and(self.write_frame(&frame));
         }
+        result
     }
 }
 
@@ -209,21 +291,22 @@ impl<P: Page + 'static> BufferPool<P> {
     /// Evict one unpinned page using the replacement strategy.
     /// If the bufferpool is full and no page is available for eviction,
     /// evict_one will block until a page can be evicted
-    fn evict_one(&self) {
+    /// Return Err(PageError::DiskWrite) if the chosen dirty page cannot be written, nothing is evicted
+    /// Return Err(PageError::EvictionStalled) if no page could be evicted over EVICT_MAX_STALLED_ROUNDS waits,
+    /// so a leaked pin surfaces as an error instead of a hang
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self), fields(page_id, stalled_rounds)))]
+    fn evict_one(&self) -> Result<(), PageError> {
         let (lock, cv) = &self.evict_cv;
 
         // acquire lock to condvar before loop
         let mut unpinned_count = lock.lock().unwrap();
+        let mut stalled_rounds = 0;
 
         loop {
-            while *unpinned_count == 0 {
-                unpinned_count = cv.wait(unpinned_count).unwrap();
-            }
-
-            let mut evicted = false;
-
+            // the count is only a hint, as pages counted as unpinned may have been pinned again.
             // restrict strategy to inner scope so that we do not hold lock while waiting
-            {
+            if *unpinned_count > 0 {
+                let mut evicted = false;
                 let mut strategy = self.strategy.lock().unwrap();
                 let candidates = strategy.get_evict();
 
@@ -243,10 +326,7 @@ impl<P: Page + 'static> BufferPool<P> {
                     }
 
                     // flush page if it's dirty
-                    if evicted_ref.is_dirty.swap(false, Ordering::SeqCst) {
-                        let page_read = evicted_ref.page.read().unwrap();
-                        self.disk.write_page(&*page_read);
-                    }
+                    self.write_frame(&evicted_ref)?;
 
                     // remove from page table (perform ABA checking)
                     let mut frames = self.page_table.write().unwrap();
@@ -254,15 +334,25 @@ impl<P: Page + 'static> BufferPool<P> {
                         .unwrap_or(false)
                     {
                         frames.remove(&evicted_id);
+                        self.evictions.fetch_add(1, Ordering::Relaxed);
+                        trace_record!("page_id", evicted_id);
                         evicted = true;
                         break;
                     }
                 }
                 if evicted {
                     *unpinned_count -= 1;
-                    return;
+                    return Ok(());
                 }
             }
+
+            // no page can be evicted, wait for one to be unpinned
+            if stalled_rounds == EVICT_MAX_STALLED_ROUNDS {
+                trace_record!("stalled_rounds", stalled_rounds);
+                return Err(PageError::EvictionStalled);
+            }
+            unpinned_count = cv.wait_timeout(unpinned_count, Duration::from_millis(EVICT_WAIT_MS)).unwrap().0;
+            stalled_rounds += 1;
         }
     }
 }
@@ -1,24 +1,178 @@
 use crate::storage::page::page::{Page};
-use crate::types::{PAGE_SIZE, PageId};
+use crate::storage::page_codec::PageCodec;
+use crate::types::{PAGE_SIZE, PageId, DEFAULT_IO_RETRIES, DEFAULT_IO_RETRY_BACKOFF_MS, PAGE_CHECKSUM_SIZE};
+use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
+use std::io;
 use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
 
 pub trait DiskManager<P: Page>: Send + Sync {
+    /// Read a page from disk, or None if it was never written, cannot be deserialized
+    /// or fails its checksum
     fn read_page(&self, id: PageId) -> Option<P>;
-    fn write_page(&self, page: &P);
+    /// Write a page to disk. On error the page may not be persisted, and the caller
+    /// must keep its in-memory copy to write again
+    fn write_page(&self, page: &P) -> io::Result<()>;
+    /// Discard every page from page_id on, releasing their space.
+    /// Disk managers that cannot release space keep the pages
+    fn truncate_to(&self, _page_id: PageId) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 pub struct FileDiskManager<P: Page> {
     file: Mutex<File>,
+    compression: Option<Compression>,
+    checksums: bool,
+    retry: RetryPolicy,
     _phantom: std::marker::PhantomData<P>,
 }
 
+/// Disk manager keeping pages in memory, for tests and databases that are not persisted.
+/// Pages are stored serialized, so a page read back is a copy like one read from a file
+pub struct MemoryDiskManager<P: Page> {
+    pages: Mutex<HashMap<PageId, [u8; PAGE_SIZE]>>,
+    next_page_id: AtomicU64,
+    _phantom: std::marker::PhantomData<P>,
+}
+
+impl<P: Page> Default for MemoryDiskManager<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: Page> MemoryDiskManager<P> {
+    pub fn new() -> Self {
+        MemoryDiskManager {
+            pages: Mutex::new(HashMap::new()),
+            next_page_id: AtomicU64::new(1),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Return a page id above every page written so far, for pages created without a free list.
+    /// Page ids of a buffer pool come from its free list instead
+    pub fn allocate_page_id(&self) -> PageId {
+        self.next_page_id.fetch_add(1, Ordering::SeqCst) as PageId
+    }
+}
+
+impl<P: Page> DiskManager<P> for MemoryDiskManager<P> {
+    fn read_page(&self, id: PageId) -> Option<P> {
+        P::deserialize(self.pages.lock().unwrap().get(&id)?)
+    }
+
+    fn write_page(&self, page: &P) -> io::Result<()> {
+        let id = page.get_id();
+        self.pages.lock().unwrap().insert(id, page.serialize());
+        self.next_page_id.fetch_max(id as u64 + 1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn truncate_to(&self, page_id: PageId) -> io::Result<()> {
+        self.pages.lock().unwrap().retain(|&id, _| id < page_id);
+        Ok(())
+    }
+}
+
+/// Retries of a disk write failing with a transient error, before the error is returned.
+/// The first retry waits backoff_ms, and each next retry waits twice as long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_IO_RETRIES,
+            backoff_ms: DEFAULT_IO_RETRY_BACKOFF_MS,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run op, running it again after a backoff while it fails with a transient error
+    pub fn run<T>(&self, mut op: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+        let mut backoff = Duration::from_millis(self.backoff_ms);
+        let mut retries = 0;
+        loop {
+            match op() {
+                Err(e) if is_transient(&e) && retries < self.max_retries => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Errors that may not happen again when the operation is retried
+fn is_transient(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+/// Compressed pages have variable size on disk, so the file is a sequence of frames:
+/// [page_id: 4][capacity: 4][codec_id: 1][length: 4][data: capacity]
+/// A page is rewritten in place if it still fits its frame, otherwise a new frame
+/// is appended. Frames later in the file supersede earlier frames of the same page,
+/// so the file grows with dead frames until it is rewritten by compact.
+struct Compression {
+    codec: Box<dyn PageCodec>,
+    path: PathBuf,
+    /// page id -> (frame offset, frame capacity), rebuilt by scanning the file on open
+    offsets: Mutex<HashMap<PageId, (u64, usize)>>,
+}
+
+const FRAME_HEADER_SIZE: usize = 4 + 4 + 1 + 4;
+const UNCOMPRESSED: u8 = 0;
+
+/// CRC-32 (IEEE) lookup table, one entry per byte value
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE) checksum of bytes
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
 impl<P: Page> FileDiskManager<P> {
 
-    // open file on disk
+    /// Open file on disk. Each page is stored in a frame of PAGE_SIZE followed by the
+    /// CRC-32 of the page, and a page whose checksum does not match is not read
     pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mut disk = Self::open_unchecked(path)?;
+        disk.checksums = true;
+        Ok(disk)
+    }
+
+    /// Open a file whose pages are stored back to back without checksums,
+    /// the layout before format version 3. Only used to migrate older databases
+    pub fn open_unchecked(path: &Path) -> std::io::Result<Self> {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
@@ -27,13 +181,97 @@ impl<P: Page> FileDiskManager<P> {
 
         Ok(FileDiskManager {
             file: Mutex::new(file),
+            compression: None,
+            checksums: false,
+            retry: RetryPolicy::default(),
+            _phantom: std::marker::PhantomData,
+        })
+    }
+
+    /// Open a file whose pages are compressed with the given codec.
+    /// The file must only be written by compressed disk managers, and only one
+    /// disk manager may have it open, since each keeps its own page-offset map.
+    /// StorageEngine does not open its files compressed
+    pub fn open_compressed(path: &Path, codec: Box<dyn PageCodec>) -> std::io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        // rebuild the page-offset map, ignoring a frame cut short by a crash
+        let mut offsets = HashMap::new();
+        let len = file.seek(SeekFrom::End(0))?;
+        let mut offset = 0;
+        let mut header = [0u8; FRAME_HEADER_SIZE];
+        while offset + FRAME_HEADER_SIZE as u64 <= len {
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut header)?;
+            let page_id = PageId::from_le_bytes(header[0..4].try_into().unwrap());
+            let capacity = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+            if offset + (FRAME_HEADER_SIZE + capacity) as u64 > len {
+                break;
+            }
+            offsets.insert(page_id, (offset, capacity));
+            offset += (FRAME_HEADER_SIZE + capacity) as u64;
+        }
+
+        Ok(FileDiskManager {
+            file: Mutex::new(file),
+            compression: Some(Compression { codec, path: path.to_path_buf(), offsets: Mutex::new(offsets) }),
+            checksums: false,
+            retry: RetryPolicy::default(),
             _phantom: std::marker::PhantomData,
         })
     }
 
+    /// Rewrite a compressed file with only the latest frame of each page, reclaiming the frames
+    /// superseded by pages that outgrew them. Return the number of bytes reclaimed.
+    /// Frames are copied to a new file that replaces the old one, so a crash leaves either file whole
+    pub fn compact(&self) -> io::Result<u64> {
+        let Some(compression) = &self.compression else { return Ok(0) };
+        let mut file = self.file.lock().unwrap();
+        let mut offsets = compression.offsets.lock().unwrap();
+
+        // copy live frames in file order
+        let mut frames: Vec<_> = offsets.iter().map(|(&page_id, &(offset, capacity))| (offset, capacity, page_id)).collect();
+        frames.sort_unstable();
+        let new_path = compression.path.with_extension("compact");
+        let mut new_file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&new_path)?;
+        let mut new_offsets = HashMap::new();
+        let mut new_len = 0;
+        for (offset, capacity, page_id) in frames {
+            let mut frame = vec![0u8; FRAME_HEADER_SIZE + capacity];
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut frame)?;
+            new_file.write_all(&frame)?;
+            new_offsets.insert(page_id, (new_len, capacity));
+            new_len += frame.len() as u64;
+        }
+        new_file.sync_all()?;
+
+        let old_len = file.seek(SeekFrom::End(0))?;
+        std::fs::rename(&new_path, &compression.path)?;
+        *file = new_file;
+        *offsets = new_offsets;
+        Ok(old_len - new_len)
+    }
+
+    /// Retry transient write errors with the given policy instead of the default one
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     // get offset of page in disk
-    fn get_offset(page_id: PageId) -> u64 {
-        (page_id as usize * PAGE_SIZE) as u64
+    fn get_offset(&self, page_id: PageId) -> u64 {
+        (page_id as usize * self.frame_size()) as u64
+    }
+
+    // size of a page and its checksum on disk
+    fn frame_size(&self) -> usize {
+        if self.checksums { PAGE_SIZE + PAGE_CHECKSUM_SIZE } else { PAGE_SIZE }
     }
 }
 
@@ -41,31 +279,115 @@ impl<P: Page> DiskManager<P> for FileDiskManager<P> {
 
     /// Read a page from disk
     fn read_page(&self, id: PageId) -> Option<P> {
-        let mut buf = [0u8; PAGE_SIZE];
+        if let Some(compression) = &self.compression {
+            return Self::read_compressed(compression, &mut self.file.lock().unwrap(), id);
+        }
+
+        let mut frame = vec![0u8; self.frame_size()];
         let mut file = self.file.lock().unwrap();
 
-        if file.seek(SeekFrom::Start(Self::get_offset(id))).is_err() {
+        if file.seek(SeekFrom::Start(self.get_offset(id))).is_err() {
             return None;
         }
 
-        if file.read_exact(&mut buf).is_err() {
+        if file.read_exact(&mut frame).is_err() {
             return None;
         }
 
-        P::deserialize(&buf)
+        let (buf, checksum) = frame.split_at(PAGE_SIZE);
+        if self.checksums && checksum != crc32(buf).to_le_bytes() {
+            return None;
+        }
+
+        P::deserialize(buf.try_into().unwrap())
     }
 
-    /// Write a page to disk
-    fn write_page(&self, page: &P) {
-        let buf = page.serialize();
+    /// Write a page to disk, retrying transient errors
+    fn write_page(&self, page: &P) -> io::Result<()> {
+        let mut buf = page.serialize().to_vec();
+        if self.checksums {
+            let checksum = crc32(&buf);
+            buf.extend_from_slice(&checksum.to_le_bytes());
+        }
         let mut file = self.file.lock().unwrap();
 
-        if file.seek(SeekFrom::Start(Self::get_offset(page.get_id()))).is_err() {
-            return;
+        self.retry.run(|| {
+            if let Some(compression) = &self.compression {
+                return Self::write_compressed(compression, &mut file, page.get_id(), buf[..].try_into().unwrap());
+            }
+
+            file.seek(SeekFrom::Start(self.get_offset(page.get_id())))?;
+            file.write_all(&buf)?;
+            file.flush()
+        })
+    }
+
+    /// Shrink the file to end before page_id. Frames of a compressed file are not
+    /// ordered by page id, so its space is kept
+    fn truncate_to(&self, page_id: PageId) -> io::Result<()> {
+        if self.compression.is_some() {
+            return Ok(());
         }
 
-        let _ = file.write_all(&buf);
-        let _ = file.flush();
+        let file = self.file.lock().unwrap();
+        let len = self.get_offset(page_id);
+        if file.metadata()?.len() > len {
+            file.set_len(len)?;
+        }
+        Ok(())
+    }
+}
+
+impl<P: Page> FileDiskManager<P> {
+
+    /// Read and decompress the latest frame of a page
+    fn read_compressed(compression: &Compression, file: &mut File, id: PageId) -> Option<P> {
+        let (offset, capacity) = *compression.offsets.lock().unwrap().get(&id)?;
+        let mut frame = vec![0u8; FRAME_HEADER_SIZE + capacity];
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        file.read_exact(&mut frame).ok()?;
+
+        let codec_id = frame[8];
+        let length = u32::from_le_bytes(frame[9..13].try_into().unwrap()) as usize;
+        let data = frame.get(FRAME_HEADER_SIZE..FRAME_HEADER_SIZE + length)?;
+        let page = match codec_id {
+            UNCOMPRESSED => data.to_vec(),
+            id if id == compression.codec.id() => compression.codec.decompress(data)?,
+            _ => return None,
+        };
+
+        P::deserialize(page.as_slice().try_into().ok()?)
+    }
+
+    /// Compress a page and write it into its frame, appending a new frame if it no longer fits
+    fn write_compressed(compression: &Compression, file: &mut File, id: PageId, page: &[u8; PAGE_SIZE]) -> std::io::Result<()> {
+        // keep the page uncompressed if the codec does not shrink it
+        let compressed = compression.codec.compress(page);
+        let (codec_id, data) = if compressed.len() < PAGE_SIZE {
+            (compression.codec.id(), compressed.as_slice())
+        } else {
+            (UNCOMPRESSED, page.as_slice())
+        };
+
+        let mut offsets = compression.offsets.lock().unwrap();
+        let (offset, capacity) = match offsets.get(&id) {
+            Some(&(offset, capacity)) if data.len() <= capacity => (offset, capacity),
+            _ => (file.seek(SeekFrom::End(0))?, data.len()),
+        };
+
+        let mut frame = Vec::with_capacity(FRAME_HEADER_SIZE + capacity);
+        frame.extend_from_slice(&id.to_le_bytes());
+        frame.extend_from_slice(&(capacity as u32).to_le_bytes());
+        frame.push(codec_id);
+        frame.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        frame.extend_from_slice(data);
+        frame.resize(FRAME_HEADER_SIZE + capacity, 0);
+
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(&frame)?;
+        file.flush()?;
+        offsets.insert(id, (offset, capacity));
+        Ok(())
     }
 }
 
@@ -75,6 +397,7 @@ mod tests {
     use tempfile::NamedTempFile;
     use crate::storage::page::page::Page;
     use crate::storage::page::data_page::DataPage;
+    use crate::storage::page_codec::RleCodec;
 
     #[test]
     fn test_open_new_file() {
@@ -106,13 +429,31 @@ mod tests {
         let page = DataPage::new(42);
         // Modify page content here if DataPage has methods to add data
 
-        disk_manager.write_page(&page);
+        disk_manager.write_page(&page).unwrap();
         let read_page = disk_manager.read_page(42).unwrap();
 
         assert_eq!(page.get_id(), read_page.get_id());
         // Add more assertions based on Page's content if modified
     }
 
+    #[test]
+    fn test_truncate_to() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let disk_manager = FileDiskManager::open(path).unwrap();
+        for id in 1..=5 {
+            disk_manager.write_page(&DataPage::new(id)).unwrap();
+        }
+
+        // pages from the truncation point on are gone, the file never grows
+        disk_manager.truncate_to(3).unwrap();
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 3 * (PAGE_SIZE + PAGE_CHECKSUM_SIZE) as u64);
+        assert!(disk_manager.read_page(2).is_some());
+        assert!(disk_manager.read_page(3).is_none());
+        disk_manager.truncate_to(10).unwrap();
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 3 * (PAGE_SIZE + PAGE_CHECKSUM_SIZE) as u64);
+    }
+
     #[test]
     fn test_read_nonexistent_page() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -129,7 +470,7 @@ mod tests {
 
         let disk_manager = FileDiskManager::open(path).unwrap();
         let page = DataPage::new(42);
-        disk_manager.write_page(&page);
+        disk_manager.write_page(&page).unwrap();
 
         // Re-open the file and check persistence
         let disk_manager = FileDiskManager::<DataPage>::open(path).unwrap();
@@ -137,4 +478,135 @@ mod tests {
         assert!(read_page.is_some());
         assert_eq!(read_page.unwrap().get_id(), 42);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_corrupted_page_fails_checksum() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let disk_manager = FileDiskManager::open(path).unwrap();
+
+        let mut page = DataPage::new(3);
+        page.insert_record(b"checksummed record").unwrap();
+        disk_manager.write_page(&page).unwrap();
+        disk_manager.write_page(&DataPage::new(4)).unwrap();
+        assert!(disk_manager.read_page(3).is_some());
+
+        // flip one byte of the record in the backing file
+        let record_offset = 3 * (PAGE_SIZE + PAGE_CHECKSUM_SIZE) + PAGE_SIZE - 1;
+        let mut file = OpenOptions::new().read(true).write(true).open(path).unwrap();
+        let mut byte = [0u8; 1];
+        file.seek(SeekFrom::Start(record_offset as u64)).unwrap();
+        file.read_exact(&mut byte).unwrap();
+        file.seek(SeekFrom::Start(record_offset as u64)).unwrap();
+        file.write_all(&[byte[0] ^ 0xFF]).unwrap();
+
+        assert!(disk_manager.read_page(3).is_none());
+        assert!(disk_manager.read_page(4).is_some(), "other pages are unaffected");
+
+        // rewriting the page restores it
+        disk_manager.write_page(&page).unwrap();
+        assert_eq!(disk_manager.read_page(3).unwrap().get_record(0).unwrap(), b"checksummed record");
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_compressed_pages_round_trip() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let disk_manager = FileDiskManager::<DataPage>::open_compressed(path, Box::new(RleCodec)).unwrap();
+
+        let mut first = DataPage::new(1);
+        first.insert_record(b"compressed record").unwrap();
+        disk_manager.write_page(&first).unwrap();
+        disk_manager.write_page(&DataPage::new(7)).unwrap();
+
+        // sparse pages take a fraction of PAGE_SIZE on disk
+        assert!(std::fs::metadata(path).unwrap().len() < PAGE_SIZE as u64);
+        assert_eq!(disk_manager.read_page(1).unwrap().get_record(0).unwrap(), b"compressed record");
+        assert!(disk_manager.read_page(7).unwrap().get_record(0).is_none());
+        assert!(disk_manager.read_page(2).is_none());
+
+        // growing a page moves it to a new frame, which supersedes the old one after reopen
+        for i in 0..50u8 {
+            first.insert_record(&[i; 40]).unwrap();
+        }
+        disk_manager.write_page(&first).unwrap();
+        drop(disk_manager);
+
+        let disk_manager = FileDiskManager::<DataPage>::open_compressed(path, Box::new(RleCodec)).unwrap();
+        let read_page = disk_manager.read_page(1).unwrap();
+        assert_eq!(read_page.get_record(0).unwrap(), b"compressed record");
+        assert_eq!(read_page.get_record(50).unwrap(), &[49u8; 40]);
+        assert_eq!(disk_manager.read_page(7).unwrap().get_id(), 7);
+    }
+
+    #[test]
+    fn test_compact_reclaims_superseded_frames() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+        let disk_manager = FileDiskManager::<DataPage>::open_compressed(path, Box::new(RleCodec)).unwrap();
+
+        let mut page = DataPage::new(1);
+        disk_manager.write_page(&page).unwrap();
+        disk_manager.write_page(&DataPage::new(2)).unwrap();
+        assert_eq!(disk_manager.compact().unwrap(), 0);
+
+        // each time page 1 outgrows its frame the old frame is left behind
+        for i in 0..3u8 {
+            for j in 0..20u8 {
+                page.insert_record(&[i * 20 + j; 40]).unwrap();
+            }
+            disk_manager.write_page(&page).unwrap();
+        }
+        let len = std::fs::metadata(path).unwrap().len();
+        let reclaimed = disk_manager.compact().unwrap();
+        assert!(reclaimed > 0);
+        assert_eq!(std::fs::metadata(path).unwrap().len(), len - reclaimed);
+
+        // pages are read back from the compacted file, before and after reopen
+        assert_eq!(disk_manager.read_page(1).unwrap().get_record(59).unwrap(), &[59u8; 40]);
+        disk_manager.write_page(&DataPage::new(3)).unwrap();
+        drop(disk_manager);
+        let disk_manager = FileDiskManager::<DataPage>::open_compressed(path, Box::new(RleCodec)).unwrap();
+        assert_eq!(disk_manager.read_page(1).unwrap().get_record(0).unwrap(), &[0u8; 40]);
+        assert_eq!(disk_manager.read_page(2).unwrap().get_id(), 2);
+        assert_eq!(disk_manager.read_page(3).unwrap().get_id(), 3);
+        assert_eq!(disk_manager.compact().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_retry_policy_retries_transient_errors() {
+        let retry = RetryPolicy { max_retries: 3, backoff_ms: 1 };
+
+        // succeeds once the transient errors stop
+        let mut attempts = 0;
+        let result = retry.run(|| {
+            attempts += 1;
+            if attempts < 3 { Err(io::Error::from(io::ErrorKind::Interrupted)) } else { Ok(attempts) }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        // gives up after max_retries
+        let mut attempts = 0;
+        let result: io::Result<()> = retry.run(|| {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::WouldBlock))
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(attempts, 4);
+
+        // other errors are returned without retrying
+        let mut attempts = 0;
+        let result: io::Result<()> = retry.run(|| {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(attempts, 1);
+    }
+}
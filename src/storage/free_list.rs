@@ -1,5 +1,6 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::io;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use crate::types::PageId;
 use crate::storage::page::header_page::{HeaderPage, FREE_HEADER_SIZE};
@@ -12,10 +13,13 @@ struct HeaderFrame {
     is_dirty: AtomicBool,
 }
 
+/// Free list of page ids, safe to share between threads.
+/// Each header page is locked separately, so allocations served by different
+/// header pages don't contend. The head lock is only taken to grow the list.
 pub struct FreeList {
     disk: Arc<dyn DiskManager<HeaderPage>>, // disk manager used by free list
     head: Mutex<PageId>,                    // PageId of head page (0 if none)
-    cache: Mutex<HashMap<PageId, Arc<Mutex<HeaderFrame>>>>,
+    cache: RwLock<HashMap<PageId, Arc<Mutex<HeaderFrame>>>>,
 }
 
 impl FreeList {
@@ -24,28 +28,63 @@ impl FreeList {
         FreeList {
             disk,
             head: Mutex::new(head_page_id),
-            cache: Mutex::new(HashMap::new()),
+            cache: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Open the free list persisted by disk. Header pages are created with consecutive ids
+    /// from 1, each linking to the one before, so the head is the last page of that chain.
+    /// An empty header file gives an empty free list
+    pub fn open(disk: Arc<dyn DiskManager<HeaderPage>>) -> Self {
+        let mut head = 0;
+        while let Some(header) = disk.read_page(head + 1) {
+            let expected_next = if head == 0 { None } else { Some(head) };
+            if header.get_id() != head + 1 || header.get_next() != expected_next {
+                break;
+            }
+            head += 1;
+        }
+        Self::new(disk, head)
+    }
+
     /// Allocate a page id from free list
-    pub fn allocate(&mut self, flush: bool) -> PageId {
-        let start = { *self.head.lock().unwrap() };
-        if start == 0 {
-            // create first header
-            return self.create_and_allocate(flush);
+    pub fn allocate(&self, flush: bool) -> PageId {
+        loop {
+            let start = { *self.head.lock().unwrap() };
+            if start != 0 {
+                if let Some(page_id) = self.allocate_from(start, flush) {
+                    return page_id;
+                }
+            }
+
+            // free list is full or empty, create new header page
+            // retry if another thread grew the list since we scanned it
+            if let Some(page_id) = self.create_and_allocate(start, flush) {
+                return page_id;
+            }
         }
+    }
+
+    /// Allocate count page ids for a bulk operation, writing each dirty header page once
+    /// at the end instead of once per allocation
+    pub fn allocate_batch(&self, count: usize) -> Vec<PageId> {
+        let page_ids = (0..count).map(|_| self.allocate(false)).collect();
+        // headers that cannot be written stay dirty for the next flush
+        let _ = self.flush_all();
+        page_ids
+    }
 
+    /// Scan header pages from start and allocate from the first one with a free slot
+    fn allocate_from(&self, start: PageId, flush: bool) -> Option<PageId> {
         let mut curr = start;
         loop {
             let entry_arc = self.load_header(curr);
             let mut entry = entry_arc.lock().unwrap();
             if let Some(page_id) = entry.header.allocate_header() {
-                if flush {
-                    self.disk.write_page(&entry.header);
-                }
-                entry.is_dirty.store(!flush, Ordering::SeqCst);
-                return page_id;
+                // a header that cannot be written stays dirty for the next flush
+                let flushed = flush && self.disk.write_page(&entry.header).is_ok();
+                entry.is_dirty.store(!flushed, Ordering::SeqCst);
+                return Some(page_id);
             }
 
             match entry.header.get_next() {
@@ -53,12 +92,9 @@ impl FreeList {
                     curr = next_page;
                     continue;
                 }
-                _ => break,
+                _ => return None,
             }
         }
-
-        // free list is full, create new header page
-        self.create_and_allocate(flush)
     }
 
     /// Deallocate page_id back into free list
@@ -72,13 +108,12 @@ impl FreeList {
         loop {
             let entry_arc = self.load_header(curr);
             let mut entry = entry_arc.lock().unwrap();
-            let offset = entry.header.get_offset();
-            if page_id >= offset as PageId && page_id < (offset + FREE_HEADER_SIZE) as PageId {
-                entry.header.deallocate_header(page_id as usize);
-                if flush {
-                    self.disk.write_page(&entry.header);
-                }
-                entry.is_dirty.store(!flush, Ordering::SeqCst);
+            if entry.header.covers(page_id as usize) {
+                entry.header.deallocate_header(page_id as usize)
+                    .map_err(|e| format!("cannot deallocate page id {}: {:?}", page_id, e))?;
+                // a header that cannot be written stays dirty for the next flush
+                let flushed = flush && self.disk.write_page(&entry.header).is_ok();
+                entry.is_dirty.store(!flushed, Ordering::SeqCst);
 
                 // update cache
                 return Ok(());
@@ -96,29 +131,44 @@ impl FreeList {
         Err(format!("no header found covering page id {}", page_id))
     }
 
-    /// Create a new header page to head of list and allocate a page header
-    fn create_and_allocate(&mut self, flush: bool) -> PageId {
+    /// Return the largest page id in use, or 0 if every page id is free
+    pub fn last_allocated(&self) -> PageId {
+        let mut curr = { *self.head.lock().unwrap() };
+        let mut last = 0;
+        while curr != 0 {
+            let entry_arc = self.load_header(curr);
+            let entry = entry_arc.lock().unwrap();
+            last = last.max(entry.header.last_allocated().unwrap_or(0));
+            curr = entry.header.get_next().unwrap_or(0);
+        }
+        last
+    }
+
+    /// Create a new header page to head of list and allocate a page header.
+    /// Return None without creating a page if the head is no longer expected_head
+    fn create_and_allocate(&self, expected_head: PageId, flush: bool) -> Option<PageId> {
         let mut head_guard = self.head.lock().unwrap();
         let start = *head_guard;
+        if start != expected_head {
+            return None;
+        }
 
         if start == 0 {
             // create first header page (id = 1)
             let mut page = HeaderPage::new(1);
             let allocated = page.allocate_header().expect("New header page should have empty slot");
 
+            // optionally flush page, it stays dirty if it cannot be written
+            let flushed = flush && self.disk.write_page(&page).is_ok();
+
             // update head and cache with the mutated page
             *head_guard = page.get_id();
-            self.cache.lock().unwrap().insert(page.get_id(), Arc::new(Mutex::new(HeaderFrame {
+            self.cache.write().unwrap().insert(page.get_id(), Arc::new(Mutex::new(HeaderFrame {
                 header: page,
-                is_dirty: AtomicBool::new(!flush),
+                is_dirty: AtomicBool::new(!flushed),
             })));
 
-            // optionally flush page
-            if flush {
-                self.disk.write_page(&page);
-            }
-
-            return allocated;
+            return Some(allocated);
         }
 
         // append header page after existing start
@@ -132,41 +182,86 @@ impl FreeList {
 
         // update head and insert the mutated page into cache using its real id
         *head_guard = page.get_id();
-        self.cache.lock().unwrap().insert(page.get_id(), Arc::new(Mutex::new(HeaderFrame {
+        self.cache.write().unwrap().insert(page.get_id(), Arc::new(Mutex::new(HeaderFrame {
             header: page,
             is_dirty: AtomicBool::new(true),
         })));
 
-        allocated
+        Some(allocated)
+    }
+
+    /// Rebuild the free list from scratch when its header pages are lost or corrupt.
+    /// live_page_ids are the pages still referenced by tables and indexes in the catalog,
+    /// they are marked used. Every other page id is free, including ids in all_page_ids,
+    /// the pages physically allocated in the data file, that nothing references anymore.
+    /// The new header pages replace the old ones and are written to disk immediately
+    pub fn rebuild(&self, all_page_ids: &[PageId], live_page_ids: &HashSet<PageId>) {
+        let mut head = self.head.lock().unwrap();
+        let max_id = all_page_ids.iter().chain(live_page_ids).copied().max().unwrap_or(0) as usize;
+
+        // lay out header pages as create_and_allocate grows the list, until the largest id is in range
+        let mut headers = vec![HeaderPage::new(1)];
+        while headers.last().unwrap().get_offset() + FREE_HEADER_SIZE * 8 < max_id {
+            let prev = headers.last().unwrap();
+            let mut page = HeaderPage::new(prev.get_id() + 1);
+            page.set_next(prev.get_id());
+            page.set_offset(prev.get_offset() + FREE_HEADER_SIZE);
+            headers.push(page);
+        }
+
+        // the allocator only hands out ids covered by a header, so every live id has one
+        for &page_id in live_page_ids {
+            if let Some(header) = headers.iter_mut().find(|header| header.covers(page_id as usize)) {
+                header.allocate_id(page_id as usize).expect("header covers page id");
+            }
+        }
+
+        let mut cache = self.cache.write().unwrap();
+        cache.clear();
+        *head = headers.last().unwrap().get_id();
+        for header in headers {
+            // a header that cannot be written stays dirty for the next flush
+            let written = self.disk.write_page(&header).is_ok();
+            cache.insert(header.get_id(), Arc::new(Mutex::new(HeaderFrame {
+                header,
+                is_dirty: AtomicBool::new(!written),
+            })));
+        }
     }
 
-    /// Flush dirty page headers to disk
-    pub fn flush_header(&self, page_id: PageId) {
-        if let Some(entry) = self.cache.lock().unwrap().get(&page_id) {
+    /// Flush dirty page headers to disk, the header stays dirty if it cannot be written
+    pub fn flush_header(&self, page_id: PageId) -> io::Result<()> {
+        if let Some(entry) = self.cache.read().unwrap().get(&page_id) {
             let page = entry.lock().unwrap();
             if page.is_dirty.load(Ordering::SeqCst) {
-                self.disk.write_page(&page.header);
+                self.disk.write_page(&page.header)?;
                 page.is_dirty.store(false, Ordering::SeqCst);
             }
         }
+        Ok(())
     }
 
     /// Flush entire header page cache
-    pub fn flush_all(&self) {
-        for entry in self.cache.lock().unwrap().values() {
+    /// Headers that cannot be written stay dirty, and the first error is returned after trying every header
+    pub fn flush_all(&self) -> io::Result<()> {
+        let mut result = Ok(());
+        for entry in self.cache.read().unwrap().values() {
             let page = entry.lock().unwrap();
             if page.is_dirty.load(Ordering::SeqCst) {
-                self.disk.write_page(&page.header);
-                page.is_dirty.store(false, Ordering::SeqCst);
+                match self.disk.write_page(&page.header) {
+                    Ok(()) => page.is_dirty.store(false, Ordering::SeqCst),
+                    Err(e) => result = result.and(Err(e)),
+                }
             }
         }
+        result
     }
 
     /// Load header from cache or disk. Caller gets ownership of HeaderEntry.
     fn load_header(&self, page_id: PageId) -> Arc<Mutex<HeaderFrame>> {
         // check cache
         {
-            let cache = self.cache.lock().unwrap();
+            let cache = self.cache.read().unwrap();
             if let Some(entry) = cache.get(&page_id) {
                 return Arc::clone(entry);
             }
@@ -176,12 +271,12 @@ impl FreeList {
         match self.disk.read_page(page_id) {
             Some(p) => {
                 // p is HeaderPage
+                // another thread may have loaded the page meanwhile, keep its frame
                 let entry = Arc::new(Mutex::new(HeaderFrame {
                     header: p,
                     is_dirty: AtomicBool::new(false),
                 }));
-                self.cache.lock().unwrap().insert(page_id, Arc::clone(&entry));
-                entry
+                Arc::clone(self.cache.write().unwrap().entry(page_id).or_insert(entry))
             }
             None => {
                 panic!("could not read header page {} from disk", page_id);
@@ -207,7 +302,7 @@ mod tests {
 
     #[test]
     fn allocate_creates_first_header() {
-        let mut freelist = setup_freelist();
+        let freelist = setup_freelist();
         freelist.allocate(FLUSH);
 
         // After allocation, the disk should contain header page with id 1 (per FreeList implementation)
@@ -223,9 +318,9 @@ mod tests {
 
     #[test]
     fn deallocate_and_reuse_page() {
-        let mut freelist = setup_freelist();
+        let freelist = setup_freelist();
         let header = freelist.allocate(NO_FLUSH);
-        freelist.flush_all();
+        freelist.flush_all().unwrap();
 
         // deallocate header back
         freelist.deallocate(header, FLUSH).expect("deallocate should succeed");
@@ -235,14 +330,34 @@ mod tests {
         assert_eq!(header, header2, "re-allocated header should be the same as deallocated one");
 
         // flush header to disk and read back to ensure no panics and data exists
-        freelist.flush_header(1);
+        freelist.flush_header(1).unwrap();
         freelist.disk.read_page(1).expect("header page 1 should exist");
     }
 
+    #[test]
+    fn deallocate_out_of_range_returns_err() {
+        let freelist = setup_freelist();
+        freelist.allocate(FLUSH);
+
+        // no header page covers the page id
+        let res = freelist.deallocate((FREE_HEADER_SIZE * 8 + 1) as PageId, FLUSH);
+        assert!(res.is_err(), "deallocate of uncovered page id should return Err");
+    }
+
+    #[test]
+    fn deallocate_twice_returns_err() {
+        let freelist = setup_freelist();
+        let page_id = freelist.allocate(FLUSH);
+
+        freelist.deallocate(page_id, FLUSH).expect("deallocate should succeed");
+        let res = freelist.deallocate(page_id, FLUSH);
+        assert!(res.is_err(), "double free should return Err");
+    }
+
     #[test]
     fn allocate_second_header_when_full() {
         use crate::storage::page::header_page::FREE_HEADER_SIZE;
-        let mut freelist = setup_freelist();
+        let freelist = setup_freelist();
 
         // fill the first header completely
         let mut allocated = vec![];
@@ -253,7 +368,7 @@ mod tests {
 
         // next allocation should force creation of a new header page
         let extra = freelist.allocate(FLUSH);
-        freelist.flush_all();
+        freelist.flush_all().unwrap();
 
         // creates new header page id = start + 1 (start == 1) -> page id 2
         let second_page = freelist.disk.read_page(2);
@@ -263,17 +378,136 @@ mod tests {
 
     #[test]
     fn flush_header_persist() {
-        let mut freelist = setup_freelist();
+        let freelist = setup_freelist();
 
         freelist.allocate(NO_FLUSH);
         // flush a specific header page
-        freelist.flush_header(1);
+        freelist.flush_header(1).unwrap();
 
         // flush all headers
-        freelist.flush_all();
+        freelist.flush_all().unwrap();
 
         // confirm header page exists on disk after flushes
         freelist.disk.read_page(1).expect("header page 1 should exist after flush");
-        assert_ne!(*freelist.head.get_mut().unwrap(), 0);
+        assert_ne!(*freelist.head.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn open_resumes_persisted_list() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let disk = Arc::new(FileDiskManager::<HeaderPage>::open(temp_file.path()).unwrap());
+        assert_eq!(*FreeList::open(disk.clone()).head.lock().unwrap(), 0);
+
+        // fill the first header so the list grows a second one
+        let freelist = FreeList::new(disk.clone(), 0);
+        let capacity = FREE_HEADER_SIZE * 8;
+        let allocated = freelist.allocate_batch(capacity + 3);
+        freelist.deallocate(allocated[10], FLUSH).unwrap();
+        freelist.flush_all().unwrap();
+        drop(freelist);
+
+        // the reopened list knows every header, so no id in use is handed out again
+        let freelist = FreeList::open(disk);
+        assert_eq!(*freelist.head.lock().unwrap(), 2);
+        for _ in 0..5 {
+            let page_id = freelist.allocate(FLUSH);
+            assert!(page_id == allocated[10] || !allocated.contains(&page_id));
+        }
+        freelist.deallocate(allocated[0], FLUSH).unwrap();
+        assert!(freelist.deallocate(allocated[10], FLUSH).is_err());
+    }
+
+    /// Disk manager counting page writes
+    struct CountingDisk {
+        inner: FileDiskManager<HeaderPage>,
+        writes: std::sync::atomic::AtomicUsize,
     }
-}
\ No newline at end of file
+
+    impl DiskManager<HeaderPage> for CountingDisk {
+        fn read_page(&self, id: PageId) -> Option<HeaderPage> {
+            self.inner.read_page(id)
+        }
+
+        fn write_page(&self, page: &HeaderPage) -> io::Result<()> {
+            self.writes.fetch_add(1, Ordering::SeqCst);
+            self.inner.write_page(page)
+        }
+    }
+
+    #[test]
+    fn allocate_batch_flushes_header_once() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let disk = Arc::new(CountingDisk {
+            inner: FileDiskManager::<HeaderPage>::open(temp_file.path()).unwrap(),
+            writes: std::sync::atomic::AtomicUsize::new(0),
+        });
+        let freelist = FreeList::new(disk.clone(), 0);
+
+        let page_ids = freelist.allocate_batch(100);
+        assert_eq!(page_ids.len(), 100);
+        assert_eq!(disk.writes.load(Ordering::SeqCst), 1);
+
+        // the flushed header records every allocation
+        let mut header = disk.read_page(1).expect("header page 1 should exist on disk");
+        for page_id in page_ids {
+            assert!(header.deallocate_header(page_id as usize).is_ok(), "page id {} not allocated on disk", page_id);
+        }
+
+        // allocating one by one with flush writes the header every time
+        for _ in 0..10 {
+            freelist.allocate(FLUSH);
+        }
+        assert_eq!(disk.writes.load(Ordering::SeqCst), 11);
+    }
+
+    #[test]
+    fn concurrent_allocate_unique_ids() {
+        use std::collections::HashSet;
+        use std::thread;
+
+        let freelist = Arc::new(setup_freelist());
+        let threads = 8;
+        // enough allocations in total to grow the list past its first header page
+        let per_thread = FREE_HEADER_SIZE * 8 / threads + 100;
+
+        let handles: Vec<_> = (0..threads).map(|_| {
+            let freelist = Arc::clone(&freelist);
+            thread::spawn(move || {
+                (0..per_thread).map(|_| freelist.allocate(NO_FLUSH)).collect::<Vec<_>>()
+            })
+        }).collect();
+
+        let mut seen = HashSet::new();
+        for handle in handles {
+            for page_id in handle.join().unwrap() {
+                assert_ne!(page_id, 0);
+                assert!(seen.insert(page_id), "page id {} allocated twice", page_id);
+            }
+        }
+        assert_eq!(seen.len(), threads * per_thread);
+        assert_eq!(*freelist.head.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn rebuild_marks_only_live_pages_used() {
+        let freelist = setup_freelist();
+        let allocated: Vec<PageId> = (0..10).map(|_| freelist.allocate(FLUSH)).collect();
+        assert_eq!(allocated, (1..=10).collect::<Vec<PageId>>());
+
+        // lose the free list, then rebuild it with pages 3 and 7 no longer referenced
+        let disk = Arc::clone(&freelist.disk);
+        drop(freelist);
+        disk.write_page(&HeaderPage::new(1)).unwrap();
+        let freelist = FreeList::new(disk, 0);
+        let live: HashSet<PageId> = allocated.iter().copied().filter(|&id| id != 3 && id != 7).collect();
+        freelist.rebuild(&allocated, &live);
+
+        // unreferenced pages are freed, live pages stay used, and the rebuilt list is on disk
+        assert!(freelist.deallocate(5, NO_FLUSH).is_ok());
+        assert!(freelist.deallocate(3, NO_FLUSH).is_err(), "page 3 should already be free");
+        let reopened = FreeList::new(Arc::clone(&freelist.disk), 1);
+        assert_eq!(reopened.allocate(NO_FLUSH), 3);
+        assert_eq!(reopened.allocate(NO_FLUSH), 7);
+        assert_eq!(reopened.allocate(NO_FLUSH), 11);
+    }
+}
@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use crate::bitmap_get;
+use crate::storage::disk_manager::{crc32, DiskManager, FileDiskManager};
+use crate::storage::page::data_page::DataPage;
+use crate::storage::page::page::Page;
+use crate::storage::page::super_page::SuperPage;
+use crate::types::{PageId, DATA_FILE, FORMAT_VERSION, HEADER_FILE, MAX_SLOTS, PAGE_CHECKSUM_SIZE, PAGE_SIZE, SUPER_PAGE_ID};
+
+/// Upgrade database files in a directory from `from` to `from + 1`
+struct Migration {
+    from: u8,
+    description: &'static str,
+    run: fn(&Path) -> io::Result<()>,
+}
+
+/// Migrations in version order. A format change bumps FORMAT_VERSION and appends a step here
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "add superpage with format version",
+        run: |_| Ok(()),    // page layouts are unchanged, only the superpage is stamped
+    },
+    Migration {
+        from: 1,
+        description: "tag data file pages with their page type",
+        run: tag_data_pages,
+    },
+    Migration {
+        from: 2,
+        description: "store a checksum after every page",
+        run: add_page_checksums,
+    },
+];
+
+/// First format version whose pages are stored with checksums
+const CHECKSUM_VERSION: u8 = 3;
+
+/// Bring the database in `database_dir` to the current format version.
+/// A new database is stamped with the current version, a database from an older version is
+/// migrated step by step, and a database from a newer version is refused
+pub fn migrate(database_dir: &Path) -> io::Result<()> {
+    let header_path = database_dir.join(HEADER_FILE);
+    let stored = read_format_version(&header_path)?;
+    let version = match stored {
+        Some(version) => version,
+        None if is_new_database(database_dir) => FORMAT_VERSION,
+        None => 0,  // created before superpages existed
+    };
+
+    if version > FORMAT_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "database format version {} is newer than supported version {}", version, FORMAT_VERSION
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.from >= version && m.from < FORMAT_VERSION) {
+        (migration.run)(database_dir).map_err(|e| io::Error::new(e.kind(), format!(
+            "migration from format version {} ({}) failed: {}", migration.from, migration.description, e
+        )))?;
+        // record progress after each step so an interrupted upgrade resumes from there
+        write_format_version(&header_path, migration.from + 1)?;
+    }
+
+    if stored != Some(FORMAT_VERSION) {
+        write_format_version(&header_path, FORMAT_VERSION)?;
+    }
+    Ok(())
+}
+
+/// Return the format version recorded in the database superpage, if any
+pub fn format_version(database_dir: &Path) -> io::Result<Option<u8>> {
+    read_format_version(&database_dir.join(HEADER_FILE))
+}
+
+/// The superpage starts the header file in every format version, so its version is read
+/// without a checksum, then the page is verified if that version stores checksums.
+/// The layout of a newer version is unknown, so its superpage is left to be refused by version
+fn read_format_version(header_path: &Path) -> io::Result<Option<u8>> {
+    let version = FileDiskManager::<SuperPage>::open_unchecked(header_path)?
+        .read_page(SUPER_PAGE_ID)
+        .map(|page| page.format_version);
+    if let Some(version) = version.filter(|version| (CHECKSUM_VERSION..=FORMAT_VERSION).contains(version)) {
+        if FileDiskManager::<SuperPage>::open(header_path)?.read_page(SUPER_PAGE_ID).is_none() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "superpage of format version {} fails its checksum", version
+            )));
+        }
+    }
+    Ok(version)
+}
+
+/// Stamp the superpage with version, in the page layout of that version
+fn write_format_version(header_path: &Path, version: u8) -> io::Result<()> {
+    let super_disk = if version >= CHECKSUM_VERSION {
+        FileDiskManager::<SuperPage>::open(header_path)?
+    } else {
+        FileDiskManager::<SuperPage>::open_unchecked(header_path)?
+    };
+    super_disk.write_page(&SuperPage { format_version: version })
+}
+
+/// A database is new if none of its files hold any page yet
+fn is_new_database(database_dir: &Path) -> bool {
+    [DATA_FILE, HEADER_FILE].iter().all(|file| {
+        std::fs::metadata(database_dir.join(file)).map_or(true, |meta| meta.len() == 0)
+    })
+}
+
+/// Rewrite every data page from the version 1 layout, which had no page type tag:
+/// [id: 4][next_id: 4][next_slot: 2][free_start: 2][valid_slots: 32][slots: 4 * MAX_SLOTS][data]
+/// Version 1 databases only persisted data pages in the data file. Valid records are re-inserted
+/// in slot order, so deleted slots are reclaimed and the remaining slots renumbered
+fn tag_data_pages(database_dir: &Path) -> io::Result<()> {
+    const V1_BITMAP_START: usize = 2 * size_of::<PageId>() + 2 * size_of::<u16>();
+    const V1_SLOTS_START: usize = V1_BITMAP_START + 32;
+    const V1_HEADER_SIZE: usize = V1_SLOTS_START + 4 * MAX_SLOTS;
+
+    let data_path = database_dir.join(DATA_FILE);
+    let mut content = Vec::new();
+    File::open(&data_path)?.read_to_end(&mut content)?;
+    let data_disk = FileDiskManager::<DataPage>::open_unchecked(&data_path)?;
+
+    for (page_id, buf) in content.chunks_exact(PAGE_SIZE).enumerate() {
+        // unused page ids leave zeroed holes in the file
+        if buf.iter().all(|&b| b == 0) {
+            continue;
+        }
+
+        let read_u16 = |at: usize| u16::from_le_bytes([buf[at], buf[at + 1]]);
+        let next_id = PageId::from_le_bytes(buf[4..8].try_into().unwrap());
+        let next_slot = read_u16(2 * size_of::<PageId>()) as usize;
+        let valid_slots = &buf[V1_BITMAP_START..V1_SLOTS_START];
+        let data = &buf[V1_HEADER_SIZE..];
+
+        let mut page = DataPage::new(page_id as PageId);
+        page.set_next_id(next_id);
+        for slot in 0..next_slot.min(MAX_SLOTS) {
+            if !bitmap_get!(valid_slots, slot) {
+                continue;
+            }
+            let offset = read_u16(V1_SLOTS_START + 4 * slot) as usize;
+            let length = read_u16(V1_SLOTS_START + 4 * slot + 2) as usize;
+            let record = data.get(offset..offset + length).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData, format!("record {} of page {} is out of bounds", slot, page_id)
+            ))?;
+            page.insert_record(record).ok_or_else(|| io::Error::new(
+                io::ErrorKind::InvalidData, format!("records of page {} no longer fit in one page", page_id)
+            ))?;
+        }
+        data_disk.write_page(&page)?;
+    }
+    Ok(())
+}
+
+/// Rewrite the data and header files from pages stored back to back into frames of a page
+/// followed by its CRC-32. Zeroed holes of unused page ids are kept as zeroed frames.
+/// A file already in the new layout is left alone, so an interrupted step can run again
+fn add_page_checksums(database_dir: &Path) -> io::Result<()> {
+    const FRAME_SIZE: usize = PAGE_SIZE + PAGE_CHECKSUM_SIZE;
+    let is_hole = |bytes: &[u8]| bytes.iter().all(|&b| b == 0);
+
+    for file in [DATA_FILE, HEADER_FILE] {
+        let path = database_dir.join(file);
+        let content = match std::fs::read(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            content => content?,
+        };
+
+        let checksummed = content.len() % FRAME_SIZE == 0 && content.chunks_exact(FRAME_SIZE).all(|frame| {
+            let (page, checksum) = frame.split_at(PAGE_SIZE);
+            is_hole(frame) || checksum == crc32(page).to_le_bytes()
+        });
+        if checksummed {
+            continue;
+        }
+
+        let mut framed = Vec::with_capacity(content.len() / PAGE_SIZE * FRAME_SIZE);
+        for page in content.chunks_exact(PAGE_SIZE) {
+            framed.extend_from_slice(page);
+            let checksum = if is_hole(page) { 0 } else { crc32(page) };
+            framed.extend_from_slice(&checksum.to_le_bytes());
+        }
+
+        // write the new layout beside the old file and swap it in, so a crash leaves either one whole
+        let tmp_path = path.with_extension("rcdb.tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&framed)?;
+        tmp.sync_all()?;
+        std::fs::rename(&tmp_path, &path)?;
+    }
+    Ok(())
+}
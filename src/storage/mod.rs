@@ -4,77 +4,494 @@ pub mod replacement_strategy;
 pub mod bplus_tree;
 pub mod page;
 pub mod free_list;
+pub mod migration;
+pub mod page_codec;
 
-use std::io;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::{fs, io, thread};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use paste::paste;
 use serde::{Serialize, Deserialize};
-use bufferpool::BufferPool;
-use disk_manager::{DiskManager, FileDiskManager};
+use bufferpool::{BufferPool, BufferPoolStats};
+use disk_manager::{DiskManager, FileDiskManager, MemoryDiskManager, RetryPolicy};
 use free_list::FreeList;
-use page::data_page::DataPage;
+use page::data_page::{stored_len, DataPage, PAYLOAD_SIZE};
 use page::header_page::HeaderPage;
+use page::index_page::{IndexPage, RecordId};
+use page::page::{Page, PageError};
 use replacement_strategy::ReplacementStrategyType;
-use crate::types::{DATA_FILE, HEADER_FILE};
+use crate::types::{PageId, SlotId, DATA_FILE, DEFAULT_BUFFERPOOL_REPLACEMENT, DEFAULT_BUFFERPOOL_SIZE, HEADER_FILE, NO_FLUSH};
+use crate::{with_create_pages, with_read_pages};
 
+// fields missing from a config file fall back to their defaults
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StorageConfig {
     pub database_dir: PathBuf,
     pub bufferpool_capacity: usize,
     pub bufferpool_replacement_strategy: ReplacementStrategyType,
+    // milliseconds between automatic checkpoints, which flush every dirty page to disk
+    // dirty pages are only written on eviction and deactivation when None
+    pub checkpoint_interval_ms: Option<u64>,
+    // retries of disk writes failing with a transient error
+    pub io_retry: RetryPolicy,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            database_dir: PathBuf::new(),
+            bufferpool_capacity: DEFAULT_BUFFERPOOL_SIZE,
+            bufferpool_replacement_strategy: DEFAULT_BUFFERPOOL_REPLACEMENT,
+            checkpoint_interval_ms: None,
+            io_retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl StorageConfig {
+    /// Read a storage config from a JSON file
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Write the storage config to a JSON file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+}
+
+/// Buffer pool counters of a storage engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageStats {
+    pub data: BufferPoolStats,
+    pub index: BufferPoolStats,
 }
 
 pub struct StorageEngine {
     // primary buffer pool for DataPage
     pub buffer_pool: Arc<BufferPool<DataPage>>,
 
+    // buffer pool for IndexPage of B+ tree indexes
+    pub index_buffer_pool: Arc<BufferPool<IndexPage>>,
+
     // disk manager for data pages
     pub data_disk: Arc<dyn DiskManager<DataPage>>,
 
+    // disk manager for index pages, over the same file as data pages
+    pub index_disk: Arc<dyn DiskManager<IndexPage>>,
+
     // disk manager used by the free list (header pages)
     pub header_disk: Arc<dyn DiskManager<HeaderPage>>,
 
     // free list (manages free pages; uses header_disk internally)
-    pub free_list: Arc<Mutex<FreeList>>,
+    pub free_list: Arc<FreeList>,
+
+    // page ids of each table page chain in chain order, by first page id, built on the first reverse scan.
+    // Chains only grow at their last page and are freed whole, so an entry is extended from its last page
+    // and must be forgotten when its chain is freed
+    page_orders: Mutex<HashMap<PageId, Vec<PageId>>>,
+
+    // background thread of automatic checkpoints if configured, stopped on drop
+    _checkpointer: Option<Checkpointer>,
 }
 
 impl StorageEngine {
     /// Create a storage engine given a config. This will:
+    /// - migrate the database files to the current format version,
     /// - open a data file manager,
     /// - open a header file manager (for freelist/metadata),
     /// - construct the FreeList,
-    /// - build the BufferPool<DataPage>.
+    /// - build the BufferPool<DataPage> and BufferPool<IndexPage>.
+    ///
+    /// Both buffer pools allocate page ids from the same free list, so data and index
+    /// pages never share an id and can be stored in the same data file.
     pub fn new(config: StorageConfig) -> io::Result<Self> {
         // prepare paths
         let header_path = config.database_dir.join(HEADER_FILE);
         let data_path = config.database_dir.join(DATA_FILE);
 
+        // upgrade files written by an older version before reading any page
+        migration::migrate(&config.database_dir)?;
+
         // open disk managers
-        let header_disk = Arc::new(FileDiskManager::<HeaderPage>::open(&header_path)?);
-        let data_disk = Arc::new(FileDiskManager::<DataPage>::open(&data_path)?);
+        let header_disk = Arc::new(FileDiskManager::<HeaderPage>::open(&header_path)?.with_retry_policy(config.io_retry));
+        let data_disk = Arc::new(FileDiskManager::<DataPage>::open(&data_path)?.with_retry_policy(config.io_retry));
+        let index_disk = Arc::new(FileDiskManager::<IndexPage>::open(&data_path)?.with_retry_policy(config.io_retry));
+
+        Ok(Self::with_disks(config, header_disk, data_disk, index_disk))
+    }
+
+    /// Create a storage engine keeping every page in memory, which is lost when it is dropped.
+    /// The database directory and I/O retries of config are not used
+    pub fn in_memory(config: StorageConfig) -> Self {
+        Self::with_disks(
+            config,
+            Arc::new(MemoryDiskManager::<HeaderPage>::new()),
+            Arc::new(MemoryDiskManager::<DataPage>::new()),
+            Arc::new(MemoryDiskManager::<IndexPage>::new()),
+        )
+    }
 
-        // init freelist with given head page id
-        let freelist =  Arc::new(Mutex::new(FreeList::new(header_disk.clone(), 0)));
+    /// Build the free list and buffer pools of a storage engine over the given disk managers
+    fn with_disks(
+        config: StorageConfig,
+        header_disk: Arc<dyn DiskManager<HeaderPage>>,
+        data_disk: Arc<dyn DiskManager<DataPage>>,
+        index_disk: Arc<dyn DiskManager<IndexPage>>,
+    ) -> Self {
+        // resume the freelist persisted in the header file, so reopened databases don't reuse page ids
+        let freelist =  Arc::new(FreeList::open(header_disk.clone()));
 
         // build buffer pool for DataPage
         let buffer_pool = BufferPool::new(
             config.bufferpool_capacity,
-            config.bufferpool_replacement_strategy,
+            config.bufferpool_replacement_strategy.clone(),
             data_disk.clone(),
             Arc::clone(&freelist),
         );
 
-        Ok(StorageEngine {
-            buffer_pool: Arc::new(buffer_pool),
+        // build buffer pool for IndexPage
+        let index_buffer_pool = BufferPool::new(
+            config.bufferpool_capacity,
+            config.bufferpool_replacement_strategy,
+            index_disk.clone(),
+            Arc::clone(&freelist),
+        );
+
+        let buffer_pool = Arc::new(buffer_pool);
+        let index_buffer_pool = Arc::new(index_buffer_pool);
+        let checkpointer = config.checkpoint_interval_ms.map(|interval| Checkpointer::start(
+            Duration::from_millis(interval),
+            Arc::downgrade(&buffer_pool),
+            Arc::downgrade(&index_buffer_pool),
+            Arc::downgrade(&freelist),
+        ));
+
+        StorageEngine {
+            buffer_pool,
+            index_buffer_pool,
             data_disk,
+            index_disk,
             header_disk,
             free_list: Arc::clone(&freelist),
-        })
+            page_orders: Mutex::new(HashMap::new()),
+            _checkpointer: checkpointer,
+        }
     }
     
     /// Deactivate storage engine and flush all dirty pages
-    pub fn deactivate(&self) {
-        self.buffer_pool.flush_all();
-        self.free_list.lock().unwrap().flush_all();
+    /// Pages that cannot be written stay dirty, and the first error is returned after trying every page
+    pub fn deactivate(&self) -> Result<(), PageError> {
+        let data = self.buffer_pool.flush_all();
+        let index = self.index_buffer_pool.flush_all();
+        let headers = self.free_list.flush_all().map_err(|e| PageError::DiskWrite(e.kind()));
+        data.and(index).and(headers)
+    }
+
+    /// Describe the header, slot directory and records of a data page for debugging, one line each,
+    /// without modifying the page. Records are shown as hex bytes
+    pub fn dump_page(&self, page_id: PageId) -> Result<String, PageError> {
+        Ok(self.dump_page_with(page_id, |bytes| bytes.iter().map(|b| format!("{:02x}", b)).collect())?.join("\n"))
+    }
+
+    /// Lines of dump_page, with each valid record formatted by describe_record
+    pub fn dump_page_with(&self, page_id: PageId, describe_record: impl Fn(&[u8]) -> String) -> Result<Vec<String>, PageError> {
+        let guard = self.buffer_pool.fetch_page(page_id)?;
+        let page = guard.read();
+        Ok(page.dump(describe_record))
+    }
+
+    /// Record ids of the records in the page chain starting at first_page_id whose bytes satisfy
+    /// predicate, in page chain and slot order. Soft-deleted records are passed to predicate too,
+    /// since only the table schema can tell them apart
+    pub fn find_record(&self, first_page_id: PageId, mut predicate: impl FnMut(&[u8]) -> bool) -> Vec<RecordId> {
+        let mut record_ids = Vec::new();
+        let mut page_id = first_page_id;
+        while page_id != 0 {
+            with_read_pages!(self.buffer_pool, [(page_id, page)], {
+                for (slot_id, record_bytes) in self.iter_record(&page) {
+                    if predicate(&record_bytes) {
+                        record_ids.push(RecordId { page_id, slot_id });
+                    }
+                }
+                page_id = page.get_next_id();
+            });
+        }
+        record_ids
+    }
+
+    /// Record of slot in page, reading a record spilled to overflow pages back from them
+    pub fn get_record<'a>(&self, page: &'a DataPage, slot_id: SlotId) -> Option<Cow<'a, [u8]>> {
+        match page.get_overflow(slot_id) {
+            Some(overflow_id) => Some(Cow::Owned(self.read_overflow(overflow_id))),
+            None => page.get_record(slot_id).map(Cow::Borrowed),
+        }
+    }
+
+    /// Iterate through the records of page as get_record returns them
+    pub fn iter_record<'a>(&'a self, page: &'a DataPage) -> impl Iterator<Item = (SlotId, Cow<'a, [u8]>)> + 'a {
+        page.iter_record().map(move |(slot_id, record_bytes)| match page.get_overflow(slot_id) {
+            Some(overflow_id) => (slot_id, Cow::Owned(self.read_overflow(overflow_id))),
+            None => (slot_id, Cow::Borrowed(record_bytes)),
+        })
+    }
+
+    /// Insert record to page, spilling a record larger than the page payload to overflow pages.
+    /// Return None when the page cannot hold the record, or its overflow pointer
+    pub fn insert_record(&self, page: &mut DataPage, record: &[u8]) -> Option<SlotId> {
+        if record.len() <= PAYLOAD_SIZE {
+            return page.insert_record(record);
+        }
+        if !page.can_fit(stored_len(record.len())) {
+            return None;
+        }
+        let overflow_id = self.write_overflow(record);
+        page.insert_overflow_record(overflow_id)
+    }
+
+    /// Update the record of slot in page keeping its slot id. A record that does not fit in the page
+    /// is spilled to overflow pages, and the overflow pages of the old record are freed.
+    /// Return Err(PageError::RecordSizeChanged) if the page cannot hold an overflow pointer either
+    pub fn update_record(&self, page: &mut DataPage, slot_id: SlotId, record: &[u8]) -> Result<(), PageError> {
+        let old_overflow = page.get_overflow(slot_id);
+        let updated = if record.len() <= PAYLOAD_SIZE {
+            page.update_record(slot_id, record)
+        } else {
+            Err(PageError::RecordSizeChanged)
+        };
+        if updated == Err(PageError::RecordSizeChanged) {
+            let overflow_id = self.write_overflow(record);
+            if let Err(e) = page.update_overflow_record(slot_id, overflow_id) {
+                self.free_overflow(overflow_id);
+                return Err(e);
+            }
+        } else {
+            updated?;
+        }
+        if let Some(overflow_id) = old_overflow {
+            self.free_overflow(overflow_id);
+        }
+        Ok(())
+    }
+
+    /// Delete the record of slot in page, freeing its overflow pages
+    pub fn delete_record(&self, page: &mut DataPage, slot_id: SlotId) -> Result<(), PageError> {
+        let overflow = page.get_overflow(slot_id);
+        page.delete_record(slot_id)?;
+        if let Some(overflow_id) = overflow {
+            self.free_overflow(overflow_id);
+        }
+        Ok(())
+    }
+
+    /// Free the chain of overflow pages starting at overflow_id
+    pub fn free_overflow(&self, overflow_id: PageId) {
+        let mut page_id = overflow_id;
+        while page_id != 0 {
+            with_read_pages!(self.buffer_pool, [(page_id, page)], {
+                self.buffer_pool.free_page(page_id, NO_FLUSH);
+                page_id = page.get_next_id();
+            });
+        }
+    }
+
+    /// Write record to a chain of new overflow pages, each holding one chunk of it in its first slot
+    /// and linked by next id. Return the id of the first page
+    fn write_overflow(&self, record: &[u8]) -> PageId {
+        // the last chunk is written first, so each page can link to the page after it
+        let mut next_id = 0;
+        for chunk in record.chunks(PAYLOAD_SIZE).rev() {
+            let page_id;
+            with_create_pages!(self.buffer_pool, [(page_id, page)], NO_FLUSH, {
+                page.insert_record(chunk).expect("Error inserting overflow chunk");
+                page.set_next_id(next_id);
+            });
+            next_id = page_id;
+        }
+        next_id
+    }
+
+    /// Read back a record from the chain of overflow pages starting at overflow_id
+    fn read_overflow(&self, overflow_id: PageId) -> Vec<u8> {
+        let mut record = Vec::new();
+        let mut page_id = overflow_id;
+        while page_id != 0 {
+            with_read_pages!(self.buffer_pool, [(page_id, page)], {
+                record.extend_from_slice(page.get_record(0).expect("Error reading overflow chunk"));
+                page_id = page.get_next_id();
+            });
+        }
+        record
+    }
+
+    /// Page ids of the page chain starting at first_page_id, in chain order.
+    /// Pages only link to the next page, so a scan walks this index backwards to visit the chain tail first.
+    /// The order is cached, so after the first call only the last known page and pages appended since are read
+    pub fn page_chain(&self, first_page_id: PageId) -> Vec<PageId> {
+        let mut page_orders = self.page_orders.lock().unwrap();
+        let page_ids = page_orders.entry(first_page_id).or_insert_with(|| vec![first_page_id]);
+        let mut page_id = *page_ids.last().unwrap();
+        loop {
+            with_read_pages!(self.buffer_pool, [(page_id, page)], {
+                page_id = page.get_next_id();
+            });
+            if page_id == 0 {
+                break;
+            }
+            page_ids.push(page_id);
+        }
+        page_ids.clone()
+    }
+
+    /// Drop the cached order of the page chain starting at first_page_id, before the chain is freed
+    pub fn forget_page_chain(&self, first_page_id: PageId) {
+        self.page_orders.lock().unwrap().remove(&first_page_id);
+    }
+
+    /// Counters of the data and index buffer pools
+    pub fn stats(&self) -> StorageStats {
+        StorageStats {
+            data: self.buffer_pool.stats(),
+            index: self.index_buffer_pool.stats(),
+        }
+    }
+
+    /// Rebuild the free list when its header pages are lost or corrupt, from the pages still in use:
+    /// the page chains starting at first_page_ids with the overflow pages of their records, and index_page_ids.
+    /// Every other page id becomes free, whether or not it was ever written to the data file
+    pub fn rebuild_free_list(&self, first_page_ids: &[PageId], index_page_ids: &[PageId]) {
+        let mut live_page_ids: HashSet<PageId> = index_page_ids.iter().copied().collect();
+        for &first_page_id in first_page_ids {
+            let mut page_id = first_page_id;
+            while page_id != 0 {
+                live_page_ids.insert(page_id);
+                with_read_pages!(self.buffer_pool, [(page_id, page)], {
+                    for (slot_id, _) in page.iter_record() {
+                        let mut overflow_id = page.get_overflow(slot_id).unwrap_or(0);
+                        while overflow_id != 0 {
+                            live_page_ids.insert(overflow_id);
+                            with_read_pages!(self.buffer_pool, [(overflow_id, overflow)], {
+                                overflow_id = overflow.get_next_id();
+                            });
+                        }
+                    }
+                    page_id = page.get_next_id();
+                });
+            }
+        }
+        let all_page_ids: Vec<PageId> = live_page_ids.iter().copied().collect();
+        self.free_list.rebuild(&all_page_ids, &live_page_ids);
+    }
+
+    /// Shrink the data file to end after the last page in use, releasing the space of freed pages at its end.
+    /// Cached frames of those pages are evicted first, so they are not written back past the new end.
+    /// Pages must not be allocated meanwhile
+    pub fn reclaim_space(&self) -> Result<(), PageError> {
+        // persist the free pages first, so a crash never leaves a used page id past the end of the file
+        self.free_list.flush_all().map_err(|e| PageError::DiskWrite(e.kind()))?;
+        let end = self.free_list.last_allocated() + 1;
+        for (page_id, _, _) in self.buffer_pool.resident_pages() {
+            if page_id >= end {
+                self.buffer_pool.evict_page(page_id)?;
+            }
+        }
+        for (page_id, _, _) in self.index_buffer_pool.resident_pages() {
+            if page_id >= end {
+                self.index_buffer_pool.evict_page(page_id)?;
+            }
+        }
+        self.data_disk.truncate_to(end).map_err(|e| PageError::DiskWrite(e.kind()))
+    }
+
+    /// Allocate count data pages up front and write them to the data file as a chain of
+    /// empty pages linked in allocation order, the last one ending the chain.
+    /// The pages are taken from the free list in one batch, so on a file without freed
+    /// pages they form a contiguous region at its end. Return the page ids in chain order
+    pub fn preallocate(&self, count: usize) -> Result<Vec<PageId>, PageError> {
+        let page_ids = self.free_list.allocate_batch(count);
+        for (i, &page_id) in page_ids.iter().enumerate() {
+            let mut page = DataPage::new(page_id);
+            page.set_next_id(page_ids.get(i + 1).copied().unwrap_or(0));
+
+            // a reused page id may still be cached from its previous use, drop the stale frame
+            let uncached = |result| match result {
+                Err(PageError::InvalidPage) => Ok(()),
+                other => other,
+            };
+            let written = uncached(self.buffer_pool.evict_page(page_id))
+                .and_then(|_| uncached(self.index_buffer_pool.evict_page(page_id)))
+                .and_then(|_| self.data_disk.write_page(&page).map_err(|e| PageError::DiskWrite(e.kind())));
+            if let Err(e) = written {
+                // return the pages so a failed preallocation does not leak them
+                for &page_id in &page_ids {
+                    let _ = self.free_list.deallocate(page_id, NO_FLUSH);
+                }
+                let _ = self.free_list.flush_all();
+                return Err(e);
+            }
+        }
+        Ok(page_ids)
+    }
+}
+
+/// Background thread flushing the dirty pages of a storage engine at a fixed interval,
+/// stopped when the storage engine is dropped.
+/// Pages are written the same way eviction writes them: statements update pages in place,
+/// so a checkpoint can write a page in the middle of a statement, as eviction already can
+struct Checkpointer {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Checkpointer {
+    fn start(
+        interval: Duration,
+        buffer_pool: Weak<BufferPool<DataPage>>,
+        index_buffer_pool: Weak<BufferPool<IndexPage>>,
+        free_list: Weak<FreeList>,
+    ) -> Self {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let signal = Arc::clone(&stop);
+        let handle = thread::spawn(move || {
+            let (lock, cv) = &*signal;
+            let mut stopped = lock.lock().unwrap();
+            loop {
+                stopped = cv.wait_timeout_while(stopped, interval, |stopped| !*stopped).unwrap().0;
+                if *stopped {
+                    break;
+                }
+
+                // the storage engine is being dropped
+                let (Some(buffer_pool), Some(index_buffer_pool), Some(free_list)) =
+                    (buffer_pool.upgrade(), index_buffer_pool.upgrade(), free_list.upgrade()) else {
+                    break;
+                };
+                // pages that cannot be written stay dirty for the next checkpoint
+                let _ = buffer_pool.flush_all();
+                let _ = index_buffer_pool.flush_all();
+                let _ = free_list.flush_all();
+            }
+        });
+        Checkpointer { stop, handle: Some(handle) }
+    }
+}
+
+impl Drop for Checkpointer {
+    fn drop(&mut self) {
+        let (lock, cv) = &*self.stop;
+        *lock.lock().unwrap() = true;
+        cv.notify_one();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
     }
 }
\ No newline at end of file
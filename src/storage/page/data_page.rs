@@ -1,21 +1,29 @@
 use crate::{bitmap_get, bitmap_set};
 use crate::types::{PAGE_SIZE, MAX_SLOTS, PageId, SlotId};
-use crate::storage::page::page::{Page, PageError};
+use crate::storage::page::page::{Page, PageError, PageType, PAGE_TYPE_SIZE};
 
 const SLOT_SIZE: usize = 4;
 const PAGE_ID_SIZE: usize = size_of::<PageId>();
 const SLOT_ID_SIZE: usize = size_of::<SlotId>();
 const FREE_START_SIZE: usize = size_of::<u16>();
 const VALID_SLOT_BITMAP_SIZE: usize = 32;
+const OVERFLOW_FLAG: u16 = 1 << 15;   // set in the stored length of a slot holding an overflow pointer
 pub const fn get_page_header_size() -> usize {
-    2 * PAGE_ID_SIZE + SLOT_ID_SIZE + FREE_START_SIZE + VALID_SLOT_BITMAP_SIZE + MAX_SLOTS * SLOT_SIZE
+    PAGE_TYPE_SIZE + 2 * PAGE_ID_SIZE + SLOT_ID_SIZE + FREE_START_SIZE + VALID_SLOT_BITMAP_SIZE + MAX_SLOTS * SLOT_SIZE
 }
 pub const PAYLOAD_SIZE: usize = PAGE_SIZE - get_page_header_size();
 
+/// Bytes a record of record_len bytes takes in its page when inserted. A record larger than
+/// the page payload is spilled to overflow pages, its slot holds the id of the first one
+pub const fn stored_len(record_len: usize) -> usize {
+    if record_len <= PAYLOAD_SIZE { record_len } else { PAGE_ID_SIZE }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct Slot {
     offset: u16, // record offset
     length: u16, // record length
+    overflow: bool, // the record is in overflow pages, the slot holds the id of the first one
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -46,12 +54,15 @@ impl Page for DataPage {
 
 
     /// Layout:
-    /// [id: 4][next_slot: 2][free_start: 2][valid_slots: 32][slot array: 4 * MAX_SLOTS][data]
+    /// [page_type: 1][id: 4][next_id: 4][next_slot: 2][free_start: 2][valid_slots: 32][slot array: 4 * MAX_SLOTS][data]
+    /// A slot is [offset: 2][length: 2], the high bit of length marks an overflow pointer
     fn serialize(&self) -> [u8; PAGE_SIZE] {
         let mut buf = [0u8; PAGE_SIZE];
         let mut cursor = 0;
 
         // serialize page header
+        buf[cursor] = PageType::Data as u8;
+        cursor += PAGE_TYPE_SIZE;
         buf[cursor..cursor + PAGE_ID_SIZE].copy_from_slice(&self.id.to_le_bytes());
         cursor += PAGE_ID_SIZE;
         buf[cursor..cursor + PAGE_ID_SIZE].copy_from_slice(&self.next_id.to_le_bytes());
@@ -68,7 +79,8 @@ impl Page for DataPage {
             if let Some(slot) = slot {
                 buf[cursor..cursor + size_of::<u16>()].copy_from_slice(&slot.offset.to_le_bytes());
                 cursor += size_of::<u16>();
-                buf[cursor..cursor + size_of::<u16>()].copy_from_slice(&slot.length.to_le_bytes());
+                let length = if slot.overflow { slot.length | OVERFLOW_FLAG } else { slot.length };
+                buf[cursor..cursor + size_of::<u16>()].copy_from_slice(&length.to_le_bytes());
                 cursor += size_of::<u16>();
             } else {
                 buf[cursor..cursor + SLOT_SIZE].fill(0);
@@ -84,7 +96,11 @@ impl Page for DataPage {
     fn deserialize(buf: &[u8; PAGE_SIZE]) -> Option<Self> {
         let mut cursor = 0;
 
-        // deserialize page header
+        // deserialize page header, rejecting pages of another type
+        if buf[cursor] != PageType::Data as u8 {
+            return None;
+        }
+        cursor += PAGE_TYPE_SIZE;
         let id = PageId::from_le_bytes(buf[cursor..cursor + PAGE_ID_SIZE].try_into().ok()?);
         cursor += PAGE_ID_SIZE;
         let next_id = PageId::from_le_bytes(buf[cursor..cursor + PAGE_ID_SIZE].try_into().ok()?);
@@ -107,7 +123,8 @@ impl Page for DataPage {
             if !(offset == 0 && length == 0) {
                 slots[i] = Some(Slot {
                     offset,
-                    length,
+                    length: length & !OVERFLOW_FLAG,
+                    overflow: length & OVERFLOW_FLAG != 0,
                 });
             }
         }
@@ -137,6 +154,13 @@ impl Page for DataPage {
         self.free_start as usize
     }
 
+    /// Header and slot array, and the records from free_start to the end of the payload,
+    /// including unreclaimed deleted records
+    #[inline]
+    fn used_bytes(&self) -> usize {
+        get_page_header_size() + PAYLOAD_SIZE - self.free_start as usize
+    }
+
     #[inline]
     fn is_empty(&self) -> bool {
         self.next_slot == 0
@@ -151,6 +175,19 @@ impl DataPage {
     #[inline]
     pub fn set_next_id(&mut self, id: PageId) { self.next_id = id; }
 
+    /// Whether a record of record_len bytes can be inserted to page
+    /// Agrees with the outcome of insert_record
+    #[inline]
+    pub fn can_fit(&self, record_len: usize) -> bool {
+        self.remaining_slots() > 0 && record_len <= self.free_start as usize
+    }
+
+    /// Number of unused entries left in slot array
+    #[inline]
+    pub fn remaining_slots(&self) -> usize {
+        MAX_SLOTS - self.next_slot as usize
+    }
+
     /// Insert record to page, return Some(SlotId) if successful
     /// Return None when there's insufficient page space or slot array
     pub fn insert_record(&mut self, record: &[u8]) -> Option<SlotId> {
@@ -174,16 +211,27 @@ impl DataPage {
         let slot = Slot {
             offset,
             length: record_len,
+            overflow: false,
         };
         bitmap_set!(self.valid_slots, self.next_slot as usize, true);
         self.slots[self.next_slot as usize] = Some(slot);
         self.next_slot += 1;
+        self.assert_invariants();
 
         Some(self.next_slot - 1)
     }
 
+    /// Insert a record spilled to overflow pages, keeping the id of its first overflow page in the slot
+    /// Return None when there's insufficient page space or slot array
+    pub fn insert_overflow_record(&mut self, overflow_id: PageId) -> Option<SlotId> {
+        let slot_id = self.insert_record(&overflow_id.to_le_bytes())?;
+        self.slots[slot_id as usize].as_mut().unwrap().overflow = true;
+        Some(slot_id)
+    }
+
     /// Get a record by SlotId
-    /// Return None if the slot is empty
+    /// Return None if the slot is empty. A record spilled to overflow pages has no bytes in the page,
+    /// StorageEngine::get_record reassembles it
     pub fn get_record(&self, slot_id: SlotId) -> Option<&[u8]> {
         // return None if slot is invalid
         if !bitmap_get!(self.valid_slots, slot_id as usize) {
@@ -191,17 +239,31 @@ impl DataPage {
         }
 
         // retrieve data by slot offset and length
-        if let Some(slot) = self.slots.get(slot_id as usize)?.as_ref() {
-            let start = slot.offset as usize;
-            let end = start + slot.length as usize;
-            Some(&self.data[start..end])
-        } else {
-            None
+        self.slots.get(slot_id as usize)?.as_ref().map(|slot| self.record_bytes(slot))
+    }
+
+    /// Id of the first overflow page of the record in slot, None if the record is stored in the page
+    pub fn get_overflow(&self, slot_id: SlotId) -> Option<PageId> {
+        if !bitmap_get!(self.valid_slots, slot_id as usize) {
+            return None;
         }
+        let slot = self.slots.get(slot_id as usize)?.as_ref().filter(|slot| slot.overflow)?;
+        let start = slot.offset as usize;
+        Some(PageId::from_le_bytes(self.data[start..start + PAGE_ID_SIZE].try_into().unwrap()))
     }
 
-    /// Update record with given SlotId and record data
-    /// Return Err(PageError::RecordSizeChanged) if the record size is not the same
+    /// Bytes of the record of slot held in the page, none for a record in overflow pages
+    #[inline]
+    fn record_bytes(&self, slot: &Slot) -> &[u8] {
+        let start = slot.offset as usize;
+        let end = if slot.overflow { start } else { start + slot.length as usize };
+        &self.data[start..end]
+    }
+
+    /// Update record with given SlotId and record data, keeping its SlotId
+    /// A record that shrinks is rewritten in place, one that grows is moved into the free space of the page
+    /// and its old bytes are left unused. An overflow pointer in the slot is replaced, its pages are not freed
+    /// Return Err(PageError::RecordSizeChanged) if a grown record does not fit in the free space of the page
     /// Return Err(PageError::InvalidSlot) if the slot is empty
     pub fn update_record(&mut self, slot_id: SlotId, new_record: &[u8]) -> Result<(), PageError> {
         // check if record exists
@@ -210,40 +272,131 @@ impl DataPage {
         }
 
         if let Some(slot) = &mut self.slots[slot_id as usize] {
-            if new_record.len() == slot.length as usize {
+            let record_len = new_record.len() as u16;
+            if record_len <= slot.length {
                 // in-place update if size is enough
-                self.data[slot.offset as usize..(slot.offset + slot.length) as usize]
-                    .copy_from_slice(new_record);
-                Ok(())
+                slot.length = record_len;
+            } else if new_record.len() <= self.free_start as usize {
+                // move the record to the start of free space
+                self.free_start -= record_len;
+                *slot = Slot { offset: self.free_start, length: record_len, overflow: false };
             } else {
-                // TODO: implement overflow page for oversized records
-                Err(PageError::RecordSizeChanged)
+                return Err(PageError::RecordSizeChanged);
             }
+            slot.overflow = false;
+            self.data[slot.offset as usize..(slot.offset + slot.length) as usize]
+                .copy_from_slice(new_record);
+            self.assert_invariants();
+            Ok(())
         } else {
             Err(PageError::InvalidSlot)
         }
     }
 
+    /// Replace the record of slot by one spilled to overflow pages starting at overflow_id, as update_record
+    pub fn update_overflow_record(&mut self, slot_id: SlotId, overflow_id: PageId) -> Result<(), PageError> {
+        self.update_record(slot_id, &overflow_id.to_le_bytes())?;
+        self.slots[slot_id as usize].as_mut().unwrap().overflow = true;
+        Ok(())
+    }
+
     /// Mark a record as deleted
     pub fn delete_record(&mut self, slot_id: SlotId) -> Result<(), PageError> {
         if bitmap_get!(self.valid_slots, slot_id as usize) {
             bitmap_set!(self.valid_slots, slot_id as usize, false);
+            self.assert_invariants();
             return Ok(());
         }
         Err(PageError::InvalidSlot)
     }
 
-    /// Iterate through records on page
+    /// Validate page structure:
+    /// 1. slots below next_slot are present and slots from next_slot on are empty
+    /// 2. only present slots are marked valid
+    /// 3. records lie within payload between free_start and the end, without overlapping
+    /// 4. free_start is the offset of the lowest record
+    /// 5. a slot holding an overflow pointer has the length of a page id
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if self.next_slot as usize > MAX_SLOTS {
+            return Err(format!("next_slot {} exceeds {} slots", self.next_slot, MAX_SLOTS));
+        }
+        if self.free_start as usize > PAYLOAD_SIZE {
+            return Err(format!("free_start {} exceeds payload size {}", self.free_start, PAYLOAD_SIZE));
+        }
+
+        let mut records = Vec::new();
+        for (i, slot) in self.slots.iter().enumerate() {
+            let used = i < self.next_slot as usize;
+            match slot {
+                Some(slot) if used && slot.overflow && slot.length as usize != PAGE_ID_SIZE => {
+                    return Err(format!("overflow slot {} has length {}", i, slot.length));
+                }
+                Some(slot) if used => records.push((slot.offset as usize, slot.length as usize, i)),
+                Some(_) => return Err(format!("slot {} is set beyond next_slot {}", i, self.next_slot)),
+                None if used => return Err(format!("slot {} below next_slot {} is empty", i, self.next_slot)),
+                None => {}
+            }
+            if bitmap_get!(self.valid_slots, i) && !used {
+                return Err(format!("slot {} is marked valid beyond next_slot {}", i, self.next_slot));
+            }
+        }
+        for i in MAX_SLOTS..VALID_SLOT_BITMAP_SIZE * 8 {
+            if bitmap_get!(self.valid_slots, i) {
+                return Err(format!("bitmap marks slot {} beyond the slot array as valid", i));
+            }
+        }
+
+        records.sort();
+        let mut prev_end = self.free_start as usize;
+        for (offset, length, i) in records {
+            if offset < prev_end {
+                return Err(format!("record of slot {} at offset {} overlaps free space or another record", i, offset));
+            }
+            if offset + length > PAYLOAD_SIZE {
+                return Err(format!("record of slot {} at offset {} with length {} exceeds payload", i, offset, length));
+            }
+            if prev_end == self.free_start as usize && offset != prev_end {
+                return Err(format!("free_start {} is below the lowest record at offset {}", self.free_start, offset));
+            }
+            prev_end = offset + length;
+        }
+
+        Ok(())
+    }
+
+    /// Panic on violated invariants after mutations when the debug_invariants feature is enabled
+    #[inline]
+    fn assert_invariants(&self) {
+        #[cfg(feature = "debug_invariants")]
+        if let Err(e) = self.check_invariants() {
+            panic!("DataPage {} invariant violated: {}", self.id, e);
+        }
+    }
+
+    /// Describe the page header and slot directory, one line each, showing every valid record
+    /// as formatted by describe_record. The page is only read
+    pub fn dump(&self, describe_record: impl Fn(&[u8]) -> String) -> Vec<String> {
+        let mut lines = vec![format!(
+            "DataPage {}: next_id={} next_slot={} free_start={}",
+            self.id, self.next_id, self.next_slot, self.free_start
+        )];
+        for (i, slot) in self.slots.iter().enumerate().take(self.next_slot as usize) {
+            let Some(slot) = slot else { continue };
+            let record = match (self.get_overflow(i as SlotId), self.get_record(i as SlotId)) {
+                (Some(overflow_id), _) => format!("overflow to page {}", overflow_id),
+                (None, Some(bytes)) => describe_record(bytes),
+                (None, None) => "deleted".to_string(),
+            };
+            lines.push(format!("slot {}: offset={} length={} {}", i, slot.offset, slot.length, record));
+        }
+        lines
+    }
+
+    /// Iterate through records on page, records in overflow pages have no bytes as in get_record
     pub fn iter_record(&self) -> impl Iterator<Item = (SlotId, &[u8])> {
         self.slots.iter().enumerate()
             .filter(move |(i, _)| bitmap_get!(self.valid_slots, *i))
-            .filter_map(|(i, slot)| {
-                slot.as_ref().map(|s| {
-                    let start = s.offset as usize;
-                    let end = start + s.length as usize;
-                    (i as SlotId, &self.data[start..end])
-                })
-            })
+            .filter_map(|(i, slot)| slot.as_ref().map(|s| (i as SlotId, self.record_bytes(s))))
     }
 }
 
@@ -376,12 +529,67 @@ mod tests {
 
     #[test]
     fn test_update_record_different_size() {
-        let (mut page, slot_id, _) = create_page_with_records();
-        let new_data = [10, 11, 12, 13]; // larger than original
+        let (mut page, slot_id, other) = create_page_with_records();
+        let initial_free_space = page.get_free_space();
 
-        let result = page.update_record(slot_id, &new_data);
-        assert!(result.is_err());
-        assert_eq!(page.get_record(slot_id).unwrap(), SMALL_RECORD);
+        // a larger record moves into free space under the same slot
+        let grown = [10, 11, 12, 13];
+        assert!(page.update_record(slot_id, &grown).is_ok());
+        assert_eq!(page.get_record(slot_id).unwrap(), grown);
+        assert_eq!(page.get_free_space(), initial_free_space - grown.len());
+
+        // a smaller record is rewritten in place
+        let shrunk = [20];
+        assert!(page.update_record(slot_id, &shrunk).is_ok());
+        assert_eq!(page.get_record(slot_id).unwrap(), shrunk);
+        assert_eq!(page.get_free_space(), initial_free_space - grown.len());
+        assert_eq!(page.get_record(other).unwrap(), LARGE_RECORD);
+        assert!(page.check_invariants().is_ok());
+
+        // a record larger than the free space is rejected and left unchanged
+        let oversized = vec![1u8; page.get_free_space() + 2];
+        assert!(matches!(page.update_record(slot_id, &oversized), Err(PageError::RecordSizeChanged)));
+        assert_eq!(page.get_record(slot_id).unwrap(), shrunk);
+    }
+
+    #[test]
+    fn test_overflow_record() {
+        let (mut page, slot1, slot2) = create_page_with_records();
+
+        // an overflow pointer has no record bytes in the page, and survives serialization
+        let slot3 = page.insert_overflow_record(7).unwrap();
+        assert_eq!(page.get_overflow(slot3), Some(7));
+        assert_eq!(page.get_record(slot3).unwrap(), b"");
+        assert_eq!(page.get_overflow(slot2), None);
+        let page_copy = DataPage::deserialize(&page.serialize()).unwrap();
+        assert_eq!(page_copy.get_overflow(slot3), Some(7));
+        assert_eq!(page_copy.get_record(slot2).unwrap(), LARGE_RECORD);
+        assert!(page_copy.check_invariants().is_ok());
+
+        // records and overflow pointers replace each other under the same slot
+        page.update_overflow_record(slot1, 9).unwrap();
+        assert_eq!(page.get_overflow(slot1), Some(9));
+        page.update_record(slot3, &SMALL_RECORD).unwrap();
+        assert_eq!(page.get_overflow(slot3), None);
+        assert_eq!(page.get_record(slot3).unwrap(), SMALL_RECORD);
+        assert!(page.check_invariants().is_ok());
+
+        let lines = page.dump(|bytes| format!("{:?}", bytes));
+        assert!(lines[1].ends_with("overflow to page 9"), "{}", lines[1]);
+        page.delete_record(slot1).unwrap();
+        assert_eq!(page.get_overflow(slot1), None);
+    }
+
+    #[test]
+    fn test_dump() {
+        let (mut page, slot1, _) = create_page_with_records();
+        page.delete_record(slot1).unwrap();
+
+        let lines = page.dump(|bytes| format!("{:?}", bytes));
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("DataPage 1: next_id=0 next_slot=2"));
+        assert!(lines[1].starts_with("slot 0:") && lines[1].ends_with("deleted"));
+        assert!(lines[2].ends_with(&format!("length=12 {:?}", LARGE_RECORD)));
     }
 
     #[test]
@@ -452,4 +660,87 @@ mod tests {
             assert!(!bitmap_get!(page.valid_slots, i))
         }
     }
+
+    #[test]
+    fn test_can_fit_agrees_with_insert() {
+        // fill page with large records until payload space runs out
+        let mut page = DataPage::new(1);
+        let record = [7u8; 100];
+        assert_eq!(page.remaining_slots(), MAX_SLOTS);
+        loop {
+            let fits = page.can_fit(record.len());
+            assert_eq!(fits, page.insert_record(&record).is_some());
+            if !fits {
+                break;
+            }
+        }
+
+        // a smaller record may still fit in the remaining space
+        let remaining = page.get_free_space();
+        assert!(page.can_fit(remaining));
+        assert!(!page.can_fit(remaining + 1));
+        assert!(page.insert_record(&vec![1u8; remaining + 1]).is_none());
+
+        // fill slot array with empty records until no slot remains
+        let mut page = DataPage::new(2);
+        for i in 0..MAX_SLOTS {
+            assert_eq!(page.remaining_slots(), MAX_SLOTS - i);
+            assert!(page.can_fit(0));
+            page.insert_record(&[]).unwrap();
+        }
+        assert_eq!(page.remaining_slots(), 0);
+        assert!(!page.can_fit(0));
+        assert!(page.insert_record(&[]).is_none());
+    }
+
+    #[test]
+    fn test_check_invariants() {
+        let (mut page, slot1, _) = create_page_with_records();
+        assert_eq!(page.check_invariants(), Ok(()));
+        page.update_record(slot1, &[9, 9, 9]).unwrap();
+        page.delete_record(slot1).unwrap();
+        assert_eq!(page.check_invariants(), Ok(()));
+
+        // free_start not matching the lowest record
+        let (mut page, _, _) = create_page_with_records();
+        page.free_start -= 1;
+        assert!(page.check_invariants().is_err());
+
+        // overlapping records
+        let (mut page, slot1, slot2) = create_page_with_records();
+        page.slots[slot1 as usize].as_mut().unwrap().offset = page.slots[slot2 as usize].unwrap().offset + 1;
+        assert!(page.check_invariants().is_err());
+
+        // record past the end of payload
+        let (mut page, slot1, _) = create_page_with_records();
+        page.slots[slot1 as usize].as_mut().unwrap().length += 1;
+        assert!(page.check_invariants().is_err());
+
+        // valid bit without a slot
+        let (mut page, _, _) = create_page_with_records();
+        bitmap_set!(page.valid_slots, 5, true);
+        assert!(page.check_invariants().is_err());
+
+        // slot missing below next_slot
+        let (mut page, slot1, _) = create_page_with_records();
+        page.slots[slot1 as usize] = None;
+        assert!(page.check_invariants().is_err());
+    }
+
+    #[test]
+    fn test_used_bytes() {
+        let mut page = DataPage::new(1);
+        assert_eq!(page.used_bytes(), get_page_header_size());
+        assert_eq!(page.used_bytes() + page.get_free_space(), PAGE_SIZE);
+
+        let slot = page.insert_record(&SMALL_RECORD).unwrap();
+        page.insert_record(&LARGE_RECORD).unwrap();
+        assert_eq!(page.used_bytes(), get_page_header_size() + SMALL_RECORD.len() + LARGE_RECORD.len());
+        assert_eq!(page.used_bytes() + page.get_free_space(), PAGE_SIZE);
+
+        // deleted records still hold their bytes until compaction
+        let used = page.used_bytes();
+        page.delete_record(slot).unwrap();
+        assert_eq!(page.used_bytes(), used);
+    }
 }
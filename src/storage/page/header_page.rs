@@ -1,6 +1,6 @@
 use crate::{bitmap_get, bitmap_set};
 use crate::types::{PAGE_SIZE, PageId};
-use crate::storage::page::page::Page;
+use crate::storage::page::page::{Page, PageError};
 
 const MAX_HEADERS: usize = 2 * size_of::<PageId>() + size_of::<u32>();
 pub const FREE_HEADER_SIZE: usize = PAGE_SIZE - MAX_HEADERS;  // free slot bitmap size in bytes
@@ -44,14 +44,44 @@ impl HeaderPage {
         }
     }
 
+    /// Whether the page id falls within [offset + 1, offset + FREE_HEADER_SIZE * 8]
+    pub fn covers(&self, page_id: usize) -> bool {
+        let offset = self.offset as usize;
+        page_id > offset && page_id <= offset + FREE_HEADER_SIZE * 8
+    }
+
+    /// Mark a given page id as used, for rebuilding a free list
+    /// Return error if the page id is not covered by this header
+    pub fn allocate_id(&mut self, page_id: usize) -> Result<(), PageError> {
+        if !self.covers(page_id) {
+            return Err(PageError::PageOutOfRange);
+        }
+
+        bitmap_set!(self.free_slot, page_id - self.offset as usize - 1, true);
+        Ok(())
+    }
+
+    /// Return the largest page id marked as used, or None if every page id of this header is free
+    pub fn last_allocated(&self) -> Option<PageId> {
+        let byte = self.free_slot.iter().rposition(|&b| b != 0)?;
+        let bit = 7 - self.free_slot[byte].leading_zeros() as usize;
+        Some((self.offset as usize + byte * 8 + bit + 1) as PageId)
+    }
+
     /// Deallocate a page header, mark the location as unused
     /// The page header index starts from 1
-    pub fn deallocate_header(&mut self, page_id: usize) {
-        if bitmap_get!(self.free_slot, page_id - self.offset as usize - 1) == true {
-            bitmap_set!(self.free_slot, page_id - self.offset as usize - 1, false)
-        } else {
-            panic!("attempt to free a header that is already freed");
+    /// Return error if the page id is not covered by this header or is already free
+    pub fn deallocate_header(&mut self, page_id: usize) -> Result<(), PageError> {
+        if !self.covers(page_id) {
+            return Err(PageError::PageOutOfRange);
+        }
+
+        let index = page_id - self.offset as usize - 1;
+        if !bitmap_get!(self.free_slot, index) {
+            return Err(PageError::PageAlreadyFreed);
         }
+        bitmap_set!(self.free_slot, index, false);
+        Ok(())
     }
 
     /// Return index of first free slot (0-bit) if exists
@@ -148,6 +178,11 @@ impl Page for HeaderPage {
         self.get_slot().unwrap_or(usize::MAX)
     }
 
+    /// Header fields and the bitmap up to its last byte marking a used page id
+    fn used_bytes(&self) -> usize {
+        MAX_HEADERS + self.free_slot.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1)
+    }
+
     fn is_empty(&self) -> bool {
         self.get_slot().is_none()
     }
@@ -213,12 +248,12 @@ mod tests {
         assert_eq!(alloc2, 102);
 
         // deallocate the first slot
-        page.deallocate_header(101);
+        page.deallocate_header(101).unwrap();
         // bit should now be cleared
         assert!(!bitmap_get!(page.free_slot, 101));
 
         // deallocate the second slot
-        page.deallocate_header(102);
+        page.deallocate_header(102).unwrap();
         assert!(!bitmap_get!(page.free_slot, 102));
 
         // allocating again should return the first slot again
@@ -226,6 +261,63 @@ mod tests {
         assert_eq!(alloc3, 101);
     }
 
+    #[test]
+    fn test_deallocate_header_out_of_range() {
+        let mut page = HeaderPage::new(0);
+        page.offset = 100;
+        page.allocate_header().expect("should allocate a slot");
+
+        // ids at or below offset and past the bitmap are not covered by this header
+        assert_eq!(page.deallocate_header(100), Err(PageError::PageOutOfRange));
+        assert_eq!(page.deallocate_header(0), Err(PageError::PageOutOfRange));
+        assert_eq!(page.deallocate_header(100 + FREE_HEADER_SIZE * 8 + 1), Err(PageError::PageOutOfRange));
+
+        // last covered id is in range but free
+        assert_eq!(page.deallocate_header(100 + FREE_HEADER_SIZE * 8), Err(PageError::PageAlreadyFreed));
+        assert_eq!(page.deallocate_header(101), Ok(()));
+    }
+
+    #[test]
+    fn test_last_allocated() {
+        let mut page = HeaderPage::new(0);
+        page.offset = 100;
+        assert_eq!(page.last_allocated(), None);
+
+        assert_eq!(page.allocate_id(101), Ok(()));
+        assert_eq!(page.last_allocated(), Some(101));
+        assert_eq!(page.allocate_id(117), Ok(()));
+        assert_eq!(page.last_allocated(), Some(117));
+        assert_eq!(page.deallocate_header(117), Ok(()));
+        assert_eq!(page.last_allocated(), Some(101));
+    }
+
+    #[test]
+    fn test_allocate_id() {
+        let mut page = HeaderPage::new(0);
+        page.offset = 100;
+
+        assert_eq!(page.allocate_id(103), Ok(()));
+        assert_eq!(page.allocate_id(100), Err(PageError::PageOutOfRange));
+
+        // the marked id is skipped by allocation and can be freed
+        assert_eq!(page.allocate_header(), Some(101));
+        assert_eq!(page.allocate_header(), Some(102));
+        assert_eq!(page.allocate_header(), Some(104));
+        assert_eq!(page.deallocate_header(103), Ok(()));
+    }
+
+    #[test]
+    fn test_deallocate_header_double_free() {
+        let mut page = HeaderPage::new(0);
+        let id = page.allocate_header().expect("should allocate a slot") as usize;
+
+        assert_eq!(page.deallocate_header(id), Ok(()));
+        assert_eq!(page.deallocate_header(id), Err(PageError::PageAlreadyFreed));
+
+        // never allocated
+        assert_eq!(page.deallocate_header(id + 1), Err(PageError::PageAlreadyFreed));
+    }
+
     #[test]
     fn test_get_free_space_and_is_empty_all_ones() {
         let mut page = HeaderPage::new(7);
@@ -281,4 +373,18 @@ mod tests {
         assert_eq!(page.get_free_space(), 0);
         assert!(!page.is_empty());
     }
+
+    #[test]
+    fn test_used_bytes() {
+        let mut page = HeaderPage::new(0);
+        assert_eq!(page.used_bytes(), MAX_HEADERS);
+
+        bitmap_set!(page.free_slot, 0, true);
+        assert_eq!(page.used_bytes(), MAX_HEADERS + 1);
+
+        bitmap_set!(page.free_slot, 8 * 10 + 3, true);
+        assert_eq!(page.used_bytes(), MAX_HEADERS + 11);
+        // bytes past used_bytes are zero padding
+        assert!(page.serialize()[page.used_bytes()..].iter().all(|&b| b == 0));
+    }
 }
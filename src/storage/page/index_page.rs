@@ -1,4 +1,4 @@
-use crate::storage::page::page::Page;
+use crate::storage::page::page::{Page, PageType, PAGE_TYPE_SIZE};
 use crate::types::{PageId, SlotId, PAGE_SIZE};
 
 type KeysLen = u16;
@@ -6,7 +6,7 @@ const PAGE_ID_SIZE: usize = size_of::<PageId>();
 const SLOT_ID_SIZE: usize = size_of::<SlotId>();
 const KEYS_LEN: usize = size_of::<KeysLen>();
 pub const fn get_page_header_size() -> usize {
-    PAGE_ID_SIZE + 1 + KEYS_LEN
+    PAGE_TYPE_SIZE + PAGE_ID_SIZE + 1 + KEYS_LEN
 }
 // maximum number of leaf nodes an index page can hold
 pub const fn get_leaf_capacity() -> usize {
@@ -81,7 +81,8 @@ impl IndexPage {
         &mut self.children
     }
 
-    /// For internal page: find child page following given key
+    /// For internal page: find child page following given key.
+    /// Keys equal to a separator may be on both sides of it, this is the rightmost child that may hold key
     pub fn search_child(&self, key: &i64) -> Option<PageId> {
         debug_assert_eq!(self.page_type, IndexType::Internal);
         for (i, k) in self.keys.iter().enumerate() {
@@ -93,7 +94,13 @@ impl IndexPage {
         self.children.last().copied()
     }
 
-    /// For leaf pages: return exact match RID if exists.
+    /// For internal page: the leftmost child page that may hold key
+    pub fn search_first_child(&self, key: &i64) -> Option<PageId> {
+        debug_assert_eq!(self.page_type, IndexType::Internal);
+        self.children.get(self.keys.partition_point(|k| k < key)).copied()
+    }
+
+    /// For leaf pages: return the first exact match RID if exists.
     pub fn search_rid(&self, key: &i64) -> Option<&RecordId> {
         debug_assert_eq!(self.page_type, IndexType::Leaf);
         self.keys
@@ -102,33 +109,60 @@ impl IndexPage {
             .map(|i| &self.rids[i])
     }
 
-    /// Insert a new key into index page if not already exist
+    /// Insert a key into index page, after the keys equal to it
     pub fn insert_key(&mut self, key: i64) {
-        if let Err(index) = self.keys.binary_search(&key) {
-            self.keys.insert(index, key);
-        }
+        let index = self.keys.partition_point(|k| *k <= key);
+        self.keys.insert(index, key);
     }
 
-    /// For leaf page: insert a key into index page
+    /// For leaf page: insert a record under key, after the records already under it.
+    /// A key may hold several records, inserting a record it already holds does nothing
     pub fn insert_record(&mut self, key: i64, record: RecordId) {
         debug_assert_eq!(self.page_type, IndexType::Leaf);
-        match self.keys.binary_search(&key) {
-            Ok(index) => self.rids[index] = record,
-            Err(index) => {
-                self.keys.insert(index, key);
-                self.rids.insert(index, record);
-            }
+        let start = self.keys.partition_point(|k| *k < key);
+        let end = self.keys.partition_point(|k| *k <= key);
+        if self.rids[start..end].contains(&record) {
+            return;
         }
+        self.keys.insert(end, key);
+        self.rids.insert(end, record);
     }
 
     /// For internal page: insert a child into index page
     pub fn insert_child(&mut self, key: i64, child: PageId) {
         debug_assert_eq!(self.page_type, IndexType::Internal);
-        let index = self.keys.binary_search(&key).unwrap_or_else(|pos| pos);
+        let index = self.keys.partition_point(|k| *k <= key);
+        self.keys.insert(index, key);
+        self.children.insert(index + 1, child);
+    }
+
+    /// For internal page: insert child right after left_child, separated from it by key.
+    /// Unlike insert_child, this places the child correctly among separators equal to key
+    pub fn insert_child_after(&mut self, left_child: PageId, key: i64, child: PageId) {
+        debug_assert_eq!(self.page_type, IndexType::Internal);
+        let index = self.children.iter()
+            .position(|&id| id == left_child)
+            .expect("Error: child not found in parent");
         self.keys.insert(index, key);
         self.children.insert(index + 1, child);
     }
 
+    /// For leaf page: remove record from the records under key.
+    /// Return false if key does not hold record
+    pub fn remove_record(&mut self, key: i64, record: RecordId) -> bool {
+        debug_assert_eq!(self.page_type, IndexType::Leaf);
+        let start = self.keys.partition_point(|k| *k < key);
+        let end = self.keys.partition_point(|k| *k <= key);
+        match self.rids[start..end].iter().position(|rid| *rid == record) {
+            Some(offset) => {
+                self.keys.remove(start + offset);
+                self.rids.remove(start + offset);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Remove a key with associated child/record
     /// Return error if the element is not found
     pub fn remove_key(&mut self, key: i64) -> bool {
@@ -218,8 +252,9 @@ impl IndexPage {
         }
     }
 
-    /// Redistribute keys between self and sibling and return the new separator key for parent
-    /// Returns: (new_separator_key, borrowed_key) where new_separator_key should update parent
+    /// Redistribute keys between self and sibling and return the new separator key for parent.
+    /// For internal pages the old parent separator rotates down into self and the borrowed
+    /// sibling key rotates up to replace it, along with the child moved between the pages
     pub fn redistribute(&mut self, sibling: &mut Self, old_parent_sep: i64, borrow_from_left: bool, min_keys: usize) -> Option<i64> {
         debug_assert_eq!(self.page_type, sibling.page_type);
 
@@ -301,14 +336,17 @@ impl Page for IndexPage {
     }
 
     /// Internal Page Layout:
-    /// [id: 4][page_type: 1][keys.len(): 4][key: children]
+    /// [tag: 1][id: 4][page_type: 1][keys.len(): 2][key: children]
     /// Leaf Page Layout:
-    /// [id: 4][page_type: 1][keys.len(): 4][has_next: 1][next: 4][keys: rids[PageId, SlotId]]
+    /// [tag: 1][id: 4][page_type: 1][keys.len(): 2][has_next: 1][next: 4][keys: rids[PageId, SlotId]]
+    /// A key holding several records is repeated once per record
     fn serialize(&self) -> [u8; PAGE_SIZE] {
         let mut buf = [0u8; PAGE_SIZE];
         let mut cursor = 0;
 
         // serialize page header
+        buf[cursor] = PageType::Index as u8;
+        cursor += PAGE_TYPE_SIZE;
         buf[cursor..cursor + PAGE_ID_SIZE].copy_from_slice(&self.id.to_le_bytes());
         cursor += PAGE_ID_SIZE;
         buf[cursor] = self.page_type as u8;
@@ -360,7 +398,11 @@ impl Page for IndexPage {
     fn deserialize(buf: &[u8; PAGE_SIZE]) -> Option<Self> {
         let mut cursor = 0;
 
-        // deserialize page header
+        // deserialize page header, rejecting pages of another type
+        if buf[cursor] != PageType::Index as u8 {
+            return None;
+        }
+        cursor += PAGE_TYPE_SIZE;
         let id = PageId::from_le_bytes(buf[cursor..cursor + PAGE_ID_SIZE].try_into().ok()?);
         cursor += PAGE_ID_SIZE;
         let page_type = IndexType::from_byte(buf[cursor])?;
@@ -428,7 +470,10 @@ impl Page for IndexPage {
     }
 
     fn get_free_space(&self) -> usize {
-        // PAGE_SIZE - used
+        PAGE_SIZE - self.used_bytes()
+    }
+
+    fn used_bytes(&self) -> usize {
         let mut used = get_page_header_size();
         match self.page_type {
             IndexType::Leaf => {
@@ -440,7 +485,7 @@ impl Page for IndexPage {
                 used += PAGE_ID_SIZE;   // last child
             }
         }
-        PAGE_SIZE - used
+        used
     }
 
     #[inline]
@@ -476,10 +521,42 @@ mod tests {
         let rid1 = create_record(2, 3);
         let rid2 = create_record(4, 5);
         page.insert_record(42, rid1);
+        page.insert_record(7, create_record(1, 1));
         page.insert_record(42, rid2);
+        // the same record is only held once
+        page.insert_record(42, rid1);
 
-        assert_eq!(page.keys, vec![42]);
-        assert_eq!(page.search_rid(&42), Some(&rid2));
+        assert_eq!(page.keys, vec![7, 42, 42]);
+        assert_eq!(page.search_range(&42, &42), vec![&rid1, &rid2]);
+        assert_eq!(page.search_rid(&42), Some(&rid1));
+
+        // removing one record of a key keeps the others
+        assert!(page.remove_record(42, rid1));
+        assert!(!page.remove_record(42, rid1));
+        assert!(!page.remove_record(7, rid2));
+        assert_eq!(page.keys, vec![7, 42]);
+        assert_eq!(page.search_range(&42, &42), vec![&rid2]);
+    }
+
+    #[test]
+    fn test_search_child_with_equal_separators() {
+        let mut page = IndexPage::new(1, IndexType::Internal);
+        page.children.push(10);
+        page.insert_child(5, 20);
+        page.insert_child_after(20, 5, 30);
+        page.insert_child(9, 40);
+
+        assert_eq!(page.children, vec![10, 20, 30, 40]);
+        // keys equal to a separator may be in any child between both
+        assert_eq!(page.search_first_child(&5), Some(10));
+        assert_eq!(page.search_child(&5), Some(30));
+        assert_eq!(page.search_first_child(&7), Some(30));
+        assert_eq!(page.search_child(&7), Some(30));
+
+        // a child inserted after a child between equal separators stays next to it
+        page.insert_child_after(10, 5, 50);
+        assert_eq!(page.keys, vec![5, 5, 5, 9]);
+        assert_eq!(page.children, vec![10, 50, 20, 30, 40]);
     }
 
     #[test]
@@ -567,6 +644,30 @@ mod tests {
         assert!(right.keys.first().unwrap() <= &10);
     }
 
+    #[test]
+    fn test_redistribute_internal() {
+        let mut left = IndexPage::new(1, IndexType::Internal);
+        let mut right = IndexPage::new(2, IndexType::Internal);
+        left.children.push(10);
+        left.insert_child(5, 11);
+        left.insert_child(8, 12);
+        right.children.push(20);
+        right.insert_child(30, 21);
+
+        // borrow from left: separator 20 moves down, left's last key 8 moves up
+        assert_eq!(right.redistribute(&mut left, 20, true, 1), Some(8));
+        assert_eq!((left.keys.clone(), left.children.clone()), (vec![5], vec![10, 11]));
+        assert_eq!((right.keys.clone(), right.children.clone()), (vec![20, 30], vec![12, 20, 21]));
+
+        // borrow from right: separator 8 moves down, right's first key 20 moves up
+        assert_eq!(left.redistribute(&mut right, 8, false, 1), Some(20));
+        assert_eq!((left.keys.clone(), left.children.clone()), (vec![5, 8], vec![10, 11, 12]));
+        assert_eq!((right.keys.clone(), right.children.clone()), (vec![30], vec![20, 21]));
+
+        // a sibling at the minimum cannot lend
+        assert_eq!(left.redistribute(&mut right, 20, false, 1), None);
+    }
+
     #[test]
     fn test_serialize_deserialize_leaf() {
         let mut page = IndexPage::new(1, IndexType::Leaf);
@@ -583,6 +684,18 @@ mod tests {
         assert_eq!(page.get_next(), deserialized.get_next());
     }
 
+    #[test]
+    fn test_serialize_deserialize_duplicate_keys() {
+        let mut page = IndexPage::new(1, IndexType::Leaf);
+        page.insert_record(42, create_record(7, 11));
+        page.insert_record(42, create_record(8, 12));
+        page.insert_record(43, create_record(9, 13));
+
+        let deserialized = IndexPage::deserialize(&page.serialize()).unwrap();
+        assert_eq!(deserialized.keys, vec![42, 42, 43]);
+        assert_eq!(deserialized.rids, page.rids);
+    }
+
     #[test]
     fn test_serialize_deserialize_internal() {
         let mut page = IndexPage::new(1, IndexType::Internal);
@@ -608,4 +721,24 @@ mod tests {
 
         assert!(free1 < free0);
     }
+
+    #[test]
+    fn test_used_bytes() {
+        let mut leaf = IndexPage::new(1, IndexType::Leaf);
+        let used0 = leaf.used_bytes();
+        assert_eq!(used0 + leaf.get_free_space(), PAGE_SIZE);
+
+        leaf.insert_record(1, create_record(1, 1));
+        leaf.insert_record(2, create_record(1, 2));
+        assert!(leaf.used_bytes() > used0);
+        assert_eq!(leaf.used_bytes() + leaf.get_free_space(), PAGE_SIZE);
+        // bytes past used_bytes are zero padding
+        assert!(leaf.serialize()[leaf.used_bytes()..].iter().all(|&b| b == 0));
+
+        let mut internal = IndexPage::new(2, IndexType::Internal);
+        internal.children.push(10);
+        internal.insert_child(5, 20);
+        assert_eq!(internal.used_bytes() + internal.get_free_space(), PAGE_SIZE);
+        assert!(internal.serialize()[internal.used_bytes()..].iter().all(|&b| b == 0));
+    }
 }
\ No newline at end of file
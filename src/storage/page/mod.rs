@@ -1,4 +1,5 @@
 pub mod page;
 pub mod data_page;
 pub mod index_page;
-pub mod header_page;
\ No newline at end of file
+pub mod header_page;
+pub mod super_page;
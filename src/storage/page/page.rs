@@ -18,15 +18,33 @@ pub trait Page: Send + Sync {
     /// Unreclaimed deleted space is not counted towards free space
     fn get_free_space(&self) -> usize;
 
+    /// Return the number of bytes of the serialized page in use, which need not be a prefix
+    /// of the page: a DataPage keeps its free gap between the slot array and the records
+    fn used_bytes(&self) -> usize;
+
     /// Return whether page is empty
     fn is_empty(&self) -> bool;
 }
 
+/// Tag stored in the first byte of pages in the data file, so that a page deserialized
+/// as the wrong type is rejected instead of read as garbage
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum PageType {
+    Data = 0xDA,
+    Index = 0x1D,
+}
+pub const PAGE_TYPE_SIZE: usize = size_of::<u8>();
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum PageError {
     InvalidPage,         // the page id is invalid
     InvalidSlot,         // the slot id is invalid
-    RecordSizeChanged,   // the record size is updated to a different size
+    RecordSizeChanged,   // the record grew beyond the free space of its page
     PageLatched,         // the page is used by other connections
     PageAlreadyUnpinned,        // an unpinned page is attempted to be unpin again
+    PageOutOfRange,      // the page id is not tracked by the header page
+    PageAlreadyFreed,    // a free page is attempted to be freed again
+    DiskWrite(std::io::ErrorKind),  // the page could not be written to disk, and is kept dirty
+    EvictionStalled,     // every page of a full buffer pool stayed pinned, usually a leaked pin
 }
\ No newline at end of file
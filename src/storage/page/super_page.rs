@@ -0,0 +1,48 @@
+use crate::types::{PAGE_SIZE, PageId, SUPER_PAGE_ID};
+use crate::storage::page::page::Page;
+
+const MAGIC: &[u8; 4] = b"RCDB";
+
+/// Database superpage, stored as page 0 of the header file (header pages start from 1)
+/// Layout: [magic: 4][format_version: 1]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SuperPage {
+    pub format_version: u8,
+}
+
+impl Page for SuperPage {
+    fn new(_id: PageId) -> Self {
+        SuperPage { format_version: 0 }
+    }
+
+    fn serialize(&self) -> [u8; PAGE_SIZE] {
+        let mut buf = [0u8; PAGE_SIZE];
+        buf[0..4].copy_from_slice(MAGIC);
+        buf[4] = self.format_version;
+        buf
+    }
+
+    /// Return None if the page does not hold a superpage (legacy database without one)
+    fn deserialize(buf: &[u8; PAGE_SIZE]) -> Option<Self> {
+        if &buf[0..4] != MAGIC {
+            return None;
+        }
+        Some(SuperPage { format_version: buf[4] })
+    }
+
+    fn get_id(&self) -> PageId {
+        SUPER_PAGE_ID
+    }
+
+    fn get_free_space(&self) -> usize {
+        0
+    }
+
+    fn used_bytes(&self) -> usize {
+        MAGIC.len() + size_of::<u8>()
+    }
+
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
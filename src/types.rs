@@ -1,9 +1,12 @@
+use serde::{Deserialize, Serialize};
+use crate::storage::page::data_page::PAYLOAD_SIZE;
 use crate::storage::replacement_strategy::ReplacementStrategyType;
 
 // global constants
 // data page configs
 pub const PAGE_SIZE: usize = 4096;
 pub const MAX_SLOTS: usize = 255;
+pub const PAGE_CHECKSUM_SIZE: usize = 4;  // CRC-32 stored after each page on disk
 
 // global types
 pub type PageId = u32;
@@ -14,7 +17,7 @@ pub type ColumnId = usize;
 pub const FLUSH: bool = true;
 pub const NO_FLUSH: bool = false;
 pub type DbResult<T> = Result<T, DbError>;
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum DbError {
     // scanner errors
     ScannerError(String),
@@ -29,10 +32,21 @@ pub enum DbError {
     DuplicateTable(String),
     ColumnNotFound(String),
     DuplicateColumn(String),
+    DuplicateIndex(String),
+    IndexNotFound(String),
+    SchemaChanged(String),
     ColumnMismatch(String),
     TypeMismatch(String),
     ExpressionNotFound(String),
     ArithmeticError(String),
+    InvalidTableOption(String),
+    ParameterError(String),
+    UnsafeUpdate(String),
+    InvalidSetting(String),
+    QuotaExceeded(String),
+    InvalidCursor(String),
+    PageNotFound(String),
+    ConstraintViolation(String),
     InternalError(String),
 }
 
@@ -40,7 +54,27 @@ pub enum DbError {
 pub const DATA_FILE: &str = "data.rcdb";
 pub const HEADER_FILE: &str = "header.rcdb";
 pub const CATALOG_FILE: &str = "catalog.json";
+pub const CONFIG_FILE: &str = "config.json";
+
+// on-disk format version of database files, stored in superpage
+pub const FORMAT_VERSION: u8 = 3;
+pub const SUPER_PAGE_ID: PageId = 0;
 
 // default storage engine configuration
 pub const DEFAULT_BUFFERPOOL_SIZE: usize = 32;
 pub const DEFAULT_BUFFERPOOL_REPLACEMENT: ReplacementStrategyType = ReplacementStrategyType::LRU;
+// retries of a disk write failing with a transient error, and the wait before the first retry
+pub const DEFAULT_IO_RETRIES: u32 = 3;
+pub const DEFAULT_IO_RETRY_BACKOFF_MS: u64 = 10;
+// rounds an eviction waits for a page to be unpinned before giving up, and the wait of each round
+pub const EVICT_MAX_STALLED_ROUNDS: u32 = 100;
+pub const EVICT_WAIT_MS: u64 = 10;
+
+// default table options
+pub const DEFAULT_FILLFACTOR: u8 = 100;
+pub const MIN_FILLFACTOR: u8 = 10;
+// seconds a soft-deleted row is kept before PURGE reclaims it
+pub const DEFAULT_RETENTION: u32 = 24 * 60 * 60;
+
+// column type limits
+pub const MAX_CHAR_LENGTH: u32 = PAYLOAD_SIZE as u32;   // a CHAR value must fit in one data page
@@ -1,17 +1,22 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hasher};
 use raincloud_db::compiler::ast::{Literal, ColumnDef, DataType, Record};
+use raincloud_db::interpreter::catalog_table::{TableOptions, TableSchema};
+use raincloud_db::storage::page::data_page::DataPage;
+use raincloud_db::storage::page::page::Page;
 
 #[test]
 fn row_def_serialization_int() {
     let schema = vec![
-        ColumnDef{ name: "a".to_string(), data_type: DataType::Int },
-        ColumnDef{ name: "b".to_string(), data_type: DataType::Int },
+        ColumnDef{ name: "a".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
+        ColumnDef{ name: "b".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
     ];
 
     let row = Record {
         record: vec![Literal::Int(1), Literal::Int(2)],
     };
 
-    let bytes = row.serialize().unwrap();
+    let bytes = row.serialize(&schema).unwrap();
     let decoded = Record::deserialize(&bytes, &schema).unwrap();
 
     assert_eq!(row, decoded);
@@ -20,8 +25,8 @@ fn row_def_serialization_int() {
 #[test]
 fn row_def_serialization_char() {
     let schema = vec![
-        ColumnDef{ name: "a".to_string(), data_type: DataType::Char(4) },
-        ColumnDef{ name: "b".to_string(), data_type: DataType::Char(3) },
+        ColumnDef{ name: "a".to_string(), data_type: DataType::Char(4), not_null: false, primary_key: false, comment: None },
+        ColumnDef{ name: "b".to_string(), data_type: DataType::Char(3), not_null: false, primary_key: false, comment: None },
     ];
 
     let row = Record {
@@ -31,7 +36,7 @@ fn row_def_serialization_char() {
         ],
     };
 
-    let bytes = row.serialize().unwrap();
+    let bytes = row.serialize(&schema).unwrap();
     let decoded = Record::deserialize(&bytes, &schema).unwrap();
 
     assert_eq!(
@@ -46,9 +51,9 @@ fn row_def_serialization_char() {
 #[test]
 fn row_def_serialization_mixed() {
     let schema = vec![
-        ColumnDef{ name: "a".to_string(), data_type: DataType::Int },
-        ColumnDef{ name: "b".to_string(), data_type: DataType::Char(5) },
-        ColumnDef{ name: "c".to_string(), data_type: DataType::Int },
+        ColumnDef{ name: "a".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
+        ColumnDef{ name: "b".to_string(), data_type: DataType::Char(5), not_null: false, primary_key: false, comment: None },
+        ColumnDef{ name: "c".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
     ];
 
     let row = Record {
@@ -59,7 +64,7 @@ fn row_def_serialization_mixed() {
         ],
     };
 
-    let bytes = row.serialize().unwrap();
+    let bytes = row.serialize(&schema).unwrap();
     let decoded = Record::deserialize(&bytes, &schema).unwrap();
 
     assert_eq!(decoded.record[0], Literal::Int(10));
@@ -67,3 +72,162 @@ fn row_def_serialization_mixed() {
     assert_eq!(decoded.record[2], Literal::Int(-3));
 }
 
+
+#[test]
+fn row_def_serialization_int_boundaries() {
+    let schema = vec![ColumnDef{ name: "a".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None }];
+
+    for v in [i32::MIN, -1, 0, 1, i32::MAX] {
+        let row = Record { record: vec![Literal::Int(v)] };
+        let bytes = row.serialize(&schema).unwrap();
+        assert_eq!(bytes, v.to_le_bytes());
+        assert_eq!(Record::deserialize(&bytes, &schema).unwrap(), row);
+    }
+}
+
+#[test]
+fn row_def_serialization_uses_column_width() {
+    let schema = vec![
+        ColumnDef{ name: "a".to_string(), data_type: DataType::Char(4), not_null: false, primary_key: false, comment: None },
+        ColumnDef{ name: "b".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
+    ];
+
+    // CHAR values are padded to the column width, so the INT after it is read from the right offset
+    let row = Record { record: vec![Literal::String("ab".into()), Literal::Int(i32::MIN)] };
+    let bytes = row.serialize(&schema).unwrap();
+    assert_eq!(bytes.len(), DataType::Char(4).size() + DataType::Int.size());
+    assert_eq!(Record::deserialize(&bytes, &schema).unwrap(), row);
+
+    // values that do not fit their column are rejected
+    let too_long = Record { record: vec![Literal::String("abcde".into()), Literal::Int(1)] };
+    assert!(too_long.serialize(&schema).is_err());
+    let wrong_type = Record { record: vec![Literal::String("ab".into()), Literal::Bool(true)] };
+    assert!(wrong_type.serialize(&schema).is_err());
+    assert!(Record { record: vec![Literal::Int(1)] }.serialize(&schema).is_err());
+
+    // a truncated buffer is rejected
+    assert!(Record::deserialize(&bytes[..bytes.len() - 1], &schema).is_err());
+}
+
+#[test]
+fn row_def_serialization_counts_utf8_bytes() {
+    let schema = vec![
+        ColumnDef{ name: "a".to_string(), data_type: DataType::Char(4), not_null: false, primary_key: false, comment: None },
+    ];
+
+    // CHAR(4) holds four bytes: two 2-byte characters fit, three do not
+    let row = Record { record: vec![Literal::String("éé".into())] };
+    let bytes = row.serialize(&schema).unwrap();
+    assert_eq!(Record::deserialize(&bytes, &schema).unwrap(), row);
+    assert!(Record { record: vec![Literal::String("ééé".into())] }.serialize(&schema).is_err());
+    assert!(Record { record: vec![Literal::String("日本".into())] }.serialize(&schema).is_err());
+
+    // stored bytes that are not valid UTF-8 are rejected instead of replaced
+    assert!(Record::deserialize(&[0xC3, 0x28, 0, 0], &schema).is_err());
+}
+
+fn nullable_schema(null_bitmap: bool) -> TableSchema {
+    let mut schema = TableSchema {
+        name: "t".to_string(),
+        columns: vec![
+            ColumnDef{ name: "a".to_string(), data_type: DataType::Char(3), not_null: false, primary_key: false, comment: None },
+            ColumnDef{ name: "b".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
+            ColumnDef{ name: "c".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
+        ],
+        first_page_id: 0,
+        options: TableOptions { soft_delete: true, ..TableOptions::default() },
+        foreign_keys: vec![],
+        physical_order: vec![],
+        null_bitmap,
+        indexes: vec![],
+        generation: 0,
+        column_index: HashMap::new(),
+    };
+    schema.physical_order = TableSchema::default_physical_order(&schema.columns);
+    schema.rebuild_column_index();
+    schema
+}
+
+#[test]
+fn row_def_null_round_trips_through_data_page() {
+    let schema = nullable_schema(true);
+    let rows = [
+        Record { record: vec![Literal::Null, Literal::Int(0), Literal::Null] },
+        Record { record: vec![Literal::String("ab".into()), Literal::Null, Literal::Int(7)] },
+        Record { record: vec![Literal::String("".into()), Literal::Int(0), Literal::Int(-1)] },
+    ];
+
+    // NULL takes the width of its column, so every record has the same size
+    let mut page = DataPage::new(1);
+    let mut slots = Vec::new();
+    for row in &rows {
+        let bytes = schema.serialize_record(row).unwrap();
+        assert_eq!(bytes.len(), schema.record_header_size() + 3 + 4 + 4);
+        slots.push(page.insert_record(&bytes).unwrap());
+    }
+
+    // NULL stays distinct from the zero value stored in its place
+    let page = DataPage::deserialize(&page.serialize()).unwrap();
+    for (row, slot) in rows.iter().zip(slots) {
+        let bytes = page.get_record(slot).unwrap();
+        assert_eq!(schema.deleted_at(bytes), None);
+        assert_eq!(&schema.deserialize_record(bytes).unwrap(), row);
+    }
+
+    // tables created before NULL support have no bitmap and cannot store NULL
+    let legacy = nullable_schema(false);
+    assert!(legacy.serialize_record(&rows[0]).is_err());
+    let bytes = legacy.serialize_record(&rows[2]).unwrap();
+    assert_eq!(legacy.deserialize_record(&bytes).unwrap(), rows[2]);
+}
+
+#[test]
+fn row_def_logical_eq_ignores_char_padding() {
+    let schema = vec![
+        ColumnDef{ name: "a".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None },
+        ColumnDef{ name: "b".to_string(), data_type: DataType::Char(5), not_null: false, primary_key: false, comment: None },
+    ];
+    let row = |id, name: &str| Record { record: vec![Literal::Int(id), Literal::String(name.into())] };
+
+    let padded = row(1, "hi\0\0\0");
+    let spaced = row(1, "hi   ");
+    let unpadded = row(1, "hi");
+
+    // raw values differ, logical values are equal
+    assert_ne!(padded, unpadded);
+    assert!(padded.logical_eq(&unpadded, &schema));
+    assert!(spaced.logical_eq(&unpadded, &schema));
+    assert!(!row(1, "hi!").logical_eq(&unpadded, &schema));
+    assert!(!row(2, "hi").logical_eq(&unpadded, &schema));
+
+    // leading spaces are significant
+    assert!(!row(1, "  hi").logical_eq(&unpadded, &schema));
+}
+
+#[test]
+fn row_def_logical_hash_groups_padded_rows() {
+    let schema = vec![
+        ColumnDef{ name: "a".to_string(), data_type: DataType::Char(4), not_null: false, primary_key: false, comment: None },
+    ];
+    let hash = |row: &Record| {
+        let mut hasher = DefaultHasher::new();
+        row.logical_hash(&schema, &mut hasher);
+        hasher.finish()
+    };
+
+    let rows: Vec<Record> = ["ab", "ab\0\0", "cd", "ab  ", "cd\0\0"].iter()
+        .map(|s| Record { record: vec![Literal::String(s.to_string())] })
+        .collect();
+
+    // group rows by logical hash, verifying members with logical_eq
+    let mut groups: HashMap<u64, Vec<&Record>> = HashMap::new();
+    for row in &rows {
+        groups.entry(hash(row)).or_default().push(row);
+    }
+    assert_eq!(groups.len(), 2);
+    for group in groups.values() {
+        assert!(group.iter().all(|row| row.logical_eq(group[0], &schema)));
+    }
+    assert_eq!(groups[&hash(&rows[0])].len(), 3);
+    assert_eq!(groups[&hash(&rows[2])].len(), 2);
+}
@@ -1,48 +1,63 @@
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use paste::paste;
 use tempfile::NamedTempFile;
 use raincloud_db::storage::bplus_tree::BPlusTree;
 use raincloud_db::storage::bufferpool::BufferPool;
 use raincloud_db::storage::disk_manager::FileDiskManager;
 use raincloud_db::storage::free_list::FreeList;
 use raincloud_db::storage::page::header_page::HeaderPage;
-use raincloud_db::storage::page::index_page::{IndexPage, RecordId};
+use raincloud_db::storage::page::index_page::{IndexPage, IndexType, RecordId};
 use raincloud_db::storage::page::page::Page;
 use raincloud_db::storage::replacement_strategy::ReplacementStrategyType;
-use raincloud_db::types::{PageId, SlotId};
+use raincloud_db::storage::{StorageConfig, StorageEngine};
+use raincloud_db::types::{PageId, SlotId, FLUSH};
+use raincloud_db::{with_create_pages, with_read_pages};
 
-// Helper function to create a test B+ tree with small capacities for easier testing
-fn create_test_tree() -> BPlusTree {
+// Helper function to create a buffer pool over a fresh index file
+fn create_test_pool() -> Arc<BufferPool<IndexPage>> {
     let temp_file = NamedTempFile::new().unwrap();
     let path = temp_file.path();
     let disk_manager =
         Arc::new(FileDiskManager::<IndexPage>::open(path).unwrap());
-    let header_disk_manager = Arc::new(FileDiskManager::<HeaderPage>::open(temp_file.path()).unwrap());
-    let free_list = Arc::new(Mutex::new(FreeList::new(header_disk_manager, 0)));
-    let buffer_pool = Arc::new(BufferPool::new(
+    // header pages live in their own file, as in the storage engine
+    let header_file = NamedTempFile::new().unwrap();
+    let header_disk_manager = Arc::new(FileDiskManager::<HeaderPage>::open(header_file.path()).unwrap());
+    let free_list = Arc::new(FreeList::new(header_disk_manager, 0));
+    Arc::new(BufferPool::new(
         100,
         ReplacementStrategyType::LRU,
         disk_manager,
         free_list
-    ));
+    ))
+}
+
+// Helper function to create a test B+ tree with small capacities for easier testing
+fn create_test_tree() -> BPlusTree {
+    let buffer_pool = create_test_pool();
     let root_page = buffer_pool.create_page().unwrap();
     let root_id = root_page.read().get_id();
     BPlusTree::new(root_id, buffer_pool, 3, 3)
 }
 
+// Record id inserted for key by insert_keys
+fn rid(key: i64) -> RecordId {
+    RecordId { page_id: key as PageId, slot_id: key as SlotId }
+}
+
 // Helper function to insert multiple keys for setup
 fn insert_keys(tree: &mut BPlusTree, keys: &[i64]) {
     for &key in keys {
-        tree.insert(key, RecordId { page_id: key as PageId, slot_id: key as SlotId });
+        tree.insert(key, rid(key));
     }
 }
 
 // Helper function to verify search results
 fn verify_searches(tree: &mut BPlusTree, expected_keys: &[i64], expected_missing: &[i64]) {
     for &key in expected_keys {
-        assert!(tree.search(&key).is_some(), "Key {} should exist", key);
+        assert!(!tree.search(&key).is_empty(), "Key {} should exist", key);
     }
     for &key in expected_missing {
-        assert!(tree.search(&key).is_none(), "Key {} should not exist", key);
+        assert!(tree.search(&key).is_empty(), "Key {} should not exist", key);
     }
 }
 
@@ -51,14 +66,14 @@ fn test_empty_tree_operations() {
     let mut tree = create_test_tree();
 
     // Search in empty tree
-    assert!(tree.search(&1).is_none());
+    assert!(tree.search(&1).is_empty());
 
     // Delete from empty tree
-    assert!(!tree.delete(1));
+    assert!(!tree.delete(1, rid(1)));
 
     // Insert first key
     tree.insert(10, RecordId { page_id: 10, slot_id: 10 });
-    assert!(tree.search(&10).is_some());
+    assert!(!tree.search(&10).is_empty());
 }
 
 #[test]
@@ -73,9 +88,9 @@ fn test_basic_insert_search_delete() {
     verify_searches(&mut tree, &keys, &[]);
 
     // Delete some keys
-    assert!(tree.delete(15));
-    assert!(tree.delete(25));
-    assert!(!tree.delete(100)); // non-existent key
+    assert!(tree.delete(15, rid(15)));
+    assert!(tree.delete(25, rid(25)));
+    assert!(!tree.delete(100, rid(100))); // non-existent key
 
     // Verify remaining keys
     verify_searches(&mut tree, &[10, 20, 30, 5], &[15, 25, 100]);
@@ -158,8 +173,8 @@ fn test_left_redistribution() {
     insert_keys(&mut tree, &[10, 20, 30, 40, 50, 60]);
 
     // Delete keys to trigger underflow and left redistribution
-    assert!(tree.delete(50));
-    assert!(tree.delete(60));
+    assert!(tree.delete(50, rid(50)));
+    assert!(tree.delete(60, rid(60)));
 
     // Verify remaining keys are still accessible
     verify_searches(&mut tree, &[10, 20, 30, 40], &[50, 60]);
@@ -177,8 +192,8 @@ fn test_right_redistribution() {
     insert_keys(&mut tree, &[5, 10, 15, 20, 25, 30]);
 
     // Delete keys to trigger right redistribution
-    assert!(tree.delete(5));
-    assert!(tree.delete(10));
+    assert!(tree.delete(5, rid(5)));
+    assert!(tree.delete(10, rid(10)));
 
     // Verify remaining keys
     verify_searches(&mut tree, &[15, 20, 25, 30], &[5, 10]);
@@ -197,7 +212,7 @@ fn test_left_merge() {
 
     // Delete keys to trigger merge with left sibling
     for key in [10, 9, 8, 7, 6] {
-        assert!(tree.delete(key));
+        assert!(tree.delete(key, rid(key)));
     }
 
     // Verify remaining keys
@@ -217,7 +232,7 @@ fn test_right_merge() {
 
     // Delete keys to trigger merge with right sibling
     for key in [10, 20, 30, 40, 50] {
-        assert!(tree.delete(key));
+        assert!(tree.delete(key, rid(key)));
     }
 
     // Verify remaining keys
@@ -238,7 +253,7 @@ fn test_root_collapse() {
 
     // Delete most keys to trigger root collapse
     for key in 6..=15 {
-        assert!(tree.delete(key));
+        assert!(tree.delete(key, rid(key)));
     }
 
     // Verify remaining keys are still accessible
@@ -246,17 +261,17 @@ fn test_root_collapse() {
 
     // Delete more to potentially trigger further collapses
     for key in [3, 4, 5] {
-        assert!(tree.delete(key));
+        assert!(tree.delete(key, rid(key)));
     }
 
     verify_searches(&mut tree, &[1, 2], &(3..=15).collect::<Vec<_>>());
 
     // Test that single remaining keys work
-    assert!(tree.delete(2));
+    assert!(tree.delete(2, rid(2)));
     verify_searches(&mut tree, &[1], &(2..=15).collect::<Vec<_>>());
 
     // Test final deletion
-    assert!(tree.delete(1));
+    assert!(tree.delete(1, rid(1)));
     verify_searches(&mut tree, &[], &(1..=15).collect::<Vec<_>>());
 }
 
@@ -272,7 +287,7 @@ fn test_complex_mixed_operations() {
 
     // Delete some keys
     for key in [5, 15, 35, 80] {
-        assert!(tree.delete(key));
+        assert!(tree.delete(key, rid(key)));
     }
 
     // Insert new keys
@@ -287,7 +302,7 @@ fn test_complex_mixed_operations() {
 
     // More complex deletions to trigger merges and redistributions
     for key in [10, 20, 12, 27] {
-        assert!(tree.delete(key));
+        assert!(tree.delete(key, rid(key)));
     }
 
     verify_searches(
@@ -300,21 +315,74 @@ fn test_complex_mixed_operations() {
 #[test]
 fn test_duplicate_key_handling() {
     let mut tree = create_test_tree();
+    let (rid1, rid2, rid3) = (RecordId { page_id: 10, slot_id: 1 }, RecordId { page_id: 10, slot_id: 2 }, RecordId { page_id: 11, slot_id: 1 });
 
     // Insert key
-    tree.insert(10, RecordId { page_id: 10, slot_id: 1 });
-    assert!(tree.search(&10).is_some());
+    tree.insert(10, rid1);
+    assert_eq!(tree.search(&10), vec![rid1]);
+
+    // Insert same key again, both records are kept in insertion order
+    tree.insert(10, rid2);
+    tree.insert(10, rid3);
+    assert_eq!(tree.search(&10), vec![rid1, rid2, rid3]);
+
+    // inserting a record the key already holds changes nothing
+    tree.insert(10, rid2);
+    assert_eq!(tree.search(&10), vec![rid1, rid2, rid3]);
 
-    // Insert same key again (should update or handle appropriately)
-    tree.insert(10, RecordId { page_id: 10, slot_id: 2 });
-    assert!(tree.search(&10).is_some());
+    // Delete removes one record, keeping the others
+    assert!(tree.delete(10, rid2));
+    assert_eq!(tree.search(&10), vec![rid1, rid3]);
+    assert!(!tree.delete(10, rid2));
+    assert!(!tree.delete(11, rid1));
 
-    // Delete should work
-    assert!(tree.delete(10));
-    assert!(tree.search(&10).is_none());
+    assert!(tree.delete(10, rid1));
+    assert!(tree.delete(10, rid3));
+    assert!(tree.search(&10).is_empty());
 
     // Delete again should fail
-    assert!(!tree.delete(10));
+    assert!(!tree.delete(10, rid1));
+}
+
+#[test]
+fn test_duplicate_keys_across_leaves() {
+    let mut tree = create_test_tree();
+    // records under one key fill several leaves, between unique keys on both sides
+    let dup = |i: usize| RecordId { page_id: 1000, slot_id: i as SlotId };
+    insert_keys(&mut tree, &[1, 2, 8, 9]);
+    for i in 0..20 {
+        tree.insert(5, dup(i));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    insert_keys(&mut tree, &[3, 4, 6, 7]);
+    assert_eq!(tree.validate(), Ok(()));
+    assert!(tree.page_count() > 5);
+
+    assert_eq!(tree.search(&5), (0..20).map(dup).collect::<Vec<_>>());
+    verify_searches(&mut tree, &[1, 2, 3, 4, 6, 7, 8, 9], &[0, 10]);
+    let scanned = tree.range_scan(4, 6);
+    assert_eq!(scanned.len(), 22);
+    assert_eq!((scanned[0], scanned[21]), (rid(4), rid(6)));
+
+    // delete every other record of the key, from whichever leaf holds it
+    for i in (0..20).step_by(2) {
+        assert!(tree.delete(5, dup(i)), "record {} should be deleted", i);
+        assert_eq!(tree.validate(), Ok(()), "invalid tree after deleting record {}", i);
+    }
+    assert_eq!(tree.search(&5), (1..20).step_by(2).map(dup).collect::<Vec<_>>());
+    verify_searches(&mut tree, &[1, 2, 3, 4, 6, 7, 8, 9], &[]);
+
+    // compaction keeps every record of the key
+    tree.compact();
+    assert_eq!(tree.validate(), Ok(()));
+    assert_eq!(tree.search(&5), (1..20).step_by(2).map(dup).collect::<Vec<_>>());
+
+    for i in (1..20).step_by(2) {
+        assert!(tree.delete(5, dup(i)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    assert!(tree.search(&5).is_empty());
+    verify_searches(&mut tree, &[1, 2, 3, 4, 6, 7, 8, 9], &[5]);
 }
 
 #[test]
@@ -330,7 +398,7 @@ fn test_large_scale_operations() {
 
     // Delete every other key
     for key in keys.iter().step_by(2) {
-        assert!(tree.delete(*key));
+        assert!(tree.delete(*key, rid(*key)));
     }
 
     // Verify remaining keys
@@ -340,7 +408,7 @@ fn test_large_scale_operations() {
 
     // Delete remaining keys in reverse order
     for key in remaining.iter().rev() {
-        assert!(tree.delete(*key));
+        assert!(tree.delete(*key, rid(*key)));
     }
 
     // Verify empty tree
@@ -357,8 +425,8 @@ fn test_boundary_conditions() {
     verify_searches(&mut tree, &extreme_keys, &[]);
 
     // Test deletion of extreme values
-    assert!(tree.delete(i64::MIN));
-    assert!(tree.delete(i64::MAX));
+    assert!(tree.delete(i64::MIN, rid(i64::MIN)));
+    assert!(tree.delete(i64::MAX, rid(i64::MAX)));
     verify_searches(&mut tree, &[0, -1, 1], &[i64::MIN, i64::MAX]);
 
     // Test that tree handles negative numbers correctly
@@ -366,22 +434,270 @@ fn test_boundary_conditions() {
     verify_searches(&mut tree, &[0, -1, 1, -100, -50, -25, -10, -5], &[]);
 }
 
-// Test Hang: to be investigated later
-// #[test]
-// fn test_sequential_insert_delete_patterns() {
-//     let mut tree = create_test_tree();
-//
-//     // Test ascending insert, descending delete
-//     insert_keys(&mut tree, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
-//     for key in (1..=10).rev() {
-//         assert!(tree.delete(key));
-//     }
-//     verify_searches(&mut tree, &[], &(1..=10).collect::<Vec<_>>());
-//
-//     // Test descending insert, ascending delete
-//     insert_keys(&mut tree, &[10, 9, 8, 7, 6, 5, 4, 3, 2, 1]);
-//     for key in 1..=10 {
-//         assert!(tree.delete(key));
-//     }
-//     verify_searches(&mut tree, &[], &(1..=10).collect::<Vec<_>>());
-// }
\ No newline at end of file
+#[test]
+fn test_sequential_insert_delete_patterns() {
+    let mut tree = create_test_tree();
+
+    // Test ascending insert, descending delete
+    insert_keys(&mut tree, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+    for key in (1..=10).rev() {
+        assert!(tree.delete(key, rid(key)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    verify_searches(&mut tree, &[], &(1..=10).collect::<Vec<_>>());
+
+    // Test descending insert, ascending delete
+    insert_keys(&mut tree, &[10, 9, 8, 7, 6, 5, 4, 3, 2, 1]);
+    for key in 1..=10 {
+        assert!(tree.delete(key, rid(key)));
+        assert_eq!(tree.validate(), Ok(()));
+    }
+    verify_searches(&mut tree, &[], &(1..=10).collect::<Vec<_>>());
+
+    // larger trees empty through merges propagating up several levels, in either direction
+    for keys_to_delete in [(1..=100).collect::<Vec<i64>>(), (1..=100).rev().collect()] {
+        insert_keys(&mut tree, &(1..=100).collect::<Vec<_>>());
+        for key in keys_to_delete {
+            assert!(tree.delete(key, rid(key)), "key {} should be deleted", key);
+            assert_eq!(tree.validate(), Ok(()), "invalid tree after deleting {}", key);
+        }
+        verify_searches(&mut tree, &[], &(1..=100).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn test_index_through_storage_engine_buffer_pool() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let config = StorageConfig {
+        database_dir: dir.path().to_path_buf(),
+        ..StorageConfig::default()
+    };
+
+    let root_id;
+    let mut rids = Vec::new();
+    {
+        let engine = StorageEngine::new(config.clone()).unwrap();
+
+        // heap records indexed by key
+        let data_id: PageId;
+        with_create_pages!(engine.buffer_pool, [(data_id, page)], FLUSH, {
+            for key in 0..5i64 {
+                let slot_id = page.insert_record(format!("record {key}").as_bytes()).unwrap();
+                rids.push(RecordId { page_id: data_id, slot_id });
+            }
+        });
+
+        // index pages come from the index buffer pool and share the data pages' free list
+        let root_page = engine.index_buffer_pool.create_page().unwrap();
+        let initial_root = root_page.read().get_id();
+        drop(root_page);
+        assert_ne!(initial_root, data_id);
+
+        let mut tree = BPlusTree::new(initial_root, engine.index_buffer_pool.clone(), 3, 3);
+        for (key, rid) in rids.iter().enumerate() {
+            tree.insert(key as i64, *rid);
+        }
+        assert!(engine.index_buffer_pool.current_size() > 1);
+        root_id = tree.get_root();
+        engine.deactivate().unwrap();
+    }
+
+    // reopen, look up a key through the index and read the record from the heap
+    let engine = StorageEngine::new(config).unwrap();
+    let mut tree = BPlusTree::new(root_id, engine.index_buffer_pool.clone(), 3, 3);
+    let found = tree.search(&3);
+    assert_eq!(found, vec![rids[3]]);
+    let rid = found[0];
+    assert!(engine.index_buffer_pool.current_size() > 0);
+
+    let data_id = rid.page_id;
+    with_read_pages!(engine.buffer_pool, [(data_id, page)], {
+        assert_eq!(page.get_record(rid.slot_id).unwrap(), b"record 3");
+    });
+}
+
+#[test]
+fn test_compact_after_deletes() {
+    let mut tree = create_test_tree();
+    let keys: Vec<i64> = (1..=300).collect();
+    insert_keys(&mut tree, &keys);
+
+    // delete most keys, leaving many sparse pages
+    let (kept, deleted): (Vec<i64>, Vec<i64>) = keys.iter().partition(|&&k| k % 5 == 0);
+    for &key in deleted.iter().rev() {
+        tree.delete(key, rid(key));
+    }
+    let expected: Vec<(i64, RecordId)> = kept.iter()
+        .map(|&k| (k, rid(k)))
+        .collect();
+    assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+    let pages_before = tree.page_count();
+
+    tree.compact();
+
+    // fewer pages, and every remaining (key, rid) pair is preserved in order
+    assert!(tree.page_count() < pages_before, "{} pages after compact, {} before", tree.page_count(), pages_before);
+    assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(tree.validate(), Ok(()));
+    verify_searches(&mut tree, &kept, &deleted);
+
+    // the compacted tree keeps working for further updates
+    insert_keys(&mut tree, &[2, 3]);
+    assert!(tree.delete(10, rid(10)));
+    verify_searches(&mut tree, &[2, 3, 5, 300], &[10, 4]);
+}
+
+#[test]
+fn test_compact_empty_tree() {
+    let mut tree = create_test_tree();
+    insert_keys(&mut tree, &[1, 2, 3, 4, 5]);
+    for key in 1..=5 {
+        assert!(tree.delete(key, rid(key)));
+    }
+
+    tree.compact();
+    assert_eq!(tree.page_count(), 1);
+    assert_eq!(tree.iter().count(), 0);
+    insert_keys(&mut tree, &[7]);
+    verify_searches(&mut tree, &[7], &[1]);
+}
+
+#[test]
+fn test_bulk_insert_merges_into_tree() {
+    let mut tree = create_test_tree();
+    let existing: Vec<i64> = (0..600).step_by(3).collect();
+    insert_keys(&mut tree, &existing);
+    let pages_before = tree.page_count();
+
+    // unsorted batch interleaved with the existing keys
+    let batch: Vec<(i64, RecordId)> = (0..600).rev()
+        .filter(|k| k % 3 != 0)
+        .map(|k| (k, rid(k)))
+        .collect();
+    tree.bulk_insert(&batch);
+
+    let all: Vec<i64> = (0..600).collect();
+    let expected: Vec<(i64, RecordId)> = all.iter().map(|&k| (k, rid(k))).collect();
+    assert_eq!(tree.iter().collect::<Vec<_>>(), expected);
+    verify_searches(&mut tree, &all, &[-1, 600]);
+    // the rebuilt tree is dense, holding three times the keys in fewer than three times the pages
+    assert!(tree.page_count() < 3 * pages_before, "{} pages after, {} before", tree.page_count(), pages_before);
+
+    // the tree keeps working for further updates
+    insert_keys(&mut tree, &[700]);
+    assert!(tree.delete(300, rid(300)));
+    verify_searches(&mut tree, &[0, 599, 700], &[300]);
+
+    // records of a key already in the tree follow the existing ones
+    tree.bulk_insert(&[(3, rid(1000))]);
+    assert_eq!(tree.search(&3), vec![rid(3), rid(1000)]);
+    assert_eq!(tree.validate(), Ok(()));
+}
+
+#[test]
+fn test_validate_after_updates() {
+    let mut tree = create_test_tree();
+    assert_eq!(tree.validate(), Ok(()));
+
+    let keys: Vec<i64> = (1..=100).collect();
+    insert_keys(&mut tree, &keys);
+    assert_eq!(tree.validate(), Ok(()));
+    for key in (1..=100).filter(|k| k % 3 != 0) {
+        tree.delete(key, rid(key));
+    }
+    assert_eq!(tree.validate(), Ok(()));
+}
+
+#[test]
+fn test_validate_reports_cycle() {
+    let buffer_pool = create_test_pool();
+
+    // internal root whose second child points back to the root itself
+    let (root_id, leaf_id);
+    with_create_pages!(buffer_pool, [(leaf_id, leaf_page)], FLUSH, {
+        leaf_page.insert_record(1, RecordId { page_id: 1, slot_id: 1 });
+    });
+    with_create_pages!(buffer_pool, [(root_id, root_page)], FLUSH, {
+        root_page.page_type = IndexType::Internal;
+        root_page.get_children_mut().push(leaf_id);
+        root_page.insert_child(10, root_id);
+    });
+
+    let tree = BPlusTree::new(root_id, buffer_pool, 3, 3);
+    let error = tree.validate().unwrap_err();
+    assert!(error.contains("Cycle"), "{}", error);
+
+    // printing stops at the revisited page instead of recursing forever
+    tree.print_tree();
+}
+
+#[test]
+fn test_range_scan_across_leaves() {
+    let mut tree = create_test_tree();
+    let keys: Vec<i64> = (1..=40).rev().map(|k| k * 2).collect();
+    insert_keys(&mut tree, &keys);
+    assert!(tree.page_count() > 10, "the tree should span many leaves");
+
+    let expected = |start: i64, end: i64| (start..=end).filter(|k| k % 2 == 0 && (2..=80).contains(k)).map(rid).collect::<Vec<_>>();
+
+    // bounds on keys, between keys, and past either end of the tree
+    for (start, end) in [(2, 80), (10, 30), (11, 29), (0, 7), (75, 100), (40, 40), (41, 41), (-5, 0), (81, 90), (30, 10)] {
+        assert_eq!(tree.range_scan(start, end), expected(start, end), "range {}..={}", start, end);
+    }
+
+    // deleted keys are skipped, including whole leaves emptied in the middle of the range
+    for key in 20..=40 {
+        tree.delete(key, rid(key));
+    }
+    let remaining: Vec<RecordId> = expected(10, 60).into_iter().filter(|r| !(20..=40).contains(&(r.page_id as i64))).collect();
+    assert_eq!(tree.range_scan(10, 60), remaining);
+}
+
+#[test]
+fn test_internal_redistribution_three_levels() {
+    // deleting from either end drains one side of a three-level tree, so its internal nodes
+    // underflow while their siblings still have keys to lend
+    for keys_to_delete in [(1..=60).collect::<Vec<i64>>(), (1..=60).rev().collect()] {
+        let mut tree = create_test_tree();
+        let keys: Vec<i64> = (1..=90).collect();
+        insert_keys(&mut tree, &keys);
+        assert_eq!(tree.validate(), Ok(()));
+
+        for key in keys_to_delete {
+            assert!(tree.delete(key, rid(key)), "key {} should be deleted", key);
+            assert_eq!(tree.validate(), Ok(()), "invalid tree after deleting {}", key);
+            assert!(tree.search(&key).is_empty());
+        }
+        let remaining: Vec<i64> = keys.iter().copied().filter(|k| !tree.search(k).is_empty()).collect();
+        assert_eq!(remaining.len(), 30);
+    }
+}
+
+#[test]
+fn test_bulk_load_matches_insert_built_tree() {
+    // three records per key, so duplicates span leaves
+    let n = 3000;
+    let entries: Vec<(i64, RecordId)> = (0..n)
+        .map(|i| (i as i64 / 3, RecordId { page_id: i as PageId, slot_id: 0 }))
+        .collect();
+
+    let mut inserted = create_test_tree();
+    for i in 0..n {
+        let (key, rid) = entries[(i * 1237) % n];
+        inserted.insert(key, rid);
+    }
+    let mut loaded = create_test_tree();
+    loaded.bulk_load(&entries);
+    assert_eq!(inserted.validate(), Ok(()));
+    assert_eq!(loaded.validate(), Ok(()));
+
+    for key in -1..=(n as i64 / 3) {
+        let mut expected = inserted.search(&key);
+        let mut actual = loaded.search(&key);
+        expected.sort_by_key(|r| r.page_id);
+        actual.sort_by_key(|r| r.page_id);
+        assert_eq!(actual, expected, "search mismatch for key {}", key);
+    }
+    assert_eq!(loaded.range_scan(100, 200).len(), inserted.range_scan(100, 200).len());
+    assert_eq!(loaded.range_scan(i64::MIN, i64::MAX).len(), n);
+}
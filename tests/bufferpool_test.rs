@@ -1,24 +1,26 @@
 use paste::paste;
-use std::sync::{Arc, Barrier, Mutex};
+use std::io;
+use std::sync::{Arc, Barrier};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 use tempfile::{NamedTempFile};
 use raincloud_db::storage::page::page::{Page, PageError};
 use raincloud_db::storage::page::data_page::{DataPage};
-use raincloud_db::storage::bufferpool::BufferPool;
-use raincloud_db::storage::disk_manager::FileDiskManager;
+use raincloud_db::storage::bufferpool::{BufferPool, BufferPoolStats};
+use raincloud_db::storage::disk_manager::{DiskManager, FileDiskManager, MemoryDiskManager};
 use raincloud_db::storage::replacement_strategy::ReplacementStrategyType;
 use raincloud_db::{with_create_pages, with_read_pages, with_write_pages};
 use raincloud_db::storage::free_list::FreeList;
 use raincloud_db::storage::page::header_page::HeaderPage;
-use raincloud_db::types::{FLUSH, NO_FLUSH};
+use raincloud_db::types::{PageId, FLUSH, NO_FLUSH};
 
 fn setup_buffer_pool(capacity: usize) -> (Arc<BufferPool<DataPage>>, NamedTempFile) {
     let temp_file = NamedTempFile::new().unwrap();
     let path = temp_file.path();
     let disk_manager = Arc::new(FileDiskManager::<DataPage>::open(path).unwrap());
     let header_disk_manager = Arc::new(FileDiskManager::<HeaderPage>::open(path).unwrap());
-    let free_list = Arc::new(Mutex::new(FreeList::new(header_disk_manager, 0)));
+    let free_list = Arc::new(FreeList::new(header_disk_manager, 0));
     let buffer_pool = Arc::new(BufferPool::new(
         capacity,
         ReplacementStrategyType::LRU,
@@ -52,7 +54,7 @@ fn test_dirty_page_persistence() {
     // Create new pool to test persistence
     let disk_manager = Arc::new(FileDiskManager::<DataPage>::open(temp_file.path()).unwrap());
     let header_disk_manager = Arc::new(FileDiskManager::<HeaderPage>::open(temp_file.path()).unwrap());
-    let free_list = Arc::new(Mutex::new(FreeList::new(header_disk_manager, 0)));
+    let free_list = Arc::new(FreeList::new(header_disk_manager, 0));
     let new_pool = BufferPool::new(2, ReplacementStrategyType::LRU, disk_manager, free_list);
 
     // Should be able to fetch from disk without error
@@ -76,7 +78,7 @@ fn test_flush_page() {
     // Verify persistence
     let disk_manager = Arc::new(FileDiskManager::<DataPage>::open(temp_file.path()).unwrap());
     let header_disk_manager = Arc::new(FileDiskManager::<HeaderPage>::open(temp_file.path()).unwrap());
-    let free_list = Arc::new(Mutex::new(FreeList::new(header_disk_manager, 0)));
+    let free_list = Arc::new(FreeList::new(header_disk_manager, 0));
     let new_pool = Arc::new(BufferPool::new(5, ReplacementStrategyType::LRU, disk_manager, free_list));
 
     // All pages should be accessible
@@ -96,12 +98,12 @@ fn test_flush_all() {
     with_create_pages!(pool, [(id1, page1), (id2, page2), (id3, page3)], FLUSH, {});
 
     // Flush all
-    pool.flush_all();
+    pool.flush_all().unwrap();
 
     // Verify persistence
     let disk_manager = Arc::new(FileDiskManager::<DataPage>::open(temp_file.path()).unwrap());
     let header_disk_manager = Arc::new(FileDiskManager::<HeaderPage>::open(temp_file.path()).unwrap());
-    let free_list = Arc::new(Mutex::new(FreeList::new(header_disk_manager, 0)));
+    let free_list = Arc::new(FreeList::new(header_disk_manager, 0));
     let new_pool = Arc::new(BufferPool::new(5, ReplacementStrategyType::LRU, disk_manager, free_list));
 
     // All pages should be accessible
@@ -230,6 +232,25 @@ fn test_pinned_page_protection() {
     });
 }
 
+#[test]
+fn test_eviction_reports_no_progress_when_all_pinned() {
+    let (pool, _temp_file) = setup_buffer_pool(2);
+
+    // pin every frame, the first page is unpinned once and pinned again so the pool counts it as evictable
+    let id1;
+    with_create_pages!(pool, [(id1, page1)], NO_FLUSH, {});
+    let guard1 = pool.fetch_page(id1).unwrap();
+    let guard2 = pool.create_page().unwrap();
+
+    assert_eq!(pool.create_page().err(), Some(PageError::EvictionStalled));
+    assert_eq!(pool.current_size(), 2);
+
+    // eviction resumes once a frame is unpinned
+    drop(guard1);
+    assert!(pool.create_page().is_ok());
+    drop(guard2);
+}
+
 #[test]
 fn test_concurrent_access_same_page() {
     let (pool, _tmp) = setup_buffer_pool(4);
@@ -305,3 +326,192 @@ fn test_concurrent_access_same_page() {
 //     assert!(pool.current_size() <= 1, "capacity must never be exceeded");
 //     assert!(pool.fetch_page(pid).is_ok(), "page should be fetchable");
 // }
+
+#[test]
+fn test_evict_page() {
+    let (pool, temp_file) = setup_buffer_pool(3);
+    let disk_manager = FileDiskManager::<DataPage>::open(temp_file.path()).unwrap();
+
+    let page_id;
+    with_create_pages!(pool, [(page_id, page)], FLUSH, {
+        page.insert_record(b"cached").unwrap();
+    });
+
+    // evicting a clean page counts as an eviction, and the next fetch is a miss
+    pool.evict_page(page_id).unwrap();
+    assert_eq!(pool.stats(), BufferPoolStats { hits: 0, misses: 0, evictions: 1, current_size: 0 });
+    with_read_pages!(pool, [(page_id, page)], {
+        assert_eq!(page.get_record(0).unwrap(), b"cached");
+    });
+    with_read_pages!(pool, [(page_id, _page)], {});
+    assert_eq!(pool.stats(), BufferPoolStats { hits: 1, misses: 1, evictions: 1, current_size: 1 });
+
+    // evicting a dirty page writes it to disk
+    with_write_pages!(pool, [(page_id, page)], NO_FLUSH, {
+        page.insert_record(b"dirty").unwrap();
+    });
+    pool.evict_page(page_id).unwrap();
+    assert_eq!(disk_manager.read_page(page_id).unwrap().get_record(1).unwrap(), b"dirty");
+
+    // pinned and absent pages cannot be evicted
+    let guard = pool.fetch_page(page_id).unwrap();
+    assert_eq!(pool.evict_page(page_id), Err(PageError::PageLatched));
+    drop(guard);
+    pool.evict_page(page_id).unwrap();
+    assert_eq!(pool.evict_page(page_id), Err(PageError::InvalidPage));
+    assert_eq!(pool.stats().evictions, 3);
+}
+
+#[test]
+fn test_create_page_reusing_freed_id_starts_empty() {
+    let (pool, _temp_file) = setup_buffer_pool(4);
+
+    let page_id;
+    with_create_pages!(pool, [(page_id, page)], NO_FLUSH, {
+        page.insert_record(&[1, 2, 3]).unwrap();
+    });
+    pool.free_page(page_id, NO_FLUSH);
+
+    // the freed id is reallocated while its old frame is still cached
+    let new_id;
+    with_create_pages!(pool, [(new_id, page)], NO_FLUSH, {
+        assert_eq!(page.iter_record().count(), 0);
+    });
+    assert_eq!(new_id, page_id);
+}
+
+#[test]
+fn test_resident_pages_snapshot() {
+    let (pool, _temp_file) = setup_buffer_pool(4);
+    assert!(pool.resident_pages().is_empty());
+
+    let dirty_id;
+    with_create_pages!(pool, [(dirty_id, page)], NO_FLUSH, {
+        page.insert_record(&[1]).unwrap();
+    });
+    let clean_id;
+    with_create_pages!(pool, [(clean_id, page)], FLUSH, {
+        page.insert_record(&[2]).unwrap();
+    });
+
+    // snapshot while a page is pinned and latched does not deadlock
+    let guard = pool.fetch_page(clean_id).unwrap();
+    let latch = guard.read();
+    let snapshot = pool.resident_pages();
+    assert_eq!(snapshot, vec![(dirty_id, true, 0), (clean_id, false, 1)]);
+    drop(latch);
+    drop(guard);
+
+    pool.flush_all().unwrap();
+    assert_eq!(pool.resident_pages(), vec![(dirty_id, false, 0), (clean_id, false, 0)]);
+}
+
+/// Disk manager whose writes fail while fail is set
+struct FailingDisk {
+    inner: FileDiskManager<DataPage>,
+    fail: AtomicBool,
+}
+
+impl DiskManager<DataPage> for FailingDisk {
+    fn read_page(&self, id: PageId) -> Option<DataPage> {
+        self.inner.read_page(id)
+    }
+
+    fn write_page(&self, page: &DataPage) -> io::Result<()> {
+        if self.fail.load(Ordering::SeqCst) {
+            return Err(io::Error::from(io::ErrorKind::StorageFull));
+        }
+        self.inner.write_page(page)
+    }
+}
+
+#[test]
+fn test_write_error_propagates_and_keeps_page_dirty() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let disk = Arc::new(FailingDisk {
+        inner: FileDiskManager::open(temp_file.path()).unwrap(),
+        fail: AtomicBool::new(true),
+    });
+    let header_disk = Arc::new(FileDiskManager::<HeaderPage>::open(temp_file.path()).unwrap());
+    let pool = Arc::new(BufferPool::new(
+        1,
+        ReplacementStrategyType::LRU,
+        disk.clone(),
+        Arc::new(FreeList::new(header_disk, 0)),
+    ));
+
+    let page_id;
+    with_create_pages!(pool, [(page_id, page)], NO_FLUSH, {
+        page.insert_record(b"unsaved").unwrap();
+    });
+
+    // flushing and evicting report the failed write, and the page stays dirty in memory
+    assert_eq!(pool.flush_page(page_id), Err(PageError::DiskWrite(io::ErrorKind::StorageFull)));
+    assert_eq!(pool.flush_all(), Err(PageError::DiskWrite(io::ErrorKind::StorageFull)));
+    // a full pool cannot make room for another page
+    assert!(matches!(pool.create_page(), Err(PageError::DiskWrite(io::ErrorKind::StorageFull))));
+    assert_eq!(pool.evict_page(page_id), Err(PageError::DiskWrite(io::ErrorKind::StorageFull)));
+    assert_eq!(pool.resident_pages(), vec![(page_id, true, 0)]);
+    assert!(disk.inner.read_page(page_id).is_none());
+
+    // the page is written once the disk recovers
+    disk.fail.store(false, Ordering::SeqCst);
+    pool.flush_page(page_id).unwrap();
+    assert_eq!(pool.resident_pages(), vec![(page_id, false, 0)]);
+    assert_eq!(disk.inner.read_page(page_id).unwrap().get_record(0).unwrap(), b"unsaved");
+}
+
+#[test]
+fn test_stats_count_hits_misses_evictions() {
+    let (pool, _temp_file) = setup_buffer_pool(2);
+    assert_eq!(pool.stats(), BufferPoolStats::default());
+
+    // created pages are neither hits nor misses
+    let (first, second, third);
+    with_create_pages!(pool, [(first, page)], NO_FLUSH, {});
+    with_create_pages!(pool, [(second, page)], NO_FLUSH, {});
+    with_read_pages!(pool, [(first, _page)], {});
+    with_read_pages!(pool, [(second, _page)], {});
+    with_read_pages!(pool, [(first, _page)], {});
+    assert_eq!(pool.stats(), BufferPoolStats { hits: 3, misses: 0, evictions: 0, current_size: 2 });
+
+    // a third page evicts the least recently used one, which is read back from disk
+    with_create_pages!(pool, [(third, page)], NO_FLUSH, {});
+    with_read_pages!(pool, [(second, _page)], {});
+    assert_eq!(pool.stats(), BufferPoolStats { hits: 3, misses: 1, evictions: 2, current_size: 2 });
+
+    // explicit evictions are counted, failed reads are not
+    pool.evict_page(second).unwrap();
+    assert!(pool.fetch_page(999).is_err());
+    with_read_pages!(pool, [(third, _page)], {});
+    assert_eq!(pool.stats(), BufferPoolStats { hits: 4, misses: 1, evictions: 3, current_size: 1 });
+}
+
+#[test]
+fn test_memory_disk_manager_round_trip() {
+    let disk_manager = Arc::new(MemoryDiskManager::<DataPage>::new());
+    let free_list = Arc::new(FreeList::new(Arc::new(MemoryDiskManager::<HeaderPage>::new()), 0));
+    let pool = Arc::new(BufferPool::new(2, ReplacementStrategyType::LRU, disk_manager.clone(), free_list));
+
+    // three dirty pages in a pool of two evict the first one to memory
+    let mut page_ids = vec![];
+    for record in [b"first", b"other", b"third"] {
+        let page_id;
+        with_create_pages!(pool, [(page_id, page)], NO_FLUSH, {
+            page.insert_record(record).unwrap();
+        });
+        page_ids.push(page_id);
+    }
+    assert_eq!(pool.stats().evictions, 1);
+    assert_eq!(disk_manager.read_page(page_ids[0]).unwrap().get_record(0).unwrap(), b"first");
+    assert!(disk_manager.read_page(page_ids[2]).is_none(), "the newest page is not written yet");
+
+    // the evicted page is read back from memory
+    let first = page_ids[0];
+    with_read_pages!(pool, [(first, page)], {
+        assert_eq!(page.get_record(0).unwrap(), b"first");
+    });
+    assert_eq!(pool.stats().misses, 1);
+    // ids handed out without a free list are above every page written
+    assert!(disk_manager.allocate_page_id() > first);
+}
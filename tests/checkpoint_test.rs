@@ -0,0 +1,71 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use paste::paste;
+use tempfile::TempDir;
+use raincloud_db::storage::{StorageConfig, StorageEngine};
+use raincloud_db::storage::page::page::Page;
+use raincloud_db::types::{PageId, NO_FLUSH};
+use raincloud_db::with_create_pages;
+
+const RECORD: &[u8] = b"checkpointed";
+
+fn storage_config(dir: &TempDir, checkpoint_interval_ms: Option<u64>) -> StorageConfig {
+    StorageConfig {
+        database_dir: dir.path().to_path_buf(),
+        checkpoint_interval_ms,
+        ..StorageConfig::default()
+    }
+}
+
+/// Create a page holding RECORD without flushing it
+fn create_dirty_page(engine: &StorageEngine) -> PageId {
+    let page_id: PageId;
+    with_create_pages!(engine.buffer_pool, [(page_id, page)], NO_FLUSH, {
+        page.set_next_id(0);
+        page.insert_record(RECORD).unwrap();
+    });
+    page_id
+}
+
+fn record_on_disk(engine: &StorageEngine, page_id: PageId) -> bool {
+    engine.data_disk.read_page(page_id)
+        .is_some_and(|page| page.get_record(0) == Some(RECORD))
+}
+
+#[test]
+fn test_checkpoint_flushes_dirty_page_within_interval() {
+    let dir = TempDir::new().unwrap();
+    let engine = StorageEngine::new(storage_config(&dir, Some(50))).unwrap();
+    let page_id = create_dirty_page(&engine);
+
+    // no explicit flush or deactivate, the checkpointer writes the page
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !record_on_disk(&engine, page_id) {
+        assert!(Instant::now() < deadline, "dirty page was not checkpointed");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_no_checkpoint_without_interval() {
+    let dir = TempDir::new().unwrap();
+    let engine = StorageEngine::new(storage_config(&dir, None)).unwrap();
+    let page_id = create_dirty_page(&engine);
+
+    thread::sleep(Duration::from_millis(200));
+    assert!(!record_on_disk(&engine, page_id));
+
+    engine.deactivate().unwrap();
+    assert!(record_on_disk(&engine, page_id));
+}
+
+#[test]
+fn test_checkpointer_stops_with_storage_engine() {
+    let dir = TempDir::new().unwrap();
+    let engine = StorageEngine::new(storage_config(&dir, Some(60 * 60 * 1000))).unwrap();
+
+    // dropping the engine does not wait for the next checkpoint
+    let start = Instant::now();
+    drop(engine);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
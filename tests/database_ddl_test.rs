@@ -1,7 +1,10 @@
 mod common;
 
-use raincloud_db::types::{DATA_FILE, HEADER_FILE};
-use crate::common::{setup_interpreter, assert_sql_success, assert_sql_failure};
+use tempfile::TempDir;
+use raincloud_db::interpreter::Interpreter;
+use raincloud_db::storage::StorageConfig;
+use raincloud_db::types::{DbError, CATALOG_FILE, CONFIG_FILE, DATA_FILE, DEFAULT_BUFFERPOOL_SIZE, HEADER_FILE};
+use crate::common::{setup_interpreter, assert_sql_success, assert_sql_failure, test_sql, get_rows};
 
 #[test]
 fn test_create_database() {
@@ -61,4 +64,246 @@ fn test_drop_database() {
 fn test_drop_nonexistent_database() {
     let mut interpreter = setup_interpreter();
    assert_sql_failure("DROP DATABASE undefined;", &mut interpreter);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_drop_database_failure_keeps_catalog_and_disk_consistent() {
+    let tmpdir = TempDir::new().unwrap();
+    let mut interpreter = Interpreter::new(tmpdir.path());
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE t (id INT); INSERT INTO t VALUES (1); DISCONNECT;",
+        &mut interpreter,
+    );
+    let db_path = tmpdir.path().join("DB1");
+    let dropping_path = interpreter.context.read().unwrap().global_catalog.dropping_path("DB1");
+    let assert_database_intact = |interpreter: &Interpreter| {
+        let ctx = interpreter.context.read().unwrap();
+        assert!(db_path.join(DATA_FILE).exists());
+        assert!(ctx.global_catalog.has_database("DB1"));
+        assert!(ctx.storage_engines.contains_key("DB1"));
+    };
+
+    // the directory cannot be moved aside: nothing changes
+    std::fs::write(&dropping_path, "blocked").unwrap();
+    assert!(matches!(test_sql("DROP DATABASE db1;", &mut interpreter)[0], Err(DbError::InternalError(_))));
+    assert_database_intact(&interpreter);
+    std::fs::remove_file(&dropping_path).unwrap();
+
+    // the catalog cannot be saved: the directory is moved back
+    let catalog_path = tmpdir.path().join(CATALOG_FILE);
+    std::fs::remove_file(&catalog_path).unwrap();
+    std::fs::create_dir(&catalog_path).unwrap();
+    assert!(matches!(test_sql("DROP DATABASE db1;", &mut interpreter)[0], Err(DbError::InternalError(_))));
+    assert_database_intact(&interpreter);
+    assert!(!dropping_path.exists());
+    std::fs::remove_dir(&catalog_path).unwrap();
+
+    // the database is still usable, and dropping it succeeds once the failure is gone
+    assert_sql_success("CONNECT TO db1;", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT id FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"]]);
+    assert_sql_success("DISCONNECT; DROP DATABASE db1;", &mut interpreter);
+    assert!(!db_path.exists());
+    assert!(!dropping_path.exists());
+}
+
+#[test]
+fn test_reconcile_finishes_interrupted_drop() {
+    let tmpdir = TempDir::new().unwrap();
+    let dropping_path;
+    {
+        let mut interpreter = Interpreter::new(tmpdir.path());
+        assert_sql_success("CREATE DATABASE db1; CREATE DATABASE db2;", &mut interpreter);
+        dropping_path = interpreter.context.read().unwrap().global_catalog.dropping_path("DB2");
+    }
+
+    // crash after the directory was renamed, before the catalog was updated
+    std::fs::rename(tmpdir.path().join("DB2"), &dropping_path).unwrap();
+
+    let interpreter = Interpreter::new(tmpdir.path());
+    assert!(!dropping_path.exists());
+    assert!(interpreter.orphans().directories.is_empty());
+    assert_eq!(interpreter.orphans().catalog_entries, vec!["DB2".to_string()]);
+    assert_eq!(interpreter.context.read().unwrap().global_catalog.list_databases(), vec!["DB1".to_string()]);
+}
+
+#[test]
+fn test_create_database_case_insensitive_collision() {
+    let tmpdir = TempDir::new().unwrap();
+    let mut interpreter = Interpreter::new(tmpdir.path());
+
+    // names differing only in case refer to the same database
+    assert_sql_success("CREATE DATABASE Db1;", &mut interpreter);
+    assert_sql_failure("CREATE DATABASE db1;", &mut interpreter);
+    assert_sql_failure("CREATE DATABASE DB1;", &mut interpreter);
+
+    // a directory differing only in case from the new database collides
+    std::fs::create_dir(tmpdir.path().join("db2")).unwrap();
+    let res = test_sql("CREATE DATABASE db2;", &mut interpreter);
+    assert!(matches!(res[0], Err(DbError::DuplicateDatabase(_))));
+    assert!(!tmpdir.path().join("DB2").exists());
+}
+
+#[test]
+fn test_database_storage_config_file() {
+    let tmpdir = TempDir::new().unwrap();
+    let db_path = tmpdir.path().join("DB1");
+    {
+        let mut interpreter = Interpreter::new(tmpdir.path());
+        assert_sql_success("CREATE DATABASE db1;", &mut interpreter);
+
+        // no config file, engine uses defaults
+        let ctx = interpreter.context.read().unwrap();
+        assert_eq!(ctx.storage_engines["DB1"].buffer_pool.capacity(), DEFAULT_BUFFERPOOL_SIZE);
+    }
+
+    let config = StorageConfig { bufferpool_capacity: 7, ..StorageConfig::default() };
+    config.save(&db_path.join(CONFIG_FILE)).unwrap();
+    let loaded = StorageConfig::load(&db_path.join(CONFIG_FILE)).unwrap();
+    assert_eq!(loaded.bufferpool_capacity, 7);
+
+    // reopened database picks up its config file
+    let interpreter = Interpreter::new(tmpdir.path());
+    let ctx = interpreter.context.read().unwrap();
+    assert_eq!(ctx.storage_engines["DB1"].buffer_pool.capacity(), 7);
+}
+
+#[test]
+fn test_partial_storage_config_file() {
+    let tmpdir = TempDir::new().unwrap();
+    let path = tmpdir.path().join(CONFIG_FILE);
+    std::fs::write(&path, r#"{ "bufferpool_capacity": 3 }"#).unwrap();
+
+    // missing fields fall back to defaults
+    let config = StorageConfig::load(&path).unwrap();
+    assert_eq!(config.bufferpool_capacity, 3);
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(StorageConfig::load(&path).is_err());
+}
+
+#[test]
+fn test_reconcile_orphaned_databases() {
+    let tmpdir = TempDir::new().unwrap();
+    {
+        let mut interpreter = Interpreter::new(tmpdir.path());
+        assert_sql_success("CREATE DATABASE db1; CREATE DATABASE db2;", &mut interpreter);
+        assert!(interpreter.orphans().is_empty());
+    }
+
+    // database directory lost, and a directory created outside the catalog
+    std::fs::remove_dir_all(tmpdir.path().join("DB2")).unwrap();
+    std::fs::create_dir(tmpdir.path().join("DB3")).unwrap();
+
+    let mut interpreter = Interpreter::new(tmpdir.path());
+    assert_eq!(interpreter.orphans().directories, vec!["DB3".to_string()]);
+    assert_eq!(interpreter.orphans().catalog_entries, vec!["DB2".to_string()]);
+    {
+        let ctx = interpreter.context.read().unwrap();
+        assert_eq!(ctx.global_catalog.list_databases(), vec!["DB1".to_string()]);
+        assert!(!ctx.storage_engines.contains_key("DB2"));
+        assert!(!tmpdir.path().join("DB2").exists());
+    }
+
+    // the stale catalog entry no longer blocks recreating the database
+    assert_sql_success("CREATE DATABASE db2; CONNECT TO db1;", &mut interpreter);
+
+    // the orphaned directory is kept and reported when recreating over it
+    assert!(tmpdir.path().join("DB3").exists());
+    let res = test_sql("CREATE DATABASE db3;", &mut interpreter);
+    match &res[0] {
+        Err(DbError::DuplicateDatabase(msg)) => assert!(msg.contains("not in the catalog"), "{}", msg),
+        other => panic!("Expected DuplicateDatabase, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_dump_and_reimport_database() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE shop; CONNECT TO shop;
+         CREATE TABLE items (name CHAR(10), id INT, price INT) WITH (fillfactor = 70);
+         CREATE TABLE logs (id INT) WITH (soft_delete = TRUE, retention = 60);
+         INSERT INTO items VALUES ('apple', 1, 3), ('pear', 2, -4), ('', 3, 0);
+         INSERT INTO logs VALUES (1), (2), (3);
+         DELETE FROM logs WHERE id = 2;
+         CREATE INDEX items_id ON items (id);",
+        &mut interpreter,
+    );
+    let dump = interpreter.dump("shop").unwrap();
+    assert!(dump.contains("CREATE TABLE ITEMS (NAME CHAR(10), ID INT, PRICE INT) WITH (FILLFACTOR = 70);"), "{}", dump);
+    assert!(dump.contains("CREATE TABLE LOGS (ID INT) WITH (SOFT_DELETE = TRUE, RETENTION = 60);"), "{}", dump);
+    assert!(dump.contains("CREATE INDEX ITEMS_ID ON ITEMS (ID);"), "{}", dump);
+
+    // replay into a fresh instance
+    let mut restored = setup_interpreter();
+    for result in restored.execute_sql(&dump).unwrap() {
+        result.unwrap();
+    }
+
+    for query in ["SELECT name, id, price FROM items ORDER BY id;", "SELECT id FROM logs ORDER BY id;"] {
+        assert_eq!(
+            get_rows(test_sql(query, &mut restored)),
+            get_rows(test_sql(query, &mut interpreter)),
+        );
+    }
+    assert_eq!(get_rows(test_sql("SELECT id FROM logs ORDER BY id;", &mut restored)).len(), 2);
+    assert_eq!(restored.dump("shop").unwrap(), dump);
+
+    assert!(matches!(interpreter.dump("missing"), Err(DbError::DatabaseNotFound(_))));
+}
+
+#[test]
+fn test_dump_orders_referenced_tables_first() {
+    let mut interpreter = setup_interpreter();
+    // ACCOUNTS references USERS but sorts before it by name
+    assert_sql_success(
+        "CREATE DATABASE shop; CONNECT TO shop;
+         CREATE TABLE users (id INT);
+         CREATE TABLE accounts (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE);
+         INSERT INTO users VALUES (1);
+         INSERT INTO accounts VALUES (1);",
+        &mut interpreter,
+    );
+    let dump = interpreter.dump("shop").unwrap();
+    assert!(dump.contains("CREATE TABLE ACCOUNTS (USER_ID INT, FOREIGN KEY (USER_ID) REFERENCES USERS (ID) ON DELETE CASCADE);"), "{}", dump);
+    assert!(dump.find("CREATE TABLE USERS").unwrap() < dump.find("CREATE TABLE ACCOUNTS").unwrap(), "{}", dump);
+
+    let mut restored = setup_interpreter();
+    for result in restored.execute_sql(&dump).unwrap() {
+        result.unwrap();
+    }
+    assert_eq!(get_rows(test_sql("SELECT user_id FROM accounts;", &mut restored)), vec![vec!["1"]]);
+}
+
+#[test]
+fn test_reopened_database_does_not_reuse_page_ids() {
+    let tmpdir = TempDir::new().unwrap();
+    {
+        let mut interpreter = Interpreter::new(tmpdir.path());
+        assert_sql_success(
+            "CREATE DATABASE db1; CONNECT TO db1;
+             CREATE TABLE a (x INT);
+             INSERT INTO a VALUES (1), (2);
+             CREATE TABLE freed (x INT);
+             INSERT INTO freed VALUES (3);
+             DROP TABLE freed;
+             DISCONNECT;",
+            &mut interpreter,
+        );
+    }
+
+    // pages allocated after a restart must not overwrite the pages of existing tables
+    let mut interpreter = Interpreter::new(tmpdir.path());
+    assert_sql_success(
+        "CONNECT TO db1;
+         CREATE TABLE b (y INT);
+         INSERT INTO b VALUES (9);
+         CREATE TABLE c (z INT);
+         INSERT INTO c VALUES (10);",
+        &mut interpreter,
+    );
+    assert_eq!(get_rows(test_sql("SELECT x FROM a;", &mut interpreter)), vec![vec!["1"], vec!["2"]]);
+    assert_eq!(get_rows(test_sql("SELECT y FROM b;", &mut interpreter)), vec![vec!["9"]]);
+    assert_eq!(get_rows(test_sql("SELECT z FROM c;", &mut interpreter)), vec![vec!["10"]]);
+}
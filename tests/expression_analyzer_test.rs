@@ -1,13 +1,14 @@
 mod common;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use raincloud_db::compiler::ast::{
     ColumnDef, DataType, ExprType, Expression, Literal, BinaryOp,
 };
-use raincloud_db::compiler::bounded_ast::{BoundExpr, BoundExprNode};
+use raincloud_db::compiler::bounded_ast::{BoundAssignment, BoundExpr, BoundExprNode, BoundStmt};
 use raincloud_db::interpreter::analyzer::Analyzer;
-use raincloud_db::interpreter::catalog_table::TableSchema;
-use raincloud_db::types::ColumnId;
+use raincloud_db::interpreter::catalog_table::{TableOptions, TableSchema};
+use raincloud_db::types::{ColumnId, DbError};
 use crate::common::setup_interpreter;
 
 fn setup_analyzer() -> Analyzer {
@@ -22,10 +23,16 @@ fn test_schema() -> TableSchema {
             ColumnDef {
                 name: "age".to_string(),
                 data_type: DataType::Int,
+                not_null: false,
+                primary_key: false,
+                comment: None,
             },
             ColumnDef {
                 name: "name".to_string(),
                 data_type: DataType::Char(50),
+                not_null: false,
+                primary_key: false,
+                comment: None,
             },
         ],
         column_index: HashMap::from([
@@ -33,6 +40,12 @@ fn test_schema() -> TableSchema {
             ("name".to_string(), 1),
         ]),
         first_page_id: 0,
+        options: TableOptions::default(),
+        physical_order: vec![0, 1],
+        foreign_keys: vec![],
+        null_bitmap: true,
+        indexes: vec![],
+        generation: 0,
     }
 }
 
@@ -115,10 +128,103 @@ fn test_equals_type_mismatch() {
     assert!(analyzer.analyze_expression(&expr, &schema).is_err());
 }
 
+#[test]
+fn test_ordering_comparison() {
+    let analyzer = setup_analyzer();
+    let schema = test_schema();
+    let expr = Expression::Binary {
+        lhs: Box::new(Expression::Identifier("age".to_string())),
+        op: BinaryOp::Lte,
+        rhs: Box::new(Expression::Literal(Literal::Int(18))),
+    };
+    let bound = analyzer.analyze_expression(&expr, &schema).unwrap();
+    assert_eq!(
+        bound,
+        BoundExprNode {
+            expr_type: ExprType::Bool,
+            expr: BoundExpr::Lte(
+                Box::new(BoundExpr::Column(0 as ColumnId)),
+                Box::new(BoundExpr::Literal(Literal::Int(18))),
+            ),
+        }
+    );
+}
+
+#[test]
+fn test_ordering_comparison_on_char_rejected() {
+    let analyzer = setup_analyzer();
+    let schema = test_schema();
+    for op in [BinaryOp::Gt, BinaryOp::Gte, BinaryOp::Lt, BinaryOp::Lte] {
+        let expr = Expression::Binary {
+            lhs: Box::new(Expression::Identifier("name".to_string())),
+            op,
+            rhs: Box::new(Expression::Literal(Literal::String("bob".to_string()))),
+        };
+        assert!(matches!(analyzer.analyze_expression(&expr, &schema), Err(DbError::TypeMismatch(_))));
+    }
+
+    // equality on CHAR is still allowed
+    let expr = Expression::Binary {
+        lhs: Box::new(Expression::Identifier("name".to_string())),
+        op: BinaryOp::NotEq,
+        rhs: Box::new(Expression::Literal(Literal::String("bob".to_string()))),
+    };
+    assert!(analyzer.analyze_expression(&expr, &schema).is_ok());
+}
+
 #[test]
 fn test_unknown_identifier() {
     let analyzer = setup_analyzer();
     let schema = test_schema();
     let expr = Expression::Identifier("height".to_string());
     assert!(analyzer.analyze_expression(&expr, &schema).is_err());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_validate_out_of_range_column() {
+    let mut interpreter = setup_interpreter();
+    interpreter.execute_sql("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE t (id INT, name CHAR(5));").unwrap();
+    let analyzer = Analyzer::new(Arc::clone(&interpreter.context));
+
+    let select = BoundStmt::Select {
+        distinct: false,
+        table: "T".to_string(),
+        generation: 0,
+        columns: vec![BoundExpr::Column(0), BoundExpr::Column(5)],
+        names: vec!["ID".to_string(), "NAME".to_string()],
+        selection: None,
+        group_by: vec![],
+        order_by: vec![],
+    };
+    assert!(matches!(analyzer.validate_bound_stmt(&select), Err(DbError::ColumnNotFound(_))));
+
+    let delete = BoundStmt::Delete {
+        table: "T".to_string(),
+        generation: 0,
+        selection: Some(BoundExprNode {
+            expr_type: ExprType::Bool,
+            expr: BoundExpr::Equals(Box::new(BoundExpr::Column(2)), Box::new(BoundExpr::Literal(Literal::Int(1)))),
+        }),
+    };
+    assert!(matches!(analyzer.validate_bound_stmt(&delete), Err(DbError::ColumnNotFound(_))));
+
+    let update = BoundStmt::Update {
+        table: "T".to_string(),
+        generation: 0,
+        assignments: vec![BoundAssignment { column_id: 2, value: BoundExpr::Literal(Literal::Int(1)) }],
+        selection: None,
+    };
+    assert!(matches!(analyzer.validate_bound_stmt(&update), Err(DbError::ColumnNotFound(_))));
+
+    let valid = BoundStmt::Select {
+        distinct: false,
+        table: "T".to_string(),
+        generation: 0,
+        columns: vec![BoundExpr::Column(0), BoundExpr::Column(1)],
+        names: vec!["ID".to_string(), "NAME".to_string()],
+        selection: None,
+        group_by: vec![],
+        order_by: vec![],
+    };
+    assert!(analyzer.validate_bound_stmt(&valid).is_ok());
+}
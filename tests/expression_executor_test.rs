@@ -234,6 +234,24 @@ fn test_boolean() {
     assert_eq!(executor.execute_expression(&not_expr, &ctx).unwrap(), Literal::Bool(true));
 }
 
+#[test]
+fn test_is_true_is_false() {
+    let executor = setup_executor();
+    let ctx = ExprContext { row: Some(&Record { record: vec![] }) };
+
+    let is_true = |b| BoundExpr::IsTrue(Box::new(BoundExpr::Literal(Literal::Bool(b))));
+    let is_false = |b| BoundExpr::IsFalse(Box::new(BoundExpr::Literal(Literal::Bool(b))));
+
+    assert_eq!(executor.execute_expression(&is_true(true), &ctx).unwrap(), Literal::Bool(true));
+    assert_eq!(executor.execute_expression(&is_true(false), &ctx).unwrap(), Literal::Bool(false));
+    assert_eq!(executor.execute_expression(&is_false(false), &ctx).unwrap(), Literal::Bool(true));
+    assert_eq!(executor.execute_expression(&is_false(true), &ctx).unwrap(), Literal::Bool(false));
+
+    // non-boolean operand
+    let is_int = BoundExpr::IsTrue(Box::new(BoundExpr::Literal(Literal::Int(1))));
+    assert!(executor.execute_expression(&is_int, &ctx).is_err());
+}
+
 #[test]
 fn test_complex_expression() {
     let executor = setup_executor();
@@ -296,4 +314,41 @@ fn test_column_empty_row() {
     };
     let expr = BoundExpr::Column(0);
     assert!(executor.execute_expression(&expr, &ctx).is_err());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_null_three_valued_logic() {
+    let executor = setup_executor();
+    let ctx = ExprContext {
+        row: Some(&Record { record: vec![Literal::Null, Literal::Int(1)] })
+    };
+    let lit = |v: Literal| Box::new(BoundExpr::Literal(v));
+    let eval = |expr: BoundExpr| executor.execute_expression(&expr, &ctx).unwrap();
+    let (null, t, f) = (Literal::Null, Literal::Bool(true), Literal::Bool(false));
+
+    // comparisons and arithmetic with NULL are NULL, even NULL = NULL
+    assert_eq!(eval(BoundExpr::Equals(Box::new(BoundExpr::Column(0)), lit(Literal::Null))), null);
+    assert_eq!(eval(BoundExpr::NotEquals(Box::new(BoundExpr::Column(0)), lit(Literal::Int(1)))), null);
+    assert_eq!(eval(BoundExpr::Lt(Box::new(BoundExpr::Column(1)), lit(Literal::Null))), null);
+    assert_eq!(eval(BoundExpr::Add(Box::new(BoundExpr::Column(0)), lit(Literal::Int(1)))), null);
+    assert_eq!(eval(BoundExpr::Minus(Box::new(BoundExpr::Column(0)))), null);
+
+    // FALSE AND NULL is FALSE, TRUE OR NULL is TRUE, otherwise NULL propagates
+    assert_eq!(eval(BoundExpr::And(lit(null.clone()), lit(f.clone()))), f);
+    assert_eq!(eval(BoundExpr::And(lit(t.clone()), lit(null.clone()))), null);
+    assert_eq!(eval(BoundExpr::Or(lit(null.clone()), lit(t.clone()))), t);
+    assert_eq!(eval(BoundExpr::Or(lit(f.clone()), lit(null.clone()))), null);
+    assert_eq!(eval(BoundExpr::Not(lit(null.clone()))), null);
+
+    // IS tests are never NULL
+    assert_eq!(eval(BoundExpr::IsTrue(lit(null.clone()))), f);
+    assert_eq!(eval(BoundExpr::IsFalse(lit(null.clone()))), f);
+    assert_eq!(eval(BoundExpr::IsNull(Box::new(BoundExpr::Column(0)))), t);
+    assert_eq!(eval(BoundExpr::IsNull(Box::new(BoundExpr::Column(1)))), f);
+
+    // IN is TRUE on a match, NULL if there is no match but a NULL is involved
+    let list = vec![BoundExpr::Literal(Literal::Null), BoundExpr::Literal(Literal::Int(1))];
+    assert_eq!(eval(BoundExpr::In(Box::new(BoundExpr::Column(1)), list.clone())), t);
+    assert_eq!(eval(BoundExpr::In(lit(Literal::Int(2)), list)), null);
+    assert_eq!(eval(BoundExpr::In(Box::new(BoundExpr::Column(0)), vec![BoundExpr::Literal(Literal::Int(1))])), null);
+}
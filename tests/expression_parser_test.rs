@@ -184,4 +184,116 @@ fn test_complex_logical_arith_comparison() {
         }),
     };
     assert_eq!(expr, expected);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_is_true_is_not_false() {
+    // a = 1 IS TRUE AND b IS NOT FALSE -> ((a = 1) IS TRUE) AND (NOT (b IS FALSE))
+    let expr = parse("a = 1 IS TRUE AND b IS NOT FALSE");
+    let expected = Expression::Binary {
+        lhs: Box::new(Expression::Unary {
+            op: UnaryOp::IsTrue,
+            expr: Box::new(Expression::Binary {
+                lhs: Box::new(Expression::Identifier("A".into())),
+                op: BinaryOp::Eq,
+                rhs: Box::new(Expression::Literal(Literal::Int(1))),
+            }),
+        }),
+        op: BinaryOp::And,
+        rhs: Box::new(Expression::Unary {
+            op: UnaryOp::Not,
+            expr: Box::new(Expression::Unary {
+                op: UnaryOp::IsFalse,
+                expr: Box::new(Expression::Identifier("B".into())),
+            }),
+        }),
+    };
+    assert_eq!(expr, expected);
+
+    let mut scanner = Scanner::new("a IS 1");
+    let mut parser = Parser::new(&mut scanner).unwrap();
+    assert!(parser.parse_expression().is_err());
+}
+
+#[test]
+fn test_between_desugars_to_range() {
+    // a BETWEEN 1 AND 2 + 3 AND b  -> ((a >= 1) AND (a <= 2 + 3)) AND b
+    let expr = parse("a BETWEEN 1 AND 2 + 3 AND b");
+    let a = || Box::new(Expression::Identifier("A".to_string()));
+    let expected = Expression::Binary {
+        lhs: Box::new(Expression::Binary {
+            lhs: Box::new(Expression::Binary {
+                lhs: a(),
+                op: BinaryOp::Gte,
+                rhs: Box::new(Expression::Literal(Literal::Int(1))),
+            }),
+            op: BinaryOp::And,
+            rhs: Box::new(Expression::Binary {
+                lhs: a(),
+                op: BinaryOp::Lte,
+                rhs: Box::new(Expression::Binary {
+                    lhs: Box::new(Expression::Literal(Literal::Int(2))),
+                    op: BinaryOp::Add,
+                    rhs: Box::new(Expression::Literal(Literal::Int(3))),
+                }),
+            }),
+        }),
+        op: BinaryOp::And,
+        rhs: Box::new(Expression::Identifier("B".to_string())),
+    };
+    assert_eq!(expr, expected);
+}
+
+#[test]
+fn test_in_list() {
+    // a + 1 IN (1, -2) AND b  -> ((a + 1) IN (1, -2)) AND b
+    let expr = parse("a + 1 IN (1, -2) AND b");
+    let expected = Expression::Binary {
+        lhs: Box::new(Expression::In {
+            target: Box::new(Expression::Binary {
+                lhs: Box::new(Expression::Identifier("A".to_string())),
+                op: BinaryOp::Add,
+                rhs: Box::new(Expression::Literal(Literal::Int(1))),
+            }),
+            list: vec![
+                Expression::Literal(Literal::Int(1)),
+                Expression::Unary {
+                    op: UnaryOp::Neg,
+                    expr: Box::new(Expression::Literal(Literal::Int(2))),
+                },
+            ],
+        }),
+        op: BinaryOp::And,
+        rhs: Box::new(Expression::Identifier("B".to_string())),
+    };
+    assert_eq!(expr, expected);
+
+    // the list cannot be empty
+    for src in ["a IN ()", "a IN (1,)", "a IN 1"] {
+        let mut scanner = Scanner::new(src);
+        let mut parser = Parser::new(&mut scanner).unwrap();
+        assert!(parser.parse_expression().is_err(), "{}", src);
+    }
+}
+
+#[test]
+fn test_null_literal_and_is_null() {
+    // a = NULL OR b IS NOT NULL  ->  (a = NULL) OR NOT (b IS NULL)
+    let expr = parse("a = NULL OR b IS NOT NULL");
+    let expected = Expression::Binary {
+        lhs: Box::new(Expression::Binary {
+            lhs: Box::new(Expression::Identifier("A".to_string())),
+            op: BinaryOp::Eq,
+            rhs: Box::new(Expression::Literal(Literal::Null)),
+        }),
+        op: BinaryOp::Or,
+        rhs: Box::new(Expression::Unary {
+            op: UnaryOp::Not,
+            expr: Box::new(Expression::Unary {
+                op: UnaryOp::IsNull,
+                expr: Box::new(Expression::Identifier("B".to_string())),
+            }),
+        }),
+    };
+    assert_eq!(expr, expected);
+}
@@ -0,0 +1,180 @@
+use paste::paste;
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use std::sync::Arc;
+use tempfile::TempDir;
+use raincloud_db::storage::{StorageConfig, StorageEngine};
+use raincloud_db::storage::migration::format_version;
+use raincloud_db::storage::page::page::Page;
+use raincloud_db::storage::page::super_page::SuperPage;
+use raincloud_db::storage::replacement_strategy::ReplacementStrategyType;
+use raincloud_db::storage::page::data_page::DataPage;
+use raincloud_db::storage::page::index_page::IndexPage;
+use raincloud_db::storage::page::header_page::HeaderPage;
+use raincloud_db::storage::free_list::FreeList;
+use raincloud_db::storage::disk_manager::{DiskManager, FileDiskManager, RetryPolicy};
+use raincloud_db::types::{DATA_FILE, FLUSH, FORMAT_VERSION, HEADER_FILE, MAX_SLOTS, NO_FLUSH, PAGE_CHECKSUM_SIZE, PAGE_SIZE};
+use raincloud_db::{with_create_pages, with_read_pages};
+
+fn storage_config(dir: &TempDir) -> StorageConfig {
+    StorageConfig {
+        database_dir: dir.path().to_path_buf(),
+        bufferpool_capacity: 4,
+        bufferpool_replacement_strategy: ReplacementStrategyType::LRU,
+        checkpoint_interval_ms: None,
+        io_retry: RetryPolicy::default(),
+    }
+}
+
+/// Data page in the version 1 layout, which had no page type tag:
+/// [id: 4][next_id: 4][next_slot: 2][free_start: 2][valid_slots: 32][slots: 4 * MAX_SLOTS][data]
+/// Records are stored from the end of the page; valid marks which of them are not deleted
+fn v1_data_page(page_id: u32, next_id: u32, records: &[(&[u8], bool)]) -> [u8; PAGE_SIZE] {
+    let header_size = 44 + 4 * MAX_SLOTS;
+    let mut buf = [0u8; PAGE_SIZE];
+    let mut free_start = PAGE_SIZE - header_size;
+    buf[0..4].copy_from_slice(&page_id.to_le_bytes());
+    buf[4..8].copy_from_slice(&next_id.to_le_bytes());
+    buf[8..10].copy_from_slice(&(records.len() as u16).to_le_bytes());
+    for (slot, (record, valid)) in records.iter().enumerate() {
+        free_start -= record.len();
+        buf[header_size + free_start..header_size + free_start + record.len()].copy_from_slice(record);
+        buf[44 + 4 * slot..46 + 4 * slot].copy_from_slice(&(free_start as u16).to_le_bytes());
+        buf[46 + 4 * slot..48 + 4 * slot].copy_from_slice(&(record.len() as u16).to_le_bytes());
+        if *valid {
+            buf[12 + slot / 8] |= 1 << (slot % 8);
+        }
+    }
+    buf[10..12].copy_from_slice(&(free_start as u16).to_le_bytes());
+    buf
+}
+
+/// Build a database in the layout before page checksums, with one allocated page holding
+/// the given version 1 page, and the given superpage to emulate another format version
+fn create_fixture_with_page(dir: &TempDir, superpage: [u8; PAGE_SIZE], records: &[(&[u8], bool)]) -> u32 {
+    let header_disk = FileDiskManager::<HeaderPage>::open_unchecked(&dir.path().join(HEADER_FILE)).unwrap();
+    let page_id = FreeList::new(Arc::new(header_disk), 0).allocate(FLUSH);
+
+    let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(dir.path().join(DATA_FILE)).unwrap();
+    file.seek(SeekFrom::Start(page_id as u64 * PAGE_SIZE as u64)).unwrap();
+    file.write_all(&v1_data_page(page_id, 0, records)).unwrap();
+
+    let mut file = OpenOptions::new().write(true).open(dir.path().join(HEADER_FILE)).unwrap();
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.write_all(&superpage).unwrap();
+    page_id
+}
+
+/// Build a version 1 database with one record, stamped with the given superpage
+fn create_fixture(dir: &TempDir, superpage: [u8; PAGE_SIZE]) -> u32 {
+    create_fixture_with_page(dir, superpage, &[(b"legacy record", true)])
+}
+
+#[test]
+fn test_new_database_has_current_format() {
+    let dir = TempDir::new().unwrap();
+    StorageEngine::new(storage_config(&dir)).unwrap();
+    assert_eq!(format_version(dir.path()).unwrap(), Some(FORMAT_VERSION));
+}
+
+#[test]
+fn test_migrate_legacy_database() {
+    let dir = TempDir::new().unwrap();
+
+    // databases created before format versions have no superpage (page 0 is a zeroed hole)
+    let page_id = create_fixture(&dir, [0u8; PAGE_SIZE]);
+    assert_eq!(format_version(dir.path()).unwrap(), None);
+
+    let engine = StorageEngine::new(storage_config(&dir)).unwrap();
+    assert_eq!(format_version(dir.path()).unwrap(), Some(FORMAT_VERSION));
+
+    // existing pages are still readable after migration
+    with_read_pages!(engine.buffer_pool, [(page_id, page)], {
+        assert_eq!(page.get_record(0).unwrap(), b"legacy record");
+    });
+}
+
+#[test]
+fn test_refuse_newer_format() {
+    let dir = TempDir::new().unwrap();
+    create_fixture(&dir, SuperPage { format_version: FORMAT_VERSION + 1 }.serialize());
+
+    let err = StorageEngine::new(storage_config(&dir)).err().expect("newer format must be refused");
+    assert!(err.to_string().contains("newer than supported"));
+    assert_eq!(format_version(dir.path()).unwrap(), Some(FORMAT_VERSION + 1));
+}
+
+#[test]
+fn test_migrate_v1_data_pages_to_tagged_layout() {
+    let dir = TempDir::new().unwrap();
+    let page_id = create_fixture_with_page(
+        &dir,
+        SuperPage { format_version: 1 }.serialize(),
+        &[(b"first", true), (b"deleted", false), (b"third", true)],
+    );
+
+    let engine = StorageEngine::new(storage_config(&dir)).unwrap();
+    assert_eq!(format_version(dir.path()).unwrap(), Some(FORMAT_VERSION));
+
+    // valid records survive in slot order, deleted ones are dropped
+    with_read_pages!(engine.buffer_pool, [(page_id, page)], {
+        let records: Vec<&[u8]> = page.iter_record().map(|(_, record)| record).collect();
+        assert_eq!(records, vec![&b"first"[..], &b"third"[..]]);
+        assert_eq!(page.get_next_id(), 0);
+    });
+}
+
+#[test]
+fn test_read_page_with_wrong_type_fails() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(DATA_FILE);
+    let data_disk = FileDiskManager::<DataPage>::open(&path).unwrap();
+    let index_disk = FileDiskManager::<IndexPage>::open(&path).unwrap();
+
+    let mut page = DataPage::new(1);
+    page.insert_record(b"data").unwrap();
+    data_disk.write_page(&page).unwrap();
+    index_disk.write_page(&IndexPage::new(2, raincloud_db::storage::page::index_page::IndexType::Leaf)).unwrap();
+
+    assert!(data_disk.read_page(1).is_some());
+    assert!(index_disk.read_page(1).is_none(), "a data page must not be read as an index page");
+    assert!(index_disk.read_page(2).is_some());
+    assert!(data_disk.read_page(2).is_none(), "an index page must not be read as a data page");
+}
+
+#[test]
+fn test_migrate_v2_pages_to_checksummed_layout() {
+    let dir = TempDir::new().unwrap();
+    let header_path = dir.path().join(HEADER_FILE);
+    let header_disk = FileDiskManager::<HeaderPage>::open_unchecked(&header_path).unwrap();
+    let page_id = FreeList::new(Arc::new(header_disk), 0).allocate(FLUSH);
+    let mut page = DataPage::new(page_id);
+    page.insert_record(b"v2 record").unwrap();
+    FileDiskManager::<DataPage>::open_unchecked(&dir.path().join(DATA_FILE)).unwrap().write_page(&page).unwrap();
+    FileDiskManager::<SuperPage>::open_unchecked(&header_path).unwrap()
+        .write_page(&SuperPage { format_version: 2 }).unwrap();
+
+    let new_page_id;
+    {
+        let engine = StorageEngine::new(storage_config(&dir)).unwrap();
+        assert_eq!(format_version(dir.path()).unwrap(), Some(FORMAT_VERSION));
+        with_read_pages!(engine.buffer_pool, [(page_id, page)], {
+            assert_eq!(page.get_record(0).unwrap(), b"v2 record");
+        });
+
+        // the migrated free list still has the old page allocated
+        with_create_pages!(engine.buffer_pool, [(new_page_id, page)], NO_FLUSH, {
+            page.set_next_id(0);
+        });
+        assert_ne!(new_page_id, page_id);
+        engine.deactivate().unwrap();
+    }
+
+    // every page is followed by its checksum, and both files still read after reopening
+    let data_len = std::fs::metadata(dir.path().join(DATA_FILE)).unwrap().len() as usize;
+    assert_eq!(data_len, (new_page_id.max(page_id) as usize + 1) * (PAGE_SIZE + PAGE_CHECKSUM_SIZE));
+    let engine = StorageEngine::new(storage_config(&dir)).unwrap();
+    with_read_pages!(engine.buffer_pool, [(page_id, page)], {
+        assert_eq!(page.get_record(0).unwrap(), b"v2 record");
+    });
+}
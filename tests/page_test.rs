@@ -1,6 +1,7 @@
-use raincloud_db::storage::page::data_page::DataPage;
+use raincloud_db::storage::page::data_page::{DataPage, PAYLOAD_SIZE};
 use raincloud_db::storage::page::page::Page;
-use raincloud_db::types::MAX_SLOTS;
+use raincloud_db::storage::{StorageConfig, StorageEngine};
+use raincloud_db::types::{MAX_SLOTS, NO_FLUSH};
 
 #[test]
 fn test_insert_and_get_record() {
@@ -158,4 +159,41 @@ fn test_full_page_lifecycle() {
             deserialized.get_record(slot_id)
         );
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_overflow_records_through_storage_engine() {
+    let engine = StorageEngine::in_memory(StorageConfig::default());
+    let mut page = DataPage::new(engine.free_list.allocate(NO_FLUSH));
+    let wide: Vec<u8> = (0..3 * PAYLOAD_SIZE).map(|i| i as u8).collect();
+
+    // a record larger than a page is spilled to overflow pages and read back whole
+    let small = page.insert_record(b"small").unwrap();
+    let slot = engine.insert_record(&mut page, &wide).unwrap();
+    let overflow_id = page.get_overflow(slot).unwrap();
+    assert_eq!(engine.get_record(&page, slot).unwrap(), wide.as_slice());
+    let records: Vec<_> = engine.iter_record(&page).map(|(slot_id, bytes)| (slot_id, bytes.into_owned())).collect();
+    assert_eq!(records, vec![(small, b"small".to_vec()), (slot, wide.clone())]);
+
+    // a record shrinking back into the page frees its overflow pages
+    engine.update_record(&mut page, slot, b"short").unwrap();
+    assert_eq!(page.get_overflow(slot), None);
+    assert_eq!(engine.get_record(&page, slot).unwrap(), b"short".as_slice());
+    let reused = engine.free_list.allocate_batch(3);
+    assert!(reused.contains(&overflow_id), "{:?} should reuse page {}", reused, overflow_id);
+    for page_id in reused {
+        engine.free_list.deallocate(page_id, NO_FLUSH).unwrap();
+    }
+
+    // a record growing beyond the free space of its page keeps its slot and chains to overflow pages
+    let grown = vec![7u8; page.get_free_space() + 100];
+    engine.update_record(&mut page, small, &grown).unwrap();
+    assert!(page.get_overflow(small).is_some());
+    assert_eq!(engine.get_record(&page, small).unwrap(), grown.as_slice());
+    assert!(page.check_invariants().is_ok());
+
+    // deleting the record frees its overflow pages
+    let overflow_id = page.get_overflow(small).unwrap();
+    engine.delete_record(&mut page, small).unwrap();
+    assert!(engine.get_record(&page, small).is_none());
+    assert!(engine.free_list.allocate_batch(3).contains(&overflow_id));
+}
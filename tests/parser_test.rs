@@ -1,6 +1,7 @@
 use raincloud_db::compiler::scanner::Scanner;
 use raincloud_db::compiler::parser::Parser;
 use raincloud_db::compiler::ast::*;
+use raincloud_db::types::MAX_CHAR_LENGTH;
 
 fn parse_sql(sql: &str) -> Vec<Statement> {
     let mut scanner = Scanner::new(sql);
@@ -56,7 +57,7 @@ fn test_create_table() {
     let stmts = parse_sql(sql);
 
     match &stmts[0] {
-        Statement::CreateTable { name, columns } => {
+        Statement::CreateTable { name, columns, .. } => {
             assert_eq!(name, "USERS");
             assert_eq!(columns.len(), 2);
             assert_eq!(columns[0].name, "ID");
@@ -68,13 +69,38 @@ fn test_create_table() {
     }
 }
 
+#[test]
+fn test_create_table_char_length_bounds() {
+    let parse_err = |sql: &str| {
+        let mut scanner = Scanner::new(sql);
+        let mut parser = Parser::new(&mut scanner).unwrap();
+        parser.parse().is_err()
+    };
+
+    // zero, negative and over-large lengths are rejected
+    assert!(parse_err("CREATE TABLE t (name CHAR(0));"));
+    assert!(parse_err("CREATE TABLE t (name CHAR(-5));"));
+    assert!(parse_err(&format!("CREATE TABLE t (name CHAR({}));", MAX_CHAR_LENGTH + 1)));
+    assert!(parse_err("CREATE TABLE t (name CHAR(2147483647));"));
+
+    // boundary lengths are accepted
+    let stmts = parse_sql(&format!("CREATE TABLE t (a CHAR(1), b CHAR({}));", MAX_CHAR_LENGTH));
+    match &stmts[0] {
+        Statement::CreateTable { columns, .. } => {
+            assert_eq!(columns[0].data_type, DataType::Char(1));
+            assert_eq!(columns[1].data_type, DataType::Char(MAX_CHAR_LENGTH));
+        }
+        _ => panic!("Expected CreateTable"),
+    }
+}
+
 #[test]
 fn test_insert() {
     let sql = "INSERT INTO users VALUES (1, 'Alice');";
     let stmts = parse_sql(sql);
 
     match &stmts[0] {
-        Statement::Insert { table, rows } => {
+        Statement::Insert { table, rows, .. } => {
             assert_eq!(table, "USERS");
             assert_eq!(rows.len(), 1);
 
@@ -100,7 +126,7 @@ fn test_insert_multiple() {
     let stmts = parse_sql(sql);
 
     match &stmts[0] {
-        Statement::Insert { table, rows } => {
+        Statement::Insert { table, rows, .. } => {
             assert_eq!(table, "USERS");
             assert_eq!(rows.len(), 2);
 
@@ -132,6 +158,44 @@ fn test_insert_multiple() {
     }
 }
 
+#[test]
+fn test_insert_three_value_groups() {
+    let stmts = parse_sql("INSERT INTO users VALUES (1, 'a'), (2, 'b'), (3, 'c');");
+
+    match &stmts[0] {
+        Statement::Insert { rows, .. } => {
+            assert_eq!(rows.len(), 3);
+            for (i, row) in rows.iter().enumerate() {
+                assert_eq!(row.len(), 2);
+                assert_eq!(row[0], Expression::Literal(Literal::Int(i as i32 + 1)));
+            }
+        }
+        _ => panic!("Expected Insert statement"),
+    }
+
+    // a trailing comma is not an empty value group
+    let mut scanner = Scanner::new("INSERT INTO users VALUES (1, 'a'),;");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_insert_with_column_list() {
+    let stmts = parse_sql("INSERT INTO users (name, id) VALUES ('Bob', 1); INSERT INTO users VALUES (2, 'Ann');");
+
+    match &stmts[0] {
+        Statement::Insert { columns, rows, .. } => {
+            assert_eq!(columns, &vec!["NAME".to_string(), "ID".to_string()]);
+            assert_eq!(rows[0][0], Expression::Literal(Literal::String("Bob".to_string())));
+        }
+        _ => panic!("Expected Insert statement"),
+    }
+    assert!(matches!(&stmts[1], Statement::Insert { columns, .. } if columns.is_empty()));
+
+    // the column list cannot be empty
+    let mut scanner = Scanner::new("INSERT INTO users () VALUES (1);");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
 #[test]
 fn test_insert_multiple_expression_rows() {
     let sql = "INSERT INTO users VALUES (1+2, 3*4), (10-5, 8/2);";
@@ -210,7 +274,7 @@ fn test_insert_with_add_expression() {
     let stmts = parse_sql(sql);
 
     match &stmts[0] {
-        Statement::Insert { table, rows } => {
+        Statement::Insert { table, rows, .. } => {
             assert_eq!(table, "USERS");
             assert_eq!(rows.len(), 1);
 
@@ -289,7 +353,7 @@ fn test_select_with_where() {
     let stmts = parse_sql(sql);
 
     match &stmts[0] {
-        Statement::Select { columns, table, selection } => {
+        Statement::Select { columns, table, selection, .. } => {
 
             assert_eq!(table, "USERS");
             assert_eq!(columns.len(), 2);
@@ -316,7 +380,7 @@ fn test_select_arithmetic_projection() {
     let stmts = parse_sql(sql);
 
     match &stmts[0] {
-        Statement::Select { columns, table, selection } => {
+        Statement::Select { columns, table, selection, .. } => {
 
             assert_eq!(table, "USERS");
             assert!(selection.is_none());
@@ -348,6 +412,154 @@ fn test_select_multiple_expressions() {
     }
 }
 
+#[test]
+fn test_select_without_from() {
+    let stmts = parse_sql("SELECT 1 + 1, 'hello' AS greeting;");
+
+    match &stmts[0] {
+        Statement::SelectConstant { columns, aliases } => {
+            assert_eq!(columns.len(), 2);
+            assert!(matches!(columns[0], Expression::Binary { op: BinaryOp::Add, .. }));
+            assert_eq!(aliases[1].as_deref(), Some("GREETING"));
+        }
+        _ => panic!(),
+    }
+
+    // there are no columns to expand * over
+    let mut scanner = Scanner::new("SELECT *;");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_set() {
+    let stmts = parse_sql("SET trim_char_output = TRUE;");
+    assert!(matches!(&stmts[0], Statement::Set { name, value: Literal::Bool(true) } if name == "TRIM_CHAR_OUTPUT"));
+
+    let mut scanner = Scanner::new("SET trim_char_output TRUE;");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_dump_page() {
+    let stmts = parse_sql("DUMP PAGE 3;");
+    assert!(matches!(stmts[0], Statement::DumpPage { page_id: 3 }));
+
+    // DUMP stays usable as a name
+    let stmts = parse_sql("SELECT dump FROM t;");
+    assert!(matches!(stmts[0], Statement::Select { .. }));
+
+    for sql in ["DUMP PAGE 0;", "DUMP 3;", "DUMP PAGE t;"] {
+        let mut scanner = Scanner::new(sql);
+        assert!(Parser::new(&mut scanner).unwrap().parse().is_err(), "{}", sql);
+    }
+}
+
+#[test]
+fn test_show_table_status() {
+    let stmts = parse_sql("SHOW TABLE STATUS;");
+    assert!(matches!(stmts[0], Statement::ShowTableStatus));
+
+    for sql in ["SHOW TABLE;", "SHOW STATUS;", "SHOW TABLE STATS;"] {
+        let mut scanner = Scanner::new(sql);
+        assert!(Parser::new(&mut scanner).unwrap().parse().is_err(), "{}", sql);
+    }
+}
+
+#[test]
+fn test_show_create_table_and_describe() {
+    let stmts = parse_sql("SHOW CREATE TABLE users; DESCRIBE users;");
+    assert!(matches!(&stmts[0], Statement::ShowCreateTable { table } if table == "USERS"));
+    assert!(matches!(&stmts[1], Statement::Describe { table } if table == "USERS"));
+
+    // DESCRIBE stays usable as a name
+    let stmts = parse_sql("SELECT describe FROM t;");
+    assert!(matches!(stmts[0], Statement::Select { .. }));
+
+    for sql in ["SHOW CREATE users;", "SHOW CREATE TABLE;", "DESCRIBE;", "DESCRIBE TABLE users;"] {
+        let mut scanner = Scanner::new(sql);
+        assert!(Parser::new(&mut scanner).unwrap().parse().is_err(), "{}", sql);
+    }
+}
+
+#[test]
+fn test_show_records() {
+    let stmts = parse_sql("SHOW RECORDS FROM users WHERE id = 1; SHOW RECORDS FROM users;");
+    assert!(matches!(&stmts[0], Statement::ShowRecords { table, selection: Some(_) } if table == "USERS"));
+    assert!(matches!(&stmts[1], Statement::ShowRecords { table, selection: None } if table == "USERS"));
+
+    for sql in ["SHOW RECORDS users;", "SHOW RECORDS FROM;", "SHOW ROWS FROM users;"] {
+        let mut scanner = Scanner::new(sql);
+        assert!(Parser::new(&mut scanner).unwrap().parse().is_err(), "{}", sql);
+    }
+}
+
+#[test]
+fn test_select_star() {
+    let stmts = parse_sql("SELECT * FROM users WHERE id = 1;");
+
+    match &stmts[0] {
+        Statement::Select { columns, aliases, table, selection, .. } => {
+            assert!(columns.is_empty() && aliases.is_empty());
+            assert_eq!(table, "USERS");
+            assert!(selection.is_some());
+        }
+        _ => panic!(),
+    }
+
+    // * cannot be mixed with other select items
+    let mut scanner = Scanner::new("SELECT *, id FROM users;");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_select_count() {
+    let stmts = parse_sql("SELECT COUNT(*), count(id), MAX(age) FROM users;");
+    let column = |name: &str| Some(Box::new(Expression::Identifier(name.to_string())));
+
+    match &stmts[0] {
+        Statement::Select { columns, .. } => {
+            assert_eq!(columns[0], Expression::Aggregate { func: AggregateFunc::Count, arg: None });
+            assert_eq!(columns[1], Expression::Aggregate { func: AggregateFunc::Count, arg: column("ID") });
+            assert_eq!(columns[2], Expression::Aggregate { func: AggregateFunc::Max, arg: column("AGE") });
+        }
+        _ => panic!(),
+    }
+
+    // only COUNT takes *, and unknown functions are rejected
+    for sql in ["SELECT SUM(*) FROM users;", "SELECT MEDIAN(id) FROM users;"] {
+        let mut scanner = Scanner::new(sql);
+        assert!(Parser::new(&mut scanner).unwrap().parse().is_err(), "{}", sql);
+    }
+}
+
+#[test]
+fn test_select_distinct() {
+    let stmts = parse_sql("SELECT DISTINCT name, age FROM users; SELECT DISTINCT * FROM users; SELECT name FROM users;");
+
+    assert!(matches!(&stmts[0], Statement::Select { distinct: true, columns, .. } if columns.len() == 2));
+    assert!(matches!(&stmts[1], Statement::Select { distinct: true, columns, .. } if columns.is_empty()));
+    assert!(matches!(&stmts[2], Statement::Select { distinct: false, .. }));
+}
+
+#[test]
+fn test_select_group_by() {
+    let stmts = parse_sql("SELECT age, name, COUNT(*) FROM users WHERE id > 1 GROUP BY age, name ORDER BY age;");
+
+    match &stmts[0] {
+        Statement::Select { columns, selection, group_by, order_by, .. } => {
+            assert_eq!(columns.len(), 3);
+            assert!(selection.is_some());
+            assert_eq!(group_by, &vec!["AGE".to_string(), "NAME".to_string()]);
+            assert_eq!(order_by.len(), 1);
+        }
+        _ => panic!(),
+    }
+
+    // GROUP BY takes column names only
+    let mut scanner = Scanner::new("SELECT COUNT(*) FROM users GROUP BY age + 1;");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
 #[test]
 fn test_select_complex_expression() {
     let sql = "
@@ -406,6 +618,106 @@ fn test_update_multiple_assignments() {
     }
 }
 
+#[test]
+fn test_create_table_with_options() {
+    let sql = "CREATE TABLE users (id INT) WITH (fillfactor = 80);";
+    let stmts = parse_sql(sql);
+
+    match &stmts[0] {
+        Statement::CreateTable { name, options, .. } => {
+            assert_eq!(name, "USERS");
+            assert_eq!(options.len(), 1);
+            assert_eq!(options[0].name, "FILLFACTOR");
+            assert_eq!(options[0].value, Literal::Int(80));
+        }
+        _ => panic!("Expected CreateTable"),
+    }
+}
+
+#[test]
+fn test_create_table_with_foreign_key() {
+    let sql = "CREATE TABLE orders (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES users (id));";
+    let stmts = parse_sql(sql);
+
+    match &stmts[0] {
+        Statement::CreateTable { columns, foreign_keys, .. } => {
+            assert_eq!(columns.len(), 2);
+            assert_eq!(foreign_keys.len(), 1);
+            assert_eq!(foreign_keys[0].column, "USER_ID");
+            assert_eq!(foreign_keys[0].ref_table, "USERS");
+            assert_eq!(foreign_keys[0].ref_column, "ID");
+            assert_eq!(foreign_keys[0].on_delete, ReferentialAction::Restrict);
+        }
+        _ => panic!("Expected CreateTable"),
+    }
+
+    let sql = "CREATE TABLE orders (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE);";
+    match &parse_sql(sql)[0] {
+        Statement::CreateTable { foreign_keys, .. } => assert_eq!(foreign_keys[0].on_delete, ReferentialAction::Cascade),
+        _ => panic!("Expected CreateTable"),
+    }
+
+    let sql = "CREATE TABLE orders (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL);";
+    match &parse_sql(sql)[0] {
+        Statement::CreateTable { foreign_keys, .. } => assert_eq!(foreign_keys[0].on_delete, ReferentialAction::SetNull),
+        _ => panic!("Expected CreateTable"),
+    }
+
+    let sql = "CREATE TABLE orders (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET DEFAULT);";
+    let mut scanner = Scanner::new(sql);
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_create_table_with_not_null() {
+    let sql = "CREATE TABLE t (id INT NOT NULL, name CHAR(5));";
+    match &parse_sql(sql)[0] {
+        Statement::CreateTable { columns, .. } => {
+            assert_eq!(columns[0], ColumnDef { name: "ID".to_string(), data_type: DataType::Int, not_null: true, primary_key: false, comment: None });
+            assert_eq!(columns[1], ColumnDef { name: "NAME".to_string(), data_type: DataType::Char(5), not_null: false, primary_key: false, comment: None });
+        }
+        _ => panic!("Expected CreateTable"),
+    }
+
+    // NOT must be followed by NULL
+    let mut scanner = Scanner::new("CREATE TABLE t (id INT NOT, name CHAR(5));");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_create_table_with_primary_key() {
+    let sql = "CREATE TABLE t (id INT PRIMARY KEY, name CHAR(5) NOT NULL);";
+    match &parse_sql(sql)[0] {
+        Statement::CreateTable { columns, .. } => {
+            // PRIMARY KEY implies NOT NULL
+            assert_eq!(columns[0], ColumnDef { name: "ID".to_string(), data_type: DataType::Int, not_null: true, primary_key: true, comment: None });
+            assert_eq!(columns[1], ColumnDef { name: "NAME".to_string(), data_type: DataType::Char(5), not_null: true, primary_key: false, comment: None });
+        }
+        _ => panic!("Expected CreateTable"),
+    }
+
+    let mut scanner = Scanner::new("CREATE TABLE t (id INT PRIMARY, name CHAR(5));");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_create_table_with_column_comment() {
+    let sql = "CREATE TABLE t (id INT PRIMARY KEY COMMENT 'primary key', comment CHAR(5) COMMENT \"it's\");";
+    match &parse_sql(sql)[0] {
+        Statement::CreateTable { columns, .. } => {
+            assert_eq!(columns[0].comment.as_deref(), Some("primary key"));
+            assert!(columns[0].primary_key);
+            // COMMENT stays usable as a column name
+            assert_eq!(columns[1].name, "COMMENT");
+            assert_eq!(columns[1].comment.as_deref(), Some("it's"));
+        }
+        _ => panic!("Expected CreateTable"),
+    }
+
+    let mut scanner = Scanner::new("CREATE TABLE t (id INT COMMENT 5);");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
 #[test]
 fn test_drop_table() {
     let sql = "DROP TABLE users;";
@@ -416,6 +728,32 @@ fn test_drop_table() {
     }
 }
 
+#[test]
+fn test_create_index() {
+    let sql = "CREATE INDEX idx ON users (id);";
+    assert_eq!(parse_sql(sql)[0], Statement::CreateIndex {
+        name: "IDX".to_string(),
+        table: "USERS".to_string(),
+        column: "ID".to_string(),
+    });
+
+    for sql in ["CREATE INDEX idx users (id);", "CREATE INDEX idx ON users id;", "CREATE INDEX ON users (id);"] {
+        let mut scanner = Scanner::new(sql);
+        assert!(Parser::new(&mut scanner).unwrap().parse().is_err(), "{}", sql);
+    }
+}
+
+#[test]
+fn test_drop_index() {
+    assert_eq!(parse_sql("DROP INDEX idx ON users;")[0], Statement::DropIndex {
+        name: "IDX".to_string(),
+        table: "USERS".to_string(),
+    });
+
+    let mut scanner = Scanner::new("DROP INDEX idx;");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
 #[test]
 fn test_multiple_statements() {
     let sql = "
@@ -436,4 +774,106 @@ fn test_parse_error_missing_semicolon() {
     let mut scanner = Scanner::new(sql);
     let mut parser = Parser::new(&mut scanner).unwrap();
     parser.parse().unwrap_err();
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_parameters() {
+    let sql = "INSERT INTO users VALUES (?, ?); SELECT id FROM users WHERE id > ?;";
+    let stmts = parse_sql(sql);
+
+    match &stmts[0] {
+        Statement::Insert { rows, .. } => {
+            assert_eq!(rows[0], vec![Expression::Parameter(0), Expression::Parameter(1)]);
+        }
+        _ => panic!("Expected Insert"),
+    }
+
+    // parameters are numbered per statement
+    match &stmts[1] {
+        Statement::Select { selection: Some(Expression::Binary { rhs, .. }), .. } => {
+            assert_eq!(**rhs, Expression::Parameter(0));
+        }
+        _ => panic!("Expected Select with WHERE"),
+    }
+}
+
+#[test]
+fn test_select_alias_and_order_by() {
+    let sql = "SELECT id AS key, name FROM users ORDER BY key DESC, 2;";
+    let stmts = parse_sql(sql);
+
+    match &stmts[0] {
+        Statement::Select { aliases, order_by, .. } => {
+            assert_eq!(aliases, &vec![Some("KEY".to_string()), None]);
+            assert_eq!(order_by, &vec![
+                OrderByItem { expr: Expression::Identifier("KEY".to_string()), descending: true },
+                OrderByItem { expr: Expression::Literal(Literal::Int(2)), descending: false },
+            ]);
+        }
+        _ => panic!("Expected Select"),
+    }
+}
+
+#[test]
+fn test_parse_explain() {
+    let stmts = parse_sql("EXPLAIN SELECT id FROM users; EXPLAIN ANALYZE SELECT id FROM users;");
+
+    match &stmts[0] {
+        Statement::Explain { analyze, stmt } => {
+            assert!(!analyze);
+            assert!(matches!(**stmt, Statement::Select { .. }));
+        }
+        _ => panic!("Expected Explain"),
+    }
+    assert!(matches!(&stmts[1], Statement::Explain { analyze: true, .. }));
+
+    // only SELECT can be explained
+    let mut scanner = Scanner::new("EXPLAIN DELETE FROM users;");
+    let mut parser = Parser::new(&mut scanner).unwrap();
+    assert!(parser.parse().is_err());
+}
+
+#[test]
+fn test_parse_purge() {
+    let stmts = parse_sql("PURGE users;");
+    assert_eq!(stmts, vec![Statement::Purge { table: "USERS".to_string() }]);
+}
+
+#[test]
+fn test_parse_insert_conflict_policy() {
+    let stmts = parse_sql("INSERT INTO t VALUES (1); INSERT OR REPLACE INTO t VALUES (1); INSERT OR IGNORE INTO t (a) VALUES (1);");
+    let policies: Vec<ConflictPolicy> = stmts.iter()
+        .map(|stmt| match stmt {
+            Statement::Insert { on_conflict, .. } => on_conflict.clone(),
+            other => panic!("Expected Insert statement, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(policies, vec![ConflictPolicy::Abort, ConflictPolicy::Replace, ConflictPolicy::Ignore]);
+
+    let mut scanner = Scanner::new("INSERT OR UPDATE INTO t VALUES (1);");
+    assert!(Parser::new(&mut scanner).unwrap().parse().is_err());
+}
+
+#[test]
+fn test_parse_insert_on_conflict_do_update() {
+    let stmts = parse_sql("INSERT INTO t VALUES (1, 2) ON CONFLICT (id) DO UPDATE SET hits = hits + 1, name = 'x';");
+    match &stmts[0] {
+        Statement::Insert { on_conflict: ConflictPolicy::Update { column, assignments }, rows, .. } => {
+            assert_eq!(column, "ID");
+            assert_eq!(rows.len(), 1);
+            let columns: Vec<&str> = assignments.iter().map(|a| a.column.as_str()).collect();
+            assert_eq!(columns, vec!["HITS", "NAME"]);
+        }
+        other => panic!("Expected Insert with ON CONFLICT DO UPDATE, got {:?}", other),
+    }
+
+    for sql in [
+        "INSERT OR IGNORE INTO t VALUES (1) ON CONFLICT (id) DO UPDATE SET a = 1;",
+        "INSERT INTO t VALUES (1) ON CONFLICT id DO UPDATE SET a = 1;",
+        "INSERT INTO t VALUES (1) ON CONFLICT (id) DO NOTHING;",
+        "INSERT INTO t VALUES (1) ON CONFLICT (id) DO UPDATE SET;",
+    ] {
+        let mut scanner = Scanner::new(sql);
+        assert!(Parser::new(&mut scanner).unwrap().parse().is_err(), "{}", sql);
+    }
+}
@@ -1,5 +1,5 @@
 use raincloud_db::compiler::scanner::Scanner;
-use raincloud_db::compiler::token::TokenType;
+use raincloud_db::compiler::token::{Span, TokenType};
 use std::panic;
 use raincloud_db::types::DbError;
 
@@ -428,4 +428,49 @@ fn test_unexpected_character_error_correct_line() {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_tokenize_all_spans() {
+    let sql = "SELECT id,\n  name FROM users\nWHERE name = 'a\nb';";
+    let tokens = Scanner::new(sql).tokenize_all();
+
+    let spans: Vec<(TokenType, Span)> = tokens.into_iter().map(|t| (t.token_type, t.span)).collect();
+    let span = |line, column, end_line, end_column| Span { line, column, end_line, end_column };
+    assert_eq!(
+        spans,
+        vec![
+            (TokenType::Select, span(1, 1, 1, 7)),
+            (TokenType::Identifier("ID".into()), span(1, 8, 1, 10)),
+            (TokenType::Comma, span(1, 10, 1, 11)),
+            (TokenType::Identifier("NAME".into()), span(2, 3, 2, 7)),
+            (TokenType::From, span(2, 8, 2, 12)),
+            (TokenType::Identifier("USERS".into()), span(2, 13, 2, 18)),
+            (TokenType::Where, span(3, 1, 3, 6)),
+            (TokenType::Identifier("NAME".into()), span(3, 7, 3, 11)),
+            (TokenType::Equal, span(3, 12, 3, 13)),
+            // a string literal may span lines
+            (TokenType::StringLiteral("a\nb".into()), span(3, 14, 4, 3)),
+            (TokenType::Semicolon, span(4, 3, 4, 4)),
+            (TokenType::Eof, span(4, 4, 4, 4)),
+        ]
+    );
+}
+
+#[test]
+fn test_tokenize_all_reports_errors_as_tokens() {
+    let tokens = Scanner::new("SELECT @ FROM t WHERE id = 99999999999;\n'open").tokenize_all();
+    let types: Vec<&TokenType> = tokens.iter().map(|t| &t.token_type).collect();
+
+    // scanning continues after each invalid token
+    assert!(matches!(types[1], TokenType::Error(_)));
+    assert_eq!(tokens[1].lexeme, "@");
+    assert_eq!(tokens[1].span, Span { line: 1, column: 8, end_line: 1, end_column: 9 });
+    assert_eq!(types[2], &TokenType::From);
+    assert!(matches!(types[7], TokenType::Error(msg) if msg.contains("out of range")));
+    assert_eq!(tokens[7].lexeme, "99999999999");
+    assert_eq!(types[8], &TokenType::Semicolon);
+    assert!(matches!(types[9], TokenType::Error(msg) if msg.contains("unterminated")));
+    assert_eq!(tokens[9].span.line, 2);
+    assert_eq!(types[10], &TokenType::Eof);
+}
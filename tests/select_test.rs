@@ -1,6 +1,9 @@
 mod common;
 
-use crate::common::{setup_interpreter, assert_sql_success, test_sql, get_rows};
+use raincloud_db::compiler::ast::Literal;
+use raincloud_db::interpreter::{ExecResult, Interpreter};
+use raincloud_db::types::DbError;
+use crate::common::{setup_interpreter, assert_sql_success, assert_sql_failure, test_sql, get_rows};
 
 #[test]
 fn test_select_no_condition() {
@@ -202,3 +205,717 @@ fn test_select_constant_expression() {
     assert_eq!(rows[0], vec!["1", "3", "0"]);
     assert_eq!(rows[1], vec!["2", "3", "0"]);
 }
+
+#[test]
+fn test_select_order_by_alias() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, val INT);
+         INSERT INTO t VALUES (1, 30), (2, 10), (3, 20);",
+        &mut interpreter,
+    );
+
+    let rows = get_rows(test_sql(
+        "SELECT id, val * 2 AS double FROM t ORDER BY double;",
+        &mut interpreter,
+    ));
+    assert_eq!(rows, vec![vec!["2", "20"], vec!["3", "40"], vec!["1", "60"]]);
+
+    // alias takes precedence over a table column with the same name
+    let rows = get_rows(test_sql(
+        "SELECT id, 0 - val AS id FROM t ORDER BY id DESC;",
+        &mut interpreter,
+    ));
+    assert_eq!(rows, vec![vec!["2", "-10"], vec!["3", "-20"], vec!["1", "-30"]]);
+}
+
+#[test]
+fn test_select_order_by_position() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, val INT);
+         INSERT INTO t VALUES (1, 30), (2, 10), (3, 20), (4, 10);",
+        &mut interpreter,
+    );
+
+    let rows = get_rows(test_sql(
+        "SELECT id, val FROM t ORDER BY 2 DESC, 1 ASC;",
+        &mut interpreter,
+    ));
+    assert_eq!(rows, vec![vec!["1", "30"], vec!["3", "20"], vec!["2", "10"], vec!["4", "10"]]);
+
+    let rows = get_rows(test_sql(
+        "SELECT val + id FROM t ORDER BY 1;",
+        &mut interpreter,
+    ));
+    assert_eq!(rows, vec![vec!["12"], vec!["14"], vec!["23"], vec!["31"]]);
+
+    // position out of range
+    assert_sql_failure("SELECT id, val FROM t ORDER BY 3;", &mut interpreter);
+    assert_sql_failure("SELECT id FROM t ORDER BY 0;", &mut interpreter);
+}
+
+/// extract the value following "actual rows=" in an EXPLAIN ANALYZE plan line
+fn actual_rows(line: &str) -> usize {
+    let start = line.find("actual rows=").expect("missing actual rows") + "actual rows=".len();
+    line[start..].split(' ').next().unwrap().parse().unwrap()
+}
+
+fn actual_time(line: &str) -> f64 {
+    let start = line.find("time=").expect("missing actual time") + "time=".len();
+    line[start..].split("ms").next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn test_explain_analyze_row_count() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, val INT);
+         INSERT INTO t VALUES (1, 30), (2, 10), (3, 20), (4, 10), (5, 50);",
+        &mut interpreter,
+    );
+
+    let query = "SELECT id FROM t WHERE val > 15 ORDER BY id DESC;";
+    let rows = get_rows(test_sql(query, &mut interpreter));
+    assert_eq!(rows.len(), 3);
+
+    let plan = get_rows(test_sql(&format!("EXPLAIN ANALYZE {}", query), &mut interpreter));
+    assert!(plan[0][0].starts_with("Sort"));
+    assert_eq!(actual_rows(&plan[0][0]), rows.len());
+    assert!(plan[1][0].trim_start().starts_with("Filter"));
+    assert_eq!(actual_rows(&plan[1][0]), rows.len());
+    assert!(plan[2][0].trim_start().starts_with("Seq Scan on T"));
+    assert_eq!(actual_rows(&plan[2][0]), 5);
+    // the filter is timed on its own, as part of the scan
+    assert!(actual_time(&plan[1][0]) <= actual_time(&plan[2][0]));
+    assert!(plan[3][0].starts_with("Execution Time:"));
+
+    // plain EXPLAIN does not execute the query
+    let plan = get_rows(test_sql("EXPLAIN SELECT id FROM t;", &mut interpreter));
+    assert_eq!(plan, vec![vec!["Seq Scan on T"]]);
+}
+
+#[test]
+fn test_select_is_true_is_false() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, val INT);
+         INSERT INTO t VALUES (1, 30), (2, 10), (3, 20);",
+        &mut interpreter,
+    );
+
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE val > 15 IS TRUE;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["3"]]);
+
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE val > 15 IS NOT TRUE;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2"]]);
+
+    let rows = get_rows(test_sql("SELECT id, val = 10 IS FALSE FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1", "TRUE"], vec!["2", "FALSE"], vec!["3", "TRUE"]]);
+
+    // IS TRUE requires a boolean operand
+    assert_sql_failure("SELECT id FROM t WHERE val IS TRUE;", &mut interpreter);
+}
+
+#[test]
+fn test_select_unknown_column_lists_candidates() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+             CREATE TABLE users (id INT, name CHAR(5));",
+        &mut interpreter,
+    );
+
+    // projection with a typo suggests the closest column
+    match &test_sql("SELECT nmae FROM users;", &mut interpreter)[0] {
+        Err(DbError::ColumnNotFound(msg)) => {
+            assert!(msg.contains("'NMAE'") && msg.contains("USERS"), "{}", msg);
+            assert!(msg.contains("did you mean 'NAME'?"), "{}", msg);
+            assert!(msg.contains("Available columns: ID, NAME"), "{}", msg);
+        }
+        other => panic!("Expected ColumnNotFound, got {:?}", other),
+    }
+
+    // WHERE reports the same candidates, without a suggestion for an unrelated name
+    match &test_sql("SELECT id FROM users WHERE salary > 1;", &mut interpreter)[0] {
+        Err(DbError::ColumnNotFound(msg)) => {
+            assert!(msg.contains("'SALARY'"), "{}", msg);
+            assert!(!msg.contains("did you mean"), "{}", msg);
+            assert!(msg.contains("Available columns: ID, NAME"), "{}", msg);
+        }
+        other => panic!("Expected ColumnNotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_select_ordering_comparisons() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT, age INT, name CHAR(5));
+         INSERT INTO users VALUES (1, 17, 'ann'), (2, 18, 'bob'), (3, 40, 'cid');",
+        &mut interpreter,
+    );
+
+    let rows = get_rows(test_sql("SELECT id FROM users WHERE age > 18;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["3".to_string()]]);
+
+    let rows = get_rows(test_sql("SELECT id FROM users WHERE age >= 18 AND age != 40;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2".to_string()]]);
+
+    let rows = get_rows(test_sql("SELECT id FROM users WHERE age <= 18;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1".to_string()], vec!["2".to_string()]]);
+
+    // CHAR columns only support equality
+    match &test_sql("SELECT id FROM users WHERE name < 'bob';", &mut interpreter)[0] {
+        Err(DbError::TypeMismatch(_)) => {}
+        other => panic!("Expected TypeMismatch, got {:?}", other),
+    }
+    let rows = get_rows(test_sql("SELECT id FROM users WHERE name != 'bob';", &mut interpreter));
+    assert_eq!(rows.len(), 2);
+}
+
+#[test]
+fn test_select_star() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (name CHAR(3), id INT, age INT);
+         INSERT INTO users VALUES ('ann', 1, 30), ('bob', 2, 25);",
+        &mut interpreter,
+    );
+
+    // columns come back in declaration order, not in the physical order INT columns are stored in
+    let rows = get_rows(test_sql("SELECT * FROM users;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["'ann'", "1", "30"], vec!["'bob'", "2", "25"]]);
+
+    let rows = get_rows(test_sql("SELECT * FROM users WHERE age < 30 ORDER BY 2 DESC;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["'bob'", "2", "25"]]);
+}
+
+#[test]
+fn test_order_by_non_projected_and_char_columns() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, score INT, name CHAR(5));
+         INSERT INTO t VALUES (1, 10, 'bob'), (2, 9, 'al'), (3, -1, 'carol'), (4, 100, 'alice');",
+        &mut interpreter,
+    );
+
+    // integers compare numerically, not by their text, and ASC is the default
+    let rows = get_rows(test_sql("SELECT id FROM t ORDER BY score;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["3"], vec!["2"], vec!["1"], vec!["4"]]);
+    let rows = get_rows(test_sql("SELECT id FROM t ORDER BY score DESC;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["4"], vec!["1"], vec!["2"], vec!["3"]]);
+
+    // strings compare lexicographically, a prefix sorts first
+    let rows = get_rows(test_sql("SELECT id FROM t ORDER BY name ASC;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2"], vec!["4"], vec!["1"], vec!["3"]]);
+    let rows = get_rows(test_sql("SELECT name FROM t ORDER BY name DESC;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["'carol'"], vec!["'bob'"], vec!["'alice'"], vec!["'al'"]]);
+}
+
+#[test]
+fn test_select_count() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, age INT);",
+        &mut interpreter,
+    );
+    let rows = get_rows(test_sql("SELECT COUNT(*) FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["0"]]);
+
+    assert_sql_success("INSERT INTO t VALUES (1, 30), (2, 25), (3, 30), (4, 41);", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT COUNT(*) FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["4"]]);
+    let rows = get_rows(test_sql("SELECT COUNT(id), COUNT(*) FROM t WHERE age = 30;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2", "2"]]);
+
+    // aggregates cannot be mixed with row values, used outside the select list, or take an unknown column
+    assert!(matches!(test_sql("SELECT id, COUNT(*) FROM t;", &mut interpreter)[0], Err(DbError::ColumnMismatch(_))));
+    assert_sql_failure("SELECT id FROM t WHERE COUNT(*) = 1;", &mut interpreter);
+    assert!(matches!(test_sql("SELECT COUNT(nope) FROM t;", &mut interpreter)[0], Err(DbError::ColumnNotFound(_))));
+
+    let plan = get_rows(test_sql("EXPLAIN SELECT COUNT(*) FROM t WHERE age = 30;", &mut interpreter));
+    assert_eq!(plan, vec![vec!["Aggregate"], vec!["  Filter"], vec!["    Seq Scan on T"]]);
+}
+
+#[test]
+fn test_select_sum_avg_min_max() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, age INT, name CHAR(5));",
+        &mut interpreter,
+    );
+
+    // over an empty table only COUNT has a value
+    let rows = get_rows(test_sql("SELECT COUNT(*), SUM(age), AVG(age), MIN(age), MAX(name) FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["0", "NULL", "NULL", "NULL", "NULL"]]);
+
+    assert_sql_success(
+        "INSERT INTO t VALUES (1, 30, 'cid'), (2, -7, 'ann'), (3, 30, 'bob'), (4, 41, 'al');",
+        &mut interpreter,
+    );
+    let rows = get_rows(test_sql("SELECT SUM(age), AVG(age), MIN(age), MAX(age) FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["94", "23", "-7", "41"]]);
+
+    // AVG truncates toward zero, MIN and MAX compare CHAR columns lexicographically
+    let rows = get_rows(test_sql("SELECT AVG(age), MIN(name), MAX(name) FROM t WHERE id < 3;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["11", "'ann'", "'cid'"]]);
+    let rows = get_rows(test_sql("SELECT SUM(id), MIN(id) FROM t WHERE age > 100;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["NULL", "NULL"]]);
+
+    // SUM and AVG require INT columns
+    assert!(matches!(test_sql("SELECT SUM(name) FROM t;", &mut interpreter)[0], Err(DbError::TypeMismatch(_))));
+    assert!(matches!(test_sql("SELECT AVG(name) FROM t;", &mut interpreter)[0], Err(DbError::TypeMismatch(_))));
+}
+
+#[test]
+fn test_select_group_by_count_sum() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, age INT, name CHAR(5));",
+        &mut interpreter,
+    );
+
+    // an empty table has no groups
+    let rows = get_rows(test_sql("SELECT age, COUNT(*) FROM t GROUP BY age;", &mut interpreter));
+    assert!(rows.is_empty());
+
+    assert_sql_success(
+        "INSERT INTO t VALUES (1, 30, 'bob'), (2, 20, 'al'), (3, 30, 'al '), (4, 41, 'bob'), (5, 20, 'cid');",
+        &mut interpreter,
+    );
+
+    // groups come out in the order they are first seen
+    let rows = get_rows(test_sql("SELECT age, COUNT(*), SUM(id) FROM t GROUP BY age;", &mut interpreter));
+    assert_eq!(rows, vec![
+        vec!["30", "2", "4"],
+        vec!["20", "2", "7"],
+        vec!["41", "1", "4"],
+    ]);
+
+    let rows = get_rows(test_sql(
+        "SELECT age, SUM(id) FROM t WHERE id > 1 GROUP BY age ORDER BY age DESC;",
+        &mut interpreter,
+    ));
+    assert_eq!(rows, vec![vec!["41", "4"], vec!["30", "3"], vec!["20", "7"]]);
+
+    // CHAR keys ignore trailing padding
+    let rows = get_rows(test_sql("SELECT name, COUNT(id) FROM t GROUP BY name ORDER BY name;", &mut interpreter));
+    assert_eq!(rows, vec![
+        vec!["'al'", "2"],
+        vec!["'bob'", "2"],
+        vec!["'cid'", "1"],
+    ]);
+
+    // grouping by several columns, not every grouping column needs to be selected
+    let rows = get_rows(test_sql("SELECT COUNT(*) FROM t GROUP BY age, name;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"]; 5]);
+
+    // row values and ORDER BY keys must be grouping columns
+    assert!(matches!(test_sql("SELECT id, COUNT(*) FROM t GROUP BY age;", &mut interpreter)[0],
+        Err(DbError::ColumnMismatch(_))));
+    assert!(matches!(test_sql("SELECT age, COUNT(*) FROM t GROUP BY age ORDER BY id;", &mut interpreter)[0],
+        Err(DbError::ColumnMismatch(_))));
+    assert!(matches!(test_sql("SELECT age FROM t GROUP BY missing;", &mut interpreter)[0],
+        Err(DbError::ColumnNotFound(_))));
+}
+
+#[test]
+fn test_select_distinct() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, age INT, name CHAR(5));
+         INSERT INTO t VALUES (1, 30, 'bob'), (2, 20, 'al'), (3, 30, 'bob'), (4, 30, 'al'), (5, 20, 'al');",
+        &mut interpreter,
+    );
+
+    // duplicates collapse, keeping the first-seen order
+    let rows = get_rows(test_sql("SELECT DISTINCT name FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["'bob'"], vec!["'al'"]]);
+
+    // only fully equal tuples collapse
+    let rows = get_rows(test_sql("SELECT DISTINCT name, age FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["'bob'", "30"], vec!["'al'", "20"], vec!["'al'", "30"]]);
+
+    // composes with WHERE and ORDER BY
+    let rows = get_rows(test_sql("SELECT DISTINCT age FROM t WHERE id > 1 ORDER BY age;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["20"], vec!["30"]]);
+    let rows = get_rows(test_sql("SELECT DISTINCT * FROM t WHERE name = 'al';", &mut interpreter));
+    assert_eq!(rows.len(), 3);
+
+    // sorting by a column outside the select list would be ambiguous
+    assert!(matches!(test_sql("SELECT DISTINCT name FROM t ORDER BY id;", &mut interpreter)[0],
+        Err(DbError::ColumnMismatch(_))));
+}
+
+#[test]
+fn test_select_paged_with_cursor() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, pad CHAR(100));",
+        &mut interpreter,
+    );
+
+    // enough rows to span several data pages
+    let values: Vec<String> = (0..300).map(|i| format!("({}, 'x')", i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", values.join(", ")), &mut interpreter);
+
+    let sql = "SELECT id FROM t WHERE id >= 10;";
+    let mut ids = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut pages = 0;
+    loop {
+        let (rows, next) = interpreter.execute_paged(sql, cursor.as_deref(), 40).unwrap();
+        assert!(rows.len() <= 40);
+        ids.extend(rows.into_iter().map(|row| row[0].parse::<i32>().unwrap()));
+        pages += 1;
+
+        // a row inserted while paging lands ahead of the cursor and is still returned
+        if pages == 2 {
+            assert_sql_success("INSERT INTO t VALUES (1000, 'y');", &mut interpreter);
+        }
+        match next {
+            Some(token) => cursor = Some(token),
+            None => break,
+        }
+    }
+
+    let expected: Vec<i32> = (10..300).chain(std::iter::once(1000)).collect();
+    assert_eq!(ids, expected);
+    assert_eq!(pages, 8);
+
+    assert!(matches!(
+        interpreter.execute_paged(sql, Some("garbage"), 40),
+        Err(DbError::InvalidCursor(_))
+    ));
+    assert!(matches!(
+        interpreter.execute_paged("SELECT id FROM t ORDER BY id;", None, 40),
+        Err(DbError::ParseError(_))
+    ));
+}
+
+#[test]
+fn test_select_reverse_heap_order() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, pad CHAR(100));",
+        &mut interpreter,
+    );
+
+    // enough rows to span several data pages
+    let values: Vec<String> = (0..300).map(|i| format!("({}, 'x')", i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", values.join(", ")), &mut interpreter);
+
+    // a reverse scan yields rows in the opposite order of insertion
+    let forward = get_rows(test_sql("SELECT id FROM t;", &mut interpreter));
+    let mut reverse = interpreter.execute_reverse("SELECT id FROM t;", 1000).unwrap();
+    reverse.reverse();
+    assert_eq!(reverse, forward);
+
+    // the most recent matching rows, stopping at the limit
+    let rows = interpreter.execute_reverse("SELECT id FROM t WHERE id < 100;", 3).unwrap();
+    assert_eq!(rows, vec![vec!["99"], vec!["98"], vec!["97"]]);
+    assert_sql_success("DELETE FROM t WHERE id = 99;", &mut interpreter);
+    let rows = interpreter.execute_reverse("SELECT id FROM t WHERE id < 100;", 1).unwrap();
+    assert_eq!(rows, vec![vec!["98"]]);
+
+    assert!(matches!(
+        interpreter.execute_reverse("SELECT id FROM t ORDER BY id;", 10),
+        Err(DbError::ParseError(_))
+    ));
+}
+
+#[test]
+fn test_select_reverse_reads_only_last_pages() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, pad CHAR(100));",
+        &mut interpreter,
+    );
+    let values: Vec<String> = (0..300).map(|i| format!("({}, 'x')", i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", values.join(", ")), &mut interpreter);
+
+    // rows of a reverse scan and the number of data pages it fetched from the buffer pool
+    let page_reads = |interpreter: &mut Interpreter, sql: &str, limit: usize| {
+        let fetches = |interpreter: &Interpreter| {
+            let stats = interpreter.context.read().unwrap().storage_engines["DB1"].stats().data;
+            stats.hits + stats.misses
+        };
+        let before = fetches(interpreter);
+        let rows = interpreter.execute_reverse(sql, limit).unwrap();
+        (rows, fetches(interpreter) - before)
+    };
+
+    // the first reverse scan walks the whole chain once to learn its page order
+    let (_, first_reads) = page_reads(&mut interpreter, "SELECT id FROM t;", 1);
+    assert!(first_reads > 10);
+
+    // later scans start from the last page, reading it once to find appended pages and once to scan it
+    let (rows, reads) = page_reads(&mut interpreter, "SELECT id FROM t;", 3);
+    assert_eq!(rows, vec![vec!["299"], vec!["298"], vec!["297"]]);
+    assert_eq!(reads, 2);
+
+    // pages appended since are found from the last known page, without walking the chain again
+    let values: Vec<String> = (300..400).map(|i| format!("({}, 'x')", i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", values.join(", ")), &mut interpreter);
+    let (rows, reads) = page_reads(&mut interpreter, "SELECT id FROM t;", 1);
+    assert_eq!(rows, vec![vec!["399"]]);
+    assert!(reads < first_reads);
+    let (_, reads) = page_reads(&mut interpreter, "SELECT id FROM t;", 1);
+    assert_eq!(reads, 2);
+
+    // a dropped table forgets its page order, so a table reusing its pages is scanned correctly
+    assert_sql_success(
+        "DROP TABLE t; CREATE TABLE u (id INT); INSERT INTO u VALUES (1), (2);",
+        &mut interpreter,
+    );
+    let (rows, _) = page_reads(&mut interpreter, "SELECT id FROM u;", 5);
+    assert_eq!(rows, vec![vec!["2"], vec!["1"]]);
+}
+
+#[test]
+fn test_select_returns_typed_rows() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(5));
+         INSERT INTO t VALUES (1, 'ann'), (2, NULL);",
+        &mut interpreter,
+    );
+
+    let result = match test_sql("SELECT id, name, id + 1 AS next, id > 1 FROM t;", &mut interpreter).remove(0) {
+        Ok(ExecResult::Rows(result)) => result,
+        other => panic!("Expected Rows, got {:?}", other),
+    };
+    assert_eq!(result.columns, vec!["ID", "NAME", "NEXT", "?column?"]);
+    assert_eq!(result.rows, vec![
+        vec![Literal::Int(1), Literal::String("ann".to_string()), Literal::Int(2), Literal::Bool(false)],
+        vec![Literal::Int(2), Literal::Null, Literal::Int(3), Literal::Bool(true)],
+    ]);
+
+    // aggregates are typed too, and rendering is separate from the values
+    let result = match test_sql("SELECT COUNT(*), MAX(name) FROM t;", &mut interpreter).remove(0) {
+        Ok(ExecResult::Rows(result)) => result,
+        other => panic!("Expected Rows, got {:?}", other),
+    };
+    assert_eq!(result.columns, vec!["COUNT(*)", "MAX(NAME)"]);
+    assert_eq!(result.rows, vec![vec![Literal::Int(2), Literal::String("ann".to_string())]]);
+    assert_eq!(result.render(), "COUNT(*) MAX(NAME) \n-------- --------- \n2        'ann'     \n");
+}
+
+#[test]
+fn test_select_where_between() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE people (id INT, age INT, name CHAR(10));
+         INSERT INTO people VALUES (1, 17, 'a'), (2, 18, 'b'), (3, 40, 'c'), (4, 65, 'd'), (5, 66, 'e');",
+        &mut interpreter,
+    );
+
+    // bounds are inclusive
+    let rows = get_rows(test_sql("SELECT id FROM people WHERE age BETWEEN 18 AND 65;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2"], vec!["3"], vec!["4"]]);
+
+    // composes with AND / OR and accepts expressions as bounds
+    let rows = get_rows(test_sql(
+        "SELECT id FROM people WHERE age BETWEEN 10 + 8 AND 65 AND id != 3 OR id = 5;",
+        &mut interpreter,
+    ));
+    assert_eq!(rows, vec![vec!["2"], vec!["4"], vec!["5"]]);
+
+    // an empty range matches nothing
+    let rows = get_rows(test_sql("SELECT id FROM people WHERE age BETWEEN 65 AND 18;", &mut interpreter));
+    assert!(rows.is_empty());
+
+    // all three operands must be INT
+    for sql in [
+        "SELECT id FROM people WHERE name BETWEEN 'a' AND 'c';",
+        "SELECT id FROM people WHERE age BETWEEN 'a' AND 65;",
+        "SELECT id FROM people WHERE age BETWEEN 18 AND TRUE;",
+    ] {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(matches!(result[0], Err(DbError::TypeMismatch(_))), "{}: {:?}", sql, result);
+    }
+}
+
+#[test]
+fn test_select_where_in() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE people (id INT, name CHAR(10));
+         INSERT INTO people VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, 'd');",
+        &mut interpreter,
+    );
+
+    let rows = get_rows(test_sql("SELECT id FROM people WHERE id IN (3, 1, 7);", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["3"]]);
+    let rows = get_rows(test_sql("SELECT id FROM people WHERE name IN ('b', 'd');", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2"], vec!["4"]]);
+
+    // no match, and negation of the whole test
+    let rows = get_rows(test_sql("SELECT id FROM people WHERE id IN (5, 6);", &mut interpreter));
+    assert!(rows.is_empty());
+    let rows = get_rows(test_sql("SELECT id FROM people WHERE NOT (id IN (1, 2)) OR id = 1;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["3"], vec!["4"]]);
+
+    // every list item must have the type of the target
+    for sql in [
+        "SELECT id FROM people WHERE id IN (1, 'b');",
+        "SELECT id FROM people WHERE name IN ('a', 2);",
+        "SELECT id FROM people WHERE id IN (TRUE);",
+    ] {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(matches!(result[0], Err(DbError::TypeMismatch(_))), "{}: {:?}", sql, result);
+    }
+}
+
+#[test]
+fn test_select_arithmetic_projection_and_predicate() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE people (id INT, age INT);
+         INSERT INTO people VALUES (1, 30), (2, 31), (3, 0);",
+        &mut interpreter,
+    );
+
+    let rows = get_rows(test_sql("SELECT id + 1, 1 + 2 * 3, (1 + 2) * 3, -7 / 2 FROM people WHERE age * 2 = 60;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2", "7", "9", "-3"]]);
+
+    // division by zero fails the statement instead of producing a row
+    let result = test_sql("SELECT id / age FROM people;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ArithmeticError(_))), "{:?}", result);
+    let result = test_sql("SELECT id FROM people WHERE 60 / age = 2;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ArithmeticError(_))), "{:?}", result);
+}
+
+#[test]
+fn test_select_null() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, x INT, name CHAR(10));
+         INSERT INTO t VALUES (1, NULL, 'a'), (2, 20, NULL), (3, NULL, NULL), (4, 10, 'd');",
+        &mut interpreter,
+    );
+
+    // NULL is returned as NULL, not as the zero value stored in its place
+    let rows = get_rows(test_sql("SELECT x, name FROM t WHERE id = 3;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["NULL", "NULL"]]);
+
+    // comparisons with NULL are unknown, so they never select a row
+    for sql in [
+        "SELECT id FROM t WHERE x = NULL;",
+        "SELECT id FROM t WHERE NULL = NULL;",
+        "SELECT id FROM t WHERE x != NULL;",
+        "SELECT id FROM t WHERE NOT (x = NULL);",
+    ] {
+        assert!(get_rows(test_sql(sql, &mut interpreter)).is_empty(), "{}", sql);
+    }
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE x < 15 OR name = 'a';", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["4"]]);
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE NOT (x > 15);", &mut interpreter));
+    assert_eq!(rows, vec![vec!["4"]]);
+
+    // IS NULL finds them
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE x IS NULL;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["3"]]);
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE name IS NOT NULL;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["4"]]);
+
+    // aggregates skip NULL, and NULL sorts last
+    let rows = get_rows(test_sql("SELECT COUNT(*), COUNT(x), SUM(x), MIN(name) FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["4", "2", "30", "'a'"]]);
+    let rows = get_rows(test_sql("SELECT id FROM t ORDER BY x, id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["4"], vec!["2"], vec!["1"], vec!["3"]]);
+
+    // UPDATE and DELETE only touch rows where the condition is TRUE
+    assert_sql_success("UPDATE t SET x = NULL WHERE id = 4;", &mut interpreter);
+    assert_sql_success("DELETE FROM t WHERE x > 0 OR x = NULL;", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT id, x FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1", "NULL"], vec!["3", "NULL"], vec!["4", "NULL"]]);
+}
+
+#[test]
+fn test_select_without_from() {
+    let mut interpreter = setup_interpreter();
+
+    // constant expressions are evaluated without a database connection
+    let rows = get_rows(test_sql("SELECT 1;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"]]);
+
+    let rows = get_rows(test_sql("SELECT 2 = 2;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["TRUE"]]);
+
+    let rows = get_rows(test_sql("SELECT 1 + 1, 'hello', NULL;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2", "'hello'", "NULL"]]);
+
+    assert_sql_failure("SELECT id;", &mut interpreter);
+    assert!(matches!(
+        test_sql("SELECT id + 1;", &mut interpreter)[0],
+        Err(DbError::ColumnNotFound(_))
+    ));
+    assert_sql_failure("SELECT COUNT(*);", &mut interpreter);
+
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT 3 * 4;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["12"]]);
+}
+
+#[test]
+fn test_set_trim_char_output() {
+    let mut interpreter = setup_interpreter();
+
+    // settings apply to the session, with or without a database connection
+    assert_sql_success("SET trim_char_output = TRUE; SET trim_char_output = FALSE;", &mut interpreter);
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(5));
+         INSERT INTO t VALUES (1, 'foo  '), (2, 'ba r');",
+        &mut interpreter,
+    );
+
+    let sql = "SELECT id, name FROM t; SELECT MIN(name) FROM t; SELECT 'x ';";
+    let physical: Vec<_> = test_sql(sql, &mut interpreter).into_iter().map(|r| get_rows(vec![r])).collect();
+    assert_eq!(physical[0], vec![vec!["1", "'foo  '"], vec!["2", "'ba r'"]]);
+    assert_eq!(physical[1], vec![vec!["'ba r'"]]);
+    assert_eq!(physical[2], vec![vec!["'x '"]]);
+
+    assert_sql_success("SET trim_char_output = TRUE;", &mut interpreter);
+    let logical: Vec<_> = test_sql(sql, &mut interpreter).into_iter().map(|r| get_rows(vec![r])).collect();
+    assert_eq!(logical[0], vec![vec!["1", "'foo'"], vec!["2", "'ba r'"]]);
+    assert_eq!(logical[1], vec![vec!["'ba r'"]]);
+    assert_eq!(logical[2], vec![vec!["'x'"]]);
+
+    // trimming only changes output, the stored value keeps its spaces
+    assert_eq!(get_rows(test_sql("SELECT id FROM t WHERE name = 'foo  ';", &mut interpreter)), vec![vec!["1"]]);
+
+    assert!(matches!(test_sql("SET trim_char_output = 1;", &mut interpreter)[0], Err(DbError::TypeMismatch(_))));
+    assert!(matches!(test_sql("SET no_such_setting = TRUE;", &mut interpreter)[0], Err(DbError::InvalidSetting(_))));
+
+    assert_sql_success("SET safe_updates = TRUE;", &mut interpreter);
+    assert!(matches!(test_sql("DELETE FROM t;", &mut interpreter)[0], Err(DbError::UnsafeUpdate(_))));
+}
@@ -0,0 +1,125 @@
+#![cfg(feature = "server")]
+mod common;
+
+use std::thread;
+use raincloud_db::compiler::ast::Literal;
+use raincloud_db::interpreter::ExecResult;
+use raincloud_db::server::{Client, Server};
+use raincloud_db::types::DbError;
+use crate::common::setup_interpreter;
+
+fn start_server() -> Client {
+    let server = Server::bind("127.0.0.1:0", setup_interpreter()).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve());
+    Client::connect(addr).unwrap()
+}
+
+#[test]
+fn test_server_query() {
+    let mut client = start_server();
+
+    let results = client.query("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE users (id INT, name CHAR(5));").unwrap().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|r| r.is_ok()));
+
+    client.query("INSERT INTO users VALUES (1, \"alice\"), (2, \"bobby\");").unwrap().unwrap();
+
+    let mut results = client.query("SELECT id, name FROM users WHERE id = 2;").unwrap().unwrap();
+    match results.remove(0) {
+        Ok(ExecResult::Rows(result)) => {
+            // typed values survive the wire format
+            assert_eq!(result.columns, vec!["ID", "NAME"]);
+            assert_eq!(result.rows, vec![vec![Literal::Int(2), Literal::String("bobby".to_string())]]);
+        }
+        other => panic!("Expected Rows, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_server_errors() {
+    let mut client = start_server();
+
+    // statement error is reported per statement
+    let results = client.query("CREATE DATABASE db1; CONNECT TO db1; SELECT id FROM missing;").unwrap().unwrap();
+    assert!(results[0].is_ok());
+    assert!(matches!(results[2], Err(DbError::TableNotFound(_))));
+
+    // parse error fails the whole request
+    let response = client.query("SELECT FROM;").unwrap();
+    assert!(matches!(response, Err(DbError::ParseError(_))));
+
+    // the connection remains usable after an error
+    assert!(client.query("DISCONNECT;").unwrap().is_ok());
+}
+
+#[test]
+fn test_server_prepared_statement() {
+    let mut client = start_server();
+    client.query("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE users (id INT, name CHAR(5));").unwrap().unwrap();
+
+    // prepare once, execute with different bindings
+    let insert = client.prepare("INSERT INTO users VALUES (?, ?);").unwrap().unwrap();
+    assert_eq!(insert.param_count, 2);
+    for (id, name) in [(1, "alice"), (2, "bobby"), (3, "carol")] {
+        client.bind(insert, &[Literal::Int(id), Literal::String(name.to_string())]).unwrap().unwrap();
+        assert!(matches!(client.execute(insert).unwrap(), Ok(ExecResult::AffectedRows(1, _))));
+    }
+
+    let select = client.prepare("SELECT name FROM users WHERE id > ? AND id < ?;").unwrap().unwrap();
+    assert_eq!(select.param_count, 2);
+
+    client.bind(select, &[Literal::Int(1), Literal::Int(3)]).unwrap().unwrap();
+    match client.execute(select).unwrap() {
+        Ok(ExecResult::Rows(result)) => assert_eq!(result.formatted_rows(), vec![vec!["'bobby'".to_string()]]),
+        other => panic!("Expected Rows, got {:?}", other),
+    }
+
+    // re-executing with new bindings reuses the same statement
+    client.bind(select, &[Literal::Int(0), Literal::Int(10)]).unwrap().unwrap();
+    match client.execute(select).unwrap() {
+        Ok(ExecResult::Rows(result)) => assert_eq!(result.rows.len(), 3),
+        other => panic!("Expected Rows, got {:?}", other),
+    }
+
+    // wrong parameter count and type errors
+    assert!(matches!(client.bind(select, &[Literal::Int(1)]).unwrap(), Err(DbError::ParameterError(_))));
+    client.bind(select, &[Literal::String("a".to_string()), Literal::Int(3)]).unwrap().unwrap();
+    assert!(matches!(client.execute(select).unwrap(), Err(DbError::TypeMismatch(_))));
+
+    // closed statements can no longer be executed
+    client.close(select).unwrap().unwrap();
+    assert!(matches!(client.execute(select).unwrap(), Err(DbError::ParameterError(_))));
+    assert!(client.prepare("SELECT name FROM users; SELECT id FROM users;").unwrap().is_err());
+}
+
+#[test]
+fn test_server_stops_after_statement_panics() {
+    let mut interpreter = setup_interpreter();
+    let results = interpreter.execute_sql("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE t (id INT); INSERT INTO t VALUES (1);").unwrap();
+    assert!(results.iter().all(|r| r.is_ok()));
+
+    // a record that cannot be deserialized makes DELETE panic while it holds the context write lock
+    {
+        let ctx = interpreter.context.read().unwrap();
+        let storage_engine = ctx.storage_engines.get("DB1").unwrap();
+        let first_page_id = ctx.catalogs.get("DB1").unwrap().get_table_schema("t").unwrap().first_page_id;
+        let mut page = storage_engine.buffer_pool.fetch_page(first_page_id).unwrap();
+        page.write().insert_record(b"?").unwrap();
+    }
+
+    let server = Server::bind("127.0.0.1:0", interpreter).unwrap();
+    let addr = server.local_addr().unwrap();
+    let serving = thread::spawn(move || server.serve());
+    let mut client = Client::connect(addr).unwrap();
+    let mut other = Client::connect(addr).unwrap();
+
+    let response = client.query("DELETE FROM t;").unwrap();
+    assert!(matches!(response, Err(DbError::InternalError(_))), "{:?}", response);
+
+    // every connection is closed at its next request, and the server stops accepting clients
+    assert!(client.query("SELECT id FROM t;").is_err());
+    assert!(other.query("SELECT id FROM t;").is_err());
+    let _ = Client::connect(addr);
+    assert!(serving.join().unwrap().is_err());
+}
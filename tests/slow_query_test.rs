@@ -0,0 +1,45 @@
+mod common;
+
+use std::fs::{self, File};
+use std::time::Duration;
+use tempfile::TempDir;
+use raincloud_db::interpreter::slow_query_log::SlowQueryLog;
+use crate::common::setup_interpreter;
+
+#[test]
+fn test_slow_query_logged() {
+    let mut interpreter = setup_interpreter();
+    let log_dir = TempDir::new().unwrap();
+    let log_path = log_dir.path().join("slow.log");
+
+    interpreter.set_slow_query_log(Some(SlowQueryLog::new(
+        Duration::ZERO,
+        Box::new(File::create(&log_path).unwrap()),
+    )));
+
+    interpreter.execute_sql("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE t (id INT);").unwrap();
+    interpreter.execute_sql("INSERT INTO t VALUES (1), (2), (3);").unwrap();
+    interpreter.execute_sql("SELECT id FROM t WHERE id > 1;").unwrap();
+
+    let log = fs::read_to_string(&log_path).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[3].contains("rows=3") && lines[3].contains("sql=INSERT INTO t VALUES ( 1 ) , ( 2 ) , ( 3 ) ;"));
+    assert!(lines[4].contains("rows=2") && lines[4].contains("sql=SELECT id FROM t WHERE id > 1 ;"));
+    assert!(lines.iter().all(|line| line.contains("duration=")));
+}
+
+#[test]
+fn test_fast_query_not_logged() {
+    let mut interpreter = setup_interpreter();
+    let log_dir = TempDir::new().unwrap();
+    let log_path = log_dir.path().join("slow.log");
+
+    interpreter.set_slow_query_log(Some(SlowQueryLog::new(
+        Duration::from_secs(3600),
+        Box::new(File::create(&log_path).unwrap()),
+    )));
+
+    interpreter.execute_sql("CREATE DATABASE db1; CONNECT TO db1;").unwrap();
+    assert!(fs::read_to_string(&log_path).unwrap().is_empty());
+}
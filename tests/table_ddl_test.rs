@@ -1,7 +1,21 @@
 mod common;
 
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 use paste::paste;
-use raincloud_db::interpreter::ExecResult;
+use tempfile::TempDir;
+use raincloud_db::compiler::ast::{ColumnDef, DataType, Literal, Record, Statement};
+use raincloud_db::compiler::parser::Parser;
+use raincloud_db::compiler::scanner::Scanner;
+use raincloud_db::interpreter::analyzer::Analyzer;
+use raincloud_db::interpreter::executor::Executor;
+use raincloud_db::interpreter::{ExecResult, Interpreter};
+use raincloud_db::storage::bplus_tree::BPlusTree;
+use raincloud_db::storage::page::data_page::PAYLOAD_SIZE;
+use raincloud_db::storage::page::index_page::{get_internal_capacity, get_leaf_capacity};
+use raincloud_db::storage::page::page::Page;
+use raincloud_db::types::{DbError, PageId, DATA_FILE, PAGE_SIZE};
 use raincloud_db::with_read_pages;
 use crate::common::{test_sql, setup_interpreter, assert_sql_success, assert_sql_failure, get_rows};
 
@@ -32,6 +46,34 @@ fn test_create_table_duplicate_error() {
     assert_sql_failure("CREATE TABLE users (id INT);", &mut interpreter);
 }
 
+#[test]
+fn test_create_table_case_insensitive_collision() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+
+    // table created with a mixed case name, bypassing identifier normalization
+    let stmt = Statement::CreateTable {
+        name: "Users".to_string(),
+        columns: vec![ColumnDef { name: "ID".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None }],
+        foreign_keys: vec![],
+        options: vec![],
+    };
+    assert!(interpreter.execute(stmt).is_ok());
+
+    for sql in ["CREATE TABLE users (id INT);", "CREATE TABLE USERS (id INT);"] {
+        let res = test_sql(sql, &mut interpreter);
+        assert!(matches!(res[0], Err(DbError::DuplicateTable(_))), "{} should collide", sql);
+    }
+
+    let stmt = Statement::CreateTable {
+        name: "uSERS".to_string(),
+        columns: vec![ColumnDef { name: "ID".to_string(), data_type: DataType::Int, not_null: false, primary_key: false, comment: None }],
+        foreign_keys: vec![],
+        options: vec![],
+    };
+    assert!(matches!(interpreter.execute(stmt), Err(DbError::DuplicateTable(_))));
+}
+
 #[test]
 fn test_create_table_duplicate_column_error() {
     let mut interpreter = setup_interpreter();
@@ -39,6 +81,49 @@ fn test_create_table_duplicate_column_error() {
     assert_sql_failure("CREATE TABLE users (id INT, id CHAR(10));", &mut interpreter);
 }
 
+#[test]
+fn test_create_table_with_fillfactor() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_success("CREATE TABLE logs (id INT, data CHAR(96)) WITH (fillfactor = 50);", &mut interpreter);
+
+    for i in 0..200 {
+        assert_sql_success(&format!("INSERT INTO logs VALUES ({i}, \"{}\");", "a".repeat(96)), &mut interpreter);
+    }
+
+    // all records are present
+    let rows = get_rows(test_sql("SELECT id FROM logs;", &mut interpreter));
+    assert_eq!(rows.len(), 200);
+
+    // every page keeps half of its payload free
+    let ctx = interpreter.context.read().unwrap();
+    let table = ctx.catalogs.get("DB1").unwrap().get_table_schema("logs").unwrap();
+    assert_eq!(table.options.fillfactor, 50);
+
+    let storage = ctx.storage_engines.get("DB1").unwrap();
+    let mut page_id = table.first_page_id;
+    let mut page_count = 0;
+    while page_id != 0 {
+        with_read_pages!(storage.buffer_pool, [(page_id, page)], {
+            assert!(page.get_free_space() >= PAYLOAD_SIZE / 2, "page {} is filled beyond fillfactor", page_id);
+            page_id = page.get_next_id();
+        });
+        page_count += 1;
+    }
+    assert!(page_count > 200 * 100 / PAYLOAD_SIZE);
+}
+
+#[test]
+fn test_create_table_invalid_options() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_failure("CREATE TABLE t (id INT) WITH (pagesize = 10);", &mut interpreter);
+    assert_sql_failure("CREATE TABLE t (id INT) WITH (fillfactor = 0);", &mut interpreter);
+    assert_sql_failure("CREATE TABLE t (id INT) WITH (fillfactor = 101);", &mut interpreter);
+    assert_sql_failure("CREATE TABLE t (id INT) WITH (fillfactor = 'full');", &mut interpreter);
+    assert_sql_failure("CREATE TABLE t (id INT) WITH (fillfactor = 50, fillfactor = 60);", &mut interpreter);
+}
+
 #[test]
 fn test_insert_and_page_overflow() {
     let mut interpreter = setup_interpreter();
@@ -52,12 +137,8 @@ fn test_insert_and_page_overflow() {
     // check all records are present
     let result =
         test_sql("SELECT ID, DATA FROM LOGS WHERE data=\"aaaaaaaaaa\";", &mut interpreter);
-    if let ExecResult::QueryResult(res) = result[0].as_ref().unwrap() {
-        for (i, record) in res.iter().enumerate() {
-            assert_eq!(*record, vec![i.to_string().as_str(), "'aaaaaaaaaa'"]);
-        }
-    } else {
-        panic!("Expected QueryResult return type");
+    for (i, record) in get_rows(result).iter().enumerate() {
+        assert_eq!(*record, vec![i.to_string().as_str(), "'aaaaaaaaaa'"]);
     }
 
     // the table schema should still point to the same first_page_id,
@@ -89,13 +170,43 @@ fn test_insert_multiple_records() {
 
     // validate total row count
     let result = test_sql("SELECT ID, DATA1, DATA2, DATA3 FROM LOGS;", &mut interpreter);
-    let rows = match result[0].as_ref().unwrap() {
-        ExecResult::QueryResult(res) => res,
-        _ => panic!("Expected QueryResult"),
-    };
+    let rows = get_rows(result);
     assert_eq!(rows.len(), 4000);
 }
 
+#[test]
+fn test_insert_with_column_list() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE users (id INT, name CHAR(5), age INT);",
+        &mut interpreter,
+    );
+
+    // reordered columns are stored in schema order
+    assert_sql_success("INSERT INTO users (name, age, id) VALUES ('Bob', 30, 1), ('Ann', 25, 2);", &mut interpreter);
+    // omitted columns get the default value of their type
+    assert_sql_success("INSERT INTO users (id) VALUES (3);", &mut interpreter);
+    assert_sql_success("INSERT INTO users (age, name) VALUES (40 + 1, 'Cy');", &mut interpreter);
+
+    let rows = get_rows(test_sql("SELECT * FROM users;", &mut interpreter));
+    assert_eq!(rows, vec![
+        vec!["1", "'Bob'", "30"],
+        vec!["2", "'Ann'", "25"],
+        vec!["3", "''", "0"],
+        vec!["0", "'Cy'", "41"],
+    ]);
+
+    // unknown and repeated columns, arity and type mismatch
+    assert!(matches!(test_sql("INSERT INTO users (id, email) VALUES (4, 'x');", &mut interpreter)[0],
+        Err(DbError::ColumnNotFound(_))));
+    assert!(matches!(test_sql("INSERT INTO users (id, id) VALUES (4, 5);", &mut interpreter)[0],
+        Err(DbError::DuplicateColumn(_))));
+    assert!(matches!(test_sql("INSERT INTO users (id, name) VALUES (4);", &mut interpreter)[0],
+        Err(DbError::ColumnMismatch(_))));
+    assert!(matches!(test_sql("INSERT INTO users (name) VALUES (4);", &mut interpreter)[0],
+        Err(DbError::TypeMismatch(_))));
+}
+
 #[test]
 fn test_insert_with_expression_records() {
     let mut interpreter = setup_interpreter();
@@ -129,10 +240,7 @@ fn test_insert_with_expression_records() {
         &mut interpreter,
     );
 
-    let rows = match result[0].as_ref().unwrap() {
-        ExecResult::QueryResult(res) => res,
-        _ => panic!("Expected QueryResult"),
-    };
+    let rows = get_rows(result);
 
     assert_eq!(rows.len(), 4000);
 
@@ -173,10 +281,7 @@ fn test_update_table_single_row() {
 
     // check the row is updated
     let result = test_sql("SELECT ID, NAME FROM TEMP;", &mut interpreter);
-    let rows = match result[0].as_ref().unwrap() {
-        ExecResult::QueryResult(res) => res,
-        _ => panic!("Expected QueryResult"),
-    };
+    let rows = get_rows(result);
     assert_eq!(rows.len(), 1);
     assert_eq!(rows[0], vec!["0", "'bar  '"]);
 }
@@ -192,10 +297,7 @@ fn test_update_no_matching_rows() {
 
     // check no update happens
     let result = test_sql("SELECT ID, NAME FROM TEMP;", &mut interpreter);
-    let rows = match result[0].as_ref().unwrap() {
-        ExecResult::QueryResult(res) => res,
-        _ => panic!("Expected QueryResult"),
-    };
+    let rows = get_rows(result);
     assert_eq!(rows.len(), 1);
     assert_eq!(rows[0], vec!["0", "'foo  '"]);
 }
@@ -212,10 +314,7 @@ fn test_update_all_rows() {
 
     // check the rows are updated
     let result = test_sql("SELECT ID, NAME FROM TEMP;", &mut interpreter);
-    let rows = match result[0].as_ref().unwrap() {
-        ExecResult::QueryResult(res) => res,
-        _ => panic!("Expected QueryResult"),
-    };
+    let rows = get_rows(result);
     assert_eq!(rows.len(), 2);
     assert_eq!(rows[0], vec!["0", "'bar  '"]);
     assert_eq!(rows[1], vec!["1", "'bar  '"]);
@@ -232,10 +331,7 @@ fn test_update_multiple_assignments() {
 
     // check the rows are updated
     let result = test_sql("SELECT ID, NAME FROM TEMP;", &mut interpreter);
-    let rows = match result[0].as_ref().unwrap() {
-        ExecResult::QueryResult(res) => res,
-        _ => panic!("Expected QueryResult"),
-    };
+    let rows = get_rows(result);
     assert_eq!(rows.len(), 1);
     assert_eq!(rows[0], vec!["10", "'bar  '"]);
 }
@@ -261,6 +357,37 @@ fn test_update_with_constant_expression() {
     assert_eq!(rows[0], vec!["0", "'bar  '"]);
 }
 
+#[test]
+fn test_update_with_and_or_predicate() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_success("CREATE TABLE temp (a INT, b INT);", &mut interpreter);
+    assert_sql_success("INSERT INTO temp VALUES (1, 10), (2, 20), (2, 30), (3, 30);", &mut interpreter);
+
+    let result = test_sql("UPDATE temp SET b = 0 WHERE a = 1 OR (a = 2 AND b = 30);", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(2, _))), "{:?}", result);
+
+    let rows = get_rows(test_sql("SELECT a, b FROM temp;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1", "0"], vec!["2", "20"], vec!["2", "0"], vec!["3", "30"]]);
+}
+
+#[test]
+fn test_and_or_short_circuit() {
+    let mut interpreter = setup_interpreter();
+
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_success("CREATE TABLE temp (a INT);", &mut interpreter);
+    assert_sql_success("INSERT INTO temp VALUES (0), (5), (20);", &mut interpreter);
+
+    // the division is never evaluated for a = 0, which would fail with an arithmetic error
+    let rows = get_rows(test_sql("SELECT a FROM temp WHERE a != 0 AND 100 / a > 10;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["5"]]);
+    let rows = get_rows(test_sql("SELECT a FROM temp WHERE a = 0 OR 100 / a < 10;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["0"], vec!["20"]]);
+    assert_sql_failure("SELECT a FROM temp WHERE 100 / a > 10 AND a != 0;", &mut interpreter);
+}
+
 #[test]
 fn test_update_type_mismatch() {
     let mut interpreter = setup_interpreter();
@@ -328,3 +455,1308 @@ fn test_table_operations_without_connection() {
     assert_sql_success("CREATE DATABASE db1;", &mut interpreter);
     assert_sql_failure("CREATE TABLE failure (id INT);", &mut interpreter);
 }
+
+#[test]
+fn test_physical_layout_differs_from_declaration_order() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_success("CREATE TABLE users (name CHAR(5), id INT, tag CHAR(2));", &mut interpreter);
+    assert_sql_success("INSERT INTO users VALUES ('ab', 7, 'x'), ('cde', 8, 'yz');", &mut interpreter);
+
+    // INT columns are stored before CHAR columns
+    {
+        let ctx = interpreter.context.read().unwrap();
+        let table = ctx.catalogs.get("DB1").unwrap().get_table_schema("users").unwrap();
+        assert_eq!(table.physical_order, vec![1, 0, 2]);
+
+        // column values follow the NULL bitmap of the 3 columns
+        let header = table.record_header_size();
+        assert_eq!(header, 1);
+        let storage = ctx.storage_engines.get("DB1").unwrap();
+        let first_id = table.first_page_id;
+        with_read_pages!(storage.buffer_pool, [(first_id, page)], {
+            let (_, bytes) = page.iter_record().next().unwrap();
+            assert_eq!(bytes.len(), header + 4 + 5 + 2);
+            assert_eq!(bytes[0], 0);
+            assert_eq!(&bytes[header..header + 4], &7i32.to_le_bytes());
+            assert_eq!(&bytes[header + 4..header + 6], b"ab");
+        });
+    }
+
+    // logical column access is unaffected by the physical layout
+    let rows = get_rows(test_sql("SELECT name, id, tag FROM users ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![
+        vec!["'ab'".to_string(), "7".to_string(), "'x'".to_string()],
+        vec!["'cde'".to_string(), "8".to_string(), "'yz'".to_string()],
+    ]);
+    let rows = get_rows(test_sql("SELECT tag, name FROM users WHERE id = 8;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["'yz'".to_string(), "'cde'".to_string()]]);
+
+    assert_sql_success("UPDATE users SET name = 'f', id = 9 WHERE tag = 'x';", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT name, id FROM users WHERE tag = 'x';", &mut interpreter));
+    assert_eq!(rows, vec![vec!["'f'".to_string(), "9".to_string()]]);
+
+    // values longer than the declared CHAR length are rejected
+    assert_sql_failure("INSERT INTO users VALUES ('abcdef', 1, 'a');", &mut interpreter);
+}
+
+#[test]
+fn test_update_unknown_column_lists_candidates() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_success("CREATE TABLE users (id INT, name CHAR(5));", &mut interpreter);
+
+    match &test_sql("UPDATE users SET nam = 'bob' WHERE id = 1;", &mut interpreter)[0] {
+        Err(DbError::ColumnNotFound(msg)) => {
+            assert!(msg.contains("did you mean 'NAME'?"), "{}", msg);
+            assert!(msg.contains("Available columns: ID, NAME"), "{}", msg);
+        }
+        other => panic!("Expected ColumnNotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_soft_delete_hides_rows_until_purged() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_success("CREATE TABLE events (id INT) WITH (soft_delete = TRUE, retention = 0);", &mut interpreter);
+    assert_sql_success("CREATE TABLE audit (id INT) WITH (soft_delete = TRUE, retention = 3600);", &mut interpreter);
+    assert_sql_success("INSERT INTO events VALUES (1), (2), (3);", &mut interpreter);
+    assert_sql_success("INSERT INTO audit VALUES (1), (2), (3);", &mut interpreter);
+
+    let result = test_sql("DELETE FROM events WHERE id < 3;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(2, _))));
+    assert_sql_success("DELETE FROM audit WHERE id = 1;", &mut interpreter);
+
+    // tombstoned rows are hidden from queries and further changes
+    let rows = get_rows(test_sql("SELECT id FROM events;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["3".to_string()]]);
+    let result = test_sql("UPDATE events SET id = 10;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(1, _))));
+    let result = test_sql("DELETE FROM events;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(1, _))));
+
+    let count_records = |interpreter: &raincloud_db::interpreter::Interpreter, table: &str| {
+        let ctx = interpreter.context.read().unwrap();
+        let first_id = ctx.catalogs.get("DB1").unwrap().get_table_schema(table).unwrap().first_page_id;
+        let storage = ctx.storage_engines.get("DB1").unwrap();
+        let count;
+        with_read_pages!(storage.buffer_pool, [(first_id, page)], {
+            count = page.iter_record().count();
+        });
+        count
+    };
+    // the records are still stored until purged
+    assert_eq!(count_records(&interpreter, "events"), 3);
+
+    // PURGE reclaims tombstones older than the retention window only
+    let result = test_sql("PURGE events;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(3, _))));
+    assert_eq!(count_records(&interpreter, "events"), 0);
+
+    let result = test_sql("PURGE audit;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(0, _))));
+    assert_eq!(count_records(&interpreter, "audit"), 3);
+    let rows = get_rows(test_sql("SELECT id FROM audit ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2".to_string()], vec!["3".to_string()]]);
+
+    // PURGE requires a soft-delete table
+    assert_sql_success("CREATE TABLE plain (id INT);", &mut interpreter);
+    assert_sql_failure("PURGE plain;", &mut interpreter);
+    assert_sql_failure("CREATE TABLE bad (id INT) WITH (soft_delete = 1);", &mut interpreter);
+}
+
+#[test]
+fn test_safe_updates_rejects_where_less_modifications() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1;", &mut interpreter);
+    assert_sql_success("CREATE TABLE users (id INT);", &mut interpreter);
+    assert_sql_success("INSERT INTO users VALUES (1), (2);", &mut interpreter);
+
+    interpreter.set_safe_updates(true);
+    assert!(matches!(test_sql("UPDATE users SET id = 0;", &mut interpreter)[0], Err(DbError::UnsafeUpdate(_))));
+    assert!(matches!(test_sql("DELETE FROM users;", &mut interpreter)[0], Err(DbError::UnsafeUpdate(_))));
+    let rows = get_rows(test_sql("SELECT id FROM users ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1".to_string()], vec!["2".to_string()]]);
+
+    // an explicit WHERE clause is allowed, including WHERE TRUE
+    assert_sql_success("UPDATE users SET id = 3 WHERE id = 2;", &mut interpreter);
+    assert_sql_success("UPDATE users SET id = id + 10 WHERE TRUE;", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT id FROM users ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["11".to_string()], vec!["13".to_string()]]);
+    let result = test_sql("DELETE FROM users WHERE TRUE;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(2, _))));
+
+    // modifications without WHERE are allowed when the mode is off
+    interpreter.set_safe_updates(false);
+    assert_sql_success("INSERT INTO users VALUES (1);", &mut interpreter);
+    assert_sql_success("UPDATE users SET id = 0;", &mut interpreter);
+    assert_sql_success("DELETE FROM users;", &mut interpreter);
+}
+
+#[test]
+fn test_swap_table_with_rebuilt_replacement() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, v INT);
+         INSERT INTO t VALUES (1, 10), (2, 20), (3, 30);
+         CREATE TABLE t_rebuild (id INT, v INT);
+         INSERT INTO t_rebuild VALUES (1, 11), (2, 21), (3, 31);",
+        &mut interpreter,
+    );
+    let old_rows = get_rows(test_sql("SELECT id, v FROM t;", &mut interpreter));
+    let new_rows = get_rows(test_sql("SELECT id, v FROM t_rebuild;", &mut interpreter));
+
+    // a reader running during the swap sees either the old or the new table, never a mix
+    let reads = Arc::new(AtomicUsize::new(0));
+    let reader = {
+        let context = Arc::clone(&interpreter.context);
+        let reads = Arc::clone(&reads);
+        let (old_rows, new_rows) = (old_rows.clone(), new_rows.clone());
+        thread::spawn(move || {
+            for _ in 0..200 {
+                let mut scanner = Scanner::new("SELECT id, v FROM t;");
+                let stmt = Parser::new(&mut scanner).unwrap().parse().unwrap().remove(0);
+                let bound = Analyzer::new(Arc::clone(&context)).analyze(stmt).unwrap();
+                // a statement analyzed before the swap and executed after it is rejected
+                match Executor::new(Arc::clone(&context)).execute(bound) {
+                    Ok(ExecResult::Rows(result)) => {
+                        let rows = result.formatted_rows();
+                        assert!(rows == old_rows || rows == new_rows, "{:?}", rows);
+                    }
+                    Err(DbError::SchemaChanged(_)) => {}
+                    other => panic!("Expected Rows, got {:?}", other),
+                }
+                reads.fetch_add(1, Ordering::SeqCst);
+            }
+        })
+    };
+    while reads.load(Ordering::SeqCst) < 10 {
+        thread::yield_now();
+    }
+
+    let mut executor = Executor::new(Arc::clone(&interpreter.context));
+    executor.swap_table("t", "t_rebuild").unwrap();
+    reader.join().unwrap();
+
+    assert_eq!(get_rows(test_sql("SELECT id, v FROM t;", &mut interpreter)), new_rows);
+    assert!(matches!(test_sql("SELECT id FROM t_rebuild;", &mut interpreter)[0], Err(DbError::TableNotFound(_))));
+
+    // the swapped table keeps working, and both names must exist to swap
+    assert_sql_success("INSERT INTO t VALUES (4, 41);", &mut interpreter);
+    assert_eq!(get_rows(test_sql("SELECT id FROM t WHERE v = 41;", &mut interpreter)), vec![vec!["4".to_string()]]);
+    assert!(matches!(executor.swap_table("t", "t_rebuild"), Err(DbError::TableNotFound(_))));
+}
+
+#[test]
+fn test_stale_bound_statement_rejected() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, v INT);
+         INSERT INTO t VALUES (1, 10);",
+        &mut interpreter,
+    );
+    let context = Arc::clone(&interpreter.context);
+    let analyze = |sql: &str| {
+        let mut scanner = Scanner::new(sql);
+        let stmt = Parser::new(&mut scanner).unwrap().parse().unwrap().remove(0);
+        Analyzer::new(Arc::clone(&context)).analyze(stmt).unwrap()
+    };
+    let mut executor = Executor::new(Arc::clone(&interpreter.context));
+
+    // statements analyzed before the table is rebuilt with a different layout
+    let select = analyze("SELECT v FROM t;");
+    let insert = analyze("INSERT INTO t VALUES (2, 20);");
+    let delete = analyze("DELETE FROM t WHERE v = 10;");
+    let explain = analyze("EXPLAIN SELECT v FROM t;");
+    let unchanged = analyze("SELECT v FROM t;");
+    assert!(executor.execute(unchanged).is_ok());
+
+    assert_sql_success(
+        "CREATE TABLE t_rebuild (v INT);
+         INSERT INTO t_rebuild VALUES (10);",
+        &mut interpreter,
+    );
+    executor.swap_table("t", "t_rebuild").unwrap();
+
+    for stmt in [select, insert, delete, explain] {
+        assert!(matches!(executor.execute(stmt), Err(DbError::SchemaChanged(_))));
+    }
+    assert_eq!(get_rows(test_sql("SELECT v FROM t;", &mut interpreter)), vec![vec!["10".to_string()]]);
+
+    // recreating a dropped table under the same name is a change too, as are index changes
+    let select = analyze("SELECT v FROM t;");
+    assert_sql_success("DROP TABLE t; CREATE TABLE t (v INT);", &mut interpreter);
+    assert!(matches!(executor.execute(select), Err(DbError::SchemaChanged(_))));
+
+    let select = analyze("SELECT v FROM t;");
+    assert_sql_success("CREATE INDEX t_v ON t (v);", &mut interpreter);
+    assert!(matches!(executor.execute(select), Err(DbError::SchemaChanged(_))));
+
+    let select = analyze("SELECT v FROM t;");
+    assert_sql_success("DROP TABLE t;", &mut interpreter);
+    assert!(matches!(executor.execute(select), Err(DbError::SchemaChanged(_))));
+}
+
+#[test]
+fn test_insert_fails_beyond_page_quota() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE big (id INT, payload CHAR(1000)) WITH (MAX_PAGES = 2);",
+        &mut interpreter,
+    );
+    assert_sql_failure("CREATE TABLE bad (id INT) WITH (MAX_PAGES = 0);", &mut interpreter);
+
+    // a couple of rows fill a page, so the quota is reached after a few inserts
+    let mut inserted = 0;
+    let error = loop {
+        match test_sql(&format!("INSERT INTO big VALUES ({}, 'x');", inserted), &mut interpreter).remove(0) {
+            Ok(_) => inserted += 1,
+            Err(e) => break e,
+        }
+        assert!(inserted < 100, "quota never enforced");
+    };
+    assert!(matches!(error, DbError::QuotaExceeded(_)), "{:?}", error);
+    assert!(inserted > 2);
+
+    // rows inserted before the quota was hit are kept, and the table does not grow further
+    let rows = get_rows(test_sql("SELECT id FROM big;", &mut interpreter));
+    assert_eq!(rows.len(), inserted);
+    let result = test_sql("INSERT INTO big VALUES (-1, 'x');", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::QuotaExceeded(_))), "{:?}", result);
+}
+
+#[test]
+fn test_insert_beyond_page_quota_leaves_table_unchanged() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE big (id INT PRIMARY KEY, payload CHAR(1000)) WITH (MAX_PAGES = 2);
+         INSERT INTO big VALUES (1, 'a');
+         INSERT INTO big VALUES (2, 'b');",
+        &mut interpreter,
+    );
+
+    // the first rows of the batch fit in the quota, the last ones do not, so none is inserted
+    // and the row the batch replaces is kept
+    let rows: Vec<String> = (3..20).map(|id| format!("({}, 'x')", id)).collect();
+    let sql = format!("INSERT OR REPLACE INTO big VALUES (1, 'replaced'), {};", rows.join(", "));
+    let result = test_sql(&sql, &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::QuotaExceeded(_))), "{:?}", result);
+    assert_eq!(
+        get_rows(test_sql("SELECT id, payload FROM big;", &mut interpreter)),
+        vec![vec!["1", "'a'"], vec!["2", "'b'"]]
+    );
+    assert_eq!(get_rows(test_sql("SELECT id FROM big WHERE id = 3;", &mut interpreter)).len(), 0);
+
+    // a batch within the quota is inserted whole
+    assert_sql_success("INSERT OR REPLACE INTO big VALUES (1, 'replaced'), (3, 'c');", &mut interpreter);
+    assert_eq!(
+        get_rows(test_sql("SELECT id, payload FROM big;", &mut interpreter)),
+        vec![vec!["2", "'b'"], vec!["1", "'replaced'"], vec!["3", "'c'"]]
+    );
+}
+
+/// Page ids of the data page chain of table
+fn table_pages(interpreter: &raincloud_db::interpreter::Interpreter, table: &str) -> Vec<PageId> {
+    let ctx = interpreter.context.read().unwrap();
+    let database = ctx.current_db.clone().unwrap();
+    let storage_engine = ctx.storage_engines.get(&database).unwrap();
+    let mut page_id = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap().first_page_id;
+    let mut pages = Vec::new();
+    while page_id != 0 {
+        pages.push(page_id);
+        with_read_pages!(storage_engine.buffer_pool, [(page_id, page)], {
+            page_id = page.get_next_id();
+        });
+    }
+    pages
+}
+
+#[test]
+fn test_create_table_preallocates_pages() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE big (id INT, payload CHAR(1000)) WITH (PREALLOCATE = 4);",
+        &mut interpreter,
+    );
+
+    // the table starts with a contiguous chain of 4 pages, already written to the data file
+    let pages = table_pages(&interpreter, "big");
+    assert_eq!(pages.len(), 4);
+    assert!(pages.windows(2).all(|w| w[1] == w[0] + 1), "{:?}", pages);
+    {
+        let ctx = interpreter.context.read().unwrap();
+        let data_file = ctx.database_dir("DB1").join(DATA_FILE);
+        let file_size = std::fs::metadata(data_file).unwrap().len() as usize;
+        assert!(file_size >= (pages[3] as usize + 1) * PAGE_SIZE, "data file has {} bytes", file_size);
+    }
+
+    // the preallocated pages are taken from the free list, other tables get different pages
+    assert_sql_success("CREATE TABLE small (id INT);", &mut interpreter);
+    assert!(!pages.contains(&table_pages(&interpreter, "small")[0]));
+
+    // inserts fill the preallocated pages instead of appending new ones
+    for i in 0..10 {
+        assert_sql_success(&format!("INSERT INTO big VALUES ({}, 'x');", i), &mut interpreter);
+    }
+    assert_eq!(table_pages(&interpreter, "big"), pages);
+    assert_eq!(get_rows(test_sql("SELECT id FROM big;", &mut interpreter)).len(), 10);
+
+    assert_sql_failure("CREATE TABLE bad (id INT) WITH (PREALLOCATE = TRUE);", &mut interpreter);
+    assert_sql_failure("CREATE TABLE bad (id INT) WITH (PREALLOCATE = 3, MAX_PAGES = 2);", &mut interpreter);
+}
+
+#[test]
+fn test_not_null_constraint() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT NOT NULL, name CHAR(5), x INT);",
+        &mut interpreter,
+    );
+
+    // NULL is rejected in NOT NULL columns only
+    for sql in [
+        "INSERT INTO t VALUES (NULL, 'a', 1);",
+        "INSERT INTO t (name, id) VALUES ('a', NULL);",
+    ] {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{}: {:?}", sql, result);
+    }
+    assert_sql_success("INSERT INTO t VALUES (1, NULL, NULL), (2, 'b', 20);", &mut interpreter);
+    assert_sql_success("INSERT INTO t (name) VALUES ('c');", &mut interpreter);
+
+    // UPDATE cannot set NULL either, whether written as a literal or read from a NULL column
+    let result = test_sql("UPDATE t SET id = NULL WHERE id = 2;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    let result = test_sql("UPDATE t SET id = x WHERE id = 1;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_sql_success("UPDATE t SET id = x WHERE id = 2;", &mut interpreter);
+
+    let rows = get_rows(test_sql("SELECT id, name FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["0", "'c'"], vec!["1", "NULL"], vec!["20", "'b'"]]);
+
+    // the constraint is kept in the table definition
+    let ctx = interpreter.context.read().unwrap();
+    let schema = ctx.catalogs.get("DB1").unwrap().get_table_schema("t").unwrap();
+    assert_eq!(schema.create_table_sql(), "CREATE TABLE T (ID INT NOT NULL, NAME CHAR(5), X INT);");
+}
+
+#[test]
+fn test_char_length_counts_utf8_bytes() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(4));",
+        &mut interpreter,
+    );
+
+    // CHAR(4) is four bytes of UTF-8, so it holds four ASCII characters but two 'é'
+    assert_sql_success("INSERT INTO t VALUES (1, 'abcd'), (2, 'éé'), (3, 'aé');", &mut interpreter);
+
+    // a value that is too long fails the whole statement, leaving earlier rows unwritten
+    for sql in [
+        "INSERT INTO t VALUES (4, 'a'), (5, 'ééé');",
+        "INSERT INTO t VALUES (4, '日本');",
+        "UPDATE t SET name = 'éée' WHERE id = 1;",
+    ] {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(matches!(result[0], Err(DbError::TypeMismatch(_))), "{}: {:?}", sql, result);
+    }
+
+    let rows = get_rows(test_sql("SELECT id, name FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1", "'abcd'"], vec!["2", "'éé'"], vec!["3", "'aé'"]]);
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE name = 'éé';", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2"]]);
+}
+
+/// B+ tree of the first index of table, as recorded in the catalog
+fn first_index(interpreter: &raincloud_db::interpreter::Interpreter, table: &str) -> BPlusTree {
+    let ctx = interpreter.context.read().unwrap();
+    let database = ctx.current_db.clone().unwrap();
+    let storage_engine = ctx.storage_engines.get(&database).unwrap();
+    let schema = ctx.catalogs.get(&database).unwrap().get_table_schema(table).unwrap();
+    BPlusTree::new(
+        schema.indexes[0].root,
+        Arc::clone(&storage_engine.index_buffer_pool),
+        get_internal_capacity() - 1,
+        get_leaf_capacity() - 1,
+    )
+}
+
+/// Keys stored in the first index of table
+fn index_keys(interpreter: &raincloud_db::interpreter::Interpreter, table: &str) -> Vec<i64> {
+    first_index(interpreter, table).iter().map(|(key, _)| key).collect()
+}
+
+#[test]
+fn test_show_records_matches_index() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(100));
+         CREATE INDEX idx ON t (id);",
+        &mut interpreter,
+    );
+    let rows: Vec<String> = (0..200).map(|i| format!("({}, 'n{}')", i, i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", rows.join(", ")), &mut interpreter);
+
+    // the heap location of a row is the record id stored in the index
+    let mut index = first_index(&interpreter, "t");
+    for id in [0, 57, 199] {
+        let found = get_rows(test_sql(&format!("SHOW RECORDS FROM t WHERE id = {};", id), &mut interpreter));
+        let rid = index.search(&id)[0];
+        assert_eq!(found, vec![vec![rid.page_id.to_string(), rid.slot_id.to_string()]]);
+    }
+
+    // every live row is listed in page chain order, deleted rows are not
+    assert_sql_success("DELETE FROM t WHERE id >= 100;", &mut interpreter);
+    let found = get_rows(test_sql("SHOW RECORDS FROM t;", &mut interpreter));
+    assert_eq!(found.len(), 100);
+    let expected: Vec<Vec<String>> = index.iter()
+        .map(|(_, rid)| vec![rid.page_id.to_string(), rid.slot_id.to_string()])
+        .collect();
+    assert_eq!(found, expected);
+    assert!(get_rows(test_sql("SHOW RECORDS FROM t WHERE id = 150;", &mut interpreter)).is_empty());
+
+    assert_sql_failure("SHOW RECORDS FROM missing;", &mut interpreter);
+    assert_sql_failure("SHOW RECORDS FROM t WHERE missing = 1;", &mut interpreter);
+}
+
+#[test]
+fn test_create_index_point_queries() {
+    let mut interpreter = setup_interpreter();
+    // a low fill factor splits index pages early, so the root moves as rows are inserted
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(8)) WITH (FILLFACTOR = 10);",
+        &mut interpreter,
+    );
+    let rows: Vec<String> = (0..500).map(|i| format!("({}, 'n{}')", i, i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", rows.join(", ")), &mut interpreter);
+
+    // the index is built from the rows already in the table
+    assert_sql_success("CREATE INDEX idx ON t (id);", &mut interpreter);
+    assert_eq!(index_keys(&interpreter, "t"), (0..500).collect::<Vec<i64>>());
+    let rows = get_rows(test_sql("EXPLAIN SELECT name FROM t WHERE id = 7;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["Filter"], vec!["  Index Scan using IDX on T"]]);
+
+    let point = |sql: &str, interpreter: &mut _| get_rows(test_sql(sql, interpreter));
+    assert_eq!(point("SELECT name FROM t WHERE id = 377;", &mut interpreter), vec![vec!["'n377'"]]);
+    assert_eq!(point("SELECT name FROM t WHERE 42 = id AND name = 'n42';", &mut interpreter), vec![vec!["'n42'"]]);
+    assert!(point("SELECT name FROM t WHERE id = 42 AND name = 'n43';", &mut interpreter).is_empty());
+    assert!(point("SELECT name FROM t WHERE id = 500;", &mut interpreter).is_empty());
+
+    // INSERT, UPDATE and DELETE keep the index up to date, one row at a time to split pages
+    for i in 500..1000 {
+        assert_sql_success(&format!("INSERT INTO t VALUES ({}, 'n{}');", i, i), &mut interpreter);
+    }
+    assert_sql_success("UPDATE t SET id = 2000 WHERE id = 1;", &mut interpreter);
+    assert_sql_success("UPDATE t SET id = 10 - id WHERE id = 4 OR id = 6;", &mut interpreter);
+    assert_sql_success("DELETE FROM t WHERE id < 4 OR id > 900;", &mut interpreter);
+    let mut expected: Vec<i64> = (4..=900).collect();
+    assert_eq!(index_keys(&interpreter, "t"), expected);
+    assert_eq!(point("SELECT name FROM t WHERE id = 777;", &mut interpreter), vec![vec!["'n777'"]]);
+    assert_eq!(point("SELECT name FROM t WHERE id = 4;", &mut interpreter), vec![vec!["'n6'"]]);
+    assert!(point("SELECT name FROM t WHERE id = 2000;", &mut interpreter).is_empty());
+
+    // indexed values stay unique, NULL is not indexed
+    for sql in [
+        "INSERT INTO t VALUES (5, 'dup');",
+        "INSERT INTO t VALUES (2, 'a'), (2, 'b');",
+        "UPDATE t SET id = 5 WHERE id = 7;",
+    ] {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{}: {:?}", sql, result);
+    }
+    assert_sql_success("INSERT INTO t VALUES (NULL, 'x'), (NULL, 'y'), (2, 'n2');", &mut interpreter);
+    expected.insert(0, 2);
+    assert_eq!(index_keys(&interpreter, "t"), expected);
+    assert_eq!(point("SELECT COUNT(*) FROM t WHERE id IS NULL;", &mut interpreter), vec![vec!["2"]]);
+}
+
+#[test]
+fn test_create_index_errors() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(8));
+         INSERT INTO t VALUES (1, 'a'), (1, 'b');
+         CREATE TABLE u (id INT);
+         CREATE INDEX idx ON u (id);",
+        &mut interpreter,
+    );
+
+    let result = test_sql("CREATE INDEX idx ON t (id);", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::DuplicateIndex(_))), "{:?}", result);
+    let result = test_sql("CREATE INDEX t_name ON t (name);", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::TypeMismatch(_))), "{:?}", result);
+    let result = test_sql("CREATE INDEX t_id ON t (missing);", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ColumnNotFound(_))), "{:?}", result);
+    let result = test_sql("CREATE INDEX t_id ON missing (id);", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::TableNotFound(_))), "{:?}", result);
+
+    // a column with duplicate values cannot be indexed, and no index is recorded
+    let result = test_sql("CREATE INDEX t_id ON t (id);", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_sql_success("DELETE FROM t WHERE name = 'b'; CREATE INDEX t_id ON t (id);", &mut interpreter);
+
+    // dropping a table drops its indexes, so their names can be reused
+    assert_sql_success("DROP TABLE u; CREATE INDEX idx ON t (id);", &mut interpreter);
+}
+
+#[test]
+fn test_drop_index_frees_pages() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT) WITH (FILLFACTOR = 10);",
+        &mut interpreter,
+    );
+    let rows: Vec<String> = (0..300).map(|i| format!("({})", i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", rows.join(", ")), &mut interpreter);
+    assert_sql_success("CREATE INDEX idx ON t (id);", &mut interpreter);
+    let mut index_pages = first_index(&interpreter, "t").page_ids();
+    assert!(index_pages.len() > 1, "{:?}", index_pages);
+
+    assert_sql_success("DROP INDEX idx ON t;", &mut interpreter);
+    {
+        let ctx = interpreter.context.read().unwrap();
+        assert!(ctx.catalogs.get("DB1").unwrap().get_table_schema("t").unwrap().indexes.is_empty());
+
+        // the freed pages are the lowest free ids, so the next allocations reuse them
+        let storage_engine = ctx.storage_engines.get("DB1").unwrap();
+        let mut reused = storage_engine.free_list.allocate_batch(index_pages.len());
+        reused.sort();
+        index_pages.sort();
+        assert_eq!(reused, index_pages);
+        for page_id in reused {
+            storage_engine.free_list.deallocate(page_id, true).unwrap();
+        }
+    }
+
+    // queries fall back to a table scan, and the index can no longer be dropped
+    assert_eq!(get_rows(test_sql("SELECT id FROM t WHERE id = 7;", &mut interpreter)), vec![vec!["7"]]);
+    let result = test_sql("DROP INDEX idx ON t;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::IndexNotFound(_))), "{:?}", result);
+    let result = test_sql("DROP INDEX idx ON missing;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::TableNotFound(_))), "{:?}", result);
+}
+
+#[test]
+fn test_bulk_index_insert() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(8)) WITH (BULK_INDEX = 100);
+         CREATE INDEX idx ON t (id);
+         INSERT INTO t VALUES (5000, 'a'), (5001, 'b');",
+        &mut interpreter,
+    );
+    let old_pages = first_index(&interpreter, "t").page_ids();
+
+    // a large INSERT rebuilds the index around the existing keys and frees the old pages
+    let rows: Vec<String> = (0..3000).rev().map(|i| format!("({}, 'n{}')", i, i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", rows.join(", ")), &mut interpreter);
+    let mut index = first_index(&interpreter, "t");
+    index.validate().unwrap();
+    assert!(old_pages.iter().all(|page_id| !index.page_ids().contains(page_id)));
+    let mut expected: Vec<i64> = (0..3000).collect();
+    expected.extend([5000, 5001]);
+    assert_eq!(index_keys(&interpreter, "t"), expected);
+
+    // every index entry points at the heap row holding its key
+    for id in [0, 1234, 2999, 5000, 5001] {
+        let found = get_rows(test_sql(&format!("SHOW RECORDS FROM t WHERE id = {};", id), &mut interpreter));
+        let rid = index.search(&id)[0];
+        assert_eq!(found, vec![vec![rid.page_id.to_string(), rid.slot_id.to_string()]]);
+    }
+    assert_eq!(get_rows(test_sql("SELECT name FROM t WHERE id = 1234;", &mut interpreter)), vec![vec!["'n1234'"]]);
+
+    // smaller inserts update the rebuilt index row by row, uniqueness is still enforced
+    assert_sql_success("INSERT INTO t VALUES (3000, 'c');", &mut interpreter);
+    let result = test_sql("INSERT INTO t VALUES (1234, 'dup');", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_eq!(index_keys(&interpreter, "t").len(), 3003);
+
+    let result = test_sql("CREATE TABLE u (id INT) WITH (BULK_INDEX = 'many');", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::InvalidTableOption(_))), "{:?}", result);
+}
+
+#[test]
+fn test_primary_key_rejects_duplicates() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT PRIMARY KEY, name CHAR(5));
+         INSERT INTO t VALUES (1, 'a'), (2, 'b');
+         INSERT INTO t VALUES (3, 'a');",
+        &mut interpreter,
+    );
+
+    // duplicates of an existing key, within one statement, and NULL are rejected without inserting anything
+    for sql in [
+        "INSERT INTO t VALUES (2, 'c');",
+        "INSERT INTO t VALUES (4, 'd'), (5, 'e'), (4, 'f');",
+        "INSERT INTO t VALUES (NULL, 'g');",
+        "UPDATE t SET id = 1 WHERE id = 3;",
+        "UPDATE t SET id = 7;",
+    ] {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{}: {:?}", sql, result);
+    }
+    let rows = get_rows(test_sql("SELECT id FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["2"], vec!["3"]]);
+
+    // keys stay distinct when every row moves, and a deleted key can be reused
+    assert_sql_success("UPDATE t SET id = id + 1;", &mut interpreter);
+    assert_sql_success("DELETE FROM t WHERE id = 2; INSERT INTO t VALUES (2, 'x');", &mut interpreter);
+    assert_sql_success("UPDATE t SET name = 'y' WHERE id = 3;", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT id, name FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2", "'x'"], vec!["3", "'y'"], vec!["4", "'a'"]]);
+
+    // CHAR keys ignore trailing padding, and a table has at most one primary key
+    assert_sql_success("CREATE TABLE names (name CHAR(5) PRIMARY KEY); INSERT INTO names VALUES ('ab');", &mut interpreter);
+    let result = test_sql("INSERT INTO names VALUES ('ab  ');", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    let result = test_sql("CREATE TABLE bad (a INT PRIMARY KEY, b INT PRIMARY KEY);", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+
+    let ctx = interpreter.context.read().unwrap();
+    let schema = ctx.catalogs.get("DB1").unwrap().get_table_schema("t").unwrap();
+    assert_eq!(schema.create_table_sql(), "CREATE TABLE T (ID INT PRIMARY KEY, NAME CHAR(5));");
+}
+
+#[test]
+fn test_foreign_key_restricts_insert_update_delete() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT, name CHAR(10));
+         CREATE TABLE orders (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES users (id));
+         INSERT INTO users VALUES (1, 'alice'), (2, 'bob');
+         INSERT INTO orders VALUES (10, 1), (11, 1);",
+        &mut interpreter,
+    );
+
+    // child rows must reference an existing parent row, no row of the INSERT is kept otherwise
+    let result = test_sql("INSERT INTO orders VALUES (12, 2), (13, 3);", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_eq!(get_rows(test_sql("SELECT id FROM orders;", &mut interpreter)).len(), 2);
+    let result = test_sql("UPDATE orders SET user_id = 3 WHERE id = 10;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_sql_success("UPDATE orders SET user_id = 2 WHERE id = 10;", &mut interpreter);
+
+    // referenced parent rows cannot be deleted or have their key changed
+    let result = test_sql("DELETE FROM users WHERE id = 1;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    let result = test_sql("UPDATE users SET id = 5 WHERE id = 2;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    let rows = get_rows(test_sql("SELECT id FROM users ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"], vec!["2"]]);
+
+    // changes to columns that are not referenced are allowed
+    assert_sql_success("UPDATE users SET name = 'carol' WHERE id = 1;", &mut interpreter);
+
+    // a parent row can be removed once nothing references it
+    assert_sql_success(
+        "DELETE FROM orders WHERE user_id = 2;
+         DELETE FROM users WHERE id = 2;",
+        &mut interpreter,
+    );
+    let rows = get_rows(test_sql("SELECT id FROM users;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"]]);
+}
+
+#[test]
+fn test_foreign_key_allows_null() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT);
+         CREATE TABLE orders (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES users (id));
+         INSERT INTO users VALUES (1), (NULL);",
+        &mut interpreter,
+    );
+
+    // NULL references nothing, so it needs no matching row and does not keep one alive
+    assert_sql_success("INSERT INTO orders VALUES (10, NULL);", &mut interpreter);
+    assert_sql_success("UPDATE orders SET user_id = 1 WHERE id = 10;", &mut interpreter);
+    assert_sql_success("DELETE FROM users WHERE id IS NULL;", &mut interpreter);
+    assert_sql_success("UPDATE orders SET user_id = NULL WHERE id = 10;", &mut interpreter);
+    assert_sql_success("DELETE FROM users;", &mut interpreter);
+}
+
+/// Predicate matching the error expected from a statement
+type ErrorCheck = fn(&DbError) -> bool;
+
+#[test]
+fn test_foreign_key_definition_errors() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT, name CHAR(10));",
+        &mut interpreter,
+    );
+
+    let cases: [(&str, ErrorCheck); 5] = [
+        ("CREATE TABLE t (a INT, FOREIGN KEY (b) REFERENCES users (id));", |e| matches!(e, DbError::ColumnNotFound(_))),
+        ("CREATE TABLE t (a INT, FOREIGN KEY (a) REFERENCES missing (id));", |e| matches!(e, DbError::TableNotFound(_))),
+        ("CREATE TABLE t (a INT, FOREIGN KEY (a) REFERENCES users (missing));", |e| matches!(e, DbError::ColumnNotFound(_))),
+        ("CREATE TABLE t (a INT, FOREIGN KEY (a) REFERENCES users (name));", |e| matches!(e, DbError::TypeMismatch(_))),
+        ("CREATE TABLE t (a INT, FOREIGN KEY (a) REFERENCES t (a));", |e| matches!(e, DbError::ConstraintViolation(_))),
+    ];
+    for (sql, expected) in cases {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(result[0].as_ref().is_err_and(expected), "{}: {:?}", sql, result);
+    }
+
+    // a referenced table cannot be dropped until the referencing table is
+    assert_sql_success("CREATE TABLE orders (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id));", &mut interpreter);
+    let result = test_sql("DROP TABLE users;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_sql_success("DROP TABLE orders; DROP TABLE users;", &mut interpreter);
+}
+
+#[test]
+fn test_update_where_between() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, val INT);
+         INSERT INTO t VALUES (1, 0), (2, 0), (3, 0), (4, 0);
+         UPDATE t SET val = 1 WHERE id BETWEEN 2 AND 3;",
+        &mut interpreter,
+    );
+    let rows = get_rows(test_sql("SELECT id, val FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1", "0"], vec!["2", "1"], vec!["3", "1"], vec!["4", "0"]]);
+}
+
+#[test]
+fn test_foreign_key_on_delete_cascade() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT);
+         CREATE TABLE orders (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE);
+         CREATE TABLE items (order_id INT, FOREIGN KEY (order_id) REFERENCES orders (id) ON DELETE CASCADE);
+         INSERT INTO users VALUES (1), (2);
+         INSERT INTO orders VALUES (10, 1), (11, 1), (20, 2);
+         INSERT INTO items VALUES (10), (10), (11), (20);",
+        &mut interpreter,
+    );
+
+    // deleting a user deletes its orders, and the items of those orders
+    let result = test_sql("DELETE FROM users WHERE id = 1;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(1, _))), "{:?}", result);
+    assert_eq!(get_rows(test_sql("SELECT id FROM users;", &mut interpreter)), vec![vec!["2"]]);
+    assert_eq!(get_rows(test_sql("SELECT id FROM orders;", &mut interpreter)), vec![vec!["20"]]);
+    assert_eq!(get_rows(test_sql("SELECT order_id FROM items;", &mut interpreter)), vec![vec!["20"]]);
+
+    // ON DELETE only applies to DELETE, changing a referenced key is still restricted
+    let result = test_sql("UPDATE users SET id = 3 WHERE id = 2;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+}
+
+#[test]
+fn test_foreign_key_cascade_stops_at_restrict() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT);
+         CREATE TABLE orders (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE);
+         CREATE TABLE invoices (order_id INT, FOREIGN KEY (order_id) REFERENCES orders (id) ON DELETE RESTRICT);
+         INSERT INTO users VALUES (1), (2);
+         INSERT INTO orders VALUES (10, 1), (20, 2);
+         INSERT INTO invoices VALUES (10);",
+        &mut interpreter,
+    );
+
+    // the cascaded order is still invoiced, so nothing is deleted
+    let result = test_sql("DELETE FROM users WHERE id = 1;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_eq!(get_rows(test_sql("SELECT id FROM users ORDER BY id;", &mut interpreter)), vec![vec!["1"], vec!["2"]]);
+    assert_eq!(get_rows(test_sql("SELECT id FROM orders ORDER BY id;", &mut interpreter)), vec![vec!["10"], vec!["20"]]);
+
+    assert_sql_success("DELETE FROM users WHERE id = 2;", &mut interpreter);
+    assert_eq!(get_rows(test_sql("SELECT id FROM orders;", &mut interpreter)), vec![vec!["10"]]);
+}
+
+#[test]
+fn test_foreign_key_on_delete_set_null() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT);
+         CREATE TABLE orders (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE);
+         CREATE TABLE notes (id INT, user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL);
+         CREATE INDEX notes_user ON notes (user_id);
+         INSERT INTO users VALUES (1), (2);
+         INSERT INTO orders VALUES (10, 1), (20, 2);
+         INSERT INTO notes VALUES (100, 1), (200, 2);",
+        &mut interpreter,
+    );
+    let result = test_sql(
+        "CREATE TABLE bad (user_id INT NOT NULL, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL);",
+        &mut interpreter,
+    );
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+
+    // deleting a user deletes its orders and keeps its notes, which no longer reference it
+    let result = test_sql("DELETE FROM users WHERE id = 1;", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(1, _))), "{:?}", result);
+    assert_eq!(get_rows(test_sql("SELECT id FROM orders;", &mut interpreter)), vec![vec!["20"]]);
+    assert_eq!(
+        get_rows(test_sql("SELECT id, user_id FROM notes ORDER BY id;", &mut interpreter)),
+        vec![vec!["100", "NULL"], vec!["200", "2"]]
+    );
+
+    // the index on the foreign key column no longer finds the nulled row
+    assert!(get_rows(test_sql("SELECT id FROM notes WHERE user_id = 1;", &mut interpreter)).is_empty());
+    assert_eq!(get_rows(test_sql("SELECT id FROM notes WHERE user_id = 2;", &mut interpreter)), vec![vec!["200"]]);
+
+    // the user id is free again, and can be referenced by new rows
+    assert_sql_success("INSERT INTO users VALUES (1); INSERT INTO notes VALUES (102, 1);", &mut interpreter);
+    assert_eq!(get_rows(test_sql("SELECT id FROM notes WHERE user_id = 1;", &mut interpreter)), vec![vec!["102"]]);
+}
+
+#[test]
+fn test_foreign_key_set_null_stops_at_restrict() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE users (id INT);
+         CREATE TABLE orders (user_id INT, FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL);
+         CREATE TABLE audits (user_id INT, FOREIGN KEY (user_id) REFERENCES orders (user_id));
+         INSERT INTO users VALUES (1), (2);
+         INSERT INTO orders VALUES (1), (2);
+         INSERT INTO audits VALUES (1);",
+        &mut interpreter,
+    );
+
+    // nulling the order would remove a value the audits still reference, so nothing changes
+    let result = test_sql("DELETE FROM users WHERE id = 1;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    assert_eq!(get_rows(test_sql("SELECT id FROM users ORDER BY id;", &mut interpreter)), vec![vec!["1"], vec!["2"]]);
+    assert_eq!(get_rows(test_sql("SELECT user_id FROM orders ORDER BY user_id;", &mut interpreter)), vec![vec!["1"], vec!["2"]]);
+
+    assert_sql_success("DELETE FROM users WHERE id = 2;", &mut interpreter);
+    assert_eq!(get_rows(test_sql("SELECT user_id FROM orders ORDER BY user_id;", &mut interpreter)), vec![vec!["1"], vec!["NULL"]]);
+}
+
+#[test]
+fn test_rows_wider_than_a_page() {
+    let tmpdir = TempDir::new().unwrap();
+    let data_path = tmpdir.path().join("DB1").join(DATA_FILE);
+    let mut interpreter = Interpreter::new(tmpdir.path());
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE docs (id INT, head CHAR(3000), tail CHAR(3000));
+         CREATE INDEX docs_id ON docs (id);",
+        &mut interpreter,
+    );
+    let wide = |c: char| c.to_string().repeat(3000);
+    for (id, c) in [(1, 'a'), (2, 'b'), (3, 'c')] {
+        let sql = format!("INSERT INTO docs VALUES ({}, '{}', '{}');", id, wide(c), wide(c.to_ascii_uppercase()));
+        assert_sql_success(&sql, &mut interpreter);
+    }
+
+    // each row is spilled to two overflow pages, and read back whole by scans and index lookups
+    assert_eq!(get_rows(test_sql("SELECT id FROM docs;", &mut interpreter)), vec![vec!["1"], vec!["2"], vec!["3"]]);
+    assert_eq!(
+        get_rows(test_sql("SELECT head, tail FROM docs WHERE id = 2;", &mut interpreter)),
+        vec![vec![format!("'{}'", wide('b')), format!("'{}'", wide('B'))]]
+    );
+    assert_eq!(get_rows(test_sql("SHOW TABLE STATUS;", &mut interpreter))[0][1], "7");
+
+    // UPDATE rewrites a row in new overflow pages, DELETE frees the overflow pages of a row
+    assert_sql_success("UPDATE docs SET head = 'short' WHERE id = 2;", &mut interpreter);
+    assert_eq!(
+        get_rows(test_sql("SELECT head, tail FROM docs WHERE id = 2;", &mut interpreter)),
+        vec![vec!["'short'".to_string(), format!("'{}'", wide('B'))]]
+    );
+    assert_sql_success("DELETE FROM docs WHERE id = 1;", &mut interpreter);
+    assert_eq!(get_rows(test_sql("SELECT id FROM docs;", &mut interpreter)), vec![vec!["2"], vec!["3"]]);
+    assert_eq!(get_rows(test_sql("SHOW TABLE STATUS;", &mut interpreter))[0][1], "5");
+
+    // the overflow pages at the end of the data file are freed with the table
+    interpreter.context.read().unwrap().storage_engines["DB1"].deactivate().unwrap();
+    let before = std::fs::metadata(&data_path).unwrap().len();
+    assert_sql_success("DROP TABLE docs;", &mut interpreter);
+    let after = std::fs::metadata(&data_path).unwrap().len();
+    assert!(after < before, "data file did not shrink: {} -> {}", before, after);
+}
+
+#[test]
+fn test_rebuild_free_list_keeps_pages_in_use() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE gone (id INT, pad CHAR(1000));
+         CREATE TABLE docs (id INT, head CHAR(3000), tail CHAR(3000));
+         CREATE INDEX docs_id ON docs (id);",
+        &mut interpreter,
+    );
+    let values: Vec<String> = (0..10).map(|i| format!("({}, 'x')", i)).collect();
+    assert_sql_success(&format!("INSERT INTO gone VALUES {};", values.join(", ")), &mut interpreter);
+    let wide = |c: char| c.to_string().repeat(3000);
+    for (id, c) in [(1, 'a'), (2, 'b'), (3, 'c')] {
+        let sql = format!("INSERT INTO docs VALUES ({}, '{}', '{}');", id, wide(c), wide(c.to_ascii_uppercase()));
+        assert_sql_success(&sql, &mut interpreter);
+    }
+
+    // the pages of the dropped table are free, ahead of the pages of docs
+    let gone_pages = {
+        let ctx = interpreter.context.read().unwrap();
+        let first_page_id = ctx.catalogs.get("DB1").unwrap().get_table_schema("gone").unwrap().first_page_id;
+        ctx.storage_engines["DB1"].page_chain(first_page_id)
+    };
+    assert!(gone_pages.len() > 1);
+    assert_sql_success("DROP TABLE gone;", &mut interpreter);
+
+    // lose the free list, which now hands out the pages of docs, then rebuild it from the catalog
+    interpreter.context.read().unwrap().storage_engines["DB1"].free_list.rebuild(&[], &Default::default());
+    let mut executor = Executor::new(Arc::clone(&interpreter.context));
+    assert!(matches!(executor.rebuild_free_list(), Ok(ExecResult::Success(_))));
+
+    // new pages reuse the pages of the dropped table, and docs with its overflow and index pages is intact
+    assert_sql_success(
+        &format!("CREATE TABLE fresh (id INT, pad CHAR(1000)); INSERT INTO fresh VALUES {};", values.join(", ")),
+        &mut interpreter,
+    );
+    let fresh_pages = {
+        let ctx = interpreter.context.read().unwrap();
+        let first_page_id = ctx.catalogs.get("DB1").unwrap().get_table_schema("fresh").unwrap().first_page_id;
+        ctx.storage_engines["DB1"].page_chain(first_page_id)
+    };
+    assert!(fresh_pages.iter().all(|page_id| gone_pages.contains(page_id)), "{:?} {:?}", fresh_pages, gone_pages);
+    assert_eq!(get_rows(test_sql("SELECT id FROM docs;", &mut interpreter)), vec![vec!["1"], vec!["2"], vec!["3"]]);
+    assert_eq!(
+        get_rows(test_sql("SELECT head, tail FROM docs WHERE id = 2;", &mut interpreter)),
+        vec![vec![format!("'{}'", wide('b')), format!("'{}'", wide('B'))]]
+    );
+    assert_eq!(get_rows(test_sql("SELECT COUNT(*) FROM fresh;", &mut interpreter)), vec![vec!["10"]]);
+}
+
+#[test]
+fn test_run_batch_matches_per_statement_results() {
+    let mut script = String::from(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE items (id INT, qty INT, name CHAR(8));
+         CREATE INDEX items_id ON items (id);"
+    );
+    for i in 0..300 {
+        script.push_str(&format!("INSERT INTO items VALUES ({}, {}, 'n{}');", i, i % 7, i));
+    }
+    script.push_str(
+        "INSERT INTO items VALUES (5, 0, 'dup');
+         UPDATE items SET qty = qty + 1 WHERE qty = 3;
+         DELETE FROM items WHERE id > 250;
+         SELECT id, qty, name FROM items WHERE id = 42;
+         SELECT COUNT(*), SUM(qty) FROM items;
+         SELECT missing FROM items;
+         DROP TABLE items;"
+    );
+
+    let mut batch = setup_interpreter();
+    let mut single = setup_interpreter();
+    let batch_results = batch.run(&script).unwrap();
+    let single_results = single.execute_sql(&script).unwrap();
+
+    // the duplicate key and unknown column fail on their own, the rest of the batch still runs
+    assert_eq!(batch_results.len(), 2 + 2 + 300 + 7);
+    assert_eq!(batch_results.iter().filter(|r| r.is_err()).count(), 2);
+    assert_eq!(format!("{:?}", batch_results), format!("{:?}", single_results));
+}
+
+#[test]
+fn test_dump_page() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT, name CHAR(5));
+         INSERT INTO t VALUES (1, 'aa'), (2, NULL), (3, 'cc');
+         DELETE FROM t WHERE id = 2;",
+        &mut interpreter,
+    );
+    let page_id = {
+        let ctx = interpreter.context.read().unwrap();
+        let database = ctx.current_db.clone().unwrap();
+        ctx.catalogs.get(&database).unwrap().get_table_schema("t").unwrap().first_page_id
+    };
+
+    let lines: Vec<String> = get_rows(test_sql(&format!("DUMP PAGE {};", page_id), &mut interpreter))
+        .into_iter()
+        .map(|row| row[0].clone())
+        .collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "Table T");
+    assert!(lines[1].starts_with(&format!("DataPage {}: next_id=0 next_slot=3", page_id)));
+    assert!(lines[2].starts_with("slot 0:") && lines[2].ends_with("(1, 'aa')"));
+    assert!(lines[3].starts_with("slot 1:") && lines[3].ends_with("deleted"));
+    assert!(lines[4].starts_with("slot 2:") && lines[4].ends_with("(3, 'cc')"));
+
+    // dumping leaves the page as it was
+    assert_eq!(get_rows(test_sql("SELECT id FROM t;", &mut interpreter)), vec![vec!["1"], vec!["3"]]);
+    {
+        let ctx = interpreter.context.read().unwrap();
+        let storage_engine = ctx.storage_engines.get(ctx.current_db.as_ref().unwrap()).unwrap();
+        let raw = storage_engine.dump_page(page_id).unwrap();
+        // without a schema, records are shown as raw bytes, holding 'aa' as 6161
+        let slot = raw.lines().nth(1).unwrap();
+        assert!(slot.starts_with("slot 0:") && slot.contains("6161") && !slot.contains('('), "{}", slot);
+    }
+
+    assert!(matches!(test_sql(&format!("DUMP PAGE {};", page_id + 100), &mut interpreter)[0], Err(DbError::PageNotFound(_))));
+}
+
+#[test]
+fn test_show_table_status() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE empty (id INT);
+         CREATE TABLE t (id INT, name CHAR(100));",
+        &mut interpreter,
+    );
+    let values: Vec<String> = (0..200).map(|i| format!("({}, 'row {}')", i, i)).collect();
+    assert_sql_success(&format!("INSERT INTO t VALUES {};", values.join(", ")), &mut interpreter);
+
+    let status = get_rows(test_sql("SHOW TABLE STATUS;", &mut interpreter));
+    assert_eq!(status.len(), 2);
+    assert_eq!(status[0][0], "EMPTY");
+    assert_eq!(status[0][2..], ["0", "0", &PAGE_SIZE.to_string(), "NULL"]);
+
+    let (name, pages, rows, logical, physical) = (&status[1][0], &status[1][1], &status[1][2], &status[1][3], &status[1][4]);
+    let pages: usize = pages.parse().unwrap();
+    let logical: usize = logical.parse().unwrap();
+    assert_eq!((name.as_str(), rows.as_str()), ("T", "200"));
+    assert!(pages > 1);
+    assert_eq!(physical, &(pages * PAGE_SIZE).to_string());
+    assert_eq!(logical % 200, 0);
+    let before: f64 = status[1][5].parse().unwrap();
+    assert!(before >= 1.0);
+
+    // deleted records keep their pages, so the same pages hold less live data
+    assert_sql_success("DELETE FROM t WHERE id >= 50;", &mut interpreter);
+    let status = get_rows(test_sql("SHOW TABLE STATUS;", &mut interpreter));
+    assert_eq!(status[1][1], pages.to_string());
+    assert_eq!(status[1][2], "50");
+    assert_eq!(status[1][3], (logical / 4).to_string());
+    let after: f64 = status[1][5].parse().unwrap();
+    assert!(after > before * 3.0, "{} {}", before, after);
+}
+
+#[test]
+fn test_insert_or_replace_and_ignore() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE t (id INT PRIMARY KEY, code INT, name CHAR(5));
+         CREATE INDEX code_idx ON t (code);
+         INSERT INTO t VALUES (1, 10, 'a'), (2, 20, 'b');",
+        &mut interpreter,
+    );
+
+    // a conflicting INSERT still fails by default
+    let result = test_sql("INSERT INTO t VALUES (1, 30, 'c');", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+
+    // REPLACE overwrites the row holding the primary key, and the index follows
+    let result = test_sql("INSERT OR REPLACE INTO t VALUES (1, 11, 'x');", &mut interpreter);
+    assert!(matches!(&result[0], Ok(ExecResult::AffectedRows(1, message)) if message.contains("replacing 1 rows")), "{:?}", result);
+    let rows = get_rows(test_sql("SELECT id, code, name FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1", "11", "'x'"], vec!["2", "20", "'b'"]]);
+    assert!(get_rows(test_sql("SELECT id FROM t WHERE code = 10;", &mut interpreter)).is_empty());
+    let rows = get_rows(test_sql("SELECT id FROM t WHERE code = 11;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"]]);
+
+    // a row conflicting on two keys replaces both rows, and a later row of the statement wins
+    assert_sql_success("INSERT OR REPLACE INTO t VALUES (2, 11, 'y'), (3, 30, 'z'), (3, 31, 'w');", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT id, code, name FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2", "11", "'y'"], vec!["3", "31", "'w'"]]);
+
+    // IGNORE skips rows conflicting with existing rows or earlier rows of the statement
+    let result = test_sql("INSERT OR IGNORE INTO t VALUES (2, 40, 'p'), (4, 31, 'q'), (5, 50, 'r'), (5, 51, 's');", &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(1, _))), "{:?}", result);
+    let rows = get_rows(test_sql("SELECT id, code, name FROM t ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2", "11", "'y'"], vec!["3", "31", "'w'"], vec!["5", "50", "'r'"]]);
+}
+
+#[test]
+fn test_insert_on_conflict_do_update() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE counters (id INT PRIMARY KEY, code INT, hits INT, name CHAR(10));
+         CREATE INDEX code_idx ON counters (code);",
+        &mut interpreter,
+    );
+
+    // without a conflict the rows are inserted
+    let upsert = "INSERT INTO counters VALUES (1, 10, 1, 'a'), (2, 20, 1, 'b') ON CONFLICT (id) DO UPDATE SET hits = hits + 1;";
+    let result = test_sql(upsert, &mut interpreter);
+    assert!(matches!(result[0], Ok(ExecResult::AffectedRows(2, _))), "{:?}", result);
+
+    // a conflicting key updates the existing row from its own values, and the others are inserted
+    let result = test_sql(
+        "INSERT INTO counters VALUES (1, 99, 1, 'ignored'), (3, 30, 1, 'c') ON CONFLICT (id) DO UPDATE SET hits = hits + 1, name = 'renamed';",
+        &mut interpreter,
+    );
+    assert!(matches!(&result[0], Ok(ExecResult::AffectedRows(2, message)) if message.contains("update 1 rows")), "{:?}", result);
+    let rows = get_rows(test_sql("SELECT id, code, hits, name FROM counters ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![
+        vec!["1", "10", "2", "'renamed'"],
+        vec!["2", "20", "1", "'b'"],
+        vec!["3", "30", "1", "'c'"],
+    ]);
+
+    // an indexed column is a conflict target too, and updating an indexed column keeps the index current
+    assert_sql_success("INSERT INTO counters VALUES (9, 20, 0, 'x') ON CONFLICT (code) DO UPDATE SET code = 21, name = 'long name';", &mut interpreter);
+    let rows = get_rows(test_sql("SELECT id, hits, name FROM counters WHERE code = 21;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2", "1", "'long name'"]]);
+    assert!(get_rows(test_sql("SELECT id FROM counters WHERE code = 20;", &mut interpreter)).is_empty());
+
+    // conflicts on other keys, the same row updated twice and an update creating a duplicate change nothing
+    for sql in [
+        "INSERT INTO counters VALUES (4, 10, 0, 'd') ON CONFLICT (id) DO UPDATE SET hits = 0;",
+        "INSERT INTO counters VALUES (1, 0, 0, 'e'), (1, 0, 0, 'f') ON CONFLICT (id) DO UPDATE SET hits = 0;",
+        "INSERT INTO counters VALUES (1, 0, 0, 'g') ON CONFLICT (id) DO UPDATE SET code = 30;",
+    ] {
+        let result = test_sql(sql, &mut interpreter);
+        assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{}: {:?}", sql, result);
+    }
+    let rows = get_rows(test_sql("SELECT id, hits FROM counters ORDER BY id;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1", "2"], vec!["2", "1"], vec!["3", "1"]]);
+
+    // the conflict target must be a unique column of the table
+    let result = test_sql("INSERT INTO counters VALUES (5, 50, 0, 'h') ON CONFLICT (hits) DO UPDATE SET hits = 0;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ConstraintViolation(_))), "{:?}", result);
+    let result = test_sql("INSERT INTO counters VALUES (5, 50, 0, 'h') ON CONFLICT (missing) DO UPDATE SET hits = 0;", &mut interpreter);
+    assert!(matches!(result[0], Err(DbError::ColumnNotFound(_))), "{:?}", result);
+}
+
+#[test]
+fn test_insert_iter_streams_page_sized_batches() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE t (id INT, name CHAR(100));", &mut interpreter);
+
+    // rows are generated lazily, the iterator is never collected
+    let total = 5000;
+    let rows = (0..total).map(|i| vec![Literal::Int(i), Literal::String(format!("row {}", i))]);
+    assert_eq!(interpreter.insert_iter("t", rows).unwrap(), total as usize);
+
+    let count = get_rows(test_sql("SELECT COUNT(*) FROM t;", &mut interpreter));
+    assert_eq!(count[0][0], total.to_string());
+    let last = get_rows(test_sql(&format!("SELECT name FROM t WHERE id = {};", total - 1), &mut interpreter));
+    assert_eq!(last[0][0], format!("'row {}'", total - 1));
+
+    // each batch fills exactly one page
+    let per_page = {
+        let ctx = interpreter.context.read().unwrap();
+        ctx.catalogs.get("DB1").unwrap().get_table_schema("t").unwrap().records_per_page()
+    };
+    assert!(per_page > 1);
+    let status = get_rows(test_sql("SHOW TABLE STATUS;", &mut interpreter));
+    assert_eq!(status[0][1], (total as usize).div_ceil(per_page).to_string());
+
+    assert!(matches!(interpreter.insert_iter("missing", Vec::new()), Err(DbError::TableNotFound(_))));
+}
+
+#[test]
+fn test_describe_and_show_create_table_with_column_comments() {
+    let tmpdir = TempDir::new().unwrap();
+    let create = "CREATE TABLE T (ID INT PRIMARY KEY COMMENT 'primary key', NAME CHAR(5) NOT NULL, NOTE CHAR(10) COMMENT \"it's free text\");";
+    {
+        let mut interpreter = Interpreter::new(tmpdir.path());
+        assert_sql_success(&format!("CREATE DATABASE db1; CONNECT TO db1; {} CREATE INDEX id_idx ON t (id);", create), &mut interpreter);
+
+        let rows = get_rows(test_sql("DESCRIBE t;", &mut interpreter));
+        assert_eq!(rows, vec![
+            vec!["ID", "INT", "PRIMARY KEY", "primary key"],
+            vec!["NAME", "CHAR(5)", "NOT NULL", "NULL"],
+            vec!["NOTE", "CHAR(10)", "", "it's free text"],
+        ]);
+        assert!(matches!(test_sql("DESCRIBE missing;", &mut interpreter)[0], Err(DbError::TableNotFound(_))));
+
+        // SHOW CREATE TABLE recreates the comments
+        let rows = get_rows(test_sql("SHOW CREATE TABLE t;", &mut interpreter));
+        assert_eq!(rows, vec![vec![create], vec!["CREATE INDEX ID_IDX ON T (ID);"]]);
+        assert_sql_success(&format!("DROP TABLE t; {}", rows[0][0]), &mut interpreter);
+        let rows = get_rows(test_sql("DESCRIBE t;", &mut interpreter));
+        assert_eq!(rows[2][3], "it's free text");
+    }
+
+    // comments are kept in the catalog
+    let mut interpreter = Interpreter::new(tmpdir.path());
+    assert_sql_success("CONNECT TO db1;", &mut interpreter);
+    let rows = get_rows(test_sql("DESCRIBE t;", &mut interpreter));
+    assert_eq!(rows[0][3], "primary key");
+    assert_eq!(rows[2][3], "it's free text");
+}
+
+#[test]
+fn test_drop_table_shrinks_data_file() {
+    let tmpdir = TempDir::new().unwrap();
+    let data_path = tmpdir.path().join("DB1").join(DATA_FILE);
+    let mut interpreter = Interpreter::new(tmpdir.path());
+    assert_sql_success(
+        "CREATE DATABASE db1; CONNECT TO db1;
+         CREATE TABLE keep (id INT); INSERT INTO keep VALUES (1);
+         CREATE TABLE big (id INT) WITH (FILLFACTOR = 10);",
+        &mut interpreter,
+    );
+    let rows: Vec<String> = (0..300).map(|i| format!("({})", i)).collect();
+    assert_sql_success(&format!("INSERT INTO big VALUES {};", rows.join(", ")), &mut interpreter);
+    assert_sql_success("CREATE INDEX idx ON big (id);", &mut interpreter);
+    interpreter.context.read().unwrap().storage_engines["DB1"].deactivate().unwrap();
+    let before = std::fs::metadata(&data_path).unwrap().len();
+
+    // the pages of the dropped table and its index were at the end of the file
+    assert_sql_success("DROP TABLE big;", &mut interpreter);
+    let after = std::fs::metadata(&data_path).unwrap().len();
+    assert!(after < before, "data file did not shrink: {} -> {}", before, after);
+
+    // the remaining table is intact, and the file grows again for new pages
+    let rows = get_rows(test_sql("SELECT id FROM keep;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["1"]]);
+    assert_sql_success("CREATE TABLE t (id INT); INSERT INTO t VALUES (2);", &mut interpreter);
+    interpreter.context.read().unwrap().storage_engines["DB1"].deactivate().unwrap();
+    let rows = get_rows(test_sql("SELECT id FROM t;", &mut interpreter));
+    assert_eq!(rows, vec![vec!["2"]]);
+    assert!(std::fs::metadata(&data_path).unwrap().len() > after);
+}
+
+#[test]
+fn test_insert_arity_mismatch() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE t (id INT, name CHAR(5), age INT);", &mut interpreter);
+
+    // too few and too many values, in a single row and in a later row of a multi-row insert
+    for sql in [
+        "INSERT INTO t VALUES (1, 'Ann');",
+        "INSERT INTO t VALUES (1, 'Ann', 30, 4);",
+        "INSERT INTO t VALUES (1, 'Ann', 30), (2, 'Bob');",
+        "INSERT INTO t VALUES (1, 'Ann', 30), (2, 'Bob', 25, 4);",
+    ] {
+        match &test_sql(sql, &mut interpreter)[0] {
+            Err(DbError::ColumnMismatch(message)) => assert!(message.contains("3 columns"), "{}", message),
+            other => panic!("Expected ColumnMismatch for {}, got {:?}", sql, other),
+        }
+    }
+    assert!(matches!(interpreter.insert_iter("t", vec![vec![Literal::Int(1)]]), Err(DbError::ColumnMismatch(_))));
+
+    // no row of a rejected insert is written
+    assert!(get_rows(test_sql("SELECT * FROM t;", &mut interpreter)).is_empty());
+
+    // a record built without the analyzer is rejected before serialization
+    let ctx = interpreter.context.read().unwrap();
+    let schema = ctx.catalogs.get("DB1").unwrap().get_table_schema("t").unwrap();
+    let record = Record { record: vec![Literal::Int(1), Literal::Null, Literal::Int(30), Literal::Int(4)] };
+    assert!(matches!(schema.serialize_record(&record), Err(DbError::ColumnMismatch(_))));
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "tracing")]
+mod common;
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_core::span::Current;
+use tracing::{Event, Metadata, Subscriber};
+use crate::common::{assert_sql_success, setup_interpreter};
+
+/// Subscriber collecting span names and recorded fields as "name.field=value"
+#[derive(Default)]
+struct CollectingSubscriber {
+    spans: Mutex<Vec<&'static Metadata<'static>>>,
+    stack: Mutex<Vec<Id>>,
+    records: Arc<Mutex<Vec<String>>>,
+}
+
+struct FieldCollector<'a> {
+    span: &'static str,
+    records: &'a mut Vec<String>,
+}
+
+impl Visit for FieldCollector<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.records.push(format!("{}.{}={:?}", self.span, field.name(), value));
+    }
+}
+
+impl Subscriber for CollectingSubscriber {
+    fn enabled(&self, _: &Metadata<'_>) -> bool { true }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span.metadata());
+        span.record(&mut FieldCollector { span: span.metadata().name(), records: &mut self.records.lock().unwrap() });
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let name = self.spans.lock().unwrap()[span.into_u64() as usize - 1].name();
+        values.record(&mut FieldCollector { span: name, records: &mut self.records.lock().unwrap() });
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, _: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        self.stack.lock().unwrap().push(span.clone());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.stack.lock().unwrap().pop();
+    }
+
+    fn current_span(&self) -> Current {
+        match self.stack.lock().unwrap().last() {
+            Some(id) => Current::new(id.clone(), self.spans.lock().unwrap()[id.into_u64() as usize - 1]),
+            None => Current::none(),
+        }
+    }
+}
+
+#[test]
+fn test_select_emits_spans() {
+    let mut interpreter = setup_interpreter();
+    assert_sql_success("CREATE DATABASE db1; CONNECT TO db1; CREATE TABLE t (id INT);", &mut interpreter);
+    assert_sql_success("INSERT INTO t VALUES (1), (2), (3);", &mut interpreter);
+
+    let subscriber = CollectingSubscriber::default();
+    let records = Arc::clone(&subscriber.records);
+    tracing::subscriber::with_default(subscriber, || {
+        interpreter.execute_sql("SELECT id FROM t WHERE id > 1;").unwrap();
+    });
+
+    let records = records.lock().unwrap();
+    assert!(records.contains(&"select.table=\"T\"".to_string()));
+    assert!(records.contains(&"select.pages_read=1".to_string()));
+    assert!(records.contains(&"select.rows=2".to_string()));
+    assert!(records.iter().any(|r| r.starts_with("fetch_page.page_id=")));
+}